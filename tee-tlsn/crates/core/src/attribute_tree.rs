@@ -0,0 +1,243 @@
+//! Merkle commitments over attested attributes.
+//!
+//! Instead of checking one signature per attribute, a verifier can check a single notary
+//! signature over the root of a Merkle tree built from the attributes of a session, along with an
+//! inclusion proof for each attribute which is disclosed. This keeps the cost of verifying a
+//! subset of the attributes on-chain independent of the number of attributes in the session.
+//!
+//! The leaves are the SHA-256 hashes of `0x00 || attribute`, with the attributes in their
+//! `name: value` form and sorted so the tree does not depend on the order in which they were
+//! extracted, and the inner nodes are the SHA-256 hashes of `0x01 || left || right`, so a node
+//! cannot be passed off as a leaf. A node without a sibling is promoted to the level above.
+//!
+//! The notary signs the [root message](root_message), which binds the root to the number of
+//! leaves of the tree and to the hash of the session the attributes were attested in.
+
+use rs_merkle::{algorithms::Sha256, Hasher, MerkleProof, MerkleTree};
+use serde::{Deserialize, Serialize};
+
+use crate::signature::{NotaryPublicKey, Signature, SignatureVerifyError};
+
+/// The domain and version of the [root message](root_message), so its signature cannot be
+/// passed off as another signature of the notary.
+pub const ATTRIBUTES_ROOT_DOMAIN: &[u8] = b"tlsn-tee/attributes-root/v1";
+
+/// The prefix of the preimage of a leaf hash
+const LEAF_PREFIX: u8 = 0x00;
+
+/// The prefix of the preimage of an inner node hash
+const NODE_PREFIX: u8 = 0x01;
+
+/// SHA-256 with the inner nodes hashed as `0x01 || left || right`
+#[derive(Clone)]
+struct AttributeHasher;
+
+impl Hasher for AttributeHasher {
+    type Hash = [u8; 32];
+
+    fn hash(data: &[u8]) -> Self::Hash {
+        Sha256::hash(data)
+    }
+
+    fn concat_and_hash(left: &Self::Hash, right: Option<&Self::Hash>) -> Self::Hash {
+        match right {
+            Some(right) => Self::hash(&[&[NODE_PREFIX], left.as_slice(), right].concat()),
+            None => *left,
+        }
+    }
+}
+
+/// A Merkle tree over the attributes of a session.
+pub struct AttributeTree {
+    attributes: Vec<String>,
+    tree: MerkleTree<AttributeHasher>,
+}
+
+impl AttributeTree {
+    /// Builds the tree over the given attributes.
+    ///
+    /// Returns `None` if there are no attributes.
+    pub fn new<I, S>(attributes: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut attributes = attributes.into_iter().map(Into::into).collect::<Vec<_>>();
+        if attributes.is_empty() {
+            return None;
+        }
+        attributes.sort();
+        attributes.dedup();
+
+        let leaves = attributes
+            .iter()
+            .map(|attribute| attribute_leaf(attribute))
+            .collect::<Vec<_>>();
+        Some(Self {
+            attributes,
+            tree: MerkleTree::<AttributeHasher>::from_leaves(&leaves),
+        })
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root().expect("tree has at least one leaf")
+    }
+
+    /// Returns the [root message](root_message) of the tree which the notary signs.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_hash` - The signed application data hash of the session.
+    pub fn root_message(&self, session_hash: &[u8]) -> Vec<u8> {
+        root_message(&self.root(), self.attributes.len(), session_hash)
+    }
+
+    /// Returns the inclusion proof of an attribute, or `None` if it is not in the tree.
+    pub fn proof(&self, attribute: &str) -> Option<AttributeProof> {
+        let index = self
+            .attributes
+            .binary_search_by(|leaf| leaf.as_str().cmp(attribute))
+            .ok()?;
+
+        Some(AttributeProof {
+            index,
+            total_leaves: self.attributes.len(),
+            proof_hashes: self.tree.proof(&[index]).proof_hashes().to_vec(),
+        })
+    }
+}
+
+/// An inclusion proof of an attribute in an [`AttributeTree`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttributeProof {
+    /// The index of the attribute among the sorted attributes
+    pub index: usize,
+    /// The number of attributes in the tree
+    pub total_leaves: usize,
+    /// The sibling hashes from the leaf up to the root
+    pub proof_hashes: Vec<[u8; 32]>,
+}
+
+impl AttributeProof {
+    /// Returns the root of the tree that the proof places the attribute in.
+    ///
+    /// The root only holds for a tree of `total_leaves` leaves, which is why the notary signs it
+    /// along with the number of leaves.
+    pub fn root(&self, attribute: &str) -> Option<[u8; 32]> {
+        if self.index >= self.total_leaves {
+            return None;
+        }
+
+        MerkleProof::<AttributeHasher>::new(self.proof_hashes.clone())
+            .root(
+                &[self.index],
+                &[attribute_leaf(attribute)],
+                self.total_leaves,
+            )
+            .ok()
+    }
+}
+
+/// Returns the message the notary signs for the root of an attribute tree
+///
+/// The message is [`ATTRIBUTES_ROOT_DOMAIN`], followed by the root, the number of leaves of the
+/// tree as a big-endian `u64` and the signed application data hash of the session.
+pub fn root_message(root: &[u8; 32], total_leaves: usize, session_hash: &[u8]) -> Vec<u8> {
+    [
+        ATTRIBUTES_ROOT_DOMAIN,
+        root.as_slice(),
+        &(total_leaves as u64).to_be_bytes(),
+        session_hash,
+    ]
+    .concat()
+}
+
+/// Verifies that an attribute is included in the attribute tree whose root was signed by the
+/// notary for a session.
+///
+/// # Arguments
+///
+/// * `root_signature` - The notary's signature over the root message of the attribute tree.
+/// * `attribute` - The attribute in its `name: value` form.
+/// * `proof` - The inclusion proof of the attribute.
+/// * `session_hash` - The signed application data hash of the session.
+/// * `notary_public_key` - The public key of the notary.
+pub fn verify_attribute_proof(
+    root_signature: &Signature,
+    attribute: &str,
+    proof: &AttributeProof,
+    session_hash: &[u8],
+    notary_public_key: impl Into<NotaryPublicKey>,
+) -> Result<(), SignatureVerifyError> {
+    let root = proof
+        .root(attribute)
+        .ok_or_else(|| SignatureVerifyError("invalid attribute proof".to_string()))?;
+    root_signature.verify(
+        &root_message(&root, proof.total_leaves, session_hash),
+        notary_public_key,
+    )
+}
+
+fn attribute_leaf(attribute: &str) -> [u8; 32] {
+    AttributeHasher::hash(&[&[LEAF_PREFIX], attribute.as_bytes()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use p256::ecdsa::{signature::Signer, Signature as P256Signature, SigningKey};
+
+    #[test]
+    fn test_attribute_inclusion_proofs() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+        let attributes = [
+            "verified: true",
+            "followers: 10",
+            "karma: 42",
+            "age: 30",
+            "bio: \"hi\"",
+        ];
+
+        let session_hash = [7; 32];
+
+        let tree = AttributeTree::new(attributes).unwrap();
+        let root_signature: P256Signature = signing_key.sign(&tree.root_message(&session_hash));
+        let root_signature: Signature = root_signature.into();
+        let verify = |attribute: &str, proof: &AttributeProof, session_hash: &[u8]| {
+            verify_attribute_proof(&root_signature, attribute, proof, session_hash, public_key)
+        };
+
+        for attribute in attributes {
+            let proof = tree.proof(attribute).unwrap();
+            assert_eq!(proof.total_leaves, attributes.len());
+            assert!(verify(attribute, &proof, &session_hash).is_ok());
+            // The root is only valid for the session it was signed for
+            assert!(verify(attribute, &proof, &[8; 32]).is_err());
+        }
+
+        let proof = tree.proof("followers: 10").unwrap();
+        assert!(verify("followers: 11", &proof, &session_hash).is_err());
+        assert!(tree.proof("followers: 11").is_none());
+        assert!(AttributeTree::new(Vec::<String>::new()).is_none());
+
+        // The number of leaves is signed, so a proof cannot claim another one
+        let mut proof = tree.proof("age: 30").unwrap();
+        proof.total_leaves = 4;
+        assert!(verify("age: 30", &proof, &session_hash).is_err());
+    }
+
+    #[test]
+    fn test_leaf_and_node_hashes_are_domain_separated() {
+        let tree = AttributeTree::new(["a: 1", "b: 2"]).unwrap();
+        let left = Sha256::hash(b"\x00a: 1");
+        let right = Sha256::hash(b"\x00b: 2");
+        assert_eq!(attribute_leaf("a: 1"), left);
+        assert_eq!(
+            tree.root(),
+            Sha256::hash(&[&[0x01], left.as_slice(), &right].concat())
+        );
+    }
+}
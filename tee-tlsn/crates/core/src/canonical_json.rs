@@ -0,0 +1,61 @@
+//! Canonical JSON serialization.
+//!
+//! Messages which are hashed or signed, e.g. the attestation set of a session, are serialized
+//! to JSON without whitespace and with the keys of every object sorted, so every party which
+//! rebuilds a message produces the same bytes.
+
+use serde_json::Value;
+
+/// Returns the canonical JSON serialization of a value.
+pub fn to_canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical_json(value, &mut out);
+    out
+}
+
+/// Writes a JSON value without whitespace and with the keys of every object sorted.
+pub fn write_canonical_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(object) => {
+            let mut entries = object.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(value, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({"b": [1, {"d": true, "c": null}], "a": "x\"y"});
+        assert_eq!(
+            to_canonical_json(&value),
+            r#"{"a":"x\"y","b":[1,{"c":null,"d":true}]}"#
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! CBOR encoding of signed sessions.
+//!
+//! The serde form of [`SignedSession`] hex encodes its byte fields, which doubles the size of the
+//! transcript. The CBOR form keeps them as byte strings, so it is roughly half the size for large
+//! transcripts.
+//!
+//! The CBOR form is for storing a session or forwarding it to a third party. The notary always
+//! sends the session to the prover in its serde form, as there is no negotiation of the encoding
+//! between them, so a prover which wants the compact form encodes the session it received.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use tls_core::msgs::enums::{CipherSuite, ProtocolVersion};
+
+use crate::{
+    msg::{CommittedBody, SignedSession, TranscriptForm},
+    Signature, SignatureScheme,
+};
+
+/// An error that can occur while encoding or decoding the CBOR form of a signed session.
+#[derive(Debug, thiserror::Error)]
+#[error("CBOR error: {0}")]
+pub struct CborError(String);
+
+/// The CBOR form of a signed session, with the hex encoded fields as raw bytes.
+#[derive(Serialize, Deserialize)]
+struct CborSignedSession {
+    application_data: ByteBuf,
+    application_signed_data: ByteBuf,
+    signature: Signature,
+    attestations: HashMap<String, Signature>,
+    attestations_signature: Signature,
+    server_cert_fingerprint: Option<ByteBuf>,
+    tls_version: Option<ProtocolVersion>,
+    cipher_suite: Option<CipherSuite>,
+    signed_at: Option<u64>,
+    attributes_root_signature: Option<Signature>,
+    eip712_signatures: Option<HashMap<String, String>>,
+    #[serde(default)]
+    scheme: SignatureScheme,
+    #[serde(default)]
+    content_encoding: Option<String>,
+    #[serde(default)]
+    committed_body: CommittedBody,
+    #[serde(default)]
+    transcript_form: TranscriptForm,
+}
+
+impl SignedSession {
+    /// Encodes the signed session as CBOR, keeping the application data as raw bytes.
+    pub fn to_cbor(&self) -> Result<Vec<u8>, CborError> {
+        let decode = |field: &str, value: &str| {
+            hex::decode(value)
+                .map(ByteBuf::from)
+                .map_err(|e| CborError(format!("invalid hex in {}: {}", field, e)))
+        };
+
+        let session = CborSignedSession {
+            application_data: decode("application_data", &self.application_data)?,
+            application_signed_data: decode(
+                "application_signed_data",
+                &self.application_signed_data,
+            )?,
+            signature: self.signature.clone(),
+            attestations: self.attestations.clone(),
+            attestations_signature: self.attestations_signature.clone(),
+            server_cert_fingerprint: self
+                .server_cert_fingerprint
+                .as_deref()
+                .map(|fingerprint| decode("server_cert_fingerprint", fingerprint))
+                .transpose()?,
+            tls_version: self.tls_version,
+            cipher_suite: self.cipher_suite,
+            signed_at: self.signed_at,
+            attributes_root_signature: self.attributes_root_signature.clone(),
+            eip712_signatures: self.eip712_signatures.clone(),
+            scheme: self.scheme,
+            content_encoding: self.content_encoding.clone(),
+            committed_body: self.committed_body,
+            transcript_form: self.transcript_form,
+        };
+
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&session, &mut bytes).map_err(|e| CborError(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Decodes a signed session from its CBOR form.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, CborError> {
+        let session: CborSignedSession =
+            ciborium::from_reader(bytes).map_err(|e| CborError(e.to_string()))?;
+
+        Ok(Self {
+            application_data: hex::encode(session.application_data),
+            application_signed_data: hex::encode(session.application_signed_data),
+            signature: session.signature,
+            attestations: session.attestations,
+            attestations_signature: session.attestations_signature,
+            server_cert_fingerprint: session.server_cert_fingerprint.map(hex::encode),
+            tls_version: session.tls_version,
+            cipher_suite: session.cipher_suite,
+            signed_at: session.signed_at,
+            attributes_root_signature: session.attributes_root_signature,
+            eip712_signatures: session.eip712_signatures,
+            scheme: session.scheme,
+            content_encoding: session.content_encoding,
+            committed_body: session.committed_body,
+            transcript_form: session.transcript_form,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use p256::ecdsa::{signature::Signer, Signature as P256Signature, SigningKey};
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let application_data = vec![0x42; 4096];
+        let signature: P256Signature = signing_key.sign(&application_data);
+        let attestation: P256Signature = signing_key.sign(b"followers: 10");
+
+        let session = SignedSession::builder()
+            .application_data(hex::encode(&application_data))
+            .application_signed_data(hex::encode([7; 32]))
+            .signature(signature)
+            .attestations(HashMap::from([(
+                "followers: 10".to_string(),
+                attestation.into(),
+            )]))
+            .attestations_signature(signature)
+            .server_cert_fingerprint(hex::encode([9; 32]))
+            .tls_parameters(
+                ProtocolVersion::TLSv1_2,
+                CipherSuite::TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+            )
+            .signed_at(1735613754)
+            .transcript_form(TranscriptForm::Canonical)
+            .build()
+            .unwrap();
+
+        let cbor = session.to_cbor().unwrap();
+        let decoded = SignedSession::from_cbor(&cbor).unwrap();
+        assert_eq!(
+            bincode::serialize(&decoded).unwrap(),
+            bincode::serialize(&session).unwrap()
+        );
+        assert_eq!(decoded.scheme, SignatureScheme::EcdsaP256Sha256);
+        assert!(cbor.len() < session.application_data.len());
+    }
+}
@@ -0,0 +1,141 @@
+//! W3C Verifiable Credentials built from signed sessions.
+
+use serde_json::{json, Map, Value};
+
+use crate::msg::SignedSession;
+
+/// The JSON-LD context of the W3C Verifiable Credentials data model
+const VC_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// The credential type of a credential built from a signed session
+pub const CREDENTIAL_TYPE: &str = "TlsAttestationCredential";
+
+/// The proof type of a credential built from a signed session
+///
+/// This is a custom proof type rather than a Data Integrity proof: the proof value signs the
+/// [attestation set message](SignedSession::attestations_message) of the session, not the
+/// canonicalized credential, so a Data Integrity cryptosuite such as `ecdsa-rdfc-2019` would not
+/// verify it. The `algorithm` of the proof is the JOSE name of the
+/// [scheme](SignedSession::scheme) of the session.
+pub const PROOF_TYPE: &str = "TlsAttestationProof2024";
+
+impl SignedSession {
+    /// Converts the attestations of the session into a W3C Verifiable Credential.
+    ///
+    /// Each attestation of the form `key: value` becomes a `credentialSubject` claim, with the
+    /// value kept as a string exactly as it was signed. The notary is the issuer, and the
+    /// [custom proof](PROOF_TYPE) carries the hex encoded signature over the attestation set
+    /// along with the signature of each attestation, keyed by the signed attestation, and the
+    /// hash of the session, so a verifier can rebuild the
+    /// [attestation set message](SignedSession::attestations_message) and check it against the
+    /// notary public key.
+    ///
+    /// # Arguments
+    ///
+    /// * `issuer_did` - The DID of the notary.
+    pub fn to_verifiable_credential(&self, issuer_did: &str) -> Value {
+        let mut subject = Map::new();
+        let mut attestation_signatures = Map::new();
+        for (attestation, signature) in &self.attestations {
+            let (key, value) = attestation
+                .split_once(": ")
+                .unwrap_or((attestation.as_str(), ""));
+            subject.insert(key.to_string(), Value::String(value.to_string()));
+            attestation_signatures.insert(
+                attestation.clone(),
+                Value::String(hex::encode(signature.to_bytes())),
+            );
+        }
+
+        json!({
+            "@context": [VC_CONTEXT],
+            "type": ["VerifiableCredential", CREDENTIAL_TYPE],
+            "issuer": issuer_did,
+            "credentialSubject": subject,
+            "proof": {
+                "type": PROOF_TYPE,
+                "algorithm": self.scheme.algorithm(),
+                "proofPurpose": "assertionMethod",
+                "verificationMethod": issuer_did,
+                "proofValue": hex::encode(self.attestations_signature.to_bytes()),
+                "attestationSignatures": attestation_signatures,
+                "sessionHash": self.application_signed_data,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{signature::ES256, Signature};
+
+    use p256::ecdsa::{signature::Signer, Signature as P256Signature, SigningKey};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_verifiable_credential() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+        let attestations = ["followers: 10", "verified: true"]
+            .iter()
+            .map(|attestation| {
+                let signature: P256Signature = signing_key.sign(attestation.as_bytes());
+                (attestation.to_string(), signature.into())
+            })
+            .collect::<HashMap<_, Signature>>();
+        let session_hash = [7; 32];
+        let attestations_signature: P256Signature = signing_key.sign(
+            &SignedSession::attestations_message(&session_hash, &attestations),
+        );
+        let session = SignedSession::new(
+            String::new(),
+            hex::encode(session_hash),
+            attestations_signature.into(),
+            attestations,
+            attestations_signature.into(),
+        );
+
+        let credential = session.to_verifiable_credential("did:example:notary");
+        assert_eq!(credential["issuer"], "did:example:notary");
+        assert_eq!(
+            credential["type"],
+            json!(["VerifiableCredential", CREDENTIAL_TYPE])
+        );
+        assert_eq!(
+            credential["credentialSubject"],
+            json!({"followers": "10", "verified": "true"})
+        );
+        assert_eq!(credential["proof"]["type"], PROOF_TYPE);
+        assert_eq!(credential["proof"]["algorithm"], ES256);
+
+        // Rebuild the signed attestation set from the proof and check it against the notary key
+        let proof = &credential["proof"];
+        let attestations = proof["attestationSignatures"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(attestation, signature)| {
+                let signature = hex::decode(signature.as_str().unwrap()).unwrap();
+                let signature: Signature = P256Signature::from_slice(&signature).unwrap().into();
+                assert!(signature.verify(attestation.as_bytes(), public_key).is_ok());
+                (attestation.clone(), signature)
+            })
+            .collect::<HashMap<_, _>>();
+        let proof_value = hex::decode(proof["proofValue"].as_str().unwrap()).unwrap();
+        let proof_value: Signature = P256Signature::from_slice(&proof_value).unwrap().into();
+        let session_hash = hex::decode(proof["sessionHash"].as_str().unwrap()).unwrap();
+        assert!(proof_value
+            .verify(
+                &SignedSession::attestations_message(&session_hash, &attestations),
+                public_key
+            )
+            .is_ok());
+        assert!(proof_value
+            .verify(
+                &SignedSession::attestations_message(&session_hash, &HashMap::new()),
+                public_key
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! EIP-712 typed data for attestations.
+//!
+//! Attestations signed over an EIP-712 digest with a secp256k1 key can be checked on-chain by
+//! reconstructing the digest in Solidity and passing it to `ecrecover`.
+//!
+//! Each attribute is hashed as the struct `Attestation(string attribute)` under an
+//! `EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)` domain.
+
+use k256::ecdsa::{RecoveryId, Signature as K256Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+#[cfg(feature = "tee")]
+use crate::msg::SignedSession;
+
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const ATTESTATION_TYPE: &str = "Attestation(string attribute)";
+
+/// An EIP-712 domain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip712Domain {
+    /// The name of the signing domain.
+    pub name: String,
+    /// The version of the signing domain.
+    pub version: String,
+    /// The chain id of the network the verifying contract is deployed on.
+    pub chain_id: u64,
+    /// The address of the contract that verifies the signatures.
+    pub verifying_contract: [u8; 20],
+}
+
+impl Eip712Domain {
+    /// Returns the domain separator.
+    pub fn separator(&self) -> [u8; 32] {
+        let mut encoded = Vec::with_capacity(5 * 32);
+        encoded.extend_from_slice(&keccak256(DOMAIN_TYPE.as_bytes()));
+        encoded.extend_from_slice(&keccak256(self.name.as_bytes()));
+        encoded.extend_from_slice(&keccak256(self.version.as_bytes()));
+        encoded.extend_from_slice(&encode_uint(self.chain_id));
+        encoded.extend_from_slice(&encode_address(&self.verifying_contract));
+        keccak256(&encoded)
+    }
+
+    /// Returns the digest which is signed for an attribute.
+    pub fn attestation_digest(&self, attribute: &str) -> [u8; 32] {
+        typed_data_digest(&self.separator(), &attestation_struct_hash(attribute))
+    }
+}
+
+/// The EIP-712 typed data of an attribute attestation, as a Solidity verifier reconstructs it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip712Attestation {
+    /// The attribute, the `attribute` member of the `Attestation` struct.
+    pub attribute: String,
+    /// The `hashStruct` of the `Attestation` struct.
+    pub struct_hash: [u8; 32],
+    /// The digest `keccak256("\x19\x01" || domainSeparator || structHash)` which is signed.
+    pub digest: [u8; 32],
+    /// The hex encoded `r || s || v` signature of the digest, if the notary signed the session
+    /// with an EIP-712 signer.
+    pub signature: Option<String>,
+}
+
+#[cfg(feature = "tee")]
+impl SignedSession {
+    /// Returns the EIP-712 typed data of each attestation of the session, sorted by attribute.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain of the contract which verifies the attestations.
+    pub fn to_eip712(&self, domain: &Eip712Domain) -> Vec<Eip712Attestation> {
+        let separator = domain.separator();
+        let mut attributes = self.attestations.keys().collect::<Vec<_>>();
+        attributes.sort();
+        attributes
+            .into_iter()
+            .map(|attribute| {
+                let struct_hash = attestation_struct_hash(attribute);
+                Eip712Attestation {
+                    attribute: attribute.clone(),
+                    struct_hash,
+                    digest: typed_data_digest(&separator, &struct_hash),
+                    signature: self
+                        .eip712_signatures
+                        .as_ref()
+                        .and_then(|signatures| signatures.get(attribute).cloned()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the `hashStruct` of the `Attestation` struct of an attribute.
+pub fn attestation_struct_hash(attribute: &str) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(2 * 32);
+    encoded.extend_from_slice(&keccak256(ATTESTATION_TYPE.as_bytes()));
+    encoded.extend_from_slice(&keccak256(attribute.as_bytes()));
+    keccak256(&encoded)
+}
+
+/// Signs an EIP-712 digest, returning the 65 byte `r || s || v` signature expected by `ecrecover`.
+pub fn sign_digest(
+    signing_key: &SigningKey,
+    digest: &[u8; 32],
+) -> Result<[u8; 65], k256::ecdsa::Error> {
+    let (signature, recovery_id) = signing_key.sign_prehash_recoverable(digest)?;
+
+    let mut bytes = [0u8; 65];
+    bytes[..64].copy_from_slice(&signature.to_bytes());
+    bytes[64] = 27 + recovery_id.to_byte();
+    Ok(bytes)
+}
+
+/// Recovers the address which signed an EIP-712 digest, as `ecrecover` does.
+pub fn recover_address(digest: &[u8; 32], signature: &[u8; 65]) -> Option<[u8; 20]> {
+    let recovery_id = RecoveryId::from_byte(signature[64].checked_sub(27)?)?;
+    let signature = K256Signature::from_slice(&signature[..64]).ok()?;
+    let verifying_key = VerifyingKey::recover_from_prehash(digest, &signature, recovery_id).ok()?;
+    Some(address(&verifying_key))
+}
+
+/// Returns the Ethereum address of a secp256k1 public key.
+pub fn address(verifying_key: &VerifyingKey) -> [u8; 20] {
+    let point = verifying_key.to_encoded_point(false);
+    let hash = keccak256(&point.as_bytes()[1..]);
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+fn typed_data_digest(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(2 + 2 * 32);
+    encoded.extend_from_slice(b"\x19\x01");
+    encoded.extend_from_slice(domain_separator);
+    encoded.extend_from_slice(struct_hash);
+    keccak256(&encoded)
+}
+
+/// Returns the Keccak-256 hash of the data, as Ethereum hashes.
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+fn encode_uint(value: u64) -> [u8; 32] {
+    let mut encoded = [0u8; 32];
+    encoded[24..].copy_from_slice(&value.to_be_bytes());
+    encoded
+}
+
+fn encode_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut encoded = [0u8; 32];
+    encoded[12..].copy_from_slice(address);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex32(s: &str) -> [u8; 32] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    fn hex20(s: &str) -> [u8; 20] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    fn mail_domain() -> Eip712Domain {
+        Eip712Domain {
+            name: "Ether Mail".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            verifying_contract: hex20("cccccccccccccccccccccccccccccccccccccccc"),
+        }
+    }
+
+    // The `Mail` example from the EIP-712 specification.
+    #[test]
+    fn test_eip712_spec_vector() {
+        let domain = mail_domain();
+        assert_eq!(
+            domain.separator(),
+            hex32("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        let person_type = keccak256(b"Person(string name,address wallet)");
+        let person = |name: &str, wallet: &str| {
+            let mut encoded = Vec::new();
+            encoded.extend_from_slice(&person_type);
+            encoded.extend_from_slice(&keccak256(name.as_bytes()));
+            encoded.extend_from_slice(&encode_address(&hex20(wallet)));
+            keccak256(&encoded)
+        };
+
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&keccak256(
+            b"Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+        ));
+        encoded.extend_from_slice(&person("Cow", "cd2a3d9f938e13cd947ec05abc7fe734df8dd826"));
+        encoded.extend_from_slice(&person("Bob", "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"));
+        encoded.extend_from_slice(&keccak256(b"Hello, Bob!"));
+
+        assert_eq!(
+            typed_data_digest(&domain.separator(), &keccak256(&encoded)),
+            hex32("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn test_sign_and_recover_attestation() {
+        // The private key of the `Cow` account from the EIP-712 specification.
+        let signing_key = SigningKey::from_slice(&keccak256(b"cow")).unwrap();
+        assert_eq!(
+            address(signing_key.verifying_key()),
+            hex20("cd2a3d9f938e13cd947ec05abc7fe734df8dd826")
+        );
+
+        let digest = mail_domain().attestation_digest("followers: 10");
+        let signature = sign_digest(&signing_key, &digest).unwrap();
+
+        assert_eq!(
+            recover_address(&digest, &signature),
+            Some(address(signing_key.verifying_key()))
+        );
+        assert_ne!(
+            recover_address(
+                &mail_domain().attestation_digest("followers: 11"),
+                &signature
+            ),
+            Some(address(signing_key.verifying_key()))
+        );
+    }
+
+    #[cfg(feature = "tee")]
+    #[test]
+    fn test_session_to_eip712() {
+        use p256::ecdsa::{signature::Signer, Signature as P256Signature};
+        use std::collections::HashMap;
+
+        let notary_key = p256::ecdsa::SigningKey::from_slice(&[1; 32]).unwrap();
+        let sign = |message: &[u8]| -> crate::Signature {
+            let signature: P256Signature = notary_key.sign(message);
+            signature.into()
+        };
+        let attestations = ["verified: true", "followers: 10"]
+            .iter()
+            .map(|attribute| (attribute.to_string(), sign(attribute.as_bytes())))
+            .collect::<HashMap<_, _>>();
+
+        let eth_key = SigningKey::from_slice(&keccak256(b"cow")).unwrap();
+        let domain = mail_domain();
+        let eip712_signature = sign_digest(&eth_key, &domain.attestation_digest("followers: 10"))
+            .map(hex::encode)
+            .unwrap();
+
+        let session = SignedSession::builder()
+            .application_data("")
+            .application_signed_data("")
+            .signature(sign(b""))
+            .attestations_signature(sign(&SignedSession::attestations_message(
+                &[],
+                &attestations,
+            )))
+            .attestations(attestations)
+            .eip712_signatures(HashMap::from([(
+                "followers: 10".to_string(),
+                eip712_signature.clone(),
+            )]))
+            .build()
+            .ok()
+            .unwrap();
+
+        // Digests of `Attestation(string attribute)` under the `Ether Mail` domain, computed
+        // independently of this implementation
+        let typed_data = session.to_eip712(&domain);
+        assert_eq!(
+            typed_data,
+            vec![
+                Eip712Attestation {
+                    attribute: "followers: 10".to_string(),
+                    struct_hash: hex32(
+                        "135a316fd4c6d63869687a5c6ff476d5ae2f15f320571e29db008ef1f2b63e1d"
+                    ),
+                    digest: hex32(
+                        "2ac8be0a8f150b768004ee207c68433a010bd81f13433d3352476a48fb485bcc"
+                    ),
+                    signature: Some(eip712_signature),
+                },
+                Eip712Attestation {
+                    attribute: "verified: true".to_string(),
+                    struct_hash: hex32(
+                        "5f8bc22e78508e8c2dbaa3b89efbc0cc260a5684b7f372e00260fab5d1595674"
+                    ),
+                    digest: hex32(
+                        "3c5c2268d7ec888bdb93f0534276e8049c048b6be8a7915f5c9c9260fde13c8a"
+                    ),
+                    signature: None,
+                },
+            ]
+        );
+
+        let signature: [u8; 65] = hex::decode(typed_data[0].signature.as_ref().unwrap())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            recover_address(&typed_data[0].digest, &signature),
+            Some(address(eth_key.verifying_key()))
+        );
+    }
+}
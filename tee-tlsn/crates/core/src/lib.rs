@@ -6,8 +6,18 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "tee")]
+pub mod attribute_tree;
+#[cfg(feature = "tee")]
+pub mod canonical_json;
+#[cfg(feature = "cbor")]
+pub mod cbor;
 #[cfg(feature = "mpz")]
 pub mod commitment;
+#[cfg(feature = "vc")]
+pub mod credential;
+#[cfg(feature = "eth")]
+pub mod eip712;
 #[cfg(any(test, feature = "fixtures"))]
 pub mod fixtures;
 #[cfg(feature = "mpz")]
@@ -20,11 +30,15 @@ pub mod session;
 mod signature;
 pub mod transcript;
 
+#[cfg(feature = "tee")]
+pub use msg::{
+    AttributeAttestation, SignedSession, SignedSessionBuilder, SignedSessionBuilderError,
+};
 #[cfg(feature = "mpz")]
 pub use session::{HandshakeSummary, NotarizedSession, SessionData, SessionHeader};
-pub use signature::{NotaryPublicKey, Signature};
-#[cfg(feature = "tee")]
-pub use msg::SignedSession;
+pub use signature::{
+    NotaryPublicKey, Signature, SignatureScheme, SignatureVerifyError, ED_DSA, ES256, ES256K_KECCAK,
+};
 #[cfg(feature = "mpz")]
 pub use transcript::{Direction, RedactedTranscript, Transcript, TranscriptSlice};
 
@@ -11,6 +11,13 @@ use utils::range::RangeSet;
 use crate::{proof::SessionInfo, SessionHeader};
 
 use crate::signature::Signature;
+#[cfg(feature = "tee")]
+use crate::{
+    canonical_json::to_canonical_json,
+    signature::{NotaryPublicKey, SignatureScheme, SignatureVerifyError},
+};
+#[cfg(feature = "tee")]
+use tls_core::msgs::enums::{CipherSuite, ProtocolVersion};
 
 /// Top-level enum for all messages
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,12 +59,86 @@ pub struct SignedSessionHeader {
 pub struct SignedSession {
     /// The hex encoded TLS application data which comprises request and response data
     pub application_data: String,
-    /// The hex encoded sha256 hash of the application data which is signed by the notary
+    /// The hex encoded sha256 hash of the application data which is signed by the notary.
+    ///
+    /// The hash covers a domain and version prefix, the application data and the recorded server
+    /// certificate fingerprint, TLS version, cipher suite and signing time, each optional one
+    /// with a presence tag, and the transcript form, see
+    /// `tlsn_verifier::session::signed_data_hash`.
     pub application_signed_data: String,
     /// The signature of the application data
     pub signature: Signature,
     /// A vector of hashmap of strings to signatures
     pub attestations: HashMap<String, Signature>,
+    /// The signature over the canonical serialization of the whole attestation set and the
+    /// session hash, see [`SignedSession::attestations_message`]
+    pub attestations_signature: Signature,
+    /// The hex encoded sha256 fingerprint of the server's end-entity certificate
+    #[serde(default)]
+    pub server_cert_fingerprint: Option<String>,
+    /// The TLS protocol version negotiated with the server
+    #[serde(default)]
+    pub tls_version: Option<ProtocolVersion>,
+    /// The cipher suite negotiated with the server
+    #[serde(default)]
+    pub cipher_suite: Option<CipherSuite>,
+    /// The unix time in seconds at which the notary signed the session
+    #[serde(default)]
+    pub signed_at: Option<u64>,
+    /// The signature over the root of the Merkle tree of the attestations, bound to the session
+    /// by its signed application data hash
+    ///
+    /// See [`AttributeTree`](crate::attribute_tree::AttributeTree) for building the tree, its
+    /// signed root message and the inclusion proofs of individual attestations.
+    #[serde(default)]
+    pub attributes_root_signature: Option<Signature>,
+    /// A hashmap of attributes to their hex encoded EIP-712 `r || s || v` signatures
+    #[serde(default)]
+    pub eip712_signatures: Option<HashMap<String, String>>,
+    /// The scheme of the notary signatures of the session, which is the curve, hash and encoding
+    /// they are verified with, see [`SignedSession::check_scheme`]
+    ///
+    /// Sessions signed before the scheme was recorded have the default
+    /// [`SignatureScheme::EcdsaP256Sha256`].
+    #[serde(default)]
+    pub scheme: SignatureScheme,
+    /// The `Content-Encoding` of the response, e.g. `gzip`, if it was encoded
+    #[serde(default)]
+    pub content_encoding: Option<String>,
+    /// The form of the response body in the application data
+    #[serde(default)]
+    pub committed_body: CommittedBody,
+    /// The form of the request in the application data
+    #[serde(default)]
+    pub transcript_form: TranscriptForm,
+}
+
+/// The form in which the request is committed to in the application data of a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg(feature = "tee")]
+pub enum TranscriptForm {
+    /// The request as it was sent
+    #[default]
+    Raw,
+    /// The request re-encoded in canonical form, see `tlsn_verifier::http::canonical_request`,
+    /// so parties which rebuild the request can reproduce the signed hash
+    Canonical,
+}
+
+/// The form in which the response body is committed to in the application data of a session.
+///
+/// The notary decodes a `gzip` or `deflate` body, along with its chunked `Transfer-Encoding`,
+/// before extracting the attributes and commits to it decoded. Attributes can only be extracted
+/// from a body the notary can read, so provers should request any other response unencoded with
+/// `Accept-Encoding: identity`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg(feature = "tee")]
+pub enum CommittedBody {
+    /// The body as it was received, still in its `Content-Encoding` if it has one
+    #[default]
+    Received,
+    /// The body after its `Content-Encoding` was decoded, following the head as received
+    Decoded,
 }
 
 #[cfg(feature = "tee")]
@@ -65,19 +146,454 @@ opaque_debug::implement!(SignedSession);
 
 #[cfg(feature = "tee")]
 impl SignedSession {
-    /// Create a new notarized session.
+    /// Create a new notarized session from its required parts.
+    ///
+    /// The optional parts, e.g. the TLS parameters or the signing time, are unset; use
+    /// [`SignedSession::builder`] to set them.
     pub fn new(
         application_data: String,
         application_signed_data: String,
         signature: Signature,
         attestations: HashMap<String, Signature>,
+        attestations_signature: Signature,
     ) -> Self {
+        let scheme = signature.scheme();
         Self {
             application_data,
             application_signed_data,
             signature,
             attestations,
+            attestations_signature,
+            server_cert_fingerprint: None,
+            tls_version: None,
+            cipher_suite: None,
+            signed_at: None,
+            attributes_root_signature: None,
+            eip712_signatures: None,
+            scheme,
+            content_encoding: None,
+            committed_body: CommittedBody::Received,
+            transcript_form: TranscriptForm::Raw,
+        }
+    }
+
+    /// Returns a builder of a signed session, e.g. to assemble one in tests of verification code.
+    pub fn builder() -> SignedSessionBuilder {
+        SignedSessionBuilder::default()
+    }
+
+    /// Returns the canonical serialization of an attestation set which is signed by the notary.
+    ///
+    /// The hash of the session, i.e. its decoded
+    /// [`application_signed_data`](Self::application_signed_data), is followed by the
+    /// [canonical JSON](crate::canonical_json) array of the attestations sorted by their bytes,
+    /// so the signature binds the set to its session, does not depend on the iteration order of
+    /// the map and no attestation can be added or removed without changing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_hash` - The hash of the session the attestations were extracted from.
+    /// * `attestations` - The attestations of the session.
+    pub fn attestations_message(
+        session_hash: &[u8],
+        attestations: &HashMap<String, Signature>,
+    ) -> Vec<u8> {
+        Self::attributes_message(session_hash, attestations.keys())
+    }
+
+    /// Returns the [`attestations_message`](Self::attestations_message) of a set of attributes.
+    fn attributes_message<'a>(
+        session_hash: &[u8],
+        attributes: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<u8> {
+        let mut keys = attributes.into_iter().collect::<Vec<_>>();
+        keys.sort();
+
+        let mut message = session_hash.to_vec();
+        message.extend_from_slice(to_canonical_json(&serde_json::json!(keys)).as_bytes());
+        message
+    }
+
+    /// Returns the hash of the session which is signed by the notary, i.e. the decoded
+    /// [`application_signed_data`](Self::application_signed_data).
+    pub fn session_hash(&self) -> Result<Vec<u8>, SignatureVerifyError> {
+        hex::decode(&self.application_signed_data)
+            .map_err(|e| SignatureVerifyError(format!("invalid application signed data: {}", e)))
+    }
+
+    /// Checks that the scheme the session claims to be signed with is the one of the notary
+    /// public key and of each of its signatures, so a session signed with another key type, or
+    /// which misreports its scheme, is rejected before verification.
+    ///
+    /// # Arguments
+    ///
+    /// * `notary_public_key` - The public key of the notary.
+    pub fn check_scheme(
+        &self,
+        notary_public_key: &NotaryPublicKey,
+    ) -> Result<(), SignatureVerifyError> {
+        if self.scheme != notary_public_key.scheme() {
+            return Err(SignatureVerifyError(format!(
+                "unsupported scheme {:?}, expected {:?}",
+                self.scheme,
+                notary_public_key.scheme()
+            )));
+        }
+        let signatures = [&self.signature, &self.attestations_signature]
+            .into_iter()
+            .chain(self.attestations.values())
+            .chain(&self.attributes_root_signature);
+        for signature in signatures {
+            if signature.scheme() != self.scheme {
+                return Err(SignatureVerifyError(format!(
+                    "{:?} signature in a session signed with {:?}",
+                    signature.scheme(),
+                    self.scheme
+                )));
+            }
         }
+        Ok(())
+    }
+
+    /// Verifies the signature over the whole attestation set.
+    ///
+    /// # Arguments
+    ///
+    /// * `notary_public_key` - The public key of the notary.
+    pub fn verify_attestations(
+        &self,
+        notary_public_key: impl Into<NotaryPublicKey>,
+    ) -> Result<(), SignatureVerifyError> {
+        let notary_public_key = notary_public_key.into();
+        self.check_scheme(&notary_public_key)?;
+        self.attestations_signature.verify(
+            &Self::attestations_message(&self.session_hash()?, &self.attestations),
+            notary_public_key,
+        )
+    }
+}
+
+/// An error for [`SignedSessionBuilder`].
+#[derive(Debug, thiserror::Error)]
+#[cfg(feature = "tee")]
+pub enum SignedSessionBuilderError {
+    /// A required field was not set.
+    #[error("missing field: {0}")]
+    MissingField(&'static str),
+}
+
+/// A builder of a [`SignedSession`] from its parts, e.g. to test verification code without
+/// running a notarization.
+#[derive(Debug, Default)]
+#[cfg(feature = "tee")]
+pub struct SignedSessionBuilder {
+    application_data: Option<String>,
+    application_signed_data: Option<String>,
+    signature: Option<Signature>,
+    attestations: HashMap<String, Signature>,
+    attestations_signature: Option<Signature>,
+    server_cert_fingerprint: Option<String>,
+    tls_version: Option<ProtocolVersion>,
+    cipher_suite: Option<CipherSuite>,
+    signed_at: Option<u64>,
+    attributes_root_signature: Option<Signature>,
+    eip712_signatures: Option<HashMap<String, String>>,
+    content_encoding: Option<String>,
+    committed_body: CommittedBody,
+    transcript_form: TranscriptForm,
+}
+
+#[cfg(feature = "tee")]
+impl SignedSessionBuilder {
+    /// Sets the hex encoded TLS application data.
+    pub fn application_data(mut self, application_data: impl Into<String>) -> Self {
+        self.application_data = Some(application_data.into());
+        self
+    }
+
+    /// Sets the hex encoded hash of the application data which is signed by the notary.
+    pub fn application_signed_data(mut self, application_signed_data: impl Into<String>) -> Self {
+        self.application_signed_data = Some(application_signed_data.into());
+        self
+    }
+
+    /// Sets the signature of the signed application data.
+    pub fn signature(mut self, signature: impl Into<Signature>) -> Self {
+        self.signature = Some(signature.into());
+        self
+    }
+
+    /// Sets the attestations, each attribute with its signature.
+    pub fn attestations(mut self, attestations: HashMap<String, Signature>) -> Self {
+        self.attestations = attestations;
+        self
+    }
+
+    /// Sets the signature over the [`SignedSession::attestations_message`] of the attestations
+    /// and the session hash.
+    pub fn attestations_signature(mut self, attestations_signature: impl Into<Signature>) -> Self {
+        self.attestations_signature = Some(attestations_signature.into());
+        self
+    }
+
+    /// Sets the hex encoded sha256 fingerprint of the server's end-entity certificate.
+    pub fn server_cert_fingerprint(mut self, server_cert_fingerprint: impl Into<String>) -> Self {
+        self.server_cert_fingerprint = Some(server_cert_fingerprint.into());
+        self
+    }
+
+    /// Sets the negotiated TLS version and cipher suite.
+    pub fn tls_parameters(mut self, version: ProtocolVersion, cipher_suite: CipherSuite) -> Self {
+        self.tls_version = Some(version);
+        self.cipher_suite = Some(cipher_suite);
+        self
+    }
+
+    /// Sets the unix time in seconds at which the notary signed the session.
+    pub fn signed_at(mut self, signed_at: u64) -> Self {
+        self.signed_at = Some(signed_at);
+        self
+    }
+
+    /// Sets the signature over the root of the Merkle tree of the attestations.
+    pub fn attributes_root_signature(mut self, signature: impl Into<Signature>) -> Self {
+        self.attributes_root_signature = Some(signature.into());
+        self
+    }
+
+    /// Sets the hex encoded EIP-712 signatures of the attributes.
+    pub fn eip712_signatures(mut self, eip712_signatures: HashMap<String, String>) -> Self {
+        self.eip712_signatures = Some(eip712_signatures);
+        self
+    }
+
+    /// Sets the `Content-Encoding` of the response, e.g. `gzip`.
+    pub fn content_encoding(mut self, content_encoding: impl Into<String>) -> Self {
+        self.content_encoding = Some(content_encoding.into());
+        self
+    }
+
+    /// Sets the form of the response body in the application data.
+    pub fn committed_body(mut self, committed_body: CommittedBody) -> Self {
+        self.committed_body = committed_body;
+        self
+    }
+
+    /// Sets the form of the request in the application data.
+    pub fn transcript_form(mut self, transcript_form: TranscriptForm) -> Self {
+        self.transcript_form = transcript_form;
+        self
+    }
+
+    /// Builds the signed session.
+    pub fn build(self) -> Result<SignedSession, SignedSessionBuilderError> {
+        let missing = SignedSessionBuilderError::MissingField;
+        let mut session = SignedSession::new(
+            self.application_data.ok_or(missing("application_data"))?,
+            self.application_signed_data
+                .ok_or(missing("application_signed_data"))?,
+            self.signature.ok_or(missing("signature"))?,
+            self.attestations,
+            self.attestations_signature
+                .ok_or(missing("attestations_signature"))?,
+        );
+        session.server_cert_fingerprint = self.server_cert_fingerprint;
+        session.tls_version = self.tls_version;
+        session.cipher_suite = self.cipher_suite;
+        session.signed_at = self.signed_at;
+        session.attributes_root_signature = self.attributes_root_signature;
+        session.eip712_signatures = self.eip712_signatures;
+        session.content_encoding = self.content_encoding;
+        session.committed_body = self.committed_body;
+        session.transcript_form = self.transcript_form;
+        Ok(session)
+    }
+}
+
+/// One transcript of a session which is streamed to the prover before the commitment closing
+/// the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg(feature = "tee")]
+pub struct SignedSessionChunk {
+    /// The position of the transcript in the session, starting at zero
+    pub index: u32,
+    /// The hex encoded TLS application data of the transcript
+    pub application_data: String,
+    /// The hex encoded sha256 hash of the application data, its index and the chunk count which
+    /// is signed by the notary, in a domain distinct from the one of [`SignedSession`]
+    pub application_signed_data: String,
+    /// The signature of the application data
+    pub signature: Signature,
+}
+
+/// The commitment sent after the chunks of a session, which covers all of them in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg(feature = "tee")]
+pub struct SignedSessionCommitment {
+    /// The number of chunks of the session
+    pub chunk_count: u32,
+    /// The signature over the commitment message of the chunks
+    pub signature: Signature,
+}
+
+/// A message of a session streamed as chunks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg(feature = "tee")]
+pub enum SessionChunkMessage {
+    /// The signed transcript at the next index
+    Chunk(SignedSessionChunk),
+    /// The commitment which ends the stream
+    Commitment(SignedSessionCommitment),
+}
+
+#[cfg(feature = "tee")]
+impl SignedSessionCommitment {
+    /// Returns the message which is signed by the notary to commit to the chunks of a session.
+    ///
+    /// The chunk count is followed by the index of each chunk and its signed data prefixed with
+    /// its big-endian u64 length, so no chunk can be dropped, reordered or added.
+    pub fn commitment_message(chunks: &[SignedSessionChunk]) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(&(chunks.len() as u64).to_be_bytes());
+        for chunk in chunks {
+            message.extend_from_slice(&chunk.index.to_be_bytes());
+            message.extend_from_slice(&(chunk.application_signed_data.len() as u64).to_be_bytes());
+            message.extend_from_slice(chunk.application_signed_data.as_bytes());
+        }
+        message
+    }
+
+    /// Verifies that the commitment covers exactly the given chunks, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks` - The chunks received before the commitment.
+    /// * `notary_public_key` - The public key of the notary.
+    pub fn verify(
+        &self,
+        chunks: &[SignedSessionChunk],
+        notary_public_key: impl Into<NotaryPublicKey>,
+    ) -> Result<(), SignatureVerifyError> {
+        if chunks.len() != self.chunk_count as usize {
+            return Err(SignatureVerifyError(format!(
+                "commitment covers {} chunks, got {}",
+                self.chunk_count,
+                chunks.len()
+            )));
+        }
+        if let Some((position, chunk)) = chunks
+            .iter()
+            .enumerate()
+            .find(|(position, chunk)| chunk.index as usize != *position)
+        {
+            return Err(SignatureVerifyError(format!(
+                "chunk {} received at position {}",
+                chunk.index, position
+            )));
+        }
+        self.signature
+            .verify(&Self::commitment_message(chunks), notary_public_key)
+    }
+}
+
+/// A single attested attribute which can be shared and verified without the rest of its session.
+///
+/// The notary signs each attribute on its own, so the attestation also carries the attribute set
+/// of its session and the notary's signature over it, which binds the attribute to the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg(feature = "tee")]
+pub struct AttributeAttestation {
+    /// The name of the attribute
+    pub name: String,
+    /// The value of the attribute, as it was signed
+    pub value: String,
+    /// The notary's signature over the attribute
+    pub signature: Signature,
+    /// The hex encoded hash of the session the attribute was attested in, see
+    /// [`SignedSession::session_hash`]
+    pub session_hash: String,
+    /// The attributes attested in the session, in the `name: value` form
+    pub attributes: Vec<String>,
+    /// The notary's signature over the attribute set of the session, see
+    /// [`SignedSession::attestations_message`]
+    pub attestations_signature: Signature,
+    /// The identifier of the notary key which signed the attribute
+    pub notary_key_id: String,
+}
+
+#[cfg(feature = "tee")]
+impl AttributeAttestation {
+    /// Extracts the attestation of an attribute from a signed session.
+    ///
+    /// Returns `None` if the session has no attestation for the attribute.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The signed session holding the attestation.
+    /// * `name` - The name of the attribute.
+    /// * `notary_key_id` - The identifier of the notary key which signed the session.
+    pub fn from_session(
+        session: &SignedSession,
+        name: &str,
+        notary_key_id: impl Into<String>,
+    ) -> Option<Self> {
+        let (value, signature) =
+            session
+                .attestations
+                .iter()
+                .find_map(|(attribute, signature)| match attribute.split_once(": ") {
+                    Some((attribute_name, value)) if attribute_name == name => {
+                        Some((value, signature))
+                    }
+                    _ => None,
+                })?;
+
+        let mut attributes = session.attestations.keys().cloned().collect::<Vec<_>>();
+        attributes.sort();
+
+        Some(Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            signature: signature.clone(),
+            session_hash: session.application_signed_data.clone(),
+            attributes,
+            attestations_signature: session.attestations_signature.clone(),
+            notary_key_id: notary_key_id.into(),
+        })
+    }
+
+    /// Returns the attribute in the `name: value` form which is signed by the notary.
+    pub fn attribute(&self) -> String {
+        format!("{}: {}", self.name, self.value)
+    }
+
+    /// Verifies the notary's signature over the attribute, and that the attribute is in the
+    /// attribute set the notary signed for the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `notary_public_key` - The public key of the notary.
+    pub fn verify(
+        &self,
+        notary_public_key: impl Into<NotaryPublicKey>,
+    ) -> Result<(), SignatureVerifyError> {
+        let notary_public_key = notary_public_key.into();
+        let attribute = self.attribute();
+        self.signature
+            .verify(attribute.as_bytes(), notary_public_key.clone())?;
+
+        if !self.attributes.contains(&attribute) {
+            return Err(SignatureVerifyError(format!(
+                "attribute '{}' is not in the attribute set of the session",
+                attribute
+            )));
+        }
+        let session_hash = hex::decode(&self.session_hash)
+            .map_err(|e| SignatureVerifyError(format!("invalid session hash: {}", e)))?;
+        self.attestations_signature.verify(
+            &SignedSession::attributes_message(&session_hash, &self.attributes),
+            notary_public_key,
+        )
     }
 }
 
@@ -92,3 +608,278 @@ pub struct ProvingInfo {
     /// Purported cleartext values
     pub cleartext: Vec<u8>,
 }
+
+#[cfg(all(test, feature = "tee"))]
+mod tests {
+    use super::*;
+
+    use p256::ecdsa::{signature::Signer, Signature as P256Signature, SigningKey};
+
+    /// The hash of the sessions of the tests, which the attestation set signature is bound to
+    const SESSION_HASH: [u8; 32] = [7; 32];
+
+    fn signed_session(signing_key: &SigningKey, attributes: &[&str]) -> SignedSession {
+        let attestations = attributes
+            .iter()
+            .map(|attribute| {
+                let signature: P256Signature = signing_key.sign(attribute.as_bytes());
+                (attribute.to_string(), signature.into())
+            })
+            .collect::<HashMap<String, Signature>>();
+        let signature: P256Signature = signing_key.sign(b"application data");
+        let attestations_signature: P256Signature = signing_key.sign(
+            &SignedSession::attestations_message(&SESSION_HASH, &attestations),
+        );
+
+        SignedSession::builder()
+            .application_data(hex::encode(b"application data"))
+            .application_signed_data(hex::encode(SESSION_HASH))
+            .signature(signature)
+            .attestations(attestations)
+            .attestations_signature(attestations_signature)
+            .tls_parameters(
+                ProtocolVersion::TLSv1_3,
+                CipherSuite::TLS13_AES_128_GCM_SHA256,
+            )
+            .signed_at(1735613754)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_verify_attestations() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        let session = signed_session(&signing_key, &["verified: true", "followers: 10"]);
+        assert!(session.verify_attestations(public_key).is_ok());
+        assert_eq!(
+            SignedSession::attestations_message(&SESSION_HASH, &session.attestations),
+            [
+                SESSION_HASH.as_slice(),
+                br#"["followers: 10","verified: true"]"#.as_slice()
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_removed_attestation_invalidates_signature() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        let mut session = signed_session(&signing_key, &["followers: 10", "verified: true"]);
+        session.attestations.remove("verified: true");
+
+        assert!(session.verify_attestations(public_key).is_err());
+    }
+
+    #[test]
+    fn test_attestation_set_is_bound_to_session() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        // The attestation set of one session presented with the hash of another
+        let mut session = signed_session(&signing_key, &["followers: 10"]);
+        session.application_signed_data = hex::encode([8; 32]);
+        assert!(session.verify_attestations(public_key).is_err());
+
+        session.application_signed_data = "not hex".to_string();
+        assert!(session.verify_attestations(public_key).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_scheme() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        let mut session = signed_session(&signing_key, &["followers: 10"]);
+        assert_eq!(session.scheme, SignatureScheme::EcdsaP256Sha256);
+
+        session.scheme = SignatureScheme::Ed25519;
+        let error = session
+            .verify_attestations(public_key)
+            .expect_err("Expected the scheme to be rejected");
+        assert!(error.to_string().contains("unsupported scheme Ed25519"));
+
+        // Sessions without a recorded scheme are verified as ECDSA over P-256
+        let mut json = serde_json::to_value(&session).unwrap();
+        json.as_object_mut().unwrap().remove("scheme");
+        let session: SignedSession = serde_json::from_value(json).unwrap();
+        assert_eq!(session.scheme, SignatureScheme::EcdsaP256Sha256);
+        assert!(session.verify_attestations(public_key).is_ok());
+    }
+
+    #[test]
+    fn test_signature_of_another_scheme() {
+        use ed25519_dalek::{Signer as _, SigningKey as Ed25519SigningKey};
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        let mut session = signed_session(&signing_key, &["followers: 10"]);
+        let ed25519_key = Ed25519SigningKey::from_bytes(&[1; 32]);
+        session.attestations.insert(
+            "verified: true".to_string(),
+            ed25519_key.sign(b"verified: true").into(),
+        );
+        let error = session
+            .check_scheme(&public_key.into())
+            .expect_err("Expected the signature to be rejected");
+        assert!(error
+            .to_string()
+            .contains("Ed25519 signature in a session signed with EcdsaP256Sha256"));
+    }
+
+    #[test]
+    fn test_ed25519_session() {
+        use ed25519_dalek::{Signer as _, SigningKey as Ed25519SigningKey};
+
+        let signing_key = Ed25519SigningKey::from_bytes(&[1; 32]);
+        let attestations = ["followers: 10", "verified: true"]
+            .iter()
+            .map(|attribute| {
+                let signature: Signature = signing_key.sign(attribute.as_bytes()).into();
+                (attribute.to_string(), signature)
+            })
+            .collect::<HashMap<String, Signature>>();
+        let attestations_signature = signing_key
+            .sign(&SignedSession::attestations_message(
+                &SESSION_HASH,
+                &attestations,
+            ))
+            .into();
+        let session = SignedSession::new(
+            hex::encode(b"application data"),
+            hex::encode(SESSION_HASH),
+            signing_key.sign(b"application data").into(),
+            attestations,
+            attestations_signature,
+        );
+        assert_eq!(session.scheme, SignatureScheme::Ed25519);
+        assert_eq!(session.scheme.algorithm(), crate::signature::ED_DSA);
+
+        let bytes = bincode::serialize(&session).unwrap();
+        let session: SignedSession = bincode::deserialize(&bytes).unwrap();
+        assert!(session
+            .verify_attestations(signing_key.verifying_key())
+            .is_ok());
+
+        // A P-256 key has another scheme
+        let p256_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let error = session
+            .verify_attestations(p256::PublicKey::from(p256_key.verifying_key()))
+            .expect_err("Expected the scheme to be rejected");
+        assert!(error.to_string().contains("unsupported scheme Ed25519"));
+    }
+
+    #[cfg(feature = "eth")]
+    #[test]
+    fn test_secp256k1_keccak_session() {
+        use crate::eip712::keccak256;
+        use k256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey as K256SigningKey};
+
+        let signing_key = K256SigningKey::from_slice(&[1; 32]).unwrap();
+        let sign = |message: &[u8]| -> Signature {
+            let signature: k256::ecdsa::Signature =
+                signing_key.sign_prehash(&keccak256(message)).unwrap();
+            signature.into()
+        };
+        let attestations = HashMap::from([("followers: 10".to_string(), sign(b"followers: 10"))]);
+        let session = SignedSession::new(
+            hex::encode(b"application data"),
+            hex::encode(SESSION_HASH),
+            sign(b"application data"),
+            attestations.clone(),
+            sign(&SignedSession::attestations_message(
+                &SESSION_HASH,
+                &attestations,
+            )),
+        );
+        assert_eq!(session.scheme, SignatureScheme::EcdsaSecp256k1Keccak256);
+        assert_eq!(session.scheme.algorithm(), crate::signature::ES256K_KECCAK);
+
+        let bytes = bincode::serialize(&session).unwrap();
+        let session: SignedSession = bincode::deserialize(&bytes).unwrap();
+        let public_key = k256::PublicKey::from(signing_key.verifying_key());
+        assert!(session.verify_attestations(public_key).is_ok());
+        assert!(session.attestations["followers: 10"]
+            .verify(b"followers: 10", public_key)
+            .is_ok());
+        assert!(session.attestations["followers: 10"]
+            .verify(b"followers: 11", public_key)
+            .is_err());
+
+        // A P-256 key has another scheme
+        let p256_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let error = session
+            .verify_attestations(p256::PublicKey::from(p256_key.verifying_key()))
+            .expect_err("Expected the scheme to be rejected");
+        assert!(error
+            .to_string()
+            .contains("unsupported scheme EcdsaSecp256k1Keccak256"));
+    }
+
+    #[test]
+    fn test_tls_parameters_roundtrip() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let session = signed_session(&signing_key, &["followers: 10"]);
+
+        let bytes = bincode::serialize(&session).unwrap();
+        let session: SignedSession = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(session.tls_version, Some(ProtocolVersion::TLSv1_3));
+        assert_eq!(
+            session.cipher_suite,
+            Some(CipherSuite::TLS13_AES_128_GCM_SHA256)
+        );
+        assert_eq!(session.signed_at, Some(1735613754));
+    }
+
+    #[test]
+    fn test_signature_scheme_roundtrip() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let session = signed_session(&signing_key, &["followers: 10"]);
+        assert_eq!(session.scheme, SignatureScheme::EcdsaP256Sha256);
+        assert_eq!(session.scheme.algorithm(), crate::signature::ES256);
+
+        let bytes = bincode::serialize(&session).unwrap();
+        let decoded: SignedSession = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.scheme, SignatureScheme::EcdsaP256Sha256);
+    }
+
+    #[test]
+    fn test_attribute_attestation_roundtrip() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let public_key = p256::PublicKey::from(signing_key.verifying_key());
+        let session = signed_session(&signing_key, &["followers: 10", "verified: true"]);
+
+        let attestation = AttributeAttestation::from_session(&session, "followers", "notary-1")
+            .expect("attestation not found");
+        assert_eq!(attestation.value, "10");
+        assert_eq!(attestation.session_hash, session.application_signed_data);
+        assert_eq!(attestation.notary_key_id, "notary-1");
+
+        let bytes = bincode::serialize(&attestation).unwrap();
+        let attestation: AttributeAttestation = bincode::deserialize(&bytes).unwrap();
+        assert!(attestation.verify(public_key).is_ok());
+
+        let mut tampered = attestation.clone();
+        tampered.value = "1000".to_string();
+        assert!(tampered.verify(public_key).is_err());
+
+        // The attribute moved to another session
+        let mut tampered = attestation.clone();
+        tampered.session_hash = hex::encode([0; 32]);
+        assert!(tampered.verify(public_key).is_err());
+
+        // The attribute dropped from the attribute set of its session
+        let mut tampered = attestation.clone();
+        tampered
+            .attributes
+            .retain(|attribute| attribute != "followers: 10");
+        assert!(tampered.verify(public_key).is_err());
+
+        assert!(AttributeAttestation::from_session(&session, "karma", "notary-1").is_none());
+    }
+}
@@ -1,6 +1,44 @@
 use serde::{Deserialize, Serialize};
 
-use p256::ecdsa::{signature::Verifier, VerifyingKey};
+use p256::{
+    ecdsa::{signature::Verifier, VerifyingKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+
+/// The JOSE name of ECDSA over NIST P-256 with SHA-256.
+pub const ES256: &str = "ES256";
+
+/// The JOSE name of Ed25519.
+pub const ED_DSA: &str = "EdDSA";
+
+/// The name of ECDSA over secp256k1 with Keccak-256, as Ethereum signs.
+///
+/// This is not a registered JOSE name: `ES256K` hashes with SHA-256.
+pub const ES256K_KECCAK: &str = "ES256K-Keccak";
+
+/// The scheme of a notary signature, which is the curve, hash and encoding it is verified with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[non_exhaustive]
+pub enum SignatureScheme {
+    /// ECDSA over NIST P-256 with SHA-256, encoded as the fixed-size 64 bytes of `r || s`.
+    #[default]
+    EcdsaP256Sha256,
+    /// Ed25519, which hashes with SHA-512, encoded as the 64 bytes of `R || S`.
+    Ed25519,
+    /// ECDSA over secp256k1 with Keccak-256, encoded as the fixed-size 64 bytes of `r || s`.
+    EcdsaSecp256k1Keccak256,
+}
+
+impl SignatureScheme {
+    /// Returns the JOSE name of the algorithm of this scheme.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Self::EcdsaP256Sha256 => ES256,
+            Self::Ed25519 => ED_DSA,
+            Self::EcdsaSecp256k1Keccak256 => ES256K_KECCAK,
+        }
+    }
+}
 
 /// A Notary public key.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -8,6 +46,39 @@ use p256::ecdsa::{signature::Verifier, VerifyingKey};
 pub enum NotaryPublicKey {
     /// A NIST P-256 public key.
     P256(p256::PublicKey),
+    /// An Ed25519 public key.
+    Ed25519(ed25519_dalek::VerifyingKey),
+    /// A secp256k1 public key.
+    #[cfg(feature = "eth")]
+    Secp256k1(k256::PublicKey),
+}
+
+impl NotaryPublicKey {
+    /// Returns the scheme signatures of this key are verified with.
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::P256(_) => SignatureScheme::EcdsaP256Sha256,
+            Self::Ed25519(_) => SignatureScheme::Ed25519,
+            #[cfg(feature = "eth")]
+            Self::Secp256k1(_) => SignatureScheme::EcdsaSecp256k1Keccak256,
+        }
+    }
+
+    /// Returns the JOSE name of the algorithm signatures of this key are verified with.
+    pub fn algorithm(&self) -> &'static str {
+        self.scheme().algorithm()
+    }
+
+    /// Returns the uncompressed SEC1 encoding of this public key, or `None` for an Ed25519 key,
+    /// which has no SEC1 encoding.
+    pub fn to_sec1_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::P256(key) => Some(key.to_encoded_point(false).as_bytes().to_vec()),
+            Self::Ed25519(_) => None,
+            #[cfg(feature = "eth")]
+            Self::Secp256k1(key) => Some(key.to_encoded_point(false).as_bytes().to_vec()),
+        }
+    }
 }
 
 impl From<p256::PublicKey> for NotaryPublicKey {
@@ -16,10 +87,23 @@ impl From<p256::PublicKey> for NotaryPublicKey {
     }
 }
 
+impl From<ed25519_dalek::VerifyingKey> for NotaryPublicKey {
+    fn from(key: ed25519_dalek::VerifyingKey) -> Self {
+        Self::Ed25519(key)
+    }
+}
+
+#[cfg(feature = "eth")]
+impl From<k256::PublicKey> for NotaryPublicKey {
+    fn from(key: k256::PublicKey) -> Self {
+        Self::Secp256k1(key)
+    }
+}
+
 /// An error occurred while verifying a signature.
 #[derive(Debug, thiserror::Error)]
 #[error("signature verification failed: {0}")]
-pub struct SignatureVerifyError(String);
+pub struct SignatureVerifyError(pub(crate) String);
 
 /// A Notary signature.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -27,6 +111,11 @@ pub struct SignatureVerifyError(String);
 pub enum Signature {
     /// A secp256r1 signature.
     P256(p256::ecdsa::Signature),
+    /// An Ed25519 signature.
+    Ed25519(ed25519_dalek::Signature),
+    /// A secp256k1 signature over the Keccak-256 hash of the message.
+    #[cfg(feature = "eth")]
+    Secp256k1(k256::ecdsa::Signature),
 }
 
 impl From<p256::ecdsa::Signature> for Signature {
@@ -35,11 +124,42 @@ impl From<p256::ecdsa::Signature> for Signature {
     }
 }
 
+impl From<ed25519_dalek::Signature> for Signature {
+    fn from(sig: ed25519_dalek::Signature) -> Self {
+        Self::Ed25519(sig)
+    }
+}
+
+#[cfg(feature = "eth")]
+impl From<k256::ecdsa::Signature> for Signature {
+    fn from(sig: k256::ecdsa::Signature) -> Self {
+        Self::Secp256k1(sig)
+    }
+}
+
 impl Signature {
+    /// Returns the JOSE name of the algorithm of this signature.
+    pub fn algorithm(&self) -> &'static str {
+        self.scheme().algorithm()
+    }
+
+    /// Returns the scheme of this signature.
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::P256(_) => SignatureScheme::EcdsaP256Sha256,
+            Self::Ed25519(_) => SignatureScheme::Ed25519,
+            #[cfg(feature = "eth")]
+            Self::Secp256k1(_) => SignatureScheme::EcdsaSecp256k1Keccak256,
+        }
+    }
+
     /// Returns the bytes of this signature.
     pub fn to_bytes(&self) -> Vec<u8> {
         match self {
             Self::P256(sig) => sig.to_vec(),
+            Self::Ed25519(sig) => sig.to_bytes().to_vec(),
+            #[cfg(feature = "eth")]
+            Self::Secp256k1(sig) => sig.to_vec(),
         }
     }
 
@@ -58,6 +178,22 @@ impl Signature {
             (Self::P256(sig), NotaryPublicKey::P256(key)) => VerifyingKey::from(key)
                 .verify(msg, sig)
                 .map_err(|e| SignatureVerifyError(e.to_string())),
+            (Self::Ed25519(sig), NotaryPublicKey::Ed25519(key)) => key
+                .verify_strict(msg, sig)
+                .map_err(|e| SignatureVerifyError(e.to_string())),
+            #[cfg(feature = "eth")]
+            (Self::Secp256k1(sig), NotaryPublicKey::Secp256k1(key)) => {
+                use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+                k256::ecdsa::VerifyingKey::from(key)
+                    .verify_prehash(&crate::eip712::keccak256(msg), sig)
+                    .map_err(|e| SignatureVerifyError(e.to_string()))
+            }
+            (sig, key) => Err(SignatureVerifyError(format!(
+                "{} signature cannot be verified with an {} key",
+                sig.algorithm(),
+                key.algorithm()
+            ))),
         }
     }
 }
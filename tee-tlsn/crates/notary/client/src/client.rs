@@ -35,6 +35,17 @@ pub struct NotarizationRequest {
     /// Maximum number of bytes that can be received.
     #[builder(default = "DEFAULT_MAX_RECV_LIMIT")]
     max_recv_data: usize,
+    /// Url of the request to be notarized, which lets the notary reject unsupported targets
+    /// before the session starts.
+    #[builder(setter(into, strip_option), default)]
+    target_url: Option<String>,
+    /// Id of the provider to be used.
+    #[builder(setter(strip_option), default)]
+    provider_id: Option<u32>,
+    /// Whether the notary streams the session as signed chunks, which the prover receives with
+    /// `Prover::finalize_streaming`.
+    #[builder(default)]
+    stream_session: bool,
 }
 
 impl NotarizationRequest {
@@ -235,6 +246,9 @@ impl NotaryClient {
                     client_type: ClientType::Tcp,
                     max_sent_data: Some(notarization_request.max_sent_data),
                     max_recv_data: Some(notarization_request.max_recv_data),
+                    target_url: notarization_request.target_url.clone(),
+                    provider_id: notarization_request.provider_id,
+                    stream_session: notarization_request.stream_session,
                 })
                 .map_err(|err| {
                     error!("Failed to serialise http request for configuration");
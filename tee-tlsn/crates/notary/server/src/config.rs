@@ -41,6 +41,26 @@ pub struct AuthorizationProperties {
 pub struct NotarizationProperties {
     /// Global limit for maximum transcript size in bytes
     pub max_transcript_size: usize,
+    /// Record the server certificate fingerprint in the signed session
+    #[serde(default)]
+    pub include_server_cert_fingerprint: bool,
+    /// Record the negotiated TLS version and cipher suite in the signed session
+    #[serde(default)]
+    pub include_tls_parameters: bool,
+    /// Record the time of signing in the signed session
+    #[serde(default)]
+    pub include_signed_at: bool,
+    /// Sign the root of the Merkle tree of the attestations in the signed session
+    #[serde(default)]
+    pub include_attributes_root: bool,
+    /// Sign and record the request in its canonical form, with normalized header casing,
+    /// ordering and line endings, so it can be rebuilt independently
+    #[serde(default)]
+    pub canonical_request: bool,
+    /// Maximum number of record bytes read from the prover ahead of processing, the TEE-TLS
+    /// default is used if unset
+    #[serde(default)]
+    pub max_buffered_bytes: Option<usize>,
 }
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -87,4 +107,7 @@ pub struct LoggingProperties {
 pub struct ProviderProperties {
     pub json_path: String,
     pub schema_url: String,
+    /// Compile the provider regexes and check the preprocess scripts at startup
+    #[serde(default)]
+    pub warm_caches: bool,
 }
@@ -1,5 +1,6 @@
+use eyre::{eyre, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Structure of each whitelisted record of the API key whitelist for authorization purpose
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -8,6 +9,34 @@ pub struct AuthorizationWhitelistRecord {
     pub name: String,
     pub api_key: String,
     pub created_at: String,
+    /// Semicolon separated ids of the providers this API key may use, all providers are allowed if empty
+    #[serde(default)]
+    pub allowed_providers: String,
+}
+
+impl AuthorizationWhitelistRecord {
+    /// Parse the allowed provider ids of this record, returns None if the key is not restricted
+    ///
+    /// A malformed id is an error rather than being skipped, as skipping it would silently
+    /// change the set of providers the key may use.
+    pub fn allowed_provider_ids(&self) -> Result<Option<HashSet<u32>>> {
+        if self.allowed_providers.trim().is_empty() {
+            return Ok(None);
+        }
+        self.allowed_providers
+            .split(';')
+            .map(|id| {
+                id.trim().parse().map_err(|_| {
+                    eyre!(
+                        "Invalid provider id '{}' in the allowed providers of '{}'",
+                        id.trim(),
+                        self.name
+                    )
+                })
+            })
+            .collect::<Result<_>>()
+            .map(Some)
+    }
 }
 
 /// Convert whitelist data structure from vector to hashmap using api_key as the key to speed up lookup
@@ -1,11 +1,10 @@
 use crate::{config::NotarizationProperties, domain::auth::AuthorizationWhitelistRecord};
-use p256::ecdsa::SigningKey;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
-use tlsn_verifier::provider::Processor;
+use tlsn_verifier::{provider::Processor, signer::NotarySigner};
 
 /// Response object of the /session API
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +23,18 @@ pub struct NotarizationSessionRequest {
     pub max_sent_data: Option<usize>,
     /// Maximum data that can be received by the prover
     pub max_recv_data: Option<usize>,
+    /// Url of the request the prover intends to notarize, to reject the session early if no
+    /// provider supports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_url: Option<String>,
+    /// Id of the provider the prover intends to use, to reject the session early if it is
+    /// unknown or not allowed for the API key used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<u32>,
+    /// Whether the notary streams the session as one signed chunk per HTTP exchange before the
+    /// signed session, see `Prover::finalize_streaming`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream_session: bool,
 }
 
 /// Request query of the /notarize API
@@ -48,12 +59,17 @@ pub enum ClientType {
 pub struct SessionData {
     pub max_sent_data: Option<usize>,
     pub max_recv_data: Option<usize>,
+    /// Provider ids that the API key of this session is restricted to
+    pub allowed_provider_ids: Option<HashSet<u32>>,
+    /// Whether the session is streamed to the prover as signed chunks
+    pub stream_session: bool,
 }
 
 /// Global data that needs to be shared with the axum handlers
 #[derive(Clone, Debug)]
 pub struct NotaryGlobals {
-    pub notary_signing_key: SigningKey,
+    /// Signer of the notarization results, which may keep the notary key in memory or in a KMS
+    pub notary_signer: Arc<dyn NotarySigner>,
     pub notarization_config: NotarizationProperties,
     /// A temporary storage to store configuration data, mainly used for WebSocket client
     pub store: Arc<Mutex<HashMap<String, SessionData>>>,
@@ -67,14 +83,14 @@ pub struct NotaryGlobals {
 
 impl NotaryGlobals {
     pub fn new(
-        notary_signing_key: SigningKey,
+        notary_signer: Arc<dyn NotarySigner>,
         notarization_config: NotarizationProperties,
         authorization_whitelist: Option<Arc<Mutex<HashMap<String, AuthorizationWhitelistRecord>>>>,
         provider_processor: Processor,
         posthog_key: String,
     ) -> Self {
         Self {
-            notary_signing_key,
+            notary_signer,
             notarization_config,
             store: Default::default(),
             authorization_whitelist,
@@ -82,4 +98,10 @@ impl NotaryGlobals {
             posthog_key,
         }
     }
+
+    /// Returns the hex encoded SEC1 form of the notary public key, as consumed by
+    /// `verify_attestation_signature` in the wasm verifier, or `None` for an Ed25519 key
+    pub fn notary_public_key_hex(&self) -> Option<String> {
+        self.notary_signer.public_key_sec1_hex()
+    }
 }
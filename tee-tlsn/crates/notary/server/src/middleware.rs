@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use axum::http::{header, request::Parts};
 use axum_core::extract::{FromRef, FromRequestParts};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tracing::{error, trace};
 
 use crate::{
@@ -60,13 +60,25 @@ fn api_key_is_valid(
     whitelist.get(api_key).is_some()
 }
 
+/// Helper function to get the provider ids an API key is restricted to, returns None if unrestricted
+pub fn allowed_provider_ids_for_api_key(
+    api_key: &str,
+    whitelist: &HashMap<String, AuthorizationWhitelistRecord>,
+) -> Result<Option<HashSet<u32>>, NotaryServerError> {
+    match whitelist.get(api_key) {
+        Some(record) => Ok(record.allowed_provider_ids()?),
+        None => Ok(None),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{api_key_is_valid, HashMap};
+    use super::{allowed_provider_ids_for_api_key, api_key_is_valid, HashMap};
     use crate::domain::auth::{
         authorization_whitelist_vec_into_hashmap, AuthorizationWhitelistRecord,
     };
     use std::sync::Arc;
+    use tlsn_verifier::tls::VerifierConfig;
 
     fn get_whitelist_fixture() -> HashMap<String, AuthorizationWhitelistRecord> {
         authorization_whitelist_vec_into_hashmap(vec![
@@ -74,16 +86,19 @@ mod test {
                 name: "test-name-0".to_string(),
                 api_key: "test-api-key-0".to_string(),
                 created_at: "2023-10-18T07:38:53Z".to_string(),
+                allowed_providers: "".to_string(),
             },
             AuthorizationWhitelistRecord {
                 name: "test-name-1".to_string(),
                 api_key: "test-api-key-1".to_string(),
                 created_at: "2023-10-11T07:38:53Z".to_string(),
+                allowed_providers: "3;5".to_string(),
             },
             AuthorizationWhitelistRecord {
                 name: "test-name-2".to_string(),
                 api_key: "test-api-key-2".to_string(),
                 created_at: "2022-10-11T07:38:53Z".to_string(),
+                allowed_providers: "".to_string(),
             },
         ])
     }
@@ -99,4 +114,46 @@ mod test {
         let whitelist = get_whitelist_fixture();
         assert!(!api_key_is_valid("test-api-keY-0", &Arc::new(whitelist)));
     }
+
+    #[test]
+    fn test_api_key_disallowed_provider_is_rejected() {
+        let whitelist = get_whitelist_fixture();
+
+        assert!(
+            allowed_provider_ids_for_api_key("test-api-key-0", &whitelist)
+                .unwrap()
+                .is_none()
+        );
+
+        let allowed_provider_ids = allowed_provider_ids_for_api_key("test-api-key-1", &whitelist)
+            .unwrap()
+            .expect("Key should be restricted to a set of providers");
+        let config = VerifierConfig::builder()
+            .id("test")
+            .allowed_provider_ids(allowed_provider_ids)
+            .build()
+            .unwrap();
+        assert!(config.is_provider_allowed(3));
+        assert!(config.is_provider_allowed(5));
+        assert!(!config.is_provider_allowed(4));
+    }
+
+    #[test]
+    fn test_api_key_malformed_provider_id_is_rejected() {
+        let mut whitelist = get_whitelist_fixture();
+        let record = whitelist.get_mut("test-api-key-1").unwrap();
+
+        // A malformed id must not be dropped, which would leave the key with fewer providers
+        for allowed_providers in ["3;x", "3;;5", "3;-5"] {
+            record.allowed_providers = allowed_providers.to_string();
+            assert!(record.allowed_provider_ids().is_err());
+        }
+        assert!(allowed_provider_ids_for_api_key("test-api-key-1", &whitelist).is_err());
+
+        record.allowed_providers = " 3 ; 5 ".to_string();
+        assert_eq!(
+            allowed_provider_ids_for_api_key("test-api-key-1", &whitelist).unwrap(),
+            Some([3, 5].into())
+        );
+    }
 }
@@ -52,7 +52,9 @@ use metrics_process::Collector;
 use prometheus::{register_counter, register_gauge, Counter, Encoder, Gauge, TextEncoder};
 use serde::Deserialize;
 use structopt::lazy_static::lazy_static;
-use tlsn_verifier::util::{get_code_attestation, verify_signature};
+use tlsn_verifier::util::{
+    deployment_user_data, get_code_attestation, verify_signature, DEFAULT_NOTARY_URL,
+};
 
 lazy_static! {
     static ref TOTAL_CONNECTION_COUNTER: Counter =
@@ -132,6 +134,12 @@ pub async fn run_server(config: &NotaryServerProperties) -> Result<(), NotarySer
     .await
     .map_err(|e| eyre!("Failed to initialize provider: {}", e))?;
     debug!("Provider config: {provider_processor:#?}");
+    if config.provider.warm_caches {
+        provider_processor
+            .warm_up()
+            .map_err(|e| eyre!("Failed to warm provider caches: {}", e))?;
+        debug!("Successfully warmed provider caches");
+    }
 
     // Load the authorization whitelist csv if it is turned on
     let authorization_whitelist =
@@ -161,9 +169,17 @@ pub async fn run_server(config: &NotaryServerProperties) -> Result<(), NotarySer
     let public_key_pem = verifying_key
         .to_public_key_pem(LineEnding::LF)
         .expect("Failed to encode public key");
+    // The code attestation binds the notary key and the provider config it runs with
+    let user_data = Arc::new(
+        deployment_user_data(
+            verifying_key.to_encoded_point(false).as_bytes(),
+            &provider_processor.config_hash(),
+        )
+        .map_err(|e| eyre!("Failed to build the attestation user data: {}", e))?,
+    );
 
     let notary_globals = NotaryGlobals::new(
-        notary_signing_key,
+        Arc::new(notary_signing_key),
         config.notarization.clone(),
         authorization_whitelist,
         provider_processor,
@@ -206,7 +222,9 @@ pub async fn run_server(config: &NotaryServerProperties) -> Result<(), NotarySer
                 use tracing::info;
                 let params = req.uri().query().unwrap_or("");
                 let nonce = params.split("=").nth(1).unwrap_or("");
-                let code_attestation = get_code_attestation(nonce.to_string()).await;
+                let code_attestation =
+                    get_code_attestation(DEFAULT_NOTARY_URL, nonce.to_string(), Some(&user_data))
+                        .await;
                 (StatusCode::OK, format!("{code_attestation}")).into_response()
             }),
         )
@@ -384,6 +402,10 @@ fn load_authorization_whitelist(
             &config.authorization.whitelist_csv_path,
         )
         .map_err(|err| eyre!("Failed to parse authorization whitelist csv: {:?}", err))?;
+        // Reject the whole whitelist if a record restricts its key to malformed provider ids
+        for record in &whitelist_csv {
+            record.allowed_provider_ids()?;
+        }
         // Convert the whitelist record into hashmap for faster lookup
         let whitelist_hashmap = authorization_whitelist_vec_into_hashmap(whitelist_csv);
         Some(whitelist_hashmap)
@@ -534,6 +556,7 @@ mod test {
             name: "unit-test-name".to_string(),
             api_key: "unit-test-api-key".to_string(),
             created_at: "unit-test-created-at".to_string(),
+            allowed_providers: "".to_string(),
         };
         let file = OpenOptions::new()
             .append(true)
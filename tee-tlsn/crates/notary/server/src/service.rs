@@ -2,11 +2,13 @@ pub mod axum_websocket;
 pub mod tcp;
 pub mod websocket;
 use crate::{
+    config::NotarizationProperties,
     domain::notary::{
         NotarizationRequestQuery, NotarizationSessionRequest, NotarizationSessionResponse,
         NotaryGlobals, SessionData,
     },
     error::NotaryServerError,
+    middleware::allowed_provider_ids_for_api_key,
     service::{
         axum_websocket::{header_eq, WebSocketUpgrade},
         tcp::{tcp_notarize, TcpUpgrade},
@@ -16,16 +18,17 @@ use crate::{
 use async_trait::async_trait;
 use axum::{
     extract::{rejection::JsonRejection, FromRequestParts, Query, State},
-    http::{header, request::Parts, StatusCode},
+    http::{header, request::Parts, HeaderMap, StatusCode},
     response::{IntoResponse, Json, Response},
 };
 use axum_macros::debug_handler;
-use p256::ecdsa::{Signature, SigningKey};
 use prometheus::{register_histogram, Histogram};
 use structopt::lazy_static::lazy_static;
 
+use std::collections::HashSet;
 use tlsn_verifier::{
-    provider::Processor,
+    provider::{Processor, ProviderError},
+    signer::NotarySigner,
     tls::{Verifier, VerifierConfig},
     util::{log_event, LogEvent},
 };
@@ -95,34 +98,21 @@ pub async fn upgrade_protocol(
 
     // Fetch the configuration data from the store using the session_id
     // This also removes the configuration data from the store as each session_id can only be used once
-    let (max_sent_data, max_recv_data) =
-        match notary_globals.store.lock().unwrap().remove(&session_id) {
-            Some(data) => (data.max_sent_data, data.max_recv_data),
-            None => {
-                let err_msg = format!("Session id {} does not exist", session_id);
-                error!(err_msg);
-                return NotaryServerError::BadProverRequest(err_msg).into_response();
-            }
-        };
+    let session_data = match notary_globals.store.lock().unwrap().remove(&session_id) {
+        Some(data) => data,
+        None => {
+            let err_msg = format!("Session id {} does not exist", session_id);
+            error!(err_msg);
+            return NotaryServerError::BadProverRequest(err_msg).into_response();
+        }
+    };
     // This completes the HTTP Upgrade request and returns a successful response to the client, meanwhile initiating the websocket or tcp connection
     match protocol_upgrade {
         ProtocolUpgrade::Ws(ws) => ws.on_upgrade(move |socket| {
-            websocket_notarize(
-                socket,
-                notary_globals,
-                session_id,
-                max_sent_data,
-                max_recv_data,
-            )
+            websocket_notarize(socket, notary_globals, session_id, session_data)
         }),
         ProtocolUpgrade::Tcp(tcp) => tcp.on_upgrade(move |stream| {
-            tcp_notarize(
-                stream,
-                notary_globals,
-                session_id,
-                max_sent_data,
-                max_recv_data,
-            )
+            tcp_notarize(stream, notary_globals, session_id, session_data)
         }),
     }
 }
@@ -131,6 +121,7 @@ pub async fn upgrade_protocol(
 #[debug_handler(state = NotaryGlobals)]
 pub async fn initialize(
     State(notary_globals): State<NotaryGlobals>,
+    headers: HeaderMap,
     payload: Result<Json<NotarizationSessionRequest>, JsonRejection>,
 ) -> impl IntoResponse {
     info!(
@@ -164,6 +155,43 @@ pub async fn initialize(
         }
     }
 
+    // Restrict the session to the providers allowed for the API key used, if any
+    let allowed_provider_ids = match (
+        &notary_globals.authorization_whitelist,
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| std::str::from_utf8(value.as_bytes()).ok()),
+    ) {
+        (Some(whitelist), Some(api_key)) => {
+            match allowed_provider_ids_for_api_key(api_key, &whitelist.lock().unwrap()) {
+                Ok(allowed_provider_ids) => allowed_provider_ids,
+                Err(err) => {
+                    error!("Failed to read the allowed providers of the API key: {err}");
+                    return err.into_response();
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // Reject the session early if no provider could match the target it declares
+    if let (Some(provider_id), Some(allowed_provider_ids)) =
+        (payload.provider_id, &allowed_provider_ids)
+    {
+        if !allowed_provider_ids.contains(&provider_id) {
+            let err = ProviderError::ProviderNotAllowed(provider_id);
+            error!("Rejected notarization session: {err}");
+            return NotaryServerError::BadProverRequest(err.to_string()).into_response();
+        }
+    }
+    if let Err(err) = notary_globals
+        .provider_processor
+        .check_session_target(payload.target_url.as_deref(), payload.provider_id)
+    {
+        error!("Rejected notarization session: {err}");
+        return NotaryServerError::BadProverRequest(err.to_string()).into_response();
+    }
+
     let prover_session_id = Uuid::new_v4().to_string();
 
     // Store the configuration data in a temporary store
@@ -172,6 +200,8 @@ pub async fn initialize(
         SessionData {
             max_sent_data: payload.max_sent_data,
             max_recv_data: payload.max_recv_data,
+            allowed_provider_ids,
+            stream_session: payload.stream_session,
         },
     );
 
@@ -188,13 +218,17 @@ pub async fn initialize(
 }
 
 /// Run the notarization
+#[allow(clippy::too_many_arguments)]
 pub async fn notary_service<T: AsyncWrite + AsyncRead + Send + Unpin + 'static>(
     socket: T,
-    signing_key: &SigningKey,
+    signer: &dyn NotarySigner,
     provider: &Processor,
     session_id: &str,
     max_sent_data: Option<usize>,
     max_recv_data: Option<usize>,
+    allowed_provider_ids: Option<HashSet<u32>>,
+    stream_session: bool,
+    notarization_config: &NotarizationProperties,
     posthog_key: &str,
 ) -> Result<(), NotaryServerError> {
     debug!(?session_id, "Starting notarization...");
@@ -226,12 +260,27 @@ pub async fn notary_service<T: AsyncWrite + AsyncRead + Send + Unpin + 'static>(
         config_builder = config_builder.max_recv_data(max_recv_data);
     }
 
-    let config = config_builder.build()?;
+    if let Some(allowed_provider_ids) = allowed_provider_ids {
+        config_builder = config_builder.allowed_provider_ids(allowed_provider_ids);
+    }
+
+    if let Some(max_buffered_bytes) = notarization_config.max_buffered_bytes {
+        config_builder = config_builder.max_buffered_bytes(max_buffered_bytes);
+    }
+
+    let config = config_builder
+        .include_server_cert_fingerprint(notarization_config.include_server_cert_fingerprint)
+        .include_tls_parameters(notarization_config.include_tls_parameters)
+        .include_signed_at(notarization_config.include_signed_at)
+        .include_attributes_root(notarization_config.include_attributes_root)
+        .canonical_request(notarization_config.canonical_request)
+        .stream_session(stream_session)
+        .build()?;
 
     Verifier::new(config)
-        .notarize::<_, Signature>(
+        .notarize(
             socket.compat(),
-            signing_key,
+            signer,
             provider,
             session_id.to_string(),
             posthog_key.to_string(),
@@ -240,3 +289,96 @@ pub async fn notary_service<T: AsyncWrite + AsyncRead + Send + Unpin + 'static>(
     timer.stop_and_record();
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::to_bytes;
+    use p256::ecdsa::SigningKey;
+    use rand_core::OsRng;
+    use std::sync::Arc;
+    use tlsn_verifier::provider::Config;
+
+    fn notary_globals() -> NotaryGlobals {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "version": "1.0.0",
+            "EXPECTED_PCRS": {},
+            "PROVIDERS": [{
+                "id": 1,
+                "host": "api.x.com",
+                "urlRegex": r"^https://api\.x\.com/1\.1/account/settings\.json(\?.*)?$",
+                "targetUrl": "https://x.com",
+                "method": "GET",
+                "title": "X account",
+                "description": "Session target test",
+                "icon": "x",
+                "responseType": "json",
+                "attributes": ["{screen_name: screen_name}"]
+            }]
+        }))
+        .unwrap();
+        NotaryGlobals::new(
+            Arc::new(SigningKey::random(&mut OsRng)),
+            NotarizationProperties {
+                max_transcript_size: 1 << 14,
+                ..Default::default()
+            },
+            None,
+            Processor::from_config(config, String::new()),
+            String::new(),
+        )
+    }
+
+    async fn create_session(
+        notary_globals: &NotaryGlobals,
+        target_url: Option<&str>,
+        provider_id: Option<u32>,
+    ) -> (StatusCode, String) {
+        let response = initialize(
+            State(notary_globals.clone()),
+            HeaderMap::new(),
+            Ok(Json(NotarizationSessionRequest {
+                client_type: crate::domain::notary::ClientType::Websocket,
+                max_sent_data: None,
+                max_recv_data: None,
+                target_url: target_url.map(str::to_string),
+                provider_id,
+                stream_session: false,
+            })),
+        )
+        .await
+        .into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_session_for_unsupported_target_is_rejected() {
+        let notary_globals = notary_globals();
+
+        let (status, body) =
+            create_session(&notary_globals, Some("https://example.com/profile"), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            "Invalid request from prover: No provider supports the target url https://example.com/profile"
+        );
+
+        let (status, body) = create_session(&notary_globals, None, Some(2)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Invalid request from prover: No provider has id 2");
+
+        // No session is stored for the rejected requests
+        assert!(notary_globals.store.lock().unwrap().is_empty());
+
+        let (status, _) = create_session(
+            &notary_globals,
+            Some("https://api.x.com/1.1/account/settings.json"),
+            Some(1),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(notary_globals.store.lock().unwrap().len(), 1);
+    }
+}
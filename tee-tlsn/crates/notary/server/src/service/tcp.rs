@@ -10,7 +10,11 @@ use hyper_util::rt::TokioIo;
 use std::future::Future;
 use tracing::{debug, error, info};
 
-use crate::{domain::notary::NotaryGlobals, service::notary_service, NotaryServerError};
+use crate::{
+    domain::notary::{NotaryGlobals, SessionData},
+    service::notary_service,
+    NotaryServerError,
+};
 
 /// Custom extractor used to extract underlying TCP connection for TCP client — using the same upgrade primitives used by
 /// the WebSocket implementation where the underlying TCP connection (wrapped in an Upgraded object) only gets polled as an OnUpgrade future
@@ -83,17 +87,19 @@ pub async fn tcp_notarize(
     stream: TokioIo<Upgraded>,
     notary_globals: NotaryGlobals,
     session_id: String,
-    max_sent_data: Option<usize>,
-    max_recv_data: Option<usize>,
+    session_data: SessionData,
 ) {
     debug!(?session_id, "Upgraded to tcp connection");
     match notary_service(
         stream,
-        &notary_globals.notary_signing_key,
+        notary_globals.notary_signer.as_ref(),
         &notary_globals.provider_processor,
         &session_id,
-        max_sent_data,
-        max_recv_data,
+        session_data.max_sent_data,
+        session_data.max_recv_data,
+        session_data.allowed_provider_ids,
+        session_data.stream_session,
+        &notary_globals.notarization_config,
         &notary_globals.posthog_key,
     )
     .await
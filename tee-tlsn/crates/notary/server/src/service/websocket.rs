@@ -2,7 +2,7 @@ use tracing::{debug, error, info};
 use ws_stream_tungstenite::WsStream;
 
 use crate::{
-    domain::notary::NotaryGlobals,
+    domain::notary::{NotaryGlobals, SessionData},
     service::{axum_websocket::WebSocket, notary_service},
 };
 
@@ -11,19 +11,21 @@ pub async fn websocket_notarize(
     socket: WebSocket,
     notary_globals: NotaryGlobals,
     session_id: String,
-    max_sent_data: Option<usize>,
-    max_recv_data: Option<usize>,
+    session_data: SessionData,
 ) {
     debug!(?session_id, "Upgraded to websocket connection");
     // Wrap the websocket in WsStream so that we have AsyncRead and AsyncWrite implemented
     let stream = WsStream::new(socket.into_inner());
     match notary_service(
         stream,
-        &notary_globals.notary_signing_key,
+        notary_globals.notary_signer.as_ref(),
         &notary_globals.provider_processor,
         &session_id,
-        max_sent_data,
-        max_recv_data,
+        session_data.max_sent_data,
+        session_data.max_recv_data,
+        session_data.allowed_provider_ids,
+        session_data.stream_session,
+        &notary_globals.notarization_config,
         &notary_globals.posthog_key,
     )
     .await
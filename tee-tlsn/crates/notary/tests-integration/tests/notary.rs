@@ -9,6 +9,7 @@ use hyper_util::{
     rt::{TokioExecutor, TokioIo},
 };
 use notary_client::{NotarizationRequest, NotaryClient, NotaryConnection};
+use p256::pkcs8::DecodePublicKey as _;
 use rstest::rstest;
 use rustls::{Certificate, RootCertStore};
 use std::{string::String, time::Duration};
@@ -32,6 +33,7 @@ const NOTARY_HOST: &str = "127.0.0.1";
 const NOTARY_DNS: &str = "tlsnotaryserver.io";
 const NOTARY_CA_CERT_PATH: &str = "../server/fixture/tls/rootCA.crt";
 const NOTARY_CA_CERT_BYTES: &[u8] = include_bytes!("../../server/fixture/tls/rootCA.crt");
+const NOTARY_PUBLIC_KEY_PEM: &str = include_str!("../../server/fixture/notary/notary.pub");
 const API_KEY: &str = "test_api_key_0";
 
 fn get_server_config(port: u16, tls_enabled: bool, auth_enabled: bool) -> NotaryServerProperties {
@@ -44,6 +46,12 @@ fn get_server_config(port: u16, tls_enabled: bool, auth_enabled: bool) -> Notary
         },
         notarization: NotarizationProperties {
             max_transcript_size: 1 << 14,
+            include_server_cert_fingerprint: false,
+            include_tls_parameters: false,
+            include_signed_at: false,
+            include_attributes_root: false,
+            canonical_request: false,
+            max_buffered_bytes: None,
         },
         tls: TLSProperties {
             enabled: tls_enabled,
@@ -88,7 +96,10 @@ async fn setup_config_and_server(
     notary_config
 }
 
-async fn tcp_prover(notary_config: NotaryServerProperties) -> (NotaryConnection, String) {
+async fn tcp_prover(
+    notary_config: NotaryServerProperties,
+    stream_session: bool,
+) -> (NotaryConnection, String) {
     let mut notary_client_builder = NotaryClient::builder();
 
     notary_client_builder
@@ -105,6 +116,7 @@ async fn tcp_prover(notary_config: NotaryServerProperties) -> (NotaryConnection,
     let notarization_request = NotarizationRequest::builder()
         .max_sent_data(MAX_SENT_DATA)
         .max_recv_data(MAX_RECV_DATA)
+        .stream_session(stream_session)
         .build()
         .unwrap();
 
@@ -156,10 +168,10 @@ async fn tls_prover(notary_config: NotaryServerProperties) -> (NotaryConnection,
     tls_prover(setup_config_and_server(100, 7047, true, false).await)
 )]
 #[case::tcp_with_auth(
-    tcp_prover(setup_config_and_server(100, 7048, false, true).await)
+    tcp_prover(setup_config_and_server(100, 7048, false, true).await, false)
 )]
 #[case::tcp_without_auth(
-    tcp_prover(setup_config_and_server(100, 7049, false, false).await)
+    tcp_prover(setup_config_and_server(100, 7049, false, false).await, false)
 )]
 #[awt]
 #[tokio::test]
@@ -244,6 +256,80 @@ async fn test_tcp_prover<S: AsyncWrite + AsyncRead + Send + Unpin + 'static>(
     debug!("Done notarization!");
 }
 
+#[tokio::test]
+async fn test_streaming_prover() {
+    let notary_config = setup_config_and_server(100, 7051, false, false).await;
+    let (notary_socket, session_id) = tcp_prover(notary_config, true).await;
+
+    let mut root_cert_store = tls_core::anchors::RootCertStore::empty();
+    root_cert_store
+        .add(&tls_core::key::Certificate(CA_CERT_DER.to_vec()))
+        .unwrap();
+
+    let prover_config = ProverConfig::builder()
+        .id(session_id)
+        .server_dns(SERVER_DOMAIN)
+        .max_sent_data(MAX_SENT_DATA)
+        .max_recv_data(MAX_RECV_DATA)
+        .root_cert_store(root_cert_store)
+        .build()
+        .unwrap();
+
+    let prover = Prover::new(prover_config)
+        .setup(notary_socket.compat())
+        .await
+        .unwrap();
+
+    let (client_socket, server_socket) = tokio::io::duplex(1 << 16);
+    let server_task = tokio::spawn(bind_test_server_hyper(server_socket.compat()));
+
+    let (tls_connection, prover_fut) = prover.connect(client_socket.compat()).await.unwrap();
+    let prover_task = tokio::spawn(prover_fut);
+
+    let (mut request_sender, connection) =
+        hyper::client::conn::http1::handshake(TokioIo::new(tls_connection.compat()))
+            .await
+            .unwrap();
+
+    tokio::spawn(connection);
+
+    let request = Request::builder()
+        .uri(format!("https://{}/echo", SERVER_DOMAIN))
+        .method("POST")
+        .header("Host", SERVER_DOMAIN)
+        .header("Connection", "close")
+        .body(Full::<Bytes>::new("echo".into()))
+        .unwrap();
+
+    let response = request_sender.send_request(request).await.unwrap();
+
+    assert!(response.status() == StatusCode::OK);
+
+    response.into_body().collect().await.unwrap();
+
+    server_task.await.unwrap().unwrap();
+
+    let prover = prover_task.await.unwrap().unwrap().start_notarize();
+
+    let mut received = 0;
+    let (signed_session, chunks, commitment) = prover
+        .finalize_streaming(|chunk| {
+            assert_eq!(chunk.index, received);
+            received += 1;
+        })
+        .await
+        .unwrap();
+
+    // The single exchange is streamed as one chunk of the whole transcript
+    assert_eq!(received, 1);
+    assert_eq!(chunks[0].application_data, signed_session.application_data);
+
+    let notary_public_key = p256::PublicKey::from_public_key_pem(NOTARY_PUBLIC_KEY_PEM).unwrap();
+    assert!(commitment.verify(&chunks, notary_public_key).is_ok());
+
+    debug!("Done streaming notarization!");
+}
+
 #[tokio::test]
 async fn test_websocket_prover() {
     // Notary server configuration setup
@@ -277,6 +363,9 @@ async fn test_websocket_prover() {
         client_type: notary_server::ClientType::Websocket,
         max_sent_data: Some(MAX_SENT_DATA),
         max_recv_data: Some(MAX_RECV_DATA),
+        target_url: None,
+        provider_id: None,
+        stream_session: false,
     })
     .unwrap();
 
@@ -4,7 +4,9 @@
 
 use super::{state::Notarize, Prover, ProverError};
 use serio::stream::IoStreamExt as _;
-use tlsn_core::msg::SignedSession;
+use tlsn_core::msg::{
+    SessionChunkMessage, SignedSession, SignedSessionChunk, SignedSessionCommitment,
+};
 use tracing::{debug, instrument};
 
 impl Prover<Notarize> {
@@ -35,11 +37,62 @@ impl Prover<Notarize> {
             mux_fut.await?;
         }
 
-        Ok(SignedSession::new(
-            signed_session.application_data,
-            signed_session.application_signed_data,
-            signed_session.signature,
-            signed_session.attestations,
-        ))
+        Ok(signed_session)
+    }
+
+    /// Finalizes a notarization which the notary streams as chunks, one per HTTP exchange,
+    /// calling `on_chunk` with each chunk as it is received.
+    ///
+    /// The notary streams the session if it was requested when the session was negotiated, see
+    /// `NotarizationRequest::stream_session` of the notary client.
+    ///
+    /// Returns the signed session along with the chunks in order and the commitment covering
+    /// them, which is verified with [`SignedSessionCommitment::verify`].
+    #[instrument(parent = &self.span, level = "debug", skip_all, err)]
+    pub async fn finalize_streaming(
+        self,
+        mut on_chunk: impl FnMut(&SignedSessionChunk),
+    ) -> Result<
+        (
+            SignedSession,
+            Vec<SignedSessionChunk>,
+            SignedSessionCommitment,
+        ),
+        ProverError,
+    > {
+        let Notarize {
+            mut io,
+            mux_ctrl,
+            mut mux_fut,
+            ..
+        } = self.state;
+
+        debug!("starting streaming finalization");
+
+        let result = mux_fut
+            .poll_with(async {
+                let mut chunks = Vec::new();
+                loop {
+                    match io.expect_next().await? {
+                        SessionChunkMessage::Chunk(chunk) => {
+                            on_chunk(&chunk);
+                            chunks.push(chunk);
+                        }
+                        SessionChunkMessage::Commitment(commitment) => {
+                            let signed_session: SignedSession = io.expect_next().await?;
+                            return Ok::<_, ProverError>((signed_session, chunks, commitment));
+                        }
+                    }
+                }
+            })
+            .await?;
+
+        // Wait for the notary to correctly close the connection.
+        if !mux_fut.is_complete() {
+            mux_ctrl.mux().close();
+            mux_fut.await?;
+        }
+
+        Ok(result)
     }
 }
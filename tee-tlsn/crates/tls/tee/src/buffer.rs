@@ -0,0 +1,161 @@
+//! A queue of messages bounded by the number of bytes they hold.
+//!
+//! The follower reads messages from the leader ahead of processing them so the connection keeps
+//! flowing while a record is decrypted. Without a bound, a leader which sends faster than the
+//! follower decrypts would grow the queue without limit, so the sender waits until the receiver
+//! has made room.
+
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+/// Creates a queue holding at most `max_bytes` bytes of messages.
+pub(crate) fn bounded<T>(max_bytes: usize) -> (BufferSender<T>, BufferReceiver<T>) {
+    let shared = Arc::new(Mutex::new(Shared {
+        queue: VecDeque::new(),
+        bytes: 0,
+        max_bytes,
+        sender_waker: None,
+        receiver_waker: None,
+        closed: false,
+    }));
+
+    (
+        BufferSender {
+            shared: shared.clone(),
+        },
+        BufferReceiver { shared },
+    )
+}
+
+struct Shared<T> {
+    queue: VecDeque<(T, usize)>,
+    /// The number of bytes held by the queued messages.
+    bytes: usize,
+    max_bytes: usize,
+    sender_waker: Option<Waker>,
+    receiver_waker: Option<Waker>,
+    /// Whether either end has been dropped.
+    closed: bool,
+}
+
+/// The sending end of a bounded buffer.
+pub(crate) struct BufferSender<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> BufferSender<T> {
+    /// Queues a message holding `len` bytes, waiting until the buffer has room for it.
+    ///
+    /// A message larger than the buffer is accepted once the buffer is empty. Returns the
+    /// message if the receiver has been dropped.
+    pub(crate) async fn send(&self, msg: T, len: usize) -> Result<(), T> {
+        let mut msg = Some(msg);
+        poll_fn(|cx| {
+            let mut shared = self.shared.lock().unwrap();
+            if shared.closed {
+                return Poll::Ready(Err(msg.take().expect("message is sent once")));
+            }
+
+            if shared.bytes > 0 && shared.bytes + len > shared.max_bytes {
+                shared.sender_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            shared
+                .queue
+                .push_back((msg.take().expect("message is sent once"), len));
+            shared.bytes += len;
+            if let Some(waker) = shared.receiver_waker.take() {
+                waker.wake();
+            }
+            Poll::Ready(Ok(()))
+        })
+        .await
+    }
+}
+
+impl<T> Drop for BufferSender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.closed = true;
+        if let Some(waker) = shared.receiver_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The receiving end of a bounded buffer.
+pub(crate) struct BufferReceiver<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> BufferReceiver<T> {
+    /// Takes the next message, waiting for one to be queued.
+    ///
+    /// Returns `None` once the sender has been dropped and the buffer is drained.
+    pub(crate) async fn recv(&self) -> Option<T> {
+        poll_fn(|cx| {
+            let mut shared = self.shared.lock().unwrap();
+            match shared.queue.pop_front() {
+                Some((msg, len)) => {
+                    shared.bytes -= len;
+                    if let Some(waker) = shared.sender_waker.take() {
+                        waker.wake();
+                    }
+                    Poll::Ready(Some(msg))
+                }
+                None if shared.closed => Poll::Ready(None),
+                None => {
+                    shared.receiver_waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl<T> Drop for BufferReceiver<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.closed = true;
+        if let Some(waker) = shared.sender_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_sender_waits_when_full() {
+        let (sender, receiver) = bounded::<&str>(10);
+
+        assert_eq!(sender.send("a", 6).now_or_never(), Some(Ok(())));
+        assert_eq!(sender.send("b", 4).now_or_never(), Some(Ok(())));
+
+        // The buffer is full, so the sender waits for the receiver
+        let mut send = Box::pin(sender.send("c", 1));
+        assert!((&mut send).now_or_never().is_none());
+
+        assert_eq!(receiver.recv().now_or_never(), Some(Some("a")));
+        assert_eq!(send.now_or_never(), Some(Ok(())));
+
+        // A message larger than the buffer is accepted once it is empty
+        assert_eq!(receiver.recv().now_or_never(), Some(Some("b")));
+        assert!(sender.send("d", 20).now_or_never().is_none());
+        assert_eq!(receiver.recv().now_or_never(), Some(Some("c")));
+        assert_eq!(sender.send("d", 20).now_or_never(), Some(Ok(())));
+
+        drop(sender);
+        assert_eq!(receiver.recv().now_or_never(), Some(Some("d")));
+        assert_eq!(receiver.recv().now_or_never(), Some(None));
+    }
+}
@@ -5,6 +5,7 @@ static DEFAULT_OPAQUE_RX_TRANSCRIPT_ID: &str = "opaque_rx";
 static DEFAULT_TX_TRANSCRIPT_ID: &str = "tx";
 static DEFAULT_RX_TRANSCRIPT_ID: &str = "rx";
 const DEFAULT_TRANSCRIPT_MAX_SIZE: usize = 1 << 14;
+pub(crate) const DEFAULT_MAX_BUFFERED_BYTES: usize = 1 << 20;
 
 /// Transcript configuration.
 #[derive(Debug, Clone, Builder)]
@@ -82,6 +83,10 @@ pub struct TeeTlsCommonConfig {
     /// Whether the leader commits to the handshake data.
     #[builder(default = "true")]
     handshake_commit: bool,
+    /// The maximum number of record bytes the follower reads from the leader ahead of
+    /// processing.
+    #[builder(default = "DEFAULT_MAX_BUFFERED_BYTES")]
+    max_buffered_bytes: usize,
 }
 
 impl TeeTlsCommonConfig {
@@ -114,6 +119,12 @@ impl TeeTlsCommonConfig {
     pub fn handshake_commit(&self) -> bool {
         self.handshake_commit
     }
+
+    /// Returns the maximum number of record bytes the follower reads from the leader ahead of
+    /// processing.
+    pub fn max_buffered_bytes(&self) -> usize {
+        self.max_buffered_bytes
+    }
 }
 
 /// Configuration for the leader
@@ -8,6 +8,7 @@ use futures::{
 use ludi::{Address, FuturesAddress};
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tls_client::{Backend, RustCryptoBackend, SignatureScheme, SupportedCipherSuite};
 use tls_core::{
     cert::ServerCertDetails,
@@ -16,7 +17,7 @@ use tls_core::{
     msgs::{
         alert::AlertMessagePayload,
         codec::Codec,
-        enums::{AlertDescription, ContentType, ProtocolVersion},
+        enums::{AlertDescription, CipherSuite, ContentType, ProtocolVersion},
         handshake::Random,
         message::{OpaqueMessage, PlainMessage},
     },
@@ -28,6 +29,8 @@ use tls_core::{
 use tracing::{debug, instrument, Instrument};
 
 use crate::{
+    buffer,
+    config::DEFAULT_MAX_BUFFERED_BYTES,
     error::Kind,
     msg::{CloseConnection, Commit, TeeTlsFollowerMsg, TeeTlsMessage},
     TeeTlsChannel, TeeTlsError,
@@ -49,6 +52,14 @@ pub struct TeeTlsFollower {
     close_notify: bool,
     /// Whether the leader has committed to the transcript.
     committed: bool,
+    /// The SHA-256 fingerprint of the server's end-entity certificate.
+    server_cert_fingerprint: Option<[u8; 32]>,
+    /// The negotiated TLS protocol version.
+    protocol_version: Option<ProtocolVersion>,
+    /// The negotiated cipher suite.
+    cipher_suite: Option<CipherSuite>,
+    /// The maximum number of record bytes read from the leader ahead of processing.
+    max_buffered_bytes: usize,
 }
 
 /// Data collected by the TEE-TLS follower
@@ -58,6 +69,33 @@ pub struct TeeTlsFollowerData {
     pub response_data: String,
     /// The recorded request data.
     pub request_data: String,
+    /// The SHA-256 fingerprint of the server's end-entity certificate, if one was received.
+    pub server_cert_fingerprint: Option<[u8; 32]>,
+    /// The negotiated TLS protocol version, if the handshake reached it.
+    pub protocol_version: Option<ProtocolVersion>,
+    /// The negotiated cipher suite, if the handshake reached it.
+    pub cipher_suite: Option<CipherSuite>,
+}
+
+/// Returns the number of record bytes a message from the leader holds in the buffer.
+fn buffered_len(msg: &TeeTlsMessage) -> usize {
+    match msg {
+        TeeTlsMessage::Decrypt(Decrypt { opq, .. }) => {
+            opq.as_ref().map_or(0, |opq| opq.payload.0.len())
+        }
+        TeeTlsMessage::Encrypt(Encrypt { msg, .. }) => {
+            msg.as_ref().map_or(0, |msg| msg.payload.0.len())
+        }
+        _ => 0,
+    }
+}
+
+/// Returns the SHA-256 fingerprint of the server's end-entity certificate.
+pub fn server_cert_fingerprint(cert_details: &ServerCertDetails) -> Option<[u8; 32]> {
+    cert_details
+        .cert_chain()
+        .first()
+        .map(|cert| Sha256::digest(&cert.0).into())
 }
 
 impl ludi::Actor for TeeTlsFollower {
@@ -74,6 +112,9 @@ impl ludi::Actor for TeeTlsFollower {
         Ok(TeeTlsFollowerData {
             response_data,
             request_data,
+            server_cert_fingerprint: self.server_cert_fingerprint,
+            protocol_version: self.protocol_version,
+            cipher_suite: self.cipher_suite,
         })
     }
 }
@@ -94,9 +135,22 @@ impl TeeTlsFollower {
             stream: Some(stream),
             close_notify: false,
             committed: false,
+            server_cert_fingerprint: None,
+            protocol_version: None,
+            cipher_suite: None,
+            max_buffered_bytes: DEFAULT_MAX_BUFFERED_BYTES,
         }
     }
 
+    /// Sets the maximum number of record bytes read from the leader ahead of processing.
+    ///
+    /// Once this many bytes are buffered, the follower stops reading from the leader until the
+    /// buffered messages have been processed.
+    pub fn with_max_buffered_bytes(mut self, max_buffered_bytes: usize) -> Self {
+        self.max_buffered_bytes = max_buffered_bytes;
+        self
+    }
+
     /// Performs any one-time setup operations.
     #[instrument(level = "trace", skip_all, err)]
     pub async fn setup(&mut self) -> Result<(), TeeTlsError> {
@@ -126,12 +180,32 @@ impl TeeTlsFollower {
             .take()
             .expect("stream should be present from constructor");
 
-        let mut remote_fut = Box::pin(async move {
+        let (buffer_tx, buffer_rx) = buffer::bounded(self.max_buffered_bytes);
+
+        let read_fut = async move {
             while let Some(msg) = stream.next().await {
-                let msg = TeeTlsFollowerMsg::try_from(msg?)?;
+                let msg = msg?;
+                let len = buffered_len(&msg);
+                let msg = TeeTlsFollowerMsg::try_from(msg)?;
+                if buffer_tx.send(msg, len).await.is_err() {
+                    break;
+                }
+            }
+
+            Ok::<_, TeeTlsError>(())
+        };
+
+        let forward_fut = async move {
+            while let Some(msg) = buffer_rx.recv().await {
                 addr.send_await(msg).await?;
             }
 
+            Ok::<_, TeeTlsError>(())
+        };
+
+        let mut remote_fut = Box::pin(async move {
+            futures::try_join!(read_fut, forward_fut)?;
+
             Ok::<_, TeeTlsError>(())
         })
         .fuse();
@@ -238,6 +312,7 @@ impl TeeTlsFollower {
         cert_details: ServerCertDetails,
     ) -> Result<(), TeeTlsError> {
         debug!("Follower setting the server cert details");
+        self.server_cert_fingerprint = server_cert_fingerprint(&cert_details);
         self.rcb
             .set_server_cert_details(cert_details.clone())
             .await
@@ -299,6 +374,7 @@ impl TeeTlsFollower {
             }
         };
         debug!("Follower setting the cipher suite to {:?}", scs);
+        self.cipher_suite = Some(scs.suite());
         self.rcb.set_cipher_suite(scs).await.map_err(|e| {
             TeeTlsError::new(Kind::Other, format!("Failed to set cipher suite: {:?}", e))
         })?;
@@ -309,6 +385,7 @@ impl TeeTlsFollower {
     #[instrument(level = "trace", skip_all, err)]
     async fn set_protocol_version(&mut self, version: ProtocolVersion) -> Result<(), TeeTlsError> {
         debug!("Follower setting the protocol version to {:?}", version);
+        self.protocol_version = Some(version);
 
         self.rcb.set_protocol_version(version).await.map_err(|e| {
             TeeTlsError::new(
@@ -661,3 +738,30 @@ mod state {
 }
 
 use state::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tls_core::key::Certificate;
+    use tls_server_fixture::SERVER_CERT_DER;
+
+    #[test]
+    fn test_server_cert_fingerprint() {
+        let cert_details =
+            ServerCertDetails::new(vec![Certificate(SERVER_CERT_DER.to_vec())], vec![], None);
+
+        let fingerprint = server_cert_fingerprint(&cert_details).unwrap();
+        assert_eq!(
+            fingerprint,
+            [
+                0xe2, 0xc0, 0xd4, 0xa7, 0xd1, 0xfa, 0x58, 0xe4, 0xc1, 0xb6, 0xa6, 0xb1, 0x45, 0xbe,
+                0x81, 0x95, 0x27, 0x54, 0x39, 0x3a, 0x48, 0x08, 0x8e, 0x1d, 0xb1, 0x59, 0xd7, 0x11,
+                0x78, 0x51, 0xe4, 0xbe
+            ]
+        );
+        assert_eq!(server_cert_fingerprint(&cert_details), Some(fingerprint));
+
+        let empty = ServerCertDetails::new(vec![], vec![], None);
+        assert_eq!(server_cert_fingerprint(&empty), None);
+    }
+}
@@ -7,6 +7,7 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
+mod buffer;
 mod config;
 pub(crate) mod error;
 pub(crate) mod follower;
@@ -20,7 +21,7 @@ pub use config::{
     TeeTranscriptConfig, TeeTranscriptConfigBuilder, TeeTranscriptConfigBuilderError,
 };
 pub use error::TeeTlsError;
-pub use follower::{TeeFollowerCtrl, TeeTlsFollower, TeeTlsFollowerData};
+pub use follower::{server_cert_fingerprint, TeeFollowerCtrl, TeeTlsFollower, TeeTlsFollowerData};
 pub use leader::{TeeLeaderCtrl, TeeTlsLeader, TeeTlsLeaderData};
 use utils_aio::duplex::Duplex;
 
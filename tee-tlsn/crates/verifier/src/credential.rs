@@ -0,0 +1,71 @@
+//! This module converts signed sessions into W3C Verifiable Credentials.
+
+use serde_json::Value;
+use tlsn_core::msg::SignedSession;
+
+/// Converts the attestations of a signed session into a W3C Verifiable Credential
+///
+/// See [`SignedSession::to_verifiable_credential`].
+///
+/// # Arguments
+///
+/// * `session` - The signed session holding the attestations.
+/// * `issuer` - The identifier of the notary, e.g. a DID.
+pub fn to_verifiable_credential(session: &SignedSession, issuer: &str) -> Value {
+    session.to_verifiable_credential(issuer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+    use tlsn_core::credential::CREDENTIAL_TYPE;
+
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_credential_subject() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let attestations = ["followers: 10", "verified: true"]
+            .iter()
+            .map(|attestation| {
+                let signature: Signature = signing_key.sign(attestation.as_bytes());
+                (attestation.to_string(), signature.into())
+            })
+            .collect::<HashMap<_, tlsn_core::Signature>>();
+        let attestations_signature: Signature = signing_key.sign(
+            &SignedSession::attestations_message(&[7; 32], &attestations),
+        );
+        let session = SignedSession::new(
+            String::new(),
+            hex::encode([7; 32]),
+            attestations_signature.into(),
+            attestations,
+            attestations_signature.into(),
+        );
+
+        let credential = to_verifiable_credential(&session, "did:example:notary");
+        assert_eq!(credential["issuer"], "did:example:notary");
+        assert_eq!(
+            credential["type"],
+            json!(["VerifiableCredential", CREDENTIAL_TYPE])
+        );
+        assert_eq!(
+            credential["credentialSubject"],
+            json!({"followers": "10", "verified": "true"})
+        );
+        assert_eq!(
+            credential["proof"]["proofValue"],
+            hex::encode(attestations_signature.to_vec())
+        );
+        assert_eq!(
+            credential["proof"]["attestationSignatures"]
+                .as_object()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,505 @@
+//! This module extracts the text of CSS selectors from an HTML response as it streams in.
+//!
+//! The tokenizer keeps only the bytes of the token it is in and the text of the elements being
+//! captured, so a large page is not buffered whole, and it stops reading once every selector
+//! has matched.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use crate::provider::ProviderError;
+
+/// Elements which have no content and no end tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose content is text up to their end tag rather than markup
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// A simple CSS selector: an optional tag name, id and classes, e.g. `h1`, `#followers`,
+/// `span.count` or `div#profile.card`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlSelector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl HtmlSelector {
+    /// Parse a simple selector
+    ///
+    /// Combinators, attribute selectors and pseudo-classes are not supported.
+    pub fn parse(selector: &str) -> Result<Self, ProviderError> {
+        let invalid =
+            |reason: &str| ProviderError::InvalidSelector(selector.to_string(), reason.to_string());
+        let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+
+        let mut parsed = Self {
+            tag: None,
+            id: None,
+            classes: Vec::new(),
+        };
+        let mut rest = selector.trim();
+        if rest.is_empty() {
+            return Err(invalid("empty selector"));
+        }
+        while !rest.is_empty() {
+            let (marker, body) = match rest.chars().next() {
+                Some(marker @ ('#' | '.')) => (Some(marker), &rest[1..]),
+                _ => (None, rest),
+            };
+            let end = body.find(|c| !is_name_char(c)).unwrap_or(body.len());
+            if end == 0 {
+                return Err(invalid("only tag, id and class selectors are supported"));
+            }
+            let name = body[..end].to_string();
+            match marker {
+                Some('#') if parsed.id.is_none() => parsed.id = Some(name),
+                Some('#') => return Err(invalid("more than one id")),
+                Some(_) => parsed.classes.push(name),
+                None if parsed.tag.is_none() && rest.len() == selector.trim().len() => {
+                    parsed.tag = Some(name.to_ascii_lowercase())
+                }
+                None => return Err(invalid("the tag name must come first")),
+            }
+            rest = &body[end..];
+        }
+        Ok(parsed)
+    }
+
+    fn matches(&self, tag: &StartTag) -> bool {
+        self.tag.as_ref().map_or(true, |name| *name == tag.name)
+            && self
+                .id
+                .as_ref()
+                .map_or(true, |id| tag.id.as_deref() == Some(id.as_str()))
+            && self
+                .classes
+                .iter()
+                .all(|class| tag.classes.iter().any(|c| c == class))
+    }
+}
+
+/// A start tag with the attributes selectors match against
+#[derive(Debug)]
+struct StartTag {
+    name: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    self_closing: bool,
+}
+
+/// The element of a selector whose text is being captured
+#[derive(Debug)]
+struct Capture {
+    key: String,
+    depth: usize,
+    text: String,
+}
+
+/// What the tokenizer is in the middle of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    Tag,
+    Comment,
+    RawText,
+}
+
+/// HtmlExtractor extracts the text of the first element matching each of a set of selectors
+/// from an HTML document fed to it in chunks
+///
+/// Feeding stops being needed as soon as every selector has matched and its element has ended,
+/// see [`HtmlExtractor::is_complete`]; the remaining chunks are ignored. The elements are
+/// expected to be closed in order, as the end tags are not matched by name, and the `script`
+/// and `style` elements never match, as their content is not text.
+#[derive(Debug)]
+pub struct HtmlExtractor {
+    selectors: Vec<(String, HtmlSelector)>,
+    found: HashMap<String, String>,
+    captures: Vec<Capture>,
+    state: State,
+    raw_text_end: String,
+    pending: Vec<u8>,
+    bytes_read: usize,
+}
+
+impl HtmlExtractor {
+    /// Create an extractor of the selectors, by the key their text is returned under
+    pub fn new(selectors: &BTreeMap<String, String>) -> Result<Self, ProviderError> {
+        Ok(Self {
+            selectors: selectors
+                .iter()
+                .map(|(key, selector)| Ok((key.clone(), HtmlSelector::parse(selector)?)))
+                .collect::<Result<_, ProviderError>>()?,
+            found: HashMap::new(),
+            captures: Vec::new(),
+            state: State::Text,
+            raw_text_end: String::new(),
+            pending: Vec::new(),
+            bytes_read: 0,
+        })
+    }
+
+    /// Returns whether every selector has been extracted
+    pub fn is_complete(&self) -> bool {
+        self.found.len() == self.selectors.len()
+    }
+
+    /// Returns the number of bytes of the document which were read before the extraction
+    /// completed
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Feed the next chunk of the document, returning whether every selector has been extracted
+    pub fn feed(&mut self, chunk: &[u8]) -> bool {
+        for &byte in chunk {
+            if self.is_complete() {
+                break;
+            }
+            self.bytes_read += 1;
+            self.step(byte);
+        }
+        self.is_complete()
+    }
+
+    /// Returns the extracted text of each selector which matched, by key, as a JSON object
+    ///
+    /// An element which is still open at the end of the document is extracted with the text
+    /// read so far.
+    pub fn finish(mut self) -> Value {
+        if self.state == State::Text {
+            self.flush_text();
+        }
+        for capture in std::mem::take(&mut self.captures) {
+            self.found
+                .entry(capture.key)
+                .or_insert_with(|| collapse_whitespace(&capture.text));
+        }
+        Value::Object(
+            self.found
+                .into_iter()
+                .map(|(key, text)| (key, Value::String(text)))
+                .collect(),
+        )
+    }
+
+    fn step(&mut self, byte: u8) {
+        if self.state == State::Text && byte == b'<' {
+            self.flush_text();
+            self.state = State::Tag;
+        }
+        // Text outside of the captured elements is not kept
+        if self.state != State::Text || !self.captures.is_empty() {
+            self.pending.push(byte);
+        }
+
+        match self.state {
+            // A `<` which does not start a tag, e.g. in `a < b`, is text
+            State::Tag
+                if self.pending.len() == 2
+                    && !(byte.is_ascii_alphabetic() || matches!(byte, b'/' | b'!' | b'?')) =>
+            {
+                self.state = State::Text;
+                if self.captures.is_empty() {
+                    self.pending.clear();
+                }
+            }
+            State::Tag if self.pending == b"<!--" => self.state = State::Comment,
+            State::Tag if byte == b'>' => {
+                let token = std::mem::take(&mut self.pending);
+                self.state = State::Text;
+                self.tag(&String::from_utf8_lossy(&token[1..token.len() - 1]));
+            }
+            State::Comment if self.pending.ends_with(b"-->") => {
+                self.pending.clear();
+                self.state = State::Text;
+            }
+            State::RawText if byte == b'>' => {
+                let end = self.pending.len().saturating_sub(self.raw_text_end.len());
+                if self.pending[end..].eq_ignore_ascii_case(self.raw_text_end.as_bytes()) {
+                    self.pending.clear();
+                    self.state = State::Text;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn flush_text(&mut self) {
+        let text = std::mem::take(&mut self.pending);
+        if self.captures.is_empty() || text.is_empty() {
+            return;
+        }
+        let text = decode_entities(&String::from_utf8_lossy(&text));
+        for capture in &mut self.captures {
+            capture.text.push_str(&text);
+        }
+    }
+
+    fn tag(&mut self, token: &str) {
+        if token.starts_with('!') || token.starts_with('?') {
+            // A doctype or processing instruction
+            return;
+        }
+        if token.starts_with('/') {
+            self.end_tag();
+            return;
+        }
+
+        let tag = parse_start_tag(token);
+        if RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) && !tag.self_closing {
+            // The content of a raw text element is not text of the element it is in
+            self.raw_text_end = format!("</{}>", tag.name);
+            self.state = State::RawText;
+            return;
+        }
+        let is_void = tag.self_closing || VOID_ELEMENTS.contains(&tag.name.as_str());
+        if !is_void {
+            for capture in &mut self.captures {
+                capture.depth += 1;
+            }
+        }
+        for (key, selector) in &self.selectors {
+            let capturing = self.captures.iter().any(|capture| capture.key == *key);
+            if self.found.contains_key(key) || capturing || !selector.matches(&tag) {
+                continue;
+            }
+            if is_void {
+                self.found.insert(key.clone(), String::new());
+            } else {
+                self.captures.push(Capture {
+                    key: key.clone(),
+                    depth: 1,
+                    text: String::new(),
+                });
+            }
+        }
+    }
+
+    fn end_tag(&mut self) {
+        let mut index = 0;
+        while index < self.captures.len() {
+            let capture = &mut self.captures[index];
+            capture.depth -= 1;
+            if capture.depth == 0 {
+                let capture = self.captures.remove(index);
+                self.found
+                    .insert(capture.key, collapse_whitespace(&capture.text));
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+/// Parse the name, id and classes of a start tag, given its content between `<` and `>`
+fn parse_start_tag(token: &str) -> StartTag {
+    let self_closing = token.ends_with('/');
+    let token = token.trim_end_matches('/');
+    let name_end = token
+        .find(|c: char| c.is_ascii_whitespace())
+        .unwrap_or(token.len());
+    let mut tag = StartTag {
+        name: token[..name_end].to_ascii_lowercase(),
+        id: None,
+        classes: Vec::new(),
+        self_closing,
+    };
+
+    let mut rest = &token[name_end..];
+    loop {
+        rest = rest.trim_start();
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            break;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, remaining) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value
+                            .find(|c: char| c.is_ascii_whitespace())
+                            .unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = remaining;
+                decode_entities(value)
+            }
+            None => String::new(),
+        };
+        match name.as_str() {
+            "id" => tag.id = Some(value),
+            "class" => tag.classes = value.split_ascii_whitespace().map(String::from).collect(),
+            _ => {}
+        }
+    }
+    tag
+}
+
+/// Decode the character references HTML text commonly uses
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = reference.and_then(|reference| match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => reference
+                .strip_prefix("#x")
+                .or_else(|| reference.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| reference.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        });
+        match (reference, character) {
+            (Some(reference), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Trim the text of an element and collapse its runs of whitespace into single spaces, as
+/// they render
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn selectors(selectors: &[(&str, &str)]) -> BTreeMap<String, String> {
+        selectors
+            .iter()
+            .map(|(key, selector)| (key.to_string(), selector.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        assert_eq!(
+            HtmlSelector::parse("DIV#profile.card.dark").unwrap(),
+            HtmlSelector {
+                tag: Some("div".to_string()),
+                id: Some("profile".to_string()),
+                classes: vec!["card".to_string(), "dark".to_string()],
+            }
+        );
+        for selector in [
+            "",
+            "div span",
+            "a[href]",
+            "li:first-child",
+            "#a#b",
+            ".card div",
+        ] {
+            assert!(
+                matches!(
+                    HtmlSelector::parse(selector),
+                    Err(ProviderError::InvalidSelector(..))
+                ),
+                "{selector}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_selectors() {
+        let mut extractor = HtmlExtractor::new(&selectors(&[
+            ("followers", "#followers"),
+            ("name", "span.name"),
+            ("bio", "p.bio"),
+            ("avatar", "img.avatar"),
+            ("missing", "#missing"),
+        ]))
+        .unwrap();
+        let document = r#"<!DOCTYPE html><html><head><script>if (a < b) { x = "<p class='bio'>fake</p>"; }</script>
+            <!-- <h1 id="followers">0</h1> --></head>
+            <body><h1 id=followers data-x='1'>1,024</h1>
+            <span class="user name">Ada &amp; <b>Bob</b></span>
+            <p class="bio">Builds
+                things &#x1F680;<br></p><IMG class="avatar" src="/a.png"/></body></html>"#;
+        for chunk in document.as_bytes().chunks(7) {
+            assert!(!extractor.feed(chunk));
+        }
+        assert_eq!(
+            extractor.finish(),
+            json!({
+                "followers": "1,024",
+                "name": "Ada & Bob",
+                "bio": "Builds things \u{1F680}",
+                "avatar": "",
+            })
+        );
+    }
+
+    #[test]
+    fn test_extraction_stops_early() {
+        let mut extractor = HtmlExtractor::new(&selectors(&[
+            ("followers", "#followers"),
+            ("karma", "#karma"),
+        ]))
+        .unwrap();
+        let head = r#"<html><body><h1 id="followers">94</h1><div id="karma"><b>47</b></div>"#;
+        let document = [head, &"<p>filler</p>".repeat(100_000), "</body></html>"].concat();
+
+        let mut chunks = document.as_bytes().chunks(16);
+        let mut fed = 0;
+        for chunk in chunks.by_ref() {
+            fed += 1;
+            if extractor.feed(chunk) {
+                break;
+            }
+        }
+        assert!(extractor.is_complete());
+        assert_eq!(extractor.bytes_read(), head.len());
+        assert_eq!(fed, head.len().div_ceil(16));
+        assert!(chunks.next().is_some());
+        // Chunks fed after the extraction completed are not read
+        assert!(extractor.feed(b"<h1 id=\"followers\">0</h1>"));
+        assert_eq!(extractor.bytes_read(), head.len());
+        assert_eq!(
+            extractor.finish(),
+            json!({"followers": "94", "karma": "47"})
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! This module contains helpers for parsing the HTTP transcript of a TLS session.
+
+use std::io::Read;
+
+use httparse::{Request, Response, Status};
+use tlsn_core::msg::SignedSession;
+
+use crate::tls::VerifierError;
+
+/// Maximum number of headers parsed from an HTTP response
+const MAX_HEADERS: usize = 64;
+
+/// Headers whose values are replaced when previewing a transcript
+const SENSITIVE_HEADERS: [&str; 7] = [
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+    "x-api-key",
+    "x-auth-token",
+    "x-csrf-token",
+];
+
+/// The value which replaces a redacted header value
+const REDACTED: &str = "[REDACTED]";
+
+/// Headers is the parsed head of an HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Headers {
+    /// The status code of the response
+    pub code: Option<u16>,
+    /// The header names and values, in the order they were received
+    pub headers: Vec<(String, String)>,
+}
+
+impl Headers {
+    /// Returns the value of the first header with the given name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns whether the body has a chunked `Transfer-Encoding`
+    pub fn is_chunked(&self) -> bool {
+        self.get("transfer-encoding")
+            .is_some_and(|encoding| encoding.trim().to_ascii_lowercase().ends_with("chunked"))
+    }
+}
+
+/// Split a raw HTTP response into its headers and body
+///
+/// Returns an error if the response is malformed, or [`VerifierError::IncompleteResponseHead`] if
+/// the head of the response is incomplete.
+pub fn split_http_body(response_bytes: &[u8]) -> Result<(Headers, &[u8]), VerifierError> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut response = Response::new(&mut headers);
+
+    match response
+        .parse(response_bytes)
+        .map_err(|e| VerifierError::HttpParseError(e.to_string()))?
+    {
+        Status::Complete(size) => Ok((
+            Headers {
+                code: response.code,
+                headers: response
+                    .headers
+                    .iter()
+                    .map(|header| {
+                        (
+                            header.name.to_string(),
+                            String::from_utf8_lossy(header.value).to_string(),
+                        )
+                    })
+                    .collect(),
+            },
+            &response_bytes[size..],
+        )),
+        Status::Partial => Err(VerifierError::IncompleteResponseHead),
+    }
+}
+
+/// Re-encode a raw HTTP request in canonical form, so parties which rebuild the request
+/// independently produce the same bytes
+///
+/// The canonical form is:
+/// - the request line as `METHOD SP target SP HTTP/1.x CRLF`,
+/// - each header as `name: value CRLF`, with the name lowercased and the value stripped of
+///   surrounding whitespace, sorted by name and, for repeated headers, in the order received,
+/// - an empty line `CRLF`, followed by the body bytes as is.
+///
+/// Line endings of the head may be `CRLF` or a bare `LF`. Returns an error if the request is
+/// malformed or its head is incomplete.
+pub fn canonical_request(request_bytes: &[u8]) -> Result<Vec<u8>, VerifierError> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut request = Request::new(&mut headers);
+
+    let size = match request
+        .parse(request_bytes)
+        .map_err(|e| VerifierError::HttpParseError(e.to_string()))?
+    {
+        Status::Complete(size) => size,
+        Status::Partial => {
+            return Err(VerifierError::HttpParseError(
+                "incomplete HTTP request head".to_string(),
+            ))
+        }
+    };
+
+    let mut canonical = format!(
+        "{} {} HTTP/1.{}\r\n",
+        request.method.unwrap_or_default(),
+        request.path.unwrap_or_default(),
+        request.version.unwrap_or(1)
+    )
+    .into_bytes();
+    let mut headers = request
+        .headers
+        .iter()
+        .map(|header| (header.name.to_ascii_lowercase(), header.value.trim_ascii()))
+        .collect::<Vec<_>>();
+    // A stable sort keeps repeated headers in the order they were received
+    headers.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, value) in headers {
+        canonical.extend_from_slice(name.as_bytes());
+        canonical.extend_from_slice(b": ");
+        canonical.extend_from_slice(value);
+        canonical.extend_from_slice(b"\r\n");
+    }
+    canonical.extend_from_slice(b"\r\n");
+    canonical.extend_from_slice(&request_bytes[size..]);
+    Ok(canonical)
+}
+
+/// Split the transcript of a connection which carried several HTTP exchanges, e.g. the pages of
+/// a paginated API over a kept-alive connection, into one transcript per exchange
+///
+/// Each transcript is a request followed by its response as received. Messages are delimited by
+/// their `Content-Length` or chunked `Transfer-Encoding`. A request without either has no body
+/// and a response without either extends to the end of the transcript.
+///
+/// Returns an error if a message is malformed or the requests and responses do not pair up.
+pub fn split_http_exchanges(
+    request_bytes: &[u8],
+    response_bytes: &[u8],
+) -> Result<Vec<Vec<u8>>, VerifierError> {
+    let requests = split_http_messages(request_bytes, true)?;
+    let responses = split_http_messages(response_bytes, false)?;
+    if requests.len() != responses.len() {
+        return Err(VerifierError::HttpParseError(format!(
+            "{} requests were answered by {} responses",
+            requests.len(),
+            responses.len()
+        )));
+    }
+
+    Ok(requests
+        .into_iter()
+        .zip(responses)
+        .map(|(request, response)| [request, response].concat())
+        .collect())
+}
+
+/// Split consecutive HTTP requests or responses into the bytes of each message
+fn split_http_messages(mut bytes: &[u8], is_request: bool) -> Result<Vec<&[u8]>, VerifierError> {
+    let mut messages = Vec::new();
+    while !bytes.is_empty() {
+        let len = http_message_len(bytes, is_request)?;
+        messages.push(&bytes[..len]);
+        bytes = &bytes[len..];
+    }
+    Ok(messages)
+}
+
+/// Returns the length of the HTTP message at the start of `bytes`, capped at the length of
+/// `bytes` if the message is truncated
+fn http_message_len(bytes: &[u8], is_request: bool) -> Result<usize, VerifierError> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let (status, code) = if is_request {
+        let mut request = Request::new(&mut headers);
+        let status = request.parse(bytes);
+        (status, None)
+    } else {
+        let mut response = Response::new(&mut headers);
+        let status = response.parse(bytes);
+        (status, response.code)
+    };
+    let head_len = match status.map_err(|e| VerifierError::HttpParseError(e.to_string()))? {
+        Status::Complete(size) => size,
+        Status::Partial => return Ok(bytes.len()),
+    };
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| {
+                String::from_utf8_lossy(header.value)
+                    .trim()
+                    .to_ascii_lowercase()
+            })
+    };
+    // Informational, `204 No Content` and `304 Not Modified` responses have no body
+    if matches!(code, Some(100..=199 | 204 | 304)) {
+        return Ok(head_len);
+    }
+    if header("transfer-encoding").is_some_and(|encoding| encoding.ends_with("chunked")) {
+        return Ok(head_len + chunked_body_len(&bytes[head_len..]));
+    }
+    if let Some(content_length) = header("content-length") {
+        let content_length = content_length
+            .parse::<usize>()
+            .map_err(|e| VerifierError::HttpParseError(format!("invalid content length: {e}")))?;
+        return Ok(bytes.len().min(head_len + content_length));
+    }
+    Ok(if is_request { head_len } else { bytes.len() })
+}
+
+/// Returns the length of the chunked body at the start of `body`, including the last chunk and
+/// the trailers, or the length of `body` if it is truncated
+fn chunked_body_len(body: &[u8]) -> usize {
+    let mut pos = 0;
+    loop {
+        let Some((data_start, size)) = chunk_at(body, pos) else {
+            return body.len();
+        };
+        pos = data_start;
+        if size == 0 {
+            break;
+        }
+        pos = pos.saturating_add(size).saturating_add(2);
+        if pos >= body.len() {
+            return body.len();
+        }
+    }
+
+    // Skip the trailers up to the empty line closing the body
+    loop {
+        match line_end(body, pos) {
+            Some(end) if end == pos + 2 => return end,
+            Some(end) => pos = end,
+            None => return body.len(),
+        }
+    }
+}
+
+/// Returns the data of a chunked body, without the chunk sizes and the trailers
+fn dechunk_body(body: &[u8]) -> Result<Vec<u8>, VerifierError> {
+    let mut data = Vec::new();
+    let mut pos = 0;
+    loop {
+        let (data_start, size) = chunk_at(body, pos)
+            .ok_or_else(|| VerifierError::BodyDecodeError("invalid chunk size".to_string()))?;
+        if size == 0 {
+            return Ok(data);
+        }
+        let chunk = data_start
+            .checked_add(size)
+            .and_then(|data_end| body.get(data_start..data_end))
+            .ok_or_else(|| VerifierError::BodyDecodeError("truncated chunk".to_string()))?;
+        data.extend_from_slice(chunk);
+        pos = data_start + size + 2;
+    }
+}
+
+/// Parses the size line of the chunk at `pos` of a chunked body, returning the start of its data
+/// and its size
+fn chunk_at(body: &[u8], pos: usize) -> Option<(usize, usize)> {
+    let size_end = line_end(body, pos)?;
+    let size_line = String::from_utf8_lossy(&body[pos..size_end - 2]);
+    let size = size_line.split(';').next().unwrap_or_default().trim();
+    let size = usize::from_str_radix(size, 16).ok()?;
+    Some((size_end, size))
+}
+
+/// Returns the end of the line starting at `from`, after its `CRLF`
+fn line_end(bytes: &[u8], from: usize) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(2)
+        .position(|window| window == b"\r\n")
+        .map(|position| from + position + 2)
+}
+
+/// Decode the body of a response from its `Content-Encoding`, after removing its chunked
+/// `Transfer-Encoding` if `chunked` is set
+///
+/// Returns `None` if the encoding is not supported, `gzip`, `x-gzip` and `deflate` are, or an
+/// error if the body is not validly encoded.
+pub fn decode_body(
+    body: &[u8],
+    content_encoding: &str,
+    chunked: bool,
+) -> Result<Option<Vec<u8>>, VerifierError> {
+    let dechunked;
+    let body = if chunked {
+        dechunked = dechunk_body(body)?;
+        dechunked.as_slice()
+    } else {
+        body
+    };
+
+    let mut decoded = Vec::new();
+    let result = match content_encoding {
+        "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body).read_to_end(&mut decoded),
+        "deflate" => flate2::read::ZlibDecoder::new(body).read_to_end(&mut decoded),
+        _ => return Ok(None),
+    };
+    result.map_err(|e| VerifierError::BodyDecodeError(format!("{content_encoding}: {e}")))?;
+    Ok(Some(decoded))
+}
+
+/// Returns a printable preview of the transcript of a signed session for logging
+///
+/// The hex encoded application data is decoded, the values of sensitive headers such as
+/// `Cookie` and `Authorization` are redacted if `redact` is set, and the result is truncated to
+/// at most `max_bytes` bytes. A truncated preview ends with a marker, not counted towards
+/// `max_bytes`, stating how many bytes were omitted.
+pub fn preview_transcript(session: &SignedSession, max_bytes: usize, redact: bool) -> String {
+    let data = match hex::decode(&session.application_data) {
+        Ok(data) => data,
+        Err(e) => return format!("[invalid application data: {}]", e),
+    };
+    let transcript = String::from_utf8_lossy(&data);
+
+    let mut preview = if redact {
+        redact_headers(&transcript)
+    } else {
+        transcript.to_string()
+    };
+
+    if preview.len() > max_bytes {
+        let mut end = max_bytes;
+        while !preview.is_char_boundary(end) {
+            end -= 1;
+        }
+        let omitted = preview.len() - end;
+        preview.truncate(end);
+        preview.push_str(&format!("\n[truncated {} bytes]", omitted));
+    }
+    preview
+}
+
+/// Replaces the values of sensitive headers in an HTTP transcript
+fn redact_headers(transcript: &str) -> String {
+    transcript
+        .split_inclusive('\n')
+        .map(|line| {
+            let Some((name, value)) = line.split_once(':') else {
+                return line.to_string();
+            };
+            if !SENSITIVE_HEADERS
+                .iter()
+                .any(|header| name.trim().eq_ignore_ascii_case(header))
+            {
+                return line.to_string();
+            }
+            let ending = &value[value.trim_end_matches(['\r', '\n']).len()..];
+            format!("{}: {}{}", name, REDACTED, ending)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_complete_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 13\r\n\r\n{\"karma\": 10}";
+
+        let (headers, body) = split_http_body(response).expect("Failed to split response");
+        assert_eq!(headers.code, Some(200));
+        assert_eq!(headers.get("content-type"), Some("application/json"));
+        assert_eq!(headers.headers.len(), 2);
+        assert_eq!(body, b"{\"karma\": 10}");
+    }
+
+    #[test]
+    fn test_split_partial_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: appl";
+
+        assert!(matches!(
+            split_http_body(response),
+            Err(VerifierError::IncompleteResponseHead)
+        ));
+    }
+
+    #[test]
+    fn test_canonical_request() {
+        let request = b"POST /api HTTP/1.1\r\nHost:  example.com \r\nAccept: */*\r\nX-Tag: a\r\nx-tag: b\r\nContent-Length: 2\r\n\r\n{}";
+
+        assert_eq!(
+            canonical_request(request).expect("Failed to canonicalize request"),
+            b"POST /api HTTP/1.1\r\naccept: */*\r\ncontent-length: 2\r\nhost: example.com\r\nx-tag: a\r\nx-tag: b\r\n\r\n{}"
+        );
+        assert!(matches!(
+            canonical_request(b"GET / HTTP/1.1\r\nHost: exa"),
+            Err(VerifierError::HttpParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_split_http_exchanges() {
+        let requests = b"GET /page/1 HTTP/1.1\r\nHost: example.com\r\n\r\nPOST /page/2 HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}GET /page/3 HTTP/1.1\r\n\r\n";
+        let responses = [
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n[1]".as_slice(),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2;ext=1\r\n[2\r\n1\r\n]\r\n0\r\nX-Trailer: t\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n[]",
+        ]
+        .concat();
+
+        let exchanges = split_http_exchanges(requests, &responses).unwrap();
+        assert_eq!(
+            exchanges,
+            vec![
+                b"GET /page/1 HTTP/1.1\r\nHost: example.com\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n[1]".to_vec(),
+                b"POST /page/2 HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2;ext=1\r\n[2\r\n1\r\n]\r\n0\r\nX-Trailer: t\r\n\r\n".to_vec(),
+                b"GET /page/3 HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\n\r\n[]".to_vec(),
+            ]
+        );
+
+        // A single exchange is the whole transcript
+        let request = b"GET / HTTP/1.1\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+        assert_eq!(
+            split_http_exchanges(request, response).unwrap(),
+            vec![[request.as_slice(), response].concat()]
+        );
+
+        // An unanswered request does not pair up
+        assert!(matches!(
+            split_http_exchanges(&[request.as_slice(), request].concat(), response),
+            Err(VerifierError::HttpParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_body() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"{\"karma\": 10}").unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert_eq!(
+            decode_body(&gzipped, "gzip", false).unwrap().unwrap(),
+            b"{\"karma\": 10}"
+        );
+
+        // A chunked body is reassembled before it is decoded
+        let (first, second) = gzipped.split_at(7);
+        let chunked = [
+            format!("{:x}\r\n", first.len()).as_bytes(),
+            first,
+            format!("\r\n{:X};ext\r\n", second.len()).as_bytes(),
+            second,
+            b"\r\n0\r\n\r\n",
+        ]
+        .concat();
+        assert_eq!(
+            decode_body(&chunked, "x-gzip", true).unwrap().unwrap(),
+            b"{\"karma\": 10}"
+        );
+
+        assert!(decode_body(&gzipped, "br", false).unwrap().is_none());
+        assert!(matches!(
+            decode_body(b"{\"karma\": 10}", "gzip", false),
+            Err(VerifierError::BodyDecodeError(_))
+        ));
+        assert!(matches!(
+            decode_body(&chunked[..chunked.len() - 10], "gzip", true),
+            Err(VerifierError::BodyDecodeError(_))
+        ));
+    }
+
+    fn session(transcript: &[u8]) -> SignedSession {
+        use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let signature: Signature = signing_key.sign(transcript);
+        SignedSession::new(
+            hex::encode(transcript),
+            String::new(),
+            signature.into(),
+            Default::default(),
+            signature.into(),
+        )
+    }
+
+    #[test]
+    fn test_preview_redacts_cookie() {
+        let session = session(
+            b"GET /me HTTP/1.1\r\nHost: example.com\r\nCookie: session=secret\r\n\r\nHTTP/1.1 200 OK\r\nSet-Cookie: token=secret\r\n\r\n{}",
+        );
+
+        let preview = preview_transcript(&session, 1024, true);
+        assert!(!preview.contains("secret"));
+        assert!(preview.contains("Cookie: [REDACTED]\r\n"));
+        assert!(preview.contains("Set-Cookie: [REDACTED]\r\n"));
+        assert!(preview.contains("Host: example.com\r\n"));
+
+        assert!(preview_transcript(&session, 1024, false).contains("session=secret"));
+    }
+
+    #[test]
+    fn test_preview_respects_limit() {
+        let session = session("GET /é HTTP/1.1\r\nHost: example.com\r\n\r\n".as_bytes());
+
+        for max_bytes in 0..8 {
+            let preview = preview_transcript(&session, max_bytes, true);
+            let (content, marker) = preview
+                .split_once("\n[truncated ")
+                .expect("Preview is not truncated");
+            assert!(content.len() <= max_bytes);
+            assert!(marker.ends_with(" bytes]"));
+        }
+        assert_eq!(
+            preview_transcript(&session, 1024, true),
+            "GET /é HTTP/1.1\r\nHost: example.com\r\n\r\n"
+        );
+    }
+}
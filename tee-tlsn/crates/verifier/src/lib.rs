@@ -4,8 +4,20 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "vc")]
+pub mod credential;
+
+pub mod html;
+
+pub mod http;
+
 pub mod tls;
 
 pub mod provider;
 
+pub mod session;
+
+pub mod signer;
+
+#[cfg(not(target_arch = "wasm32"))]
 pub mod util;
@@ -1,13 +1,30 @@
 //! Provider configuration for the verifier
 
-use boa_engine::{js_str, property::Attribute, Context, JsValue, Source};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use boa_engine::{js_str, property::Attribute, Context, JsValue, Script, Source};
 
+use crate::{html::HtmlExtractor, http::Headers};
+
+use lazy_static::lazy_static;
+use percent_encoding::percent_decode_str;
 use regex::Regex;
 use reqwest;
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
-use std::{cell::RefCell, collections::HashMap};
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 use thiserror::Error;
+use tlsn_core::canonical_json::write_canonical_json;
+use web_time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Error)]
 /// ProviderError is the error that is returned when the provider is invalid
@@ -15,7 +32,7 @@ pub enum ProviderError {
     /// InvalidRegex is the error that is returned when the regex is invalid
     #[error("Invalid regex '{0}': {1}")]
     InvalidRegex(String, regex::Error),
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
     /// InvalidJmespath is the error that is returned when the JMESPath expression is invalid
     #[error("Invalid JSONPath expression '{0}': {1}")]
     InvalidJsonpath(String, String),
@@ -31,6 +48,15 @@ pub enum ProviderError {
     /// PreProcessScriptError is the error that is returned when the preprocess script is invalid
     #[error("Preprocess script error: {0}")]
     PreProcessScriptError(String),
+    /// ScriptThrew is the error that is returned when the preprocess script throws an `Error` to reject the response
+    #[error("Preprocess script threw: {message}")]
+    ScriptThrew {
+        /// The message of the thrown error
+        message: String,
+    },
+    /// ResponseDecodeError is the error that is returned when the response does not decode with the `responseEncoding` of the provider
+    #[error("Failed to decode response: {0}")]
+    ResponseDecodeError(String),
     /// ProcessError is the error that is returned when the process script is invalid
     #[error("Process script error: {0}")]
     ProcessError(String),
@@ -49,13 +75,249 @@ pub enum ProviderError {
     /// CacheError is the error that is returned when the cache is invalid
     #[error("Cache error: {0}")]
     CacheError(String),
+    /// ProviderNotAllowed is the error that is returned when the matched provider is not permitted for the session
+    #[error("Provider {0} is not allowed for this session")]
+    ProviderNotAllowed(u32),
+    /// ErrorCode is the error that is returned when the preprocess script throws a message mapped in `errorCodes`
+    #[error("Provider error {0}: {1}")]
+    ErrorCode(String, String),
+    /// UrlDenied is the error that is returned when the url matches the deny list of the config
+    #[error("Url {0} is on the deny list")]
+    UrlDenied(String),
+    /// StaleResponse is the error that is returned when the caching headers violate the cache policy of the provider
+    #[error("Response may have been served from a cache: {0}")]
+    StaleResponse(String),
+    /// TlsVersionNotAllowed is the error that is returned when the negotiated TLS version is not accepted by the provider
+    #[error("TLS version {0} is not allowed for provider {1}")]
+    TlsVersionNotAllowed(String, u32),
+    /// UnexpectedStatus is the error that is returned when the response status does not match the expected status of the provider
+    #[error("Response status {0} is not expected by provider {1}")]
+    UnexpectedStatus(String, u32),
+    /// AttributeTooLong is the error that is returned when an attribute value is longer than the `maxAttributeValueLength` of the provider
+    #[error("Attribute '{0}' is {1} bytes long, more than the {2} allowed")]
+    AttributeTooLong(String, usize, usize),
+    /// SampleResponseMismatch is the error that is returned when the `sampleResponse` of a provider does not yield its expected attributes
+    #[error("Sample response of provider {0} failed validation: {1}")]
+    SampleResponseMismatch(u32, String),
+    /// TooManyAttributes is the error that is returned when a provider yields more attributes than the `MAX_ATTRIBUTES` of the config
+    #[error("Provider {0} has {1} attributes, more than the {2} allowed")]
+    TooManyAttributes(u32, usize, usize),
+    /// ScriptsNotAllowed is the error that is returned when a provider has a preprocess script but the config sets `ALLOW_SCRIPTS` to false
+    #[error("Provider {0} has a preprocess script but scripts are not allowed")]
+    ScriptsNotAllowed(u32),
+    /// UnknownProvider is the error that is returned when a session declares a provider id that is not in the config
+    #[error("No provider has id {0}")]
+    UnknownProvider(u32),
+    /// UnsupportedTarget is the error that is returned when no provider can match the target url declared by a session
+    #[error("No provider supports the target url {0}")]
+    UnsupportedTarget(String),
+    /// InvalidSelector is the error that is returned when an `htmlSelectors` selector of a provider is not a supported CSS selector
+    #[error("Invalid selector '{0}': {1}")]
+    InvalidSelector(String, String),
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+impl ProviderError {
+    /// Returns the machine-readable code of the error, if the provider defined one
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::ErrorCode(code, _) => Some(code),
+            _ => None,
+        }
+    }
+}
+
+/// Key of the compiled artifacts of a provider in the caches: its id and the SHA-256 hash of the
+/// inputs they are compiled from, see [`Provider::cache_key`]
+///
+/// The caches hold an entry per key, so two versions of a provider sharing an id, e.g. in the
+/// configs of two processors, never evict each other's artifacts.
+pub type CacheKey = (u32, [u8; 32]);
+
+// The compiled artifacts are shared by all threads, so warming them once serves every worker
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+lazy_static! {
+    static ref COMPILED_ATTRIBUTES_CACHE: RwLock<HashMap<CacheKey, Arc<Vec<String>>>> =
+        RwLock::new(HashMap::new());
+    static ref COMPILED_REGEX_CACHE: RwLock<HashMap<CacheKey, Regex>> = RwLock::new(HashMap::new());
+    static ref COMPILED_PATTERN_CACHE: RwLock<HashMap<String, Regex>> = RwLock::new(HashMap::new());
+    static ref COMPILED_SCHEMA_CACHE: RwLock<HashMap<CacheKey, Arc<jsonschema::Validator>>> =
+        RwLock::new(HashMap::new());
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
 thread_local! {
-    static COMPILED_ATTRIBUTES_CACHE: RefCell<HashMap<u32, Vec<String>>> = RefCell::new(HashMap::new());
-    static COMPILED_REGEX_CACHE: RefCell<HashMap<u32, Regex>> = RefCell::new(HashMap::new());
-    static COMPILED_PREPROCESS_CACHE: RefCell<HashMap<u32, Context>> = RefCell::new(HashMap::new());
+    /// The constants of the provider whose expressions are being evaluated
+    static EXPRESSION_CONSTANTS: RefCell<HashMap<String, Value>> = RefCell::new(HashMap::new());
+    /// The outputs of the attributes evaluated so far, which later expressions reference as `$key`
+    static ATTRIBUTE_OUTPUTS: RefCell<serde_json::Map<String, Value>> =
+        RefCell::new(serde_json::Map::new());
+}
+
+/// Response type of providers whose body is plain text
+pub const TEXT_RESPONSE_TYPE: &str = "text";
+
+/// Key under which the body of a plain text response is exposed to the attributes
+pub const TEXT_RESPONSE_KEY: &str = "text";
+
+/// Response type of providers whose body is newline-delimited JSON
+pub const NDJSON_RESPONSE_TYPE: &str = "ndjson";
+
+/// Response type of providers whose body is an HTML page
+pub const HTML_RESPONSE_TYPE: &str = "html";
+
+/// Functions that can be called from attribute expressions, with their number of arguments
+const FUNCTIONS: &[(&str, usize)] = &[
+    ("contains", 2),
+    ("regex_match", 2),
+    ("regex_extract", 3),
+    ("to_number", 1),
+    ("to_number_lenient", 1),
+    ("length", 1),
+    ("first", 1),
+    ("last", 1),
+    ("sum", 1),
+    ("find", 3),
+    ("has_keys", 2),
+    ("is_absent", 1),
+    ("is_present", 1),
+    ("between", 3),
+];
+
+/// Names of the functions that can be called from attribute expressions
+const SUPPORTED_FUNCTIONS: [&str; FUNCTIONS.len()] = {
+    let mut names = [""; FUNCTIONS.len()];
+    let mut i = 0;
+    while i < FUNCTIONS.len() {
+        names[i] = FUNCTIONS[i].0;
+        i += 1;
+    }
+    names
+};
+
+/// Operators that can be used in attribute expressions
+const SUPPORTED_OPERATORS: &[&str] = &["&&", ">", "=="];
+
+/// Returns the functions supported by the attribute expression language
+pub fn supported_functions() -> &'static [&'static str] {
+    &SUPPORTED_FUNCTIONS
+}
+
+/// Returns the operators supported by the attribute expression language
+pub fn supported_operators() -> &'static [&'static str] {
+    SUPPORTED_OPERATORS
+}
+
+/// Largest x.com response that is escaped into the preprocess source, larger responses are bound
+/// as a global instead to avoid copying them several times
+const MAX_EMBEDDED_RESPONSE_SIZE: usize = 256 * 1024;
+
+/// Number of characters of an unparseable preprocess result that are included in the error
+const MAX_ERROR_SNIPPET_LEN: usize = 200;
+
+/// Number of characters of the preprocessed body that are logged with `debug_bodies`
+const MAX_DEBUG_BODY_LEN: usize = 4096;
+
+/// Longest attribute value, in bytes, of providers which do not set `maxAttributeValueLength`
+pub const DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH: usize = 16 * 1024;
+
+/// Most attributes a provider may yield, and so notary signatures per session, when the config
+/// does not set `MAX_ATTRIBUTES`
+pub const DEFAULT_MAX_ATTRIBUTES: usize = 64;
+
+/// Key under which the HTTP status code of the response is exposed to the attributes
+pub const STATUS_CODE_KEY: &str = "status_code";
+
+/// Key under which the processed response body is exposed to providers with `includeHeaders`
+pub const BODY_KEY: &str = "body";
+
+/// Key under which the response headers are exposed to providers with `includeHeaders`
+pub const HEADERS_KEY: &str = "headers";
+
+/// AuditRecord is the record of a request processed by a provider, without the response contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// The time the request was processed, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The id of the notarization session, when the request was processed for one
+    pub session_id: Option<String>,
+    /// The id of the provider that processed the request
+    pub provider_id: u32,
+    /// The url regex of the provider that processed the request
+    pub url_regex: String,
+    /// The method of the request
+    pub method: String,
+    /// The number of attributes extracted from the response
+    pub attribute_count: usize,
+}
+
+/// AuditSink receives a record of every request processed by a [`Processor`]
+///
+/// Unlike the telemetry events, the records are meant to be kept locally as an audit trail.
+pub trait AuditSink: fmt::Debug + Send + Sync {
+    /// Record a processed request
+    fn record(&self, record: AuditRecord);
+}
+
+/// RequestContext is the request a response answers, along with what is known of the response
+/// besides its body
+///
+/// Only the url and method are required. The request body is matched against the providers
+/// with a `graphqlOperation`, the status code is checked against the `expectedStatus` of the
+/// provider and exposed to the attributes as the `status_code` field, the headers to the providers that set `includeHeaders`, and the session
+/// id is recorded in the audit record and the spans of the processing.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestContext<'a> {
+    /// The url of the request
+    pub url: &'a str,
+    /// The method of the request
+    pub method: &'a str,
+    /// The body of the request, if known
+    pub request_body: Option<&'a str>,
+    /// The HTTP status code of the response, if known
+    pub status_code: Option<u16>,
+    /// The headers of the response, if known
+    pub headers: Option<&'a Headers>,
+    /// The id of the notarization session the request was made in, if any
+    pub session_id: Option<&'a str>,
+}
+
+impl<'a> RequestContext<'a> {
+    /// Create the context of a request of which only the url and method are known
+    pub fn new(url: &'a str, method: &'a str) -> Self {
+        Self {
+            url,
+            method,
+            request_body: None,
+            status_code: None,
+            headers: None,
+            session_id: None,
+        }
+    }
+
+    /// Set the body of the request
+    pub fn with_request_body(mut self, request_body: &'a str) -> Self {
+        self.request_body = Some(request_body);
+        self
+    }
+
+    /// Set the HTTP status code of the response
+    pub fn with_status_code(mut self, status_code: u16) -> Self {
+        self.status_code = Some(status_code);
+        self
+    }
+
+    /// Set the headers of the response, along with its status code when they have one
+    pub fn with_headers(mut self, headers: &'a Headers) -> Self {
+        self.status_code = headers.code.or(self.status_code);
+        self.headers = Some(headers);
+        self
+    }
+
+    /// Set the id of the notarization session the request was made in
+    pub fn with_session_id(mut self, session_id: &'a str) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
 }
 
 /// Processor is the processor configuration for the verifier
@@ -65,6 +327,11 @@ pub struct Processor {
     pub schema_url: String,
     /// Config is the provider configuration for the verifier
     pub config: Config,
+    /// Audit sink is the sink that receives a record of every processed request, if any
+    pub audit_sink: Option<Arc<dyn AuditSink>>,
+    /// Debug bodies logs the truncated preprocessed body at `trace` level when attribute
+    /// extraction fails. Bodies hold user data, so this is meant for staging only
+    pub debug_bodies: bool,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -103,19 +370,155 @@ impl Processor {
 
         let local_config_json: Config = serde_json::from_str(&json_path_content)
             .map_err(|e| ProviderError::JsonParseError(e))?;
+        local_config_json.validate()?;
 
         Ok(Self {
             schema_url,
-            config: local_config_json,
+            config: local_config_json.load_providers(),
+            audit_sink: None,
+            debug_bodies: false,
         })
     }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+impl Processor {
+    /// Create a processor from a config assembled in code
+    ///
+    /// Unlike [`Processor::new`], nothing is fetched and the config is not validated against the
+    /// schema.
+    pub fn from_config(config: Config, schema_url: String) -> Self {
+        Self {
+            schema_url,
+            config: config.load_providers(),
+            audit_sink: None,
+            debug_bodies: false,
+        }
+    }
+
+    /// Set the sink that receives a record of every processed request
+    pub fn with_audit_sink(mut self, audit_sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(audit_sink);
+        self
+    }
+
+    /// Set whether the preprocessed body is logged when attribute extraction fails
+    pub fn with_debug_bodies(mut self, debug_bodies: bool) -> Self {
+        self.debug_bodies = debug_bodies;
+        self
+    }
+
+    /// Warm the provider caches ahead of traffic
+    ///
+    /// Compiles the deny list, and the url regexes and attribute expressions of every provider,
+    /// and checks that the preprocess scripts parse, so the first request of each provider pays
+    /// no compilation cost and a broken provider is reported at startup. Preprocess scripts are evaluated in a fresh
+    /// context on every request, so there is no script context to cache.
+    ///
+    /// The caches are shared by all threads, so warming them once on any thread serves the
+    /// requests of every worker.
+    pub fn warm_up(&self) -> Result<(), ProviderError> {
+        for pattern in &self.config.deny_list {
+            with_compiled_pattern(pattern, |_| ())?;
+        }
+        for provider in &self.config.providers {
+            provider.get_compiled_regex(|_| Ok(true))?;
+            provider.get_compiled_attributes(|_| Ok(()))?;
+            if let Some(preprocess) = provider.preprocess.as_deref().filter(|p| !p.is_empty()) {
+                let mut context = Context::default();
+                Script::parse(Source::from_bytes(preprocess), None, &mut context)
+                    .map_err(|e| ProviderError::PreProcessScriptError(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the hex encoded SHA-256 hash of the canonical JSON serialization of the config
+    ///
+    /// Object keys are sorted, so the hash only changes when the content of the config changes
+    /// and not with the order of its maps.
+    pub fn config_hash(&self) -> String {
+        let config = serde_json::to_value(&self.config).expect("Config is serializable");
+        let mut canonical = String::new();
+        write_canonical_json(&config, &mut canonical);
+        hex::encode(Sha256::digest(canonical.as_bytes()))
+    }
+
+    /// Returns the most attributes a provider of the config may yield, see
+    /// [`Config::max_attributes`]
+    pub fn max_attributes(&self) -> usize {
+        self.config.max_attributes()
+    }
+
+    /// Check that the url does not match the deny list
+    pub fn check_deny_list(&self, url: &str) -> Result<(), ProviderError> {
+        for pattern in &self.config.deny_list {
+            if with_compiled_pattern(pattern, |regex| regex.is_match(url))? {
+                return Err(ProviderError::UrlDenied(url.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a provider could match the target url and provider id a session declares, to
+    /// reject the session before the TLS session is run
+    ///
+    /// As the method of the request is not known yet, only the `urlRegex` of the providers is
+    /// matched, which also compiles it ahead of the session.
+    pub fn check_session_target(
+        &self,
+        target_url: Option<&str>,
+        provider_id: Option<u32>,
+    ) -> Result<(), ProviderError> {
+        let candidates: Vec<&Provider> = self
+            .config
+            .providers
+            .iter()
+            .filter(|p| provider_id.is_none() || provider_id == Some(p.id))
+            .collect();
+        if let Some(id) = provider_id {
+            if candidates.is_empty() {
+                return Err(ProviderError::UnknownProvider(id));
+            }
+        }
+        let Some(url) = target_url else {
+            return Ok(());
+        };
+        self.check_deny_list(url)?;
+        for provider in candidates {
+            if provider.check_url(url)? {
+                return Ok(());
+            }
+        }
+        Err(ProviderError::UnsupportedTarget(url.to_string()))
+    }
 
     /// Find the provider that matches the url and method
-    pub fn find_provider(&self, url: &str, method: &str) -> Option<&Provider> {
-        self.config.providers.iter().find(|p| {
-            p.check_url_method(url, method)
-                .expect("Failed to check url method")
-        })
+    pub fn find_provider(
+        &self,
+        url: &str,
+        method: &str,
+    ) -> Result<Option<&Provider>, ProviderError> {
+        self.find_provider_for_request(url, method, None)
+    }
+
+    /// Find the provider that matches the url, method and request body
+    ///
+    /// Providers with a `graphqlOperation` only match when the request body is given. A url on
+    /// the deny list, or a pattern of a provider which is not a valid regex, is an error.
+    pub fn find_provider_for_request(
+        &self,
+        url: &str,
+        method: &str,
+        request_body: Option<&str>,
+    ) -> Result<Option<&Provider>, ProviderError> {
+        self.check_deny_list(url)?;
+        for provider in &self.config.providers {
+            if provider.check_request(url, method, request_body)? {
+                return Ok(Some(provider));
+            }
+        }
+        Ok(None)
     }
     /// Process the response using the providers
     pub fn process(
@@ -124,24 +527,113 @@ impl Processor {
         method: &str,
         response: &str,
     ) -> Result<Vec<String>, ProviderError> {
-        let mut result: Vec<String> = Vec::new();
+        self.process_request(&RequestContext::new(url, method), response)
+    }
+
+    /// Process the response to a request using the providers, with what is known of the request
+    /// and the response besides its body, see [`RequestContext`]
+    pub fn process_request(
+        &self,
+        request: &RequestContext<'_>,
+        response: &str,
+    ) -> Result<Vec<String>, ProviderError> {
+        self.process_attribute_values(request, response)
+            .map(format_attributes)
+    }
+
+    /// Process the response using the providers, returning the attributes as a single JSON
+    /// object of their keys and values rather than as `key: value` strings
+    ///
+    /// The values keep their JSON types, e.g. `{"followers": 10, "verified": true}`. When
+    /// several attributes have the same key, the last one is kept.
+    pub fn process_json(
+        &self,
+        url: &str,
+        method: &str,
+        response: &str,
+    ) -> Result<Value, ProviderError> {
+        let attributes =
+            self.process_attribute_values(&RequestContext::new(url, method), response)?;
+        Ok(Value::Object(attributes.into_iter().collect()))
+    }
 
-        let provider = self.find_provider(url, method);
+    fn process_attribute_values(
+        &self,
+        request: &RequestContext<'_>,
+        response: &str,
+    ) -> Result<Vec<(String, Value)>, ProviderError> {
+        let RequestContext {
+            url,
+            method,
+            request_body,
+            status_code,
+            headers,
+            session_id,
+        } = *request;
+        let mut result = Vec::new();
+
+        let provider = tracing::debug_span!("select_provider", session_id, url = %url)
+            .in_scope(|| self.find_provider_for_request(url, method, request_body))?;
 
         match provider {
             Some(provider) => {
-                let processed_response = provider
-                    .preprocess_response(response)
-                    .map_err(|e| ProviderError::ProcessError(e.to_string()))?;
-                match provider.get_attributes(&processed_response) {
+                provider.check_status(status_code)?;
+                let processed_response = tracing::debug_span!(
+                    "preprocess_response",
+                    session_id,
+                    provider_id = provider.id
+                )
+                .in_scope(|| provider.preprocess_response(response))
+                .map_err(|e| match e {
+                    ProviderError::ErrorCode(..)
+                    | ProviderError::ScriptThrew { .. }
+                    | ProviderError::ScriptsNotAllowed(_) => e,
+                    _ => ProviderError::ProcessError(e.to_string()),
+                })?;
+                provider.validate_output(&processed_response)?;
+                let processed_response = if provider.include_headers.unwrap_or(false) {
+                    with_headers(processed_response, headers)
+                } else {
+                    processed_response
+                };
+                let processed_response = match status_code {
+                    Some(status_code) => with_status_code(processed_response, status_code),
+                    None => processed_response,
+                };
+                let attributes = tracing::debug_span!(
+                    "extract_attributes",
+                    session_id,
+                    provider_id = provider.id
+                )
+                .in_scope(|| provider.get_attribute_values(&processed_response, status_code));
+                match attributes {
                     Ok(attributes) => {
-                        for attribute in attributes {
-                            let attribute_str = attribute.to_string();
-                            result.push(attribute_str);
+                        result.extend(attributes);
+                        if let Some(audit_sink) = &self.audit_sink {
+                            audit_sink.record(AuditRecord {
+                                timestamp: SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .map_or(0, |elapsed| elapsed.as_secs()),
+                                session_id: session_id.map(str::to_string),
+                                provider_id: provider.id,
+                                url_regex: provider.url_regex.clone(),
+                                method: method.to_string(),
+                                attribute_count: result.len(),
+                            });
                         }
                     }
                     Err(e) => {
                         tracing::error!("Failed to get attributes: {}", e);
+                        if self.debug_bodies {
+                            tracing::trace!(
+                                provider_id = provider.id,
+                                body = %truncate_chars(
+                                    &processed_response.to_string(),
+                                    MAX_DEBUG_BODY_LEN
+                                ),
+                                "Preprocessed body of the failed attribute extraction"
+                            );
+                        }
                         return Err(ProviderError::ProcessError(e.to_string()));
                     }
                 }
@@ -159,7 +651,7 @@ impl Processor {
 }
 
 /// Provider is the provider configuration for the verifier
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Provider {
     /// Id is the id of the provider
     pub id: u32,
@@ -172,7 +664,7 @@ pub struct Provider {
     #[serde(rename = "targetUrl")]
     pub target_url: String,
     /// Method is the HTTP method that the provider will use
-    pub method: String,
+    pub method: HttpMethod,
     /// Title is the title of the provider
     pub title: String,
     /// Description is the description of the provider
@@ -186,125 +678,564 @@ pub struct Provider {
     pub attributes: Option<Vec<String>>,
     /// Preprocess is a JMESPath expression that is applied to the response before the attributes are extracted
     pub preprocess: Option<String>,
+    /// Error codes maps messages thrown by the preprocess script to machine-readable codes
+    #[serde(
+        rename = "errorCodes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error_codes: Option<HashMap<String, String>>,
+    /// GraphQL operation is the `operationName` the request body must carry for the provider to match
+    #[serde(
+        rename = "graphqlOperation",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub graphql_operation: Option<String>,
+    /// Query params maps query parameter names to regexes one of their decoded values must match
+    /// for the provider to match
+    #[serde(
+        rename = "queryParams",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub query_params: Option<HashMap<String, String>>,
+    /// Require HTTPS only matches urls with the `https` scheme, so a request downgraded to plain
+    /// HTTP is not attested
+    #[serde(
+        rename = "requireHttps",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub require_https: Option<bool>,
+    /// Allowed ports are the ports the url must use for the provider to match, where a url
+    /// without a port uses the default port of its scheme, e.g. `443` for `https`
+    #[serde(
+        rename = "allowedPorts",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allowed_ports: Option<Vec<u16>>,
+    /// Normalize url matches the url regex against the normalized url, with a lowercase scheme
+    /// and host, no default port, the unreserved characters percent-decoded and the query
+    /// parameters sorted by name, so cosmetic differences do not fail the match
+    #[serde(
+        rename = "normalizeUrl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub normalize_url: Option<bool>,
+    /// Cache policy is the freshness the caching headers of the response must prove
+    #[serde(
+        rename = "cachePolicy",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cache_policy: Option<CachePolicy>,
+    /// TLS versions are the protocol versions, e.g. `TLSv1.2`, a session must negotiate for the provider
+    #[serde(
+        rename = "tlsVersions",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tls_versions: Option<Vec<String>>,
+    /// Attribute groups are attributes which are only extracted when the response status matches
+    #[serde(
+        rename = "attributeGroups",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub attribute_groups: Option<Vec<AttributeGroup>>,
+    /// Conditional attributes are attribute sets of which only the first whose condition holds
+    /// for the response is extracted, for endpoints returning different shapes
+    #[serde(
+        rename = "conditionalAttributes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub conditional_attributes: Option<Vec<ConditionalAttributes>>,
+    /// Include headers evaluates the attributes against `{body, headers}` instead of the body, so
+    /// an expression can reference both, e.g. `body.status` and `headers.content_type`
+    #[serde(
+        rename = "includeHeaders",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub include_headers: Option<bool>,
+    /// Partial skips the attribute expressions that fail to evaluate instead of failing the
+    /// provider, so the other attributes are still attested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<bool>,
+    /// Response encoding is the encoding the body is wrapped in, which is decoded before the
+    /// response is preprocessed
+    #[serde(
+        rename = "responseEncoding",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub response_encoding: Option<ResponseEncoding>,
+    /// Output schema is a JSON Schema the preprocessed response must match before the attributes
+    /// are extracted
+    #[serde(
+        rename = "outputSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_schema: Option<Value>,
+    /// Expected status is the status the response must have to be attested, as a comma separated
+    /// list of status codes, e.g. `200`, classes, e.g. `2xx`, or ranges, e.g. `200-204`
+    #[serde(
+        rename = "expectedStatus",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expected_status: Option<String>,
+    /// Normalize numbers attests the numeric strings in the attribute values, e.g. the `"30.03"`
+    /// returned by `toFixed(2)`, as numbers, e.g. `30.03`
+    #[serde(
+        rename = "normalizeNumbers",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub normalize_numbers: Option<bool>,
+    /// Max attribute value length is the longest attribute value, in bytes, that is attested,
+    /// defaulting to [`DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH`]; a longer value fails the provider
+    #[serde(
+        rename = "maxAttributeValueLength",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_attribute_value_length: Option<usize>,
+    /// Sample response is a response of the provider which [`Config::validate`] runs through
+    /// the provider, so attributes which do not work are caught when the config is loaded
+    #[serde(
+        rename = "sampleResponse",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_response: Option<SampleResponse>,
+    /// Root path is the field path of the subtree of the preprocessed response which the
+    /// attributes are evaluated against, e.g. `data` to unwrap a `{status, data: {...}}` envelope
+    #[serde(rename = "rootPath", default, skip_serializing_if = "Option::is_none")]
+    pub root_path: Option<String>,
+    /// Constants are named values the expressions of the provider can reference by name, e.g.
+    /// `followers > MIN_FOLLOWERS`, which take precedence over response fields of the same name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constants: Option<HashMap<String, Value>>,
+    /// Html selectors maps keys to the CSS selectors, e.g. `#followers` or `span.karma`, whose
+    /// element text an `html` response without a preprocess script is exposed as, by key
+    #[serde(
+        rename = "htmlSelectors",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub html_selectors: Option<BTreeMap<String, String>>,
+    /// The key of the compiled artifacts of the provider in the caches, computed once when the
+    /// processor is created from its config, see [`Provider::cache_key`]
+    #[serde(skip)]
+    cache_key: Option<CacheKey>,
+    /// Whether the config the provider was loaded from allows preprocess scripts, set when the
+    /// processor is created from its config, see [`Config::allow_scripts`]
+    #[serde(skip)]
+    allow_scripts: Option<bool>,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+/// SampleResponse is a response body paired with the attributes a provider must extract from it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SampleResponse {
+    /// Body is the response body as received, before it is decoded and preprocessed
+    pub body: String,
+    /// Expected attributes are the attributes, in any order, the body must yield, e.g.
+    /// `followers: 10`. When unset, the body must yield at least one attribute
+    #[serde(
+        rename = "expectedAttributes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expected_attributes: Option<Vec<String>>,
+}
+
+/// ResponseEncoding is the encoding a response body is wrapped in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseEncoding {
+    /// The body is standard base64
+    Base64,
+    /// The body is percent-encoded, with `+` for spaces
+    Urlencoded,
+    /// The body is used as is
+    #[default]
+    None,
+}
+
+impl ResponseEncoding {
+    /// Decode a response body
+    pub fn decode<'a>(&self, response: &'a str) -> Result<Cow<'a, str>, ProviderError> {
+        match self {
+            Self::Base64 => {
+                let bytes = BASE64_STANDARD
+                    .decode(response.trim())
+                    .map_err(|e| ProviderError::ResponseDecodeError(e.to_string()))?;
+                String::from_utf8(bytes)
+                    .map(Cow::Owned)
+                    .map_err(|e| ProviderError::ResponseDecodeError(e.to_string()))
+            }
+            Self::Urlencoded => percent_decode_str(&response.trim().replace('+', " "))
+                .decode_utf8()
+                .map(|decoded| Cow::Owned(decoded.into_owned()))
+                .map_err(|e| ProviderError::ResponseDecodeError(e.to_string())),
+            Self::None => Ok(Cow::Borrowed(response)),
+        }
+    }
+}
+
+/// PreprocessPath is the branch of [`Provider::preprocess_response`] a response went through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreprocessPath {
+    /// A `text` body without a preprocess script
+    Text,
+    /// An `ndjson` body without a preprocess script
+    Ndjson,
+    /// An `html` body without a preprocess script whose `htmlSelectors` were extracted
+    HtmlSelectors,
+    /// A body without a preprocess script
+    NoScript,
+    /// A body with an empty preprocess script
+    EmptyScript,
+    /// An x.com body, whose JSON is extracted from the chunked response before the script runs
+    XScript,
+    /// A body passed to the preprocess script as is
+    Script,
+}
+
+/// PreprocessMetadata describes how a response was preprocessed
+///
+/// There is no script engine fallback to report: every preprocess call evaluates its script in
+/// a context of its own, which is dropped before the call returns, rather than retrying in a
+/// fresh context after the engine panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreprocessMetadata {
+    /// The branch the response went through
+    pub path: PreprocessPath,
+    /// Whether the body was not valid JSON and was replaced with the string `"{}"`
+    pub parse_fallback: bool,
+    /// Whether the body was too large to embed in the script and was passed to `process` as the
+    /// `response` global instead
+    pub response_global: bool,
+}
+
+/// HttpMethod is the HTTP method of the requests a provider matches
+///
+/// Methods are deserialized case-insensitively and serialized in uppercase, and the JSON schema
+/// accepts them in any case as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    /// GET
+    Get,
+    /// POST
+    Post,
+    /// PUT
+    Put,
+    /// DELETE
+    Delete,
+    /// PATCH
+    Patch,
+    /// HEAD
+    Head,
+    /// OPTIONS
+    Options,
+}
+
+impl HttpMethod {
+    /// Every method, in the order of the enum
+    const ALL: [HttpMethod; 7] = [
+        Self::Get,
+        Self::Post,
+        Self::Put,
+        Self::Delete,
+        Self::Patch,
+        Self::Head,
+        Self::Options,
+    ];
+
+    /// Returns the uppercase name of the method
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Delete => "DELETE",
+            Self::Patch => "PATCH",
+            Self::Head => "HEAD",
+            Self::Options => "OPTIONS",
+        }
+    }
+}
+
+impl FromStr for HttpMethod {
+    type Err = String;
+
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.as_str().eq_ignore_ascii_case(method.trim()))
+            .ok_or_else(|| format!("unknown HTTP method '{}'", method))
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl JsonSchema for HttpMethod {
+    fn schema_name() -> String {
+        "HttpMethod".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, Metadata, SchemaObject, StringValidation};
+
+        // JSON schema patterns have no case-insensitive flag, so each letter is a class of both
+        // cases, e.g. `[Gg][Ee][Tt]`, with the surrounding whitespace `from_str` trims
+        let methods = Self::ALL
+            .iter()
+            .map(|method| {
+                method
+                    .as_str()
+                    .chars()
+                    .map(|c| format!("[{}{}]", c, c.to_ascii_lowercase()))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some("The HTTP method, in any case".to_string()),
+                ..Default::default()
+            })),
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some(format!(r"^\s*({})\s*$", methods.join("|"))),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpMethod {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let method = String::deserialize(deserializer)?;
+        method.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// CachePolicy is the freshness requirement on the caching headers of a response
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CachePolicy {
+    /// Require no store is whether the response must carry `Cache-Control: no-store`
+    #[serde(rename = "requireNoStore", default)]
+    pub require_no_store: bool,
+    /// Max age is the largest `Age` header in seconds that is accepted
+    #[serde(rename = "maxAge", default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+}
+
+/// AttributeGroup is a set of attributes guarded by the status of the response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AttributeGroup {
+    /// When status is a comma separated list of status codes, e.g. `200`, or classes, e.g. `4xx`,
+    /// one of which the response status must match for the attributes to be extracted
+    #[serde(
+        rename = "whenStatus",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub when_status: Option<String>,
+    /// Attributes is a list of expressions that are applied to the response to extract the attributes
+    pub attributes: Vec<String>,
+}
+
+impl AttributeGroup {
+    /// Check if the group applies to a response with the given status code
+    ///
+    /// A group without a guard always applies, and a guarded group never applies when the status
+    /// code is unknown.
+    pub fn matches_status(&self, status_code: Option<u64>) -> bool {
+        let when_status = match &self.when_status {
+            Some(when_status) => when_status,
+            None => return true,
+        };
+        match status_code {
+            Some(status_code) => status_matches(when_status, status_code),
+            None => false,
+        }
+    }
+}
+
+/// ConditionalAttributes is a set of attributes guarded by a condition on the response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConditionalAttributes {
+    /// When is a boolean expression, e.g. `plan == 'paid'`, which must evaluate to `true` for the
+    /// attributes to be extracted. A condition which fails to evaluate, e.g. because a field is
+    /// missing from the response, does not hold
+    pub when: String,
+    /// Attributes is a list of expressions that are applied to the response to extract the attributes
+    pub attributes: Vec<String>,
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+impl ConditionalAttributes {
+    /// Check if the condition holds for the response
+    fn holds(&self, response: &Value) -> Result<bool, ProviderError> {
+        check_function_calls(&self.when).map_err(ProviderError::ProcessError)?;
+        Ok(evaluate_field_expression(&self.when, response) == Ok(Value::Bool(true)))
+    }
+}
+
+/// Check if a status code matches a comma separated list of status codes, e.g. `200`, classes,
+/// e.g. `4xx`, or inclusive ranges, e.g. `200-204`
+fn status_matches(patterns: &str, status_code: u64) -> bool {
+    let status = status_code.to_string();
+    patterns.split(',').map(str::trim).any(|pattern| {
+        if let Some((start, end)) = pattern.split_once('-') {
+            return match (start.trim().parse::<u64>(), end.trim().parse::<u64>()) {
+                (Ok(start), Ok(end)) => (start..=end).contains(&status_code),
+                _ => false,
+            };
+        }
+        match pattern.to_ascii_lowercase().strip_suffix("xx") {
+            Some(class) if class.len() == 1 => status.len() == 3 && status.starts_with(class),
+            _ => pattern == status,
+        }
+    })
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
 impl Provider {
+    /// Get the key of the compiled artifacts of the provider in the caches
+    ///
+    /// The key is computed once when the processor is created from its config and stored on the
+    /// provider, so a cache lookup does not hash the inputs again. A provider which was not
+    /// loaded by a processor, e.g. one assembled in code, computes it on every call.
+    ///
+    /// The key combines the id with a hash of exactly these inputs: the url regex, the preprocess
+    /// script, the attributes and the output schema. The regex, attributes and schema are the
+    /// sources of the cached artifacts and the script is included so a reloaded script counts as a
+    /// new version of the provider, so a provider reloaded with the same id but changed inputs is
+    /// compiled again rather than served the artifacts of its earlier version. No other field is
+    /// covered, e.g. the `method` or `constants`, as they are read when a response is processed
+    /// rather than compiled.
+    ///
+    /// Each input is prefixed with its big-endian u64 length, so no two sets of inputs hash the
+    /// same bytes.
+    pub fn cache_key(&self) -> CacheKey {
+        self.cache_key.unwrap_or_else(|| self.compute_cache_key())
+    }
+
+    /// Hash the inputs of the compiled artifacts of the provider, see [`Provider::cache_key`]
+    fn compute_cache_key(&self) -> CacheKey {
+        let mut hasher = Sha256::new();
+        let mut update = |input: &str| {
+            hasher.update((input.len() as u64).to_be_bytes());
+            hasher.update(input.as_bytes());
+        };
+        update(&self.url_regex);
+        update(self.preprocess.as_deref().unwrap_or_default());
+        for attribute in self.attributes.iter().flatten() {
+            update(attribute);
+        }
+        let mut output_schema = String::new();
+        if let Some(schema) = &self.output_schema {
+            write_canonical_json(schema, &mut output_schema);
+        }
+        update(&output_schema);
+        (self.id, hasher.finalize().into())
+    }
+
     /// Get the compiled attributes from the JMESPath expressions
-    fn get_compiled_attributes<F>(&self, f: F) -> Result<Vec<String>, ProviderError>
+    fn get_compiled_attributes<T, F>(&self, f: F) -> Result<T, ProviderError>
     where
-        F: FnOnce(&Vec<String>) -> Result<Vec<String>, ProviderError>,
+        F: FnOnce(&Vec<String>) -> Result<T, ProviderError>,
     {
-        // Use the thread-local cache
-        COMPILED_ATTRIBUTES_CACHE.with(|cache| {
-            let mut cache = cache.borrow_mut();
-            if let Some(compiled_exprs) = cache.get(&self.id) {
-                // Return the cached compiled expressions
-                return f(compiled_exprs);
-            } else {
+        let key = self.cache_key();
+        let cached = COMPILED_ATTRIBUTES_CACHE.read().unwrap().get(&key).cloned();
+        let compiled_exprs = match cached {
+            Some(compiled_exprs) => compiled_exprs,
+            None => {
                 // Compile the expressions and store them in the cache
-                let compiled_exprs = self
-                    .attributes
-                    .as_deref()
-                    .unwrap_or(&[])
-                    .iter()
-                    .filter(|attr| !attr.is_empty())
-                    .map(|attr| attr.to_string())
-                    .collect::<Vec<_>>();
-                // Cache the compiled expressions
-                cache.insert(self.id, compiled_exprs);
-                if let Some(compiled_exprs) = cache.get(&self.id) {
-                    return f(compiled_exprs);
-                }
-                return Err(ProviderError::CacheError(
-                    "Failed to get compiled attributes".to_string(),
-                ));
+                let compiled_exprs = Arc::new(
+                    self.attributes
+                        .as_deref()
+                        .unwrap_or(&[])
+                        .iter()
+                        .filter(|attr| !attr.is_empty())
+                        .map(|attr| attr.to_string())
+                        .collect::<Vec<_>>(),
+                );
+                COMPILED_ATTRIBUTES_CACHE
+                    .write()
+                    .unwrap()
+                    .insert(key, compiled_exprs.clone());
+                compiled_exprs
             }
-        })
+        };
+        // The lock is released before evaluating, which may compile other patterns
+        f(&compiled_exprs)
     }
 
-    /// Get the compiled regex from the thread-local cache
+    /// Get the compiled regex from the shared cache
     fn get_compiled_regex<F>(&self, f: F) -> Result<bool, ProviderError>
     where
         F: FnOnce(&Regex) -> Result<bool, ProviderError>,
     {
-        COMPILED_REGEX_CACHE.with(|cache| {
-            let mut cache = cache.borrow_mut();
-            if let Some(compiled_regex) = cache.get(&self.id) {
-                return f(compiled_regex);
-            } else {
+        let key = self.cache_key();
+        let cached = COMPILED_REGEX_CACHE.read().unwrap().get(&key).cloned();
+        let regex = match cached {
+            Some(regex) => regex,
+            None => {
                 let regex = Regex::new(&self.url_regex)
                     .map_err(|e| ProviderError::InvalidRegex(self.url_regex.to_string(), e))?;
-                cache.insert(self.id, regex);
-                if let Some(compiled_regex) = cache.get(&self.id) {
-                    return f(compiled_regex);
-                }
-                return Err(ProviderError::CacheError(
-                    "Failed to get compiled regex".to_string(),
-                ));
+                COMPILED_REGEX_CACHE
+                    .write()
+                    .unwrap()
+                    .insert(key, regex.clone());
+                regex
             }
-        })
+        };
+        f(&regex)
     }
 
-    /// Get the compiled preprocess from the thread-local cache
-    fn get_compiled_preprocess<F>(&self, f: F) -> Result<Value, ProviderError>
-    where
-        F: FnOnce(&mut Context) -> Result<Value, ProviderError>,
-    {
-        // Try to use the cache first, but fall back to creating a new context if there's a GC issue
-        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            COMPILED_PREPROCESS_CACHE.with(|cache| {
-                let mut cache = cache.borrow_mut();
-                if let Some(context) = cache.get_mut(&self.id) {
-                    return f(context);
-                }
-                let mut context = Context::default();
-                if let Some(preprocess) = &self.preprocess {
-                    context
-                        .eval(Source::from_bytes(preprocess))
-                        .map_err(|e| ProviderError::PreProcessScriptError(e.to_string()))?;
-                }
-                cache.insert(self.id, context);
-                if let Some(context) = cache.get_mut(&self.id) {
-                    return f(context);
-                }
-                Err(ProviderError::CacheError(
-                    "Failed to get compiled preprocess".to_string(),
-                ))
-            })
-        }));
-
-        match result {
-            Ok(success_result) => success_result,
-            Err(_panic) => {
-                // If there's a panic (likely due to Boa GC bug), create a fresh context
-                tracing::warn!(
-                    "Boa GC panic detected, creating fresh context for provider {}",
-                    self.id
+    /// Get the compiled `outputSchema` of the provider from the shared cache, if it has one
+    fn get_compiled_output_schema(
+        &self,
+    ) -> Result<Option<Arc<jsonschema::Validator>>, ProviderError> {
+        let Some(output_schema) = &self.output_schema else {
+            return Ok(None);
+        };
+        let key = self.cache_key();
+        let cached = COMPILED_SCHEMA_CACHE.read().unwrap().get(&key).cloned();
+        let compiled_schema = match cached {
+            Some(compiled_schema) => compiled_schema,
+            None => {
+                let compiled_schema = Arc::new(
+                    jsonschema::Validator::new(output_schema)
+                        .map_err(|e| ProviderError::SchemaError(e.to_string()))?,
                 );
-                let mut context = Context::default();
-                if let Some(preprocess) = &self.preprocess {
-                    context
-                        .eval(Source::from_bytes(preprocess))
-                        .map_err(|e| ProviderError::PreProcessScriptError(e.to_string()))?;
-                }
-
-                let js_string = JsValue::String("{}".to_string().into());
-                context
-                    .register_global_property(js_str!("response"), js_string, Attribute::all())
-                    .map_err(|e| ProviderError::PreprocessError(e.to_string()))?;
-
-                let value = context
-                    .eval(Source::from_bytes("process(response)"))
-                    .map_err(|e| ProviderError::PreprocessError(e.to_string()))?;
-                let json = value
-                    .to_json(&mut context)
-                    .map_err(|e| ProviderError::PreProcessScriptError(e.to_string()))?;
-
-                // Don't store this context in cache to avoid GC issues
-                Ok(json)
+                COMPILED_SCHEMA_CACHE
+                    .write()
+                    .unwrap()
+                    .insert(key, compiled_schema.clone());
+                compiled_schema
             }
-        }
+        };
+        Ok(Some(compiled_schema))
     }
 
     /// Escape a string for safe JavaScript execution
@@ -317,149 +1248,853 @@ impl Provider {
     }
 
     /// Extract JSON from HTTP chunked response
+    ///
+    /// The response is returned unchanged if it has no braces, or if the last closing brace
+    /// comes before the first opening one.
     fn extract_json_from_response(response: &str) -> &str {
         let json_start = response.find('{');
         let json_end = response.rfind('}');
 
-        if let (Some(start), Some(end)) = (json_start, json_end) {
-            &response[start..=end]
-        } else {
-            response
+        match (json_start, json_end) {
+            // `get` rather than indexing so a reversed range can't panic
+            (Some(start), Some(end)) => response.get(start..=end).unwrap_or(response),
+            _ => response,
+        }
+    }
+
+    /// Build a snippet of an unparseable preprocess result for error messages
+    ///
+    /// The snippet is capped to [`MAX_ERROR_SNIPPET_LEN`] characters and only the structural
+    /// characters of JSON and whitespace are kept, every other character is masked, so the shape
+    /// of the output is visible without leaking any of the names or values it holds.
+    fn error_snippet(output: &str) -> String {
+        let masked = output
+            .chars()
+            .map(|c| {
+                if c.is_whitespace() || "{}[]:,\"".contains(c) {
+                    c
+                } else {
+                    '*'
+                }
+            })
+            .collect::<String>();
+        truncate_chars(&masked, MAX_ERROR_SNIPPET_LEN)
+    }
+
+    /// Map an error of the preprocess script to a provider error
+    ///
+    /// An `Error` thrown by the script is an intentional rejection, which is mapped to its error
+    /// code if one is defined. Syntax errors and runtime errors such as a `TypeError` are reported
+    /// as script errors.
+    fn preprocess_error(&self, error: String) -> ProviderError {
+        let message = match error.strip_prefix("Error: ") {
+            Some(message) => message,
+            None => {
+                return ProviderError::PreprocessError(format!(
+                    "Preprocess script error: {}",
+                    error
+                ))
+            }
+        };
+        match self
+            .error_codes
+            .as_ref()
+            .and_then(|error_codes| error_codes.get(message))
+        {
+            Some(code) => ProviderError::ErrorCode(code.to_string(), message.to_string()),
+            None => ProviderError::ScriptThrew {
+                message: message.to_string(),
+            },
+        }
+    }
+
+    /// Validate the preprocessed response against the `outputSchema` of the provider, if any
+    pub fn validate_output(&self, processed_response: &Value) -> Result<(), ProviderError> {
+        let Some(compiled_schema) = self.get_compiled_output_schema()? else {
+            return Ok(());
+        };
+
+        if let Err(errors) = compiled_schema.validate(processed_response) {
+            return Err(ProviderError::ValidationError(
+                errors.map(|e| e.to_string()).collect::<Vec<_>>().join(", "),
+            ));
         }
+        Ok(())
     }
 
     /// Preprocess the response using the preprocess JavaScript function
+    ///
+    /// The script must define a function named `process`, which is called with the response
+    /// (the JSON extracted from it for x.com) and returns the object attributes are evaluated
+    /// against, or an array which they index as a top-level array response, see
+    /// [`Provider::get_attributes`]. The same response is always available to the script as the
+    /// `response` global.
+    ///
+    /// Without a preprocess script, `text` bodies are exposed under `text` and `ndjson` bodies as
+    /// an array of their lines, which attributes reference as `@`.
+    ///
+    /// The script is not run, and [`ProviderError::ScriptsNotAllowed`] returned, when the
+    /// provider was loaded by a [`Processor`] whose config does not allow scripts, whether the
+    /// response is processed by the processor or preprocessed by calling this directly.
     pub fn preprocess_response(&self, response: &str) -> Result<Value, ProviderError> {
+        self.preprocess_response_with_metadata(response)
+            .map(|(processed_response, _)| processed_response)
+    }
+
+    /// Preprocess the response as [`Provider::preprocess_response`] does, and also return how it
+    /// was preprocessed
+    pub fn preprocess_response_with_metadata(
+        &self,
+        response: &str,
+    ) -> Result<(Value, PreprocessMetadata), ProviderError> {
+        let metadata = |path| PreprocessMetadata {
+            path,
+            parse_fallback: false,
+            response_global: false,
+        };
+        let response = self
+            .response_encoding
+            .unwrap_or_default()
+            .decode(response)?;
+        let response = response.as_ref();
+
+        if self.response_type == TEXT_RESPONSE_TYPE
+            && self.preprocess.as_deref().map_or(true, str::is_empty)
+        {
+            // Plain text bodies are exposed as a single string value
+            return Ok((
+                serde_json::json!({ TEXT_RESPONSE_KEY: response }),
+                metadata(PreprocessPath::Text),
+            ));
+        }
+
+        if self.response_type == NDJSON_RESPONSE_TYPE
+            && self.preprocess.as_deref().map_or(true, str::is_empty)
+        {
+            // Each non-blank line is a JSON value, exposed as an array of the lines
+            return response
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| serde_json::from_str(line).map_err(ProviderError::JsonParseError))
+                .collect::<Result<Vec<Value>, _>>()
+                .map(|lines| (Value::Array(lines), metadata(PreprocessPath::Ndjson)));
+        }
+
+        if let Some(mut extractor) = self.html_extractor()? {
+            // The selectors are extracted as the page is read, stopping once all have matched
+            extractor.feed(response.as_bytes());
+            return Ok((extractor.finish(), metadata(PreprocessPath::HtmlSelectors)));
+        }
+
         if let Some(preprocess) = &self.preprocess {
             if preprocess.is_empty() {
-                let json = match serde_json::from_str(response) {
-                    Ok(json) => json,
-                    Err(_) => serde_json::Value::String("{}".to_string()),
-                };
-                return Ok(json);
+                return Ok(Self::parse_without_script(
+                    response,
+                    PreprocessPath::EmptyScript,
+                ));
             }
 
-            // Create a fresh context for each request to avoid GC issues
-            let mut context = Context::default();
+            // The config is validated at load, this also covers configs assembled in code
+            if !self.allow_scripts.unwrap_or(true) {
+                return Err(ProviderError::ScriptsNotAllowed(self.id));
+            }
 
-            // Wrap the script execution to catch GC-related panics
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                let is_x_provider = self.host == "x.com";
-
-                // Prepare script and response data
-                let (script_content, response_data) = if is_x_provider {
-                    // For X providers: escape function and extract clean JSON
-                    let escaped_script = Self::escape_js_string(preprocess);
-                    let json_response = Self::extract_json_from_response(response);
-                    let escaped_response = Self::escape_js_string(json_response);
-                    (escaped_script, escaped_response)
-                } else {
-                    // For other providers: use standard escaping
-                    (preprocess.to_string(), Self::escape_js_string(response))
-                };
+            let is_x_provider = self.host == "x.com";
+            let mut metadata = metadata(if is_x_provider {
+                PreprocessPath::XScript
+            } else {
+                PreprocessPath::Script
+            });
 
-                // Build the execution code
-                let code = if is_x_provider {
-                    format!(
-                        "eval('{}'); 
-                         (function() {{ 
-                             try {{ 
-                                 const result = process('{}'); 
-                                 return JSON.stringify(result); 
-                             }} catch (error) {{ 
-                                 throw new Error(error.message); 
-                             }} 
-                         }})();",
-                        script_content, response_data
-                    )
-                } else {
-                    format!(
-                        "{} 
-                         (function() {{ 
-                             try {{ 
-                                 const result = process('{}'); 
-                                 return JSON.stringify(result); 
-                             }} catch (error) {{ 
-                                 throw new Error(error.message); 
-                             }} 
-                         }})();",
-                        script_content, response_data
-                    )
-                };
+            // The context is owned by this call and dropped before returning, so no script
+            // engine object outlives it. Contexts kept alive in a thread-local were torn down
+            // after the engine's own thread-local state at thread exit, which made it panic.
+            let mut context = Context::default();
 
-                context.eval(Source::from_bytes(&code)).map_err(|e| {
-                    ProviderError::PreprocessError(format!("Preprocess script error: {}", e))
-                })
-            }));
+            // The response handed to `process` is also bound as the `response` global, so
+            // scripts can rely on it whichever way the argument was passed
+            let (script_content, response) = if is_x_provider {
+                // For X providers: escape function and extract clean JSON
+                (
+                    format!("eval('{}');", Self::escape_js_string(preprocess)),
+                    Self::extract_json_from_response(response),
+                )
+            } else {
+                (preprocess.to_string(), response)
+            };
+            context
+                .register_global_property(
+                    js_str!("response"),
+                    JsValue::String(response.into()),
+                    Attribute::all(),
+                )
+                .map_err(|e| ProviderError::PreprocessError(e.to_string()))?;
+            let response_data = if is_x_provider && response.len() > MAX_EMBEDDED_RESPONSE_SIZE {
+                metadata.response_global = true;
+                "response".to_string()
+            } else {
+                format!("'{}'", Self::escape_js_string(response))
+            };
 
-            match result {
-                Ok(eval_result) => match eval_result {
-                    Ok(js_value) => {
-                        let result_str = js_value.to_string(&mut context).map_err(|e| {
-                            ProviderError::PreprocessError(format!(
-                                "Failed to convert result to string: {}",
-                                e
-                            ))
-                        })?;
-
-                        let json_value: Value = serde_json::from_str(
-                            &result_str.to_std_string_escaped(),
-                        )
-                        .map_err(|e| {
-                            ProviderError::PreprocessError(format!(
-                                "Failed to parse result JSON: {}",
-                                e
-                            ))
-                        })?;
-
-                        Ok(json_value)
-                    }
-                    Err(e) => Err(e),
-                },
-                Err(_) => {
-                    // If we caught a panic (likely GC bug), try to extract the actual error
-                    // The preprocessing likely succeeded but cleanup failed
-                    Err(ProviderError::PreprocessError(
-                        "JavaScript execution completed but cleanup failed due to Boa GC bug"
-                            .to_string(),
+            context
+                .eval(Source::from_bytes(&script_content))
+                .map_err(|e| self.preprocess_error(e.to_string()))?;
+            let defines_process = context
+                .eval(Source::from_bytes("typeof process === 'function'"))
+                .map_err(|e| ProviderError::PreprocessError(e.to_string()))?;
+            if defines_process.as_boolean() != Some(true) {
+                return Err(ProviderError::PreprocessError(
+                    "preprocess script must define a function named `process`".to_string(),
+                ));
+            }
+
+            // Build the execution code
+            let code = format!(
+                "(function() {{ 
+                     try {{ 
+                         const result = process({}); 
+                         return JSON.stringify(result); 
+                     }} catch (error) {{ 
+                         if (error instanceof Error && error.name !== 'Error') {{ 
+                             throw error; 
+                         }} 
+                         throw new Error(error instanceof Error ? error.message : String(error)); 
+                     }} 
+                 }})();",
+                response_data
+            );
+
+            let js_value = context
+                .eval(Source::from_bytes(&code))
+                .map_err(|e| self.preprocess_error(e.to_string()))?;
+            let result_str = js_value
+                .to_string(&mut context)
+                .map_err(|e| {
+                    ProviderError::PreprocessError(format!(
+                        "Failed to convert result to string: {}",
+                        e
                     ))
-                }
+                })?
+                .to_std_string_escaped();
+            drop(js_value);
+            drop(context);
+
+            let json_value: Value = serde_json::from_str(&result_str).map_err(|e| {
+                ProviderError::PreprocessError(format!(
+                    "Failed to parse result JSON: {} (output: {})",
+                    e,
+                    Self::error_snippet(&result_str)
+                ))
+            })?;
+            if !json_value.is_object() && !json_value.is_array() {
+                // Attributes resolve their fields against an object, or index an array
+                return Err(ProviderError::ProcessError(format!(
+                    "preprocess must return an object or an array, got {}",
+                    json_type_name(&json_value)
+                )));
             }
+
+            Ok((json_value, metadata))
         } else {
-            let json = match serde_json::from_str(response) {
-                Ok(json) => json,
-                Err(_) => serde_json::Value::String("{}".to_string()),
-            };
-            Ok(json)
+            Ok(Self::parse_without_script(
+                response,
+                PreprocessPath::NoScript,
+            ))
+        }
+    }
+
+    /// Returns an extractor of the `htmlSelectors` of an `html` provider without a preprocess
+    /// script, to feed the response to as it streams in, or `None` if the provider does not
+    /// extract selectors
+    ///
+    /// The extracted object is what [`Provider::preprocess_response`] returns for the whole
+    /// response, so it can be passed to [`Provider::get_attributes`] in its place.
+    pub fn html_extractor(&self) -> Result<Option<HtmlExtractor>, ProviderError> {
+        match &self.html_selectors {
+            Some(selectors)
+                if self.response_type == HTML_RESPONSE_TYPE
+                    && self.preprocess.as_deref().map_or(true, str::is_empty) =>
+            {
+                HtmlExtractor::new(selectors).map(Some)
+            }
+            _ => Ok(None),
         }
     }
 
+    /// Parse a response which has no preprocess script, falling back to the string `"{}"` if it
+    /// is not valid JSON
+    fn parse_without_script(response: &str, path: PreprocessPath) -> (Value, PreprocessMetadata) {
+        let (json, parse_fallback) = match serde_json::from_str(response) {
+            Ok(json) => (json, false),
+            Err(_) => (serde_json::Value::String("{}".to_string()), true),
+        };
+        (
+            json,
+            PreprocessMetadata {
+                path,
+                parse_fallback,
+                response_global: false,
+            },
+        )
+    }
+
     /// Get the attributes from the response using the JMESPath expressions
+    ///
+    /// Each attribute expression can reference the output keys of the expressions before it as
+    /// `$key`, e.g. `{good_score: $score > `700`}`, so an output never shadows a response field
+    /// of the same name. The attribute groups are
+    /// evaluated after the attributes, skipping the groups whose `whenStatus` does not match the
+    /// status code of the response. The status code is not known here, so the groups guarded by
+    /// `whenStatus` do not apply, see [`Provider::get_attribute_values`].
+    ///
+    /// A response which is a JSON array at the top level, e.g. the lines of an `ndjson` body, is
+    /// the root of the expressions, which index it directly, e.g. `[0].id`, `[*].id` or
+    /// `length(@)`.
+    ///
+    /// With a `rootPath`, every expression is evaluated against that subtree of the response, so
+    /// `data.karma` is written `karma` with a `rootPath` of `data`. A root path that is missing
+    /// from the response fails the provider.
+    ///
+    /// In `partial` mode an expression that fails to evaluate is logged and skipped, otherwise
+    /// the first failing expression fails the provider.
+    ///
+    /// Each attribute is signed as `key: value`, with the value in its compact JSON form, so a
+    /// string is quoted and a number is bare: `"30.03"` and `30.03` are different attributes.
+    /// With `normalizeNumbers`, a string that is already a number in its canonical JSON form,
+    /// i.e. one that serializes back to the same text, is attested as that number. Other
+    /// strings, e.g. `"007"`, `"1e3"` or `"30.10"`, are kept as strings so no digits are lost.
+    ///
+    /// An array or object value is attested in canonical JSON form: without whitespace, with
+    /// array elements in order and the keys of every object sorted by their UTF-8 bytes, e.g.
+    /// `roles: [{"id":1,"name":"admin"}]`, so a verifier can rebuild the signed bytes from the
+    /// value whatever the key order of the response.
     pub fn get_attributes(
         &self,
         response: &serde_json::Value,
     ) -> Result<Vec<String>, ProviderError> {
-        let mut result: Vec<String> = Vec::new();
-        self.get_compiled_attributes(|attribute_expressions| {
-            for attr_expr in attribute_expressions {
-                let eval_result = evaluate_attribute_expression(attr_expr, response)
-                    .map_err(|e| ProviderError::JsonpathError(e))?;
-                for (key, value) in eval_result {
-                    result.push(format!("{}: {}", key, value.to_string()));
+        Ok(format_attributes(
+            self.get_attribute_values(response, None)?,
+        ))
+    }
+
+    /// Get the attributes of the response as keys and values, before they are formatted as
+    /// `key: value` for signing, see [`Provider::get_attributes`]
+    ///
+    /// `status_code` is the status of the response in the transcript, which selects the
+    /// attribute groups by their `whenStatus`. It is never read from the response itself, so a
+    /// `status_code` field of the body cannot select a group, and the groups also apply to a
+    /// response which is an array.
+    pub fn get_attribute_values(
+        &self,
+        response: &serde_json::Value,
+        status_code: Option<u16>,
+    ) -> Result<Vec<(String, Value)>, ProviderError> {
+        with_constants(self.constants.as_ref(), || {
+            with_attribute_outputs(|| self.evaluate_attribute_values(response, status_code))
+        })
+    }
+
+    /// Evaluate the attributes of the response, with the constants of the provider in scope
+    fn evaluate_attribute_values(
+        &self,
+        response: &serde_json::Value,
+        status_code: Option<u16>,
+    ) -> Result<Vec<(String, Value)>, ProviderError> {
+        let partial = self.partial.unwrap_or(false);
+        let normalize_numbers = self.normalize_numbers.unwrap_or(false);
+        let max_value_length = self
+            .max_attribute_value_length
+            .unwrap_or(DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH);
+        let root = self.attribute_root(response)?;
+        let response = root.as_ref().unwrap_or(response);
+        let mut result = self.get_compiled_attributes(|attribute_expressions| {
+            let mut result = Vec::new();
+            evaluate_attributes(
+                attribute_expressions,
+                response,
+                partial,
+                normalize_numbers,
+                max_value_length,
+                &mut result,
+            )?;
+            Ok(result)
+        })?;
+
+        for group in self.attribute_groups.iter().flatten() {
+            if group.matches_status(status_code.map(u64::from)) {
+                evaluate_attributes(
+                    &group.attributes,
+                    response,
+                    partial,
+                    normalize_numbers,
+                    max_value_length,
+                    &mut result,
+                )?;
+            }
+        }
+
+        for conditional in self.conditional_attributes.iter().flatten() {
+            if conditional.holds(response)? {
+                evaluate_attributes(
+                    &conditional.attributes,
+                    response,
+                    partial,
+                    normalize_numbers,
+                    max_value_length,
+                    &mut result,
+                )?;
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Get the number of attributes the expressions of the provider declare, counting every key
+    /// of the attributes and the attribute groups and of the largest conditional set
+    pub fn declared_attribute_count(&self) -> usize {
+        let count = |expressions: &[String]| -> usize {
+            expressions
+                .iter()
+                .map(|expr| attribute_field_mappings(expr).map_or(1, |fields| fields.len()))
+                .sum()
+        };
+        let attributes = self.attributes.as_deref().map_or(0, count);
+        let groups: usize = self
+            .attribute_groups
+            .iter()
+            .flatten()
+            .map(|group| count(&group.attributes))
+            .sum();
+        let conditional = self
+            .conditional_attributes
+            .iter()
+            .flatten()
+            .map(|conditional| count(&conditional.attributes))
+            .max()
+            .unwrap_or(0);
+        attributes + groups + conditional
+    }
+
+    /// Resolve the `rootPath` of the provider in the response, if it has one
+    fn attribute_root(&self, response: &Value) -> Result<Option<Value>, ProviderError> {
+        let Some(root_path) = &self.root_path else {
+            return Ok(None);
+        };
+        match evaluate_field_expression(root_path, response) {
+            Ok(Value::Null) => Err(ProviderError::ProcessError(format!(
+                "rootPath '{}' is not in the response",
+                root_path
+            ))),
+            Ok(root) => Ok(Some(root)),
+            Err(e) => Err(ProviderError::ProcessError(format!(
+                "rootPath '{}' is not in the response: {}",
+                root_path, e
+            ))),
+        }
+    }
+
+    /// Check that the `sampleResponse` of the provider, if any, yields its expected attributes
+    ///
+    /// The sample goes through preprocessing, output validation and attribute extraction like a
+    /// notarized response, without headers or a status code.
+    pub fn check_sample_response(&self) -> Result<(), ProviderError> {
+        let Some(sample) = &self.sample_response else {
+            return Ok(());
+        };
+        let mismatch = |reason: String| ProviderError::SampleResponseMismatch(self.id, reason);
+
+        let processed_response = self
+            .preprocess_response(&sample.body)
+            .map_err(|e| mismatch(e.to_string()))?;
+        self.validate_output(&processed_response)
+            .map_err(|e| mismatch(e.to_string()))?;
+        let processed_response = if self.include_headers.unwrap_or(false) {
+            with_headers(processed_response, None)
+        } else {
+            processed_response
+        };
+        let mut attributes = self
+            .get_attributes(&processed_response)
+            .map_err(|e| mismatch(e.to_string()))?;
+
+        match &sample.expected_attributes {
+            Some(expected) => {
+                let mut expected = expected.clone();
+                expected.sort();
+                attributes.sort();
+                if attributes != expected {
+                    return Err(mismatch(format!(
+                        "expected attributes {:?}, got {:?}",
+                        expected, attributes
+                    )));
                 }
             }
-            Ok(result)
-        })
+            None if attributes.is_empty() => {
+                return Err(mismatch("no attributes were extracted".to_string()))
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Check that the caching headers of the response satisfy the provider's cache policy
+    pub fn check_cache_policy(&self, headers: &Headers) -> Result<(), ProviderError> {
+        let policy = match &self.cache_policy {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+
+        if policy.require_no_store {
+            let no_store = headers.get("cache-control").is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+            });
+            if !no_store {
+                return Err(ProviderError::StaleResponse(
+                    "missing Cache-Control: no-store".to_string(),
+                ));
+            }
+        }
+
+        if let (Some(max_age), Some(age)) = (policy.max_age, headers.get("age")) {
+            match age.trim().parse::<u64>() {
+                Ok(age) if age <= max_age => {}
+                Ok(age) => {
+                    return Err(ProviderError::StaleResponse(format!(
+                        "Age {} exceeds {} seconds",
+                        age, max_age
+                    )))
+                }
+                Err(_) => {
+                    return Err(ProviderError::StaleResponse(format!(
+                        "invalid Age header '{}'",
+                        age
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the negotiated TLS version is one of the provider's TLS versions
+    ///
+    /// Versions are compared ignoring case and the `TLS`/`v` prefix, so `TLSv1_3`, `TLSv1.3` and
+    /// `1.3` are the same version. A session whose version is unknown is only accepted when the
+    /// provider does not restrict the TLS version.
+    pub fn check_tls_version(&self, version: Option<&str>) -> Result<(), ProviderError> {
+        let tls_versions = match &self.tls_versions {
+            Some(tls_versions) => tls_versions,
+            None => return Ok(()),
+        };
+        let version = version.unwrap_or("unknown");
+        if tls_versions
+            .iter()
+            .any(|allowed| normalize_tls_version(allowed) == normalize_tls_version(version))
+        {
+            Ok(())
+        } else {
+            Err(ProviderError::TlsVersionNotAllowed(
+                version.to_string(),
+                self.id,
+            ))
+        }
+    }
+
+    /// List the output keys of the attributes, attribute groups and conditional attributes of the
+    /// provider, in the order they are declared, without evaluating them
+    ///
+    /// Attribute expressions which do not parse are skipped.
+    pub fn declared_attribute_names(&self) -> Vec<String> {
+        let group_attributes = self
+            .attribute_groups
+            .iter()
+            .flatten()
+            .flat_map(|group| &group.attributes);
+        let conditional_attributes = self
+            .conditional_attributes
+            .iter()
+            .flatten()
+            .flat_map(|conditional| &conditional.attributes);
+        let mut names: Vec<String> = Vec::new();
+        for expr in self
+            .attributes
+            .iter()
+            .flatten()
+            .chain(group_attributes)
+            .chain(conditional_attributes)
+        {
+            for (output_key, _) in attribute_field_mappings(expr).unwrap_or_default() {
+                if !names.contains(&output_key) {
+                    names.push(output_key);
+                }
+            }
+        }
+        names
+    }
+
+    /// Check that the status code of the response is the expected status of the provider
+    ///
+    /// A response whose status is unknown is only accepted when the provider does not expect a
+    /// status.
+    pub fn check_status(&self, status_code: Option<u16>) -> Result<(), ProviderError> {
+        let expected_status = match &self.expected_status {
+            Some(expected_status) => expected_status,
+            None => return Ok(()),
+        };
+        match status_code {
+            Some(status_code) if status_matches(expected_status, status_code.into()) => Ok(()),
+            status_code => Err(ProviderError::UnexpectedStatus(
+                status_code.map_or("unknown".to_string(), |code| code.to_string()),
+                self.id,
+            )),
+        }
     }
 
     /// Check if the url and method match the provider's url_regex and method
+    ///
+    /// With `normalizeUrl`, the regex is matched against the url as [`normalize_url`] returns it.
     pub fn check_url_method(&self, url: &str, method: &str) -> Result<bool, ProviderError> {
-        self.get_compiled_regex(|regex| Ok(regex.is_match(url) && self.method == method))
+        let method_matches = method.parse::<HttpMethod>() == Ok(self.method);
+        Ok(method_matches && self.check_url(url)?)
+    }
+
+    /// Check if the url matches the provider's url_regex, whatever the method of the request
+    pub fn check_url(&self, url: &str) -> Result<bool, ProviderError> {
+        let url = if self.normalize_url.unwrap_or(false) {
+            normalize_url(url)
+        } else {
+            Cow::Borrowed(url)
+        };
+        self.get_compiled_regex(|regex| Ok(regex.is_match(&url)))
+    }
+
+    /// Check if the request matches the provider, including the GraphQL operation name carried
+    /// in the request body when the provider sets `graphqlOperation`, the query parameters of
+    /// the url when it sets `queryParams` and the scheme and port of the url when it sets
+    /// `requireHttps` or `allowedPorts`
+    pub fn check_request(
+        &self,
+        url: &str,
+        method: &str,
+        request_body: Option<&str>,
+    ) -> Result<bool, ProviderError> {
+        if !self.check_url_method(url, method)? {
+            return Ok(false);
+        }
+        if let Some(reason) = self.transport_mismatch(url) {
+            tracing::debug!("provider {} does not match {}: {}", self.id, url, reason);
+            return Ok(false);
+        }
+        if let Some(operation) = &self.graphql_operation {
+            let operation_matches = request_body
+                .and_then(graphql_operation_name)
+                .is_some_and(|name| &name == operation);
+            if !operation_matches {
+                return Ok(false);
+            }
+        }
+        self.check_query_params(url)
+    }
+
+    /// Get the reason the scheme or port of the url violates the `requireHttps` or
+    /// `allowedPorts` of the provider, if it does
+    ///
+    /// A url without a scheme, e.g. a bare path, violates both as its transport is unknown.
+    pub fn transport_mismatch(&self, url: &str) -> Option<String> {
+        if !self.require_https.unwrap_or(false) && self.allowed_ports.is_none() {
+            return None;
+        }
+        let Some((scheme, port)) = scheme_and_port_of(url) else {
+            return Some(format!("url '{}' has no scheme or a malformed port", url));
+        };
+        if self.require_https.unwrap_or(false) && !scheme.eq_ignore_ascii_case("https") {
+            return Some(format!("scheme '{}' is not https", scheme));
+        }
+        match (&self.allowed_ports, port) {
+            (Some(allowed_ports), Some(port)) if !allowed_ports.contains(&port) => {
+                Some(format!("port {} is not one of {:?}", port, allowed_ports))
+            }
+            (Some(_), None) => Some(format!("scheme '{}' has no default port", scheme)),
+            _ => None,
+        }
+    }
+
+    /// Check if the query parameters of the url match the `queryParams` of the provider
+    ///
+    /// The patterns are not anchored, so `^` and `$` are needed to match a whole value.
+    fn check_query_params(&self, url: &str) -> Result<bool, ProviderError> {
+        let Some(query_params) = &self.query_params else {
+            return Ok(true);
+        };
+        let params = query_params_of(url);
+        for (name, pattern) in query_params {
+            let values = params
+                .iter()
+                .filter(|(param, _)| param == name)
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>();
+            let matches = with_compiled_pattern(pattern, |regex| {
+                values.iter().any(|value| regex.is_match(value))
+            })?;
+            if !matches {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Get the scheme of a url and its port, which is the default port of the scheme when the url
+/// has none, or `None` if the url has no scheme or its port is not a number
+fn scheme_and_port_of(url: &str) -> Option<(&str, Option<u16>)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // The colons of an IPv6 address are inside the brackets
+    let host = host.rsplit_once(']').map_or(host, |(_, rest)| rest);
+    let port = match host.rsplit_once(':') {
+        Some((_, port)) => Some(port.parse::<u16>().ok()?),
+        None if scheme.eq_ignore_ascii_case("https") => Some(443),
+        None if scheme.eq_ignore_ascii_case("http") => Some(80),
+        None => None,
+    };
+    Some((scheme, port))
+}
+
+/// Normalize a url so cosmetic variations of it compare equal
+///
+/// The scheme and host are lowercased and the default port of the scheme is removed. In the
+/// path and query, escapes of unreserved characters, e.g. `%7E`, are decoded and the others are
+/// uppercased, and the query parameters are sorted by name, keeping the order of repeated names.
+/// An empty path becomes `/`. A url without a scheme is returned as is.
+pub fn normalize_url(url: &str) -> Cow<'_, str> {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Cow::Borrowed(url);
+    };
+    let scheme = scheme.to_ascii_lowercase();
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, rest) = rest.split_at(authority_end);
+    let (rest, fragment) = match rest.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (rest, None),
+    };
+    let (path, query) = match rest.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (rest, None),
+    };
+
+    let (userinfo, host) = match authority.rsplit_once('@') {
+        Some((userinfo, host)) => (Some(userinfo), host),
+        None => (None, authority),
+    };
+    let mut host = host.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "https" => Some(":443"),
+        "http" => Some(":80"),
+        _ => None,
+    };
+    if let Some(default_port) = default_port {
+        // The colons of an IPv6 address are inside the brackets
+        if host.ends_with(default_port) && !host.ends_with(']') {
+            host.truncate(host.len() - default_port.len());
+        }
+    }
+
+    let mut normalized = format!("{}://", scheme);
+    if let Some(userinfo) = userinfo {
+        normalized.push_str(userinfo);
+        normalized.push('@');
+    }
+    normalized.push_str(&host);
+    if path.is_empty() {
+        normalized.push('/');
+    } else {
+        normalized.push_str(&normalize_percent_escapes(path));
+    }
+    if let Some(query) = query {
+        let mut params = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(normalize_percent_escapes)
+            .collect::<Vec<_>>();
+        params.sort_by(|a, b| a.split('=').next().cmp(&b.split('=').next()));
+        normalized.push('?');
+        normalized.push_str(&params.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        normalized.push('#');
+        normalized.push_str(fragment);
     }
+    Cow::Owned(normalized)
+}
+
+/// Decode the percent-escapes of unreserved characters and uppercase the hex digits of the
+/// other escapes, which are equivalent forms under RFC 3986
+fn normalize_percent_escapes(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut normalized = String::with_capacity(component.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| component.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(byte as char);
+                i += 3;
+            }
+            Some(byte) => {
+                normalized.push_str(&format!("%{:02X}", byte));
+                i += 3;
+            }
+            None => {
+                let c = component[i..].chars().next().unwrap_or_default();
+                normalized.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    normalized
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Get the percent-decoded query parameters of a url, in order, with `+` decoded as a space
+fn query_params_of(url: &str) -> Vec<(String, String)> {
+    let Some((_, query)) = url.split_once('?') else {
+        return Vec::new();
+    };
+    let query = query.split_once('#').map_or(query, |(query, _)| query);
+    let decode = |component: &str| {
+        percent_decode_str(&component.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+    query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Get the `operationName` of a GraphQL request body
+fn graphql_operation_name(request_body: &str) -> Option<String> {
+    let body: Value = serde_json::from_str(request_body.trim()).ok()?;
+    body.get("operationName")?.as_str().map(str::to_string)
 }
 
 /// Config is the provider configuration for the verifier
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     /// Version is the version of the config
     pub version: String,
@@ -469,9 +2104,253 @@ pub struct Config {
     /// Providers is a list of providers that the verifier will use to process the response
     #[serde(rename = "PROVIDERS")]
     pub providers: Vec<Provider>,
+    /// Deny list is a list of regexes of urls that are never notarized, even if a provider matches
+    #[serde(rename = "DENY_LIST", alias = "denyList", default)]
+    pub deny_list: Vec<String>,
+    /// Max attributes is the most attributes a provider may yield, each of which the notary signs,
+    /// defaulting to [`DEFAULT_MAX_ATTRIBUTES`]
+    #[serde(
+        rename = "MAX_ATTRIBUTES",
+        alias = "maxAttributes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_attributes: Option<usize>,
+    /// Allow scripts lets providers preprocess responses with JavaScript, defaulting to `true`;
+    /// without scripts only the declarative expressions of the providers are evaluated
+    #[serde(
+        rename = "allowScripts",
+        alias = "ALLOW_SCRIPTS",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allow_scripts: Option<bool>,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+impl Config {
+    /// Returns the JSON schema of the config, generated from the Rust types so it never drifts
+    /// from the shape the verifier expects
+    pub fn json_schema() -> Value {
+        serde_json::to_value(schemars::schema_for!(Config)).expect("Config schema is serializable")
+    }
+
+    /// Returns the most attributes a provider may yield
+    pub fn max_attributes(&self) -> usize {
+        self.max_attributes.unwrap_or(DEFAULT_MAX_ATTRIBUTES)
+    }
+
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+    /// Record on every provider, once as the config is loaded by a processor, its cache key and
+    /// whether the config allows scripts
+    fn load_providers(mut self) -> Self {
+        for provider in &mut self.providers {
+            provider.cache_key = Some(provider.compute_cache_key());
+            provider.allow_scripts = self.allow_scripts;
+        }
+        self
+    }
+
+    /// Check that the provider has no preprocess script if the config does not allow scripts
+    pub fn check_scripts_allowed(&self, provider: &Provider) -> Result<(), ProviderError> {
+        let has_script = provider
+            .preprocess
+            .as_deref()
+            .is_some_and(|p| !p.is_empty());
+        if has_script && !self.allow_scripts.unwrap_or(true) {
+            return Err(ProviderError::ScriptsNotAllowed(provider.id));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+    /// Validate the providers against their `sampleResponse`, failing on the first provider
+    /// whose sample does not yield its expected attributes
+    ///
+    /// The ids of the providers must be unique, so an id names a single provider, e.g. in the
+    /// allowed provider ids of a session. No provider may declare more attributes than
+    /// [`Config::max_attributes`], nor have a preprocess script when the config does not allow
+    /// scripts. The patterns of the deny list must be valid regexes, as must the `queryParams`
+    /// patterns of the providers, the `htmlSelectors` of each provider must be supported CSS
+    /// selectors, and its `outputSchema` a valid JSON schema.
+    pub fn validate(&self) -> Result<(), ProviderError> {
+        for pattern in &self.deny_list {
+            with_compiled_pattern(pattern, |_| ())?;
+        }
+
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for provider in &self.providers {
+            *counts.entry(provider.id).or_default() += 1;
+        }
+        let mut duplicates: Vec<u32> = counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(id, _)| id)
+            .collect();
+        if !duplicates.is_empty() {
+            duplicates.sort_unstable();
+            return Err(ProviderError::ValidationError(format!(
+                "duplicate provider ids: {}",
+                duplicates
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let max_attributes = self.max_attributes();
+        for provider in &self.providers {
+            self.check_scripts_allowed(provider)?;
+            let count = provider.declared_attribute_count();
+            if count > max_attributes {
+                return Err(ProviderError::TooManyAttributes(
+                    provider.id,
+                    count,
+                    max_attributes,
+                ));
+            }
+        }
+
+        for provider in &self.providers {
+            for pattern in provider.query_params.iter().flat_map(HashMap::values) {
+                with_compiled_pattern(pattern, |_| ())?;
+            }
+            provider.html_extractor()?;
+            provider.get_compiled_output_schema()?;
+            provider.check_sample_response()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Evaluate attribute expressions in order, appending the attributes to the result and their
+/// outputs to the [`attribute_output`]s of the expressions that follow
+///
+/// When `partial` is set, expressions that fail to evaluate are skipped. Calls to unknown
+/// functions or with the wrong number of arguments are a mistake in the provider rather than in
+/// the response, so they always fail.
+/// When `normalize_numbers` is set, the numeric strings in the outputs become numbers. A value
+/// longer than `max_value_length` bytes always fails, even when `partial` is set.
+fn evaluate_attributes(
+    attribute_expressions: &[String],
+    response: &Value,
+    partial: bool,
+    normalize_numbers: bool,
+    max_value_length: usize,
+    result: &mut Vec<(String, Value)>,
+) -> Result<(), ProviderError> {
+    for attr_expr in attribute_expressions {
+        check_function_calls(attr_expr).map_err(ProviderError::ProcessError)?;
+        let eval_result = match evaluate_attribute_expression(attr_expr, response) {
+            Ok(eval_result) => eval_result,
+            Err(e) if partial => {
+                tracing::warn!("Skipping attribute '{}': {}", attr_expr, e);
+                continue;
+            }
+            Err(e) => return Err(ProviderError::JsonpathError(e)),
+        };
+        for (key, value) in eval_result {
+            let value = if normalize_numbers {
+                normalize_numeric_strings(value)
+            } else {
+                value
+            };
+            // Values come from the response, so bound what is signed and stored
+            let mut formatted = String::new();
+            write_canonical_json(&value, &mut formatted);
+            if formatted.len() > max_value_length {
+                return Err(ProviderError::AttributeTooLong(
+                    key,
+                    formatted.len(),
+                    max_value_length,
+                ));
+            }
+            ATTRIBUTE_OUTPUTS
+                .with(|outputs| outputs.borrow_mut().insert(key.clone(), value.clone()));
+            result.push((key, value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Format attributes as the `key: value` strings which are signed, with the values in canonical
+/// JSON form
+fn format_attributes(attributes: Vec<(String, Value)>) -> Vec<String> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| {
+            let mut formatted = format!("{}: ", key);
+            write_canonical_json(&value, &mut formatted);
+            formatted
+        })
+        .collect()
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Replace the strings which are numbers in their canonical JSON form with those numbers
+///
+/// A string is only replaced when the number serializes back to the same text, so `"30.03"`
+/// becomes `30.03` but `"007"`, `"1e3"` and `"30.10"` are kept.
+fn normalize_numeric_strings(value: Value) -> Value {
+    match value {
+        Value::String(string) => match serde_json::from_str::<serde_json::Number>(&string) {
+            Ok(number) if number.to_string() == string => Value::Number(number),
+            _ => Value::String(string),
+        },
+        Value::Array(values) => {
+            Value::Array(values.into_iter().map(normalize_numeric_strings).collect())
+        }
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, normalize_numeric_strings(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Nest the processed response under `body` next to the response headers under `headers`
+///
+/// Header names are lowercased with dashes replaced by underscores, so `Content-Type` is
+/// `headers.content_type`. When a header is repeated, its first value is kept.
+fn with_headers(processed_response: Value, headers: Option<&Headers>) -> Value {
+    let mut header_values = serde_json::Map::new();
+    for (name, value) in headers
+        .map(|headers| headers.headers.as_slice())
+        .unwrap_or(&[])
+    {
+        header_values
+            .entry(name.to_ascii_lowercase().replace('-', "_"))
+            .or_insert_with(|| Value::String(value.clone()));
+    }
+    serde_json::json!({
+        BODY_KEY: processed_response,
+        HEADERS_KEY: header_values,
+    })
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Add the status code to the processed response, replacing any field of the same name so the
+/// attested value always comes from the transcript
+///
+/// An array has no field to hold the status code, so it is returned as is.
+fn with_status_code(processed_response: Value, status_code: u16) -> Value {
+    let status_code = Value::Number(serde_json::Number::from(status_code));
+    match processed_response {
+        Value::Object(mut object) => {
+            object.insert(STATUS_CODE_KEY.to_string(), status_code);
+            Value::Object(object)
+        }
+        Value::Array(array) => Value::Array(array),
+        _ => serde_json::json!({ STATUS_CODE_KEY: status_code }),
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
 /// Simple attribute expression evaluator
 fn evaluate_attribute_expression(
     expr: &str,
@@ -479,6 +2358,20 @@ fn evaluate_attribute_expression(
 ) -> Result<std::collections::HashMap<String, serde_json::Value>, String> {
     use std::collections::HashMap;
 
+    let mut result = HashMap::new();
+
+    for (output_key, field_expr) in attribute_field_mappings(expr)? {
+        let value = evaluate_field_expression(&field_expr, data)?;
+        result.insert(output_key, value);
+    }
+
+    Ok(result)
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Split an attribute expression of the form `{key: expr, ...}` into its output keys and field
+/// expressions
+fn attribute_field_mappings(expr: &str) -> Result<Vec<(String, String)>, String> {
     // Remove outer braces
     let content = expr
         .trim()
@@ -487,32 +2380,33 @@ fn evaluate_attribute_expression(
         .unwrap_or(expr)
         .trim();
 
-    let mut result = HashMap::new();
-
     // Split by comma, handling nested expressions
-    let fields = split_attribute_fields(content)?;
-
-    for field in fields {
-        let (output_key, field_expr) = parse_field_mapping(&field)?;
-        let value = evaluate_field_expression(&field_expr, data)?;
-        result.insert(output_key, value);
-    }
-
-    Ok(result)
+    split_attribute_fields(content)?
+        .iter()
+        .map(|field| parse_field_mapping(field))
+        .collect()
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
 fn split_attribute_fields(content: &str) -> Result<Vec<String>, String> {
     let mut fields = Vec::new();
     let mut current = String::new();
     let mut paren_count = 0;
     let mut in_backticks = false;
+    let mut in_quotes: Option<char> = None;
 
     for ch in content.chars() {
         match ch {
+            '\'' | '"' if !in_backticks => match in_quotes {
+                Some(quote) if quote == ch => in_quotes = None,
+                None => in_quotes = Some(ch),
+                _ => {}
+            },
+            _ if in_quotes.is_some() => {}
             '`' => in_backticks = !in_backticks,
-            '(' if !in_backticks => paren_count += 1,
-            ')' if !in_backticks => paren_count -= 1,
+            // The commas of a `['a', 'b']` key list belong to the list
+            '(' | '[' if !in_backticks => paren_count += 1,
+            ')' | ']' if !in_backticks => paren_count -= 1,
             ',' if !in_backticks && paren_count == 0 => {
                 if !current.trim().is_empty() {
                     fields.push(current.trim().to_string());
@@ -532,7 +2426,7 @@ fn split_attribute_fields(content: &str) -> Result<Vec<String>, String> {
     Ok(fields)
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
 fn parse_field_mapping(field_str: &str) -> Result<(String, String), String> {
     if let Some((output_key, expr_str)) = field_str.split_once(':') {
         Ok((output_key.trim().to_string(), expr_str.trim().to_string()))
@@ -541,7 +2435,7 @@ fn parse_field_mapping(field_str: &str) -> Result<(String, String), String> {
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
 fn evaluate_field_expression(
     expr: &str,
     data: &serde_json::Value,
@@ -568,8 +2462,12 @@ fn evaluate_field_expression(
         let left_val = evaluate_field_expression(left_expr, data)?;
         let right_val = parse_literal_value(right_expr)?;
 
-        if let (Some(l), Some(r)) = (left_val.as_f64(), right_val.as_f64()) {
-            return Ok(serde_json::Value::Bool(l > r));
+        if let (serde_json::Value::Number(l), serde_json::Value::Number(r)) =
+            (&left_val, &right_val)
+        {
+            return Ok(serde_json::Value::Bool(
+                compare_numbers(l, r) == Ordering::Greater,
+            ));
         } else {
             return Err(format!("Cannot compare {:?} > {:?}", left_val, right_val));
         }
@@ -580,32 +2478,68 @@ fn evaluate_field_expression(
         let right_expr = &expr[eq_pos + 2..].trim();
         let left_val = evaluate_field_expression(left_expr, data)?;
         let right_val = parse_literal_value(right_expr)?;
-
-        return Ok(serde_json::Value::Bool(left_val == right_val));
+        return Ok(serde_json::Value::Bool(values_equal(&left_val, &right_val)));
     }
 
-    if expr.starts_with("to_number(") && expr.ends_with(')') {
-        let inner = &expr[10..expr.len() - 1];
-        let inner_val = evaluate_field_expression(inner, data)?;
-        match inner_val {
-            serde_json::Value::Number(n) => return Ok(serde_json::Value::Number(n)),
-            serde_json::Value::String(ref s) => {
-                if let Ok(f) = s.parse::<f64>() {
-                    if let Some(number) = serde_json::Number::from_f64(f) {
-                        return Ok(serde_json::Value::Number(number));
-                    } else {
-                        return Err(format!("Invalid number value: {} (NaN or infinite)", f));
-                    }
-                }
+    if let Some(args) = function_args(expr, "contains")? {
+        let (field, needle) = (&args[0], &args[1]);
+        let haystack = evaluate_field_expression(field, data)?;
+        let needle = parse_literal_value(needle)?;
+        let contains = match (&haystack, &needle) {
+            (serde_json::Value::String(h), serde_json::Value::String(n)) => h.contains(n.as_str()),
+            (serde_json::Value::Array(a), _) => a.contains(&needle),
+            _ => {
+                return Err(format!(
+                    "Cannot check if {:?} contains {:?}",
+                    haystack, needle
+                ))
             }
-            _ => {}
-        }
-        return Err(format!("Cannot convert {:?} to number", inner_val));
+        };
+        return Ok(serde_json::Value::Bool(contains));
+    }
+
+    if let Some(args) = function_args(expr, "regex_match")? {
+        let (field, pattern) = (&args[0], &args[1]);
+        let text = evaluate_string_argument("regex_match", field, data)?;
+        let pattern = evaluate_pattern_argument(pattern)?;
+        let is_match = with_compiled_pattern(&pattern, |regex| regex.is_match(&text))
+            .map_err(|e| e.to_string())?;
+        return Ok(serde_json::Value::Bool(is_match));
+    }
+
+    if let Some(args) = function_args(expr, "regex_extract")? {
+        let (field, pattern, group) = (&args[0], &args[1], &args[2]);
+        let text = evaluate_string_argument("regex_extract", field, data)?;
+        let pattern = evaluate_pattern_argument(pattern)?;
+        let group = parse_literal_value(group)?
+            .as_f64()
+            .filter(|g| *g >= 0.0 && g.fract() == 0.0)
+            .ok_or_else(|| format!("Invalid capture group '{}'", group))?
+            as usize;
+        let captured = with_compiled_pattern(&pattern, |regex| {
+            regex
+                .captures(&text)
+                .and_then(|captures| captures.get(group))
+                .map(|m| m.as_str().to_string())
+        })
+        .map_err(|e| e.to_string())?;
+        return Ok(captured
+            .map(serde_json::Value::String)
+            .unwrap_or(serde_json::Value::Null));
+    }
+
+    if let Some(args) = function_args(expr, "to_number")? {
+        let inner_val = evaluate_field_expression(&args[0], data)?;
+        return value_to_number(inner_val, false);
+    }
+
+    if let Some(args) = function_args(expr, "to_number_lenient")? {
+        let inner_val = evaluate_field_expression(&args[0], data)?;
+        return value_to_number(inner_val, true);
     }
 
-    if expr.starts_with("length(") && expr.ends_with(')') {
-        let inner = &expr[7..expr.len() - 1];
-        let inner_val = evaluate_field_expression(inner, data)?;
+    if let Some(args) = function_args(expr, "length")? {
+        let inner_val = evaluate_field_expression(&args[0], data)?;
         match inner_val {
             serde_json::Value::String(s) => {
                 return Ok(serde_json::Value::Number(serde_json::Number::from(s.len())))
@@ -617,1622 +2551,2488 @@ fn evaluate_field_expression(
         }
     }
 
-    if expr.contains('.') {
-        let parts: Vec<&str> = expr.split('.').collect();
-        let mut current = data;
-        for part in parts {
-            current = current
-                .get(part)
-                .ok_or_else(|| format!("Field '{}' not found", part))?;
+    for name in ["first", "last"] {
+        if let Some(args) = function_args(expr, name)? {
+            let inner_val = evaluate_field_expression(&args[0], data)?;
+            let array = inner_val
+                .as_array()
+                .ok_or_else(|| format!("Cannot get {} of {:?}", name, inner_val))?;
+            let element = if name == "first" {
+                array.first()
+            } else {
+                array.last()
+            };
+            return element
+                .cloned()
+                .ok_or_else(|| format!("Cannot get {} of an empty array", name));
         }
-        return Ok(current.clone());
     }
 
-    data.get(expr)
-        .cloned()
-        .ok_or_else(|| format!("Field '{}' not found", expr))
-}
+    if let Some(args) = function_args(expr, "sum")? {
+        let inner_val = evaluate_field_expression(&args[0], data)?;
+        let array = inner_val
+            .as_array()
+            .ok_or_else(|| format!("Cannot get sum of {:?}", inner_val))?;
+        return sum_numbers(array);
+    }
 
-#[cfg(not(target_arch = "wasm32"))]
-fn find_operator_position(expr: &str, op: &str) -> Option<usize> {
-    let mut paren_count = 0;
-    let mut in_backticks = false;
+    if let Some(args) = function_args(expr, "find")? {
+        let (array, field, literal) = (&args[0], &args[1], &args[2]);
+        let array_val = evaluate_field_expression(array, data)?;
+        let array = array_val
+            .as_array()
+            .ok_or_else(|| format!("Cannot find in {:?}", array_val))?;
+        let field = evaluate_pattern_argument(field)?;
+        let literal = parse_literal_value(literal)?;
+        let element = array.iter().find(|element| {
+            evaluate_field_expression(&field, element)
+                .is_ok_and(|value| values_equal(&value, &literal))
+        });
+        return Ok(element.cloned().unwrap_or(serde_json::Value::Null));
+    }
 
-    for (i, ch) in expr.char_indices() {
-        match ch {
-            '`' => in_backticks = !in_backticks,
-            '(' if !in_backticks => paren_count += 1,
-            ')' if !in_backticks => paren_count -= 1,
-            _ if !in_backticks && paren_count == 0 => {
-                if expr[i..].starts_with(op) {
-                    return Some(i);
-                }
+    if let Some(args) = function_args(expr, "has_keys")? {
+        let (object, keys) = (&args[0], &args[1]);
+        let keys = parse_key_list(keys)?;
+        let has_keys = match evaluate_field_expression(object, data)? {
+            serde_json::Value::Object(object) => keys.iter().all(|key| object.contains_key(key)),
+            _ => false,
+        };
+        return Ok(serde_json::Value::Bool(has_keys));
+    }
+
+    for name in ["is_absent", "is_present"] {
+        if let Some(args) = function_args(expr, name)? {
+            // A missing or null field is absent, which is a fact rather than an error
+            if args[0].starts_with(['`', '\'', '"']) || function_call_name(&args[0]).is_some() {
+                return Err(format!("{} expects a field path, got '{}'", name, args[0]));
             }
-            _ => {}
+            parse_field_path(&args[0])?;
+            let present =
+                evaluate_field_expression(&args[0], data).is_ok_and(|value| !value.is_null());
+            return Ok(serde_json::Value::Bool(present == (name == "is_present")));
         }
     }
-    None
-}
 
-#[cfg(not(target_arch = "wasm32"))]
-fn parse_literal_value(value_str: &str) -> Result<serde_json::Value, String> {
-    let value_str = value_str.trim();
+    if let Some(args) = function_args(expr, "between")? {
+        // Inclusive on both ends, e.g. `between(score, `700`, `800`)`
+        let value = evaluate_field_expression(&args[0], data)?;
+        let number = |value: &serde_json::Value| match value {
+            serde_json::Value::Number(number) => Ok(number.clone()),
+            _ => Err(format!("between expects numbers, got {:?}", value)),
+        };
+        let (low, high) = (
+            parse_literal_value(&args[1])?,
+            parse_literal_value(&args[2])?,
+        );
+        let value = number(&value)?;
+        return Ok(serde_json::Value::Bool(
+            compare_numbers(&number(&low)?, &value) != Ordering::Greater
+                && compare_numbers(&value, &number(&high)?) != Ordering::Greater,
+        ));
+    }
 
-    if value_str.starts_with('`') && value_str.ends_with('`') {
-        let inner = &value_str[1..value_str.len() - 1];
-        if let Ok(num) = inner.parse::<f64>() {
-            if let Some(number) = serde_json::Number::from_f64(num) {
-                return Ok(serde_json::Value::Number(number));
-            } else {
-                return Err(format!(
-                    "Invalid number value in backticks: {} (NaN or infinite)",
-                    num
-                ));
-            }
-        } else {
-            return Ok(serde_json::Value::String(inner.to_string()));
-        }
+    if let Some(name) = function_call_name(expr) {
+        return Err(unknown_function_error(name));
     }
 
-    if let Ok(num) = value_str.parse::<f64>() {
-        if let Some(number) = serde_json::Number::from_f64(num) {
-            return Ok(serde_json::Value::Number(number));
-        } else {
-            return Err(format!("Invalid number value: {} (NaN or infinite)", num));
-        }
+    if let Some((call, path)) = split_call_path(expr) {
+        // A path into the result of a call, e.g. `find(values, 'type', 'Likes').value`
+        let value = evaluate_field_expression(call, data)?;
+        return evaluate_field_expression(path, &value);
     }
 
-    if (value_str.starts_with('"') && value_str.ends_with('"'))
-        || (value_str.starts_with('\'') && value_str.ends_with('\''))
-    {
-        let inner = &value_str[1..value_str.len() - 1];
-        return Ok(serde_json::Value::String(inner.to_string()));
+    if expr == "@" {
+        // The current node, e.g. the array of lines of an NDJSON response
+        return Ok(data.clone());
     }
 
-    Ok(serde_json::Value::String(value_str.to_string()))
-}
+    if let Some(path) = expr.strip_prefix('$') {
+        return attribute_output(path);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[cfg(not(target_arch = "wasm32"))]
-    use tokio;
+    if let Some(value) = constant(expr) {
+        return Ok(value);
+    }
 
-    const MISSING_ATTRIBUTES_PROVIDER_TEXT: &str = r#"{
-        "id": 7,
-        "host": "github.com",
-        "urlRegex": "^https:\\/\\/api\\.github\\.com\\/users\\/[a-zA-Z0-9]+(\\?.*)?$",
-        "targetUrl": "https://github.com",
-        "method": "GET",
-        "title": "Github profile",
-        "description": "Go to your profile",
-        "icon": "https://github.githubassets.com/images/modules/logos_page/GitHub-Mark.png",  
-        "responseType": "json"
-    }"#;
+    // Once the path is projected, it selects every matching node and missing keys are skipped
+    let mut nodes = vec![data];
+    let mut projected = false;
+    for segment in parse_field_path(expr)? {
+        match segment {
+            PathSegment::Key(key) if projected => {
+                nodes = nodes
+                    .into_iter()
+                    .filter_map(|node| node.get(&key))
+                    .collect();
+            }
+            PathSegment::Key(key) => {
+                let node = nodes[0]
+                    .get(&key)
+                    .ok_or_else(|| format!("Field '{}' not found", key))?;
+                nodes = vec![node];
+            }
+            PathSegment::Index(index) if projected => {
+                nodes = nodes
+                    .into_iter()
+                    .filter_map(|node| node.get(index))
+                    .collect();
+            }
+            PathSegment::Index(index) => {
+                let node = nodes[0]
+                    .get(index)
+                    .ok_or_else(|| format!("Index {} not found", index))?;
+                nodes = vec![node];
+            }
+            PathSegment::Wildcard | PathSegment::Filter(_) => {
+                let mut elements = Vec::new();
+                for node in nodes {
+                    match node.as_array() {
+                        Some(array) => elements.extend(array),
+                        None if projected => {}
+                        None => return Err(format!("Cannot project {:?}", node)),
+                    }
+                }
+                if let PathSegment::Filter(condition) = &segment {
+                    elements.retain(|element| {
+                        evaluate_field_expression(condition, element)
+                            == Ok(serde_json::Value::Bool(true))
+                    });
+                }
+                nodes = elements;
+                projected = true;
+            }
+        }
+    }
 
-    #[test]
-    fn test_missing_attributes_provider() {
-        let provider: Provider = serde_json::from_str(MISSING_ATTRIBUTES_PROVIDER_TEXT)
-            .expect("Failed to parse provider");
-        let response_text = r#"{
-            "login": "xxxxxx",
-            "id": 4715448,    
-            "public_repos": 47,
-            "public_gists": 0
-    }"#;
-        let processed_response = provider
-            .preprocess_response(&response_text)
-            .expect("Failed to preprocess response");
-        let result = provider
-            .get_attributes(&processed_response)
-            .expect("Failed to get attributes");
-        assert_eq!(result.len(), 0);
+    if projected {
+        Ok(serde_json::Value::Array(
+            nodes.into_iter().cloned().collect(),
+        ))
+    } else {
+        Ok(nodes[0].clone())
     }
+}
 
-    const HTML_MISSING_ATTRIBUTES_PROVIDER_TEXT: &str = r#"{
-      "id": 7,
-      "host": "github.com",
-      "urlRegex": "^https:\\/\\/api\\.github\\.com\\/users\\/[a-zA-Z0-9]+(\\?.*)?$",
-      "targetUrl": "https://github.com",
-      "method": "GET",
-      "title": "Github profile",
-      "description": "Go to your profile",
-      "icon": "https://github.githubassets.com/images/modules/logos_page/GitHub-Mark.png",  
-      "responseType": "json",
-      "preprocess" : ""
-  }"#;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Parse a bracketed list of keys, e.g. `['id', "name"]`
+fn parse_key_list(list: &str) -> Result<Vec<String>, String> {
+    let inner = list
+        .trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| format!("Invalid key list '{}', expected e.g. ['a', 'b']", list))?;
+    split_attribute_fields(inner)?
+        .iter()
+        .map(|key| evaluate_pattern_argument(key))
+        .collect()
+}
 
-    #[test]
-    fn test_html_missing_attributes_provider() {
-        let provider: Provider = serde_json::from_str(HTML_MISSING_ATTRIBUTES_PROVIDER_TEXT)
-            .expect("Failed to parse provider");
-        let response_text = r#"<html><body><h1 id="followers">94</h1><h1 id="following">80</h1><h1 id="public_repos">47</h1></body></html>"#;
-        let processed_response = provider
-            .preprocess_response(response_text)
-            .expect("Failed to preprocess response");
-        let result = provider
-            .get_attributes(&processed_response)
-            .expect("Failed to get attributes");
-        assert_eq!(result.len(), 0);
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Truncate a string to at most `max_len` characters, marking the cut with `...`
+fn truncate_chars(s: &str, max_len: usize) -> String {
+    match s.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s.to_string(),
     }
+}
 
-    const JSON_PROVIDER_TEXT: &str = r#"{
-      "id": 7,
-      "host": "github.com",
-      "urlRegex": "^https:\\/\\/chatgpt\\.com\\/backend-api\\/sentinel\\/chat-requirements(\\?.*)?$",
-      "targetUrl": "https://github.com",
-      "method": "GET",
-      "title": "Github profile",
-      "description": "Go to your profile",
-      "icon": "https://github.githubassets.com/images/modules/logos_page/GitHub-Mark.png",  
-      "responseType": "json",
-      "attributes": ["{followers: followers, following: following}", "{public_repos: public_repos}", "{is_active: followers + following > public_repos}"]
-    }"#;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Returns the JSON type of a value, e.g. `array`
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
 
-    #[test]
-    fn test_check_url_method() {
-        let provider: Provider =
-            serde_json::from_str(JSON_PROVIDER_TEXT).expect("Failed to parse provider");
-        assert!(provider
-            .check_url_method(
-                "https://chatgpt.com/backend-api/sentinel/chat-requirements",
-                "GET"
-            )
-            .expect("Failed to check url method"));
-        assert!(!provider
-            .check_url_method("https://api.github.com/users/xxxxxx/followers", "GET")
-            .expect("Failed to check url method"));
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Compares two values for `==`, comparing numbers by value so `1` equals `1.0`
+fn values_equal(left: &serde_json::Value, right: &serde_json::Value) -> bool {
+    match (left, right) {
+        (serde_json::Value::Number(l), serde_json::Value::Number(r)) => {
+            compare_numbers(l, r) == Ordering::Equal
+        }
+        _ => left == right,
     }
+}
 
-    const SSA_PROVIDER_TEXT: &str = r#"{
-        "id": 4,
-        "host": "secure.ssa.gov",
-        "urlRegex": "https://secure.ssa.gov/myssa/myprofile-api/profileInfo",
-        "targetUrl": "https://secure.ssa.gov/myssa/myprofile-ui/main",
-        "method": "GET",
-        "title": "US SSA",
-        "description": "Go to your profile",
-        "icon": "https://brandslogos.com/wp-content/uploads/images/large/us-social-security-administration-logo-black-and-white.png",
-        "responseType": "json",
-        "attributes": ["{age: age, isValid: length(loggedInUserInfo.cossn) == `11` } "],
-        "preprocess": "function process(jsonString) { const startIndex = jsonString.indexOf('{'); const endIndex = jsonString.lastIndexOf('}') + 1; if (startIndex === -1 || endIndex === 0) { return {}; } try { const cleanedResponse = jsonString.slice(startIndex, endIndex); const s = JSON.parse(cleanedResponse); const currentDate = new Date(); const currentYear = currentDate.getFullYear(); let age = currentYear - s.loggedInUserInfo.dobYear; const currentMonth = currentDate.getMonth(); const currentDay = currentDate.getDate(); if (currentMonth === 0 && currentDay < 1) { age--; } s.age = age; return s; } catch (e) { return {}; }  }"
-      }"#;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Splits an expression of the form `name(...).path` into the call and the path
+fn split_call_path(expr: &str) -> Option<(&str, &str)> {
+    let open = expr.find('(')?;
+    let name = &expr[..open];
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        return None;
+    }
+    let end = open + 1 + call_arguments(&expr[open + 1..])?.len() + 1;
+    match expr[end..].strip_prefix('.') {
+        Some(path) if !path.is_empty() => Some((&expr[..end], path)),
+        _ => None,
+    }
+}
 
-    const SSA_RESPONSE_TEXT: &str = r#"
-          1e0
-          {
-              "responseStatus": {
-                "returnCode": "0000",
-                "reasonCode": "0000",
-                "reasonDescription": "Successfully obtained the profile info"
-              },
-              "urlPath": "/myssa/bec-plan-prep-ui/",
-              "loggedInUserInfo": {
-                  "cossn": "***-**-9999",
-                  "name": {
-                    "firstName": "JOHN",
-                    "middleName": "",
-                    "lastName": "DOE",
-                    "suffix": ""
-                  },
-                  "formattedName": "John Doe",
-                  "otherServicesInd": "N",
-                  "messageCount": "",
-                  "dobYear": "1999",
-                  "dobMonth": "09",
-                  "dobDay": "09",
-                  "contactDisplayInd": "N",
-                  "bankingDisplayInd": "N"
-              }
-          }
-          0"#;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Sums an array of numbers, exactly while the sum fits in an `i64`
+fn sum_numbers(array: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    let numbers = array
+        .iter()
+        .map(|value| match value {
+            serde_json::Value::Number(n) => Ok(n),
+            other => Err(format!("sum expects an array of numbers, got {:?}", other)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    #[test]
-    fn test_ssa_provider() {
-        let provider: Provider =
-            serde_json::from_str(SSA_PROVIDER_TEXT).expect("Failed to parse provider");
-        let processed_response = provider
-            .preprocess_response(&SSA_RESPONSE_TEXT)
-            .expect("Failed to preprocess response");
-        let result = provider
-            .get_attributes(&processed_response)
-            .expect("Failed to get attributes");
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&"age: 26".to_string()));
-        assert!(result.contains(&"isValid: false".to_string()));
+    let integer_sum = numbers
+        .iter()
+        .try_fold(0i64, |sum, n| n.as_i64().and_then(|n| sum.checked_add(n)));
+    if let Some(sum) = integer_sum {
+        return Ok(serde_json::Value::Number(sum.into()));
     }
+    let sum = numbers.iter().filter_map(|n| n.as_f64()).sum::<f64>();
+    serde_json::Number::from_f64(sum)
+        .map(serde_json::Value::Number)
+        .ok_or_else(|| format!("Cannot represent sum {}", sum))
+}
 
-    #[cfg(not(target_arch = "wasm32"))]
-    #[tokio::test]
-    async fn test_processor() {
-        let processor = Processor::new(
-            "https://raw159opfy.ufs.sh/f/taibMU1XxiEPtYPjjZ1XxiEPsjzpNu8frqFdalI30V7yCJBO"
-                .to_string(),
-            "https://link.freysa.ai/provider-schema".to_string(),
-        )
-        .await
-        .expect("Failed to initialize processor");
-        let result = processor
-            .process(
-                "https://secure.ssa.gov/myssa/myprofile-api/profileInfo",
-                "GET",
-                SSA_RESPONSE_TEXT,
-            )
-            .expect("Failed to process");
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&"age: 25.0".to_string()));
-        assert!(result.contains(&"isValid: true".to_string()));
-    }
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// A segment of a field path
+#[derive(Debug, PartialEq)]
+enum PathSegment {
+    /// A key of an object
+    Key(String),
+    /// `[n]`, the element of an array at an index
+    Index(usize),
+    /// `[*]`, every element of an array
+    Wildcard,
+    /// `[?condition]`, the elements of an array for which the condition evaluates to `true`
+    Filter(String),
+}
 
-    const CHASE_RESPONSE_TEXT: &str = r#"{
-        "creditScoreOutlineResponse": {
-            "creditBureauName": "EXPERIAN",
-            "creditScore": {
-                "currentCreditScoreSummary": {
-                    "creditRiskScore": 701,
-                    "creditScoreGradeName": "GOOD"
-                    },
-                "previousCreditScoreSummary": {
-                    "creditRiskScore": 123,
-                    "creditScoreGradeName": "GOOD"
-                },
-                "creditScoreModelIdentifier": {
-                    "riskModelName": "VantageScore",
-                    "riskModelVersionNumber": "1.2"
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Split a field path into its segments, e.g. `a.b["c.d"]` into the keys `a`, `b` and `c.d`
+///
+/// Keys are separated by dots, a key containing dots can be quoted in brackets. `[n]` selects
+/// the element of an array at index `n`, e.g. `[0].id` on a response which is an array. `[*]` projects
+/// every element of an array and `[?condition]` the elements matching the condition, e.g.
+/// `series[*].values[?type=='Impressions'].value`. Unlike JMESPath, nested projections are
+/// flattened into a single array.
+fn parse_field_path(expr: &str) -> Result<Vec<PathSegment>, String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = expr.chars().peekable();
+    let mut after_bracket = false;
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '[' if matches!(chars.peek(), Some('*') | Some('?')) => {
+                if !current.is_empty() {
+                    parts.push(PathSegment::Key(std::mem::take(&mut current)));
+                }
+                if chars.next() == Some('*') {
+                    if chars.next() != Some(']') {
+                        return Err(format!("Expected ']' after '[*' in '{}'", expr));
+                    }
+                    parts.push(PathSegment::Wildcard);
+                } else {
+                    parts.push(PathSegment::Filter(parse_filter_condition(
+                        &mut chars, expr,
+                    )?));
+                }
+                after_bracket = true;
+                continue;
+            }
+            '[' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                if !current.is_empty() {
+                    parts.push(PathSegment::Key(std::mem::take(&mut current)));
+                }
+                let mut index = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => index.push(c),
+                        None => return Err(format!("Unterminated index in '{}'", expr)),
+                    }
+                }
+                let index = index
+                    .parse()
+                    .map_err(|_| format!("Invalid index '{}' in '{}'", index, expr))?;
+                parts.push(PathSegment::Index(index));
+                after_bracket = true;
+                continue;
+            }
+            '[' if matches!(chars.peek(), Some('"') | Some('\'')) => {
+                let quote = chars.next().expect("peeked quote");
+                if !current.is_empty() {
+                    parts.push(PathSegment::Key(std::mem::take(&mut current)));
+                }
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => key.push(c),
+                        None => return Err(format!("Unterminated quoted key in '{}'", expr)),
+                    }
                 }
+                if chars.next() != Some(']') {
+                    return Err(format!("Expected ']' after quoted key in '{}'", expr));
+                }
+                parts.push(PathSegment::Key(key));
+                after_bracket = true;
+                continue;
             }
+            '.' if after_bracket => {}
+            '.' => parts.push(PathSegment::Key(std::mem::take(&mut current))),
+            _ => current.push(ch),
         }
-    }"#;
+        after_bracket = false;
+    }
 
-    const CHASE_PROVIDER_TEXT: &str = r#"{
-      "id": 6,
-      "host": "secure.chase.com",
-      "urlRegex":
-        "^https:\/\/secure.chase.com\/svc\/wr\/profile\/secure\/creditscore\/v2\/credit-journey\/servicing\/inquiry-maintenance\/v1\/customers\/credit-journey-insight-outlines.*",
-      "targetUrl": "https://secure.chase.com/web/auth/dashboard#/dashboard/overview",
-      "method": "GET",
-      "title": "Chase credit score",
-      "description": "Login to your chase account",
-      "icon": "https://download.logo.wine/logo/Chase_Bank/Chase_Bank-Logo.wine.png",
-      "responseType": "json",
-      "attributes": ["{creditScore: score, high_score: high_score, grade_name: grade_name}"],
-      "preprocess": "function process(jsonString) { const s =JSON.parse(jsonString); return {score: s.creditScoreOutlineResponse.creditScore.currentCreditScoreSummary.creditRiskScore, high_score: s.creditScoreOutlineResponse.creditScore.currentCreditScoreSummary.creditRiskScore > 700, grade_name: s.creditScoreOutlineResponse.creditScore.currentCreditScoreSummary.creditScoreGradeName} }"
-    }"#;
+    if !after_bracket {
+        parts.push(PathSegment::Key(current));
+    }
+    Ok(parts)
+}
 
-    #[test]
-    fn test_chase_provider() {
-        let provider: Provider =
-            serde_json::from_str(CHASE_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&CHASE_RESPONSE_TEXT)
-            .expect("Failed to preprocess response");
-        let result = provider
-            .get_attributes(&result)
-            .expect("Failed to get attributes");
-        println!("result: {:?}", result);
-        // assert_eq!(result.len(), 2);
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Read the condition of a `[?condition]` filter up to its closing bracket
+fn parse_filter_condition(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    expr: &str,
+) -> Result<String, String> {
+    let mut condition = String::new();
+    let mut depth = 0;
+    let mut in_quotes: Option<char> = None;
+
+    for ch in chars.by_ref() {
+        match ch {
+            '\'' | '"' => match in_quotes {
+                Some(quote) if quote == ch => in_quotes = None,
+                None => in_quotes = Some(ch),
+                _ => {}
+            },
+            _ if in_quotes.is_some() => {}
+            '[' => depth += 1,
+            ']' if depth == 0 => return Ok(condition),
+            ']' => depth -= 1,
+            _ => {}
+        }
+        condition.push(ch);
     }
+    Err(format!("Unterminated filter in '{}'", expr))
+}
 
-    const UBEREATS_RESPONSE_TEXT: &str = r#"
-  {
-  "status": "success",
-  "data": {
-    "ordersMap": {
-      "6a58f37d-5258-4ac7-902c-2c9c26d72259": {
-        "baseEaterOrder": {
-          "uuid": "6a58f37d-5258-4ac7-902c-2c9c26d72259",
-          "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-          "isCancelled": false,
-          "isCompleted": true,
-          "completedAt": "2024-08-19T20:38:34.000Z",
-          "lastStateChangeAt": "2024-08-19T20:38:34.000Z",
-          "orderStateChanges": [
-            {
-              "stateChangeTime": "2024-08-19T19:31:56.000Z",
-              "type": "CREATED",
-              "changingEntityUuid": null
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:31:56.000Z",
-              "type": "OFFERED",
-              "changingEntityUuid": null
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:32:01.000Z",
-              "type": "ASSIGNED",
-              "changingEntityUuid": null
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Returns the split arguments if the expression is a call to the given function,
+/// checking that it was called with the number of arguments in the function table
+fn function_args(expr: &str, name: &str) -> Result<Option<Vec<String>>, String> {
+    let arity = function_arity(name).expect("function is in the function table");
+    let inner = match expr
+        .strip_prefix(name)
+        .and_then(|rest| rest.trim_start().strip_prefix('('))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        // The closing parenthesis must belong to this call, not to a nested one
+        Some(inner) if has_balanced_parens(inner) => inner,
+        _ => return Ok(None),
+    };
+
+    let args = split_attribute_fields(inner)?;
+    if args.len() != arity {
+        return Err(arity_error(name, arity, args.len()));
+    }
+    Ok(Some(args))
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn has_balanced_parens(content: &str) -> bool {
+    let mut paren_count = 0;
+    let mut in_backticks = false;
+    let mut in_quotes: Option<char> = None;
+
+    for ch in content.chars() {
+        match ch {
+            '\'' | '"' if !in_backticks => match in_quotes {
+                Some(quote) if quote == ch => in_quotes = None,
+                None => in_quotes = Some(ch),
+                _ => {}
             },
-            {
-              "stateChangeTime": "2024-08-19T20:38:34.000Z",
-              "type": "COMPLETED",
-              "changingEntityUuid": null
+            _ if in_quotes.is_some() => {}
+            '`' => in_backticks = !in_backticks,
+            '(' if !in_backticks => paren_count += 1,
+            ')' if !in_backticks => {
+                paren_count -= 1;
+                if paren_count < 0 {
+                    return false;
+                }
             }
-          ],
-          "deliveryStateChanges": [
-            {
-              "stateChangeTime": "2024-08-19T19:41:49.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:43:03.000Z",
-              "type": "UNKNOWN"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:44:33.000Z",
-              "type": "CANCELED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:44:50.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:45:03.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:45:19.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:46:03.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:46:35.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:47:03.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:47:19.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:48:03.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:48:19.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:48:48.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:51:34.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:52:19.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:52:34.000Z",
-              "type": "DISPATCHED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:53:33.000Z",
-              "type": "UNKNOWN"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:55:39.000Z",
-              "type": "PICKUP_ARRIVED"
-            },
-            {
-              "stateChangeTime": "2024-08-19T19:59:31.000Z",
-              "type": "BEGINTRIP"
-            },
-            {
-              "stateChangeTime": "2024-08-19T20:37:11.000Z",
-              "type": "PICKUP_ARRIVED"
+            _ => {}
+        }
+    }
+    paren_count == 0
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Returns the function name if the expression has the shape of a function call
+fn function_call_name(expr: &str) -> Option<&str> {
+    if !expr.ends_with(')') {
+        return None;
+    }
+    let name = expr[..expr.find('(')?].trim_end();
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {}
+        _ => return None,
+    }
+    if chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Returns the number of arguments of a function in the function table
+fn function_arity(name: &str) -> Option<usize> {
+    FUNCTIONS
+        .iter()
+        .find(|(function, _)| *function == name)
+        .map(|(_, arity)| *arity)
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn arity_error(name: &str, arity: usize, got: usize) -> String {
+    format!(
+        "{} expects {} argument{}, got {}",
+        name,
+        arity,
+        if arity == 1 { "" } else { "s" },
+        got
+    )
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Checks every function call in the expression against the function table, returning an error
+/// for the first call to an unknown function or with the wrong number of arguments
+fn check_function_calls(expr: &str) -> Result<(), String> {
+    let mut name = String::new();
+    let mut in_backticks = false;
+    let mut in_quotes: Option<char> = None;
+
+    for (i, ch) in expr.char_indices() {
+        match ch {
+            '\'' | '"' if !in_backticks => match in_quotes {
+                Some(quote) if quote == ch => in_quotes = None,
+                None => in_quotes = Some(ch),
+                _ => {}
             },
-            {
-              "stateChangeTime": "2024-08-19T20:38:34.000Z",
-              "type": "COMPLETED"
-            }
-          ],
-          "shoppingCart": {
-            "cartUuid": "8b3a9eb6-d13a-42b8-b862-2a96c19e8d99",
-            "items": [
-              {
-                "uuid": "c7ff3e87-932d-58cd-9f58-cd324172feb6",
-                "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-                "shoppingCartItemUuid": "7cec3d83-c757-410e-bb93-8a48fc6d5e58",
-                "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
-                "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
-                "title": "Thai Coconut Curry Soup",
-                "price": 875,
-                "quantity": 1,
-                "specialInstructions": "",
-                "customizations": [],
-                "cartItemCustomizations": {},
-                "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
-                "rating": null,
-                "itemQuantity": {
-                  "inSellableUnit": {
-                    "value": {
-                      "coefficient": 1,
-                      "exponent": 0
-                    },
-                    "measurementUnit": {
-                      "measurementType": "MEASUREMENT_TYPE_COUNT",
-                      "length": null,
-                      "weight": null,
-                      "volume": null
-                    },
-                    "measurementUnitAbbreviationText": null
-                  },
-                  "inPriceableUnit": null
-                }
-              },
-              {
-                "uuid": "5151f82e-8b5a-5932-b50c-9be8d212c5f4",
-                "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-                "shoppingCartItemUuid": "c1f8bac1-c5be-4694-b661-178e9eb90af3",
-                "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
-                "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
-                "title": "Taiwanese Fried Cashew Mozzarella Croquettes",
-                "price": 1125,
-                "quantity": 1,
-                "specialInstructions": "",
-                "customizations": [
-                  {
-                    "uuid": "c0d24887-fb53-5759-8725-9d3a8669ed97",
-                    "title": "Sauce (Mozzarella Balls)",
-                    "childOptions": {
-                      "options": [
-                        {
-                          "price": 0,
-                          "quantity": 1,
-                          "title": "Spicy Ketchup on the side",
-                          "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
-                          "childCustomizationList": []
+            _ if in_quotes.is_some() => {}
+            '`' => in_backticks = !in_backticks,
+            _ if in_backticks => {}
+            '(' => {
+                let is_identifier = name
+                    .chars()
+                    .next()
+                    .is_some_and(|first| first.is_ascii_alphabetic() || first == '_');
+                if is_identifier {
+                    let arity =
+                        function_arity(&name).ok_or_else(|| unknown_function_error(&name))?;
+                    // Unbalanced calls are reported when the expression is evaluated
+                    if let Some(inner) = call_arguments(&expr[i + 1..]) {
+                        let got = split_attribute_fields(inner)?.len();
+                        if got != arity {
+                            return Err(arity_error(&name, arity, got));
                         }
-                      ]
-                    },
-                    "groupId": 0
-                  }
-                ],
-                "cartItemCustomizations": {
-                  "c0d24887-fb53-5759-8725-9d3a8669ed97+0": [
-                    {
-                      "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
-                      "price": 0,
-                      "quantity": 1,
-                      "title": "Spicy Ketchup on the side",
-                      "defaultQuantity": 0,
-                      "customizationMeta": {
-                        "title": "Sauce (Mozzarella Balls)",
-                        "isPickOne": false
-                      }
                     }
-                  ]
-                },
-                "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
-                "rating": null,
-                "itemQuantity": {
-                  "inSellableUnit": {
-                    "value": {
-                      "coefficient": 1,
-                      "exponent": 0
-                    },
-                    "measurementUnit": {
-                      "measurementType": "MEASUREMENT_TYPE_COUNT",
-                      "length": null,
-                      "weight": null,
-                      "volume": null
-                    },
-                    "measurementUnitAbbreviationText": null
-                  },
-                  "inPriceableUnit": null
                 }
-              },
-              {
-                "uuid": "48a6aa8d-a703-5357-b873-772351b5fde8",
-                "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-                "shoppingCartItemUuid": "6aae0ca3-a184-4e47-a05c-8b10ee2d3563",
-                "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
-                "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
-                "title": "Firecracker Cauliflower",
-                "price": 1125,
-                "quantity": 1,
-                "specialInstructions": "",
-                "customizations": [
-                  {
-                    "uuid": "ae63620e-de6d-51f6-bb25-a75e990d6ea2",
-                    "title": "Substitute Firecracker sauce",
-                    "childOptions": {
-                      "options": [
-                        {
-                          "price": 0,
-                          "quantity": 1,
-                          "title": "Sub for Teriyaki sauce",
-                          "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
-                          "childCustomizationList": []
-                        }
-                      ]
-                    },
-                    "groupId": 0
-                  }
-                ],
-                "cartItemCustomizations": {
-                  "ae63620e-de6d-51f6-bb25-a75e990d6ea2+0": [
-                    {
-                      "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
-                      "price": 0,
-                      "quantity": 1,
-                      "title": "Sub for Teriyaki sauce",
-                      "defaultQuantity": 0,
-                      "customizationMeta": {
-                        "title": "Substitute Firecracker sauce",
-                        "isPickOne": false
-                      }
-                    }
-                  ]
-                },
-                "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
-                "rating": null,
-                "itemQuantity": {
-                  "inSellableUnit": {
-                    "value": {
-                      "coefficient": 1,
-                      "exponent": 0
-                    },
-                    "measurementUnit": {
-                      "measurementType": "MEASUREMENT_TYPE_COUNT",
-                      "length": null,
-                      "weight": null,
-                      "volume": null
-                    },
-                    "measurementUnitAbbreviationText": null
-                  },
-                  "inPriceableUnit": null
-                }
-              }
-            ],
-            "currencyCode": "USD"
-          },
-          "currencyCode": "USD",
-          "isScheduledOrder": false,
-          "numItems": 3,
-          "totalQuantity": 3,
-          "fulfillmentType": "DELIVERY",
-          "userGroupedItems": [
-            {
-              "items": [
-                {
-                  "uuid": "c7ff3e87-932d-58cd-9f58-cd324172feb6",
-                  "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-                  "shoppingCartItemUuid": "7cec3d83-c757-410e-bb93-8a48fc6d5e58",
-                  "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
-                  "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
-                  "title": "Thai Coconut Curry Soup",
-                  "price": 875,
-                  "quantity": 1,
-                  "specialInstructions": "",
-                  "customizations": [],
-                  "cartItemCustomizations": {},
-                  "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
-                  "rating": null,
-                  "itemQuantity": {
-                    "inSellableUnit": {
-                      "value": {
-                        "coefficient": 1,
-                        "exponent": 0
-                      },
-                      "measurementUnit": {
-                        "measurementType": "MEASUREMENT_TYPE_COUNT",
-                        "length": null,
-                        "weight": null,
-                        "volume": null
-                      },
-                      "measurementUnitAbbreviationText": null
-                    },
-                    "inPriceableUnit": null
-                  }
-                },
-                {
-                  "uuid": "5151f82e-8b5a-5932-b50c-9be8d212c5f4",
-                  "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-                  "shoppingCartItemUuid": "c1f8bac1-c5be-4694-b661-178e9eb90af3",
-                  "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
-                  "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
-                  "title": "Taiwanese Fried Cashew Mozzarella Croquettes",
-                  "price": 1125,
-                  "quantity": 1,
-                  "specialInstructions": "",
-                  "customizations": [
-                    {
-                      "uuid": "c0d24887-fb53-5759-8725-9d3a8669ed97",
-                      "title": "Sauce (Mozzarella Balls)",
-                      "childOptions": {
-                        "options": [
-                          {
-                            "price": 0,
-                            "quantity": 1,
-                            "title": "Spicy Ketchup on the side",
-                            "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
-                            "childCustomizationList": []
-                          }
-                        ]
-                      },
-                      "groupId": 0
-                    }
-                  ],
-                  "cartItemCustomizations": {
-                    "c0d24887-fb53-5759-8725-9d3a8669ed97+0": [
-                      {
-                        "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
-                        "price": 0,
-                        "quantity": 1,
-                        "title": "Spicy Ketchup on the side",
-                        "defaultQuantity": 0,
-                        "customizationMeta": {
-                          "title": "Sauce (Mozzarella Balls)",
-                          "isPickOne": false
-                        }
-                      }
-                    ]
-                  },
-                  "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
-                  "rating": null,
-                  "itemQuantity": {
-                    "inSellableUnit": {
-                      "value": {
-                        "coefficient": 1,
-                        "exponent": 0
-                      },
-                      "measurementUnit": {
-                        "measurementType": "MEASUREMENT_TYPE_COUNT",
-                        "length": null,
-                        "weight": null,
-                        "volume": null
-                      },
-                      "measurementUnitAbbreviationText": null
-                    },
-                    "inPriceableUnit": null
-                  }
-                },
-                {
-                  "uuid": "48a6aa8d-a703-5357-b873-772351b5fde8",
-                  "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-                  "shoppingCartItemUuid": "6aae0ca3-a184-4e47-a05c-8b10ee2d3563",
-                  "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
-                  "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
-                  "title": "Firecracker Cauliflower",
-                  "price": 1125,
-                  "quantity": 1,
-                  "specialInstructions": "",
-                  "customizations": [
-                    {
-                      "uuid": "ae63620e-de6d-51f6-bb25-a75e990d6ea2",
-                      "title": "Substitute Firecracker sauce",
-                      "childOptions": {
-                        "options": [
-                          {
-                            "price": 0,
-                            "quantity": 1,
-                            "title": "Sub for Teriyaki sauce",
-                            "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
-                            "childCustomizationList": []
-                          }
-                        ]
-                      },
-                      "groupId": 0
-                    }
-                  ],
-                  "cartItemCustomizations": {
-                    "ae63620e-de6d-51f6-bb25-a75e990d6ea2+0": [
-                      {
-                        "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
-                        "price": 0,
-                        "quantity": 1,
-                        "title": "Sub for Teriyaki sauce",
-                        "defaultQuantity": 0,
-                        "customizationMeta": {
-                          "title": "Substitute Firecracker sauce",
-                          "isPickOne": false
-                        }
-                      }
-                    ]
-                  },
-                  "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
-                  "rating": null,
-                  "itemQuantity": {
-                    "inSellableUnit": {
-                      "value": {
-                        "coefficient": 1,
-                        "exponent": 0
-                      },
-                      "measurementUnit": {
-                        "measurementType": "MEASUREMENT_TYPE_COUNT",
-                        "length": null,
-                        "weight": null,
-                        "volume": null
-                      },
-                      "measurementUnitAbbreviationText": null
-                    },
-                    "inPriceableUnit": null
-                  }
-                }
-              ],
-              "quantity": 3
             }
-          ],
-          "posType": "UNDEFINED",
-          "orderAppVariant": "UBEREATS",
-          "isBackfilledOrder": false,
-          "displayName": "",
-          "billSplitOption": "UNKNOWN_BILL_SPLIT_OPTION",
-          "isOrderCreator": true,
-          "creatorDisplayName": "xxxxxxxxx"
-        },
-        "storeInfo": {
-          "uuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
-          "heroImageUrl": "https://duyt4h9nfnj50.cloudfront.net/sku/c5929f791f8cad88221c2f3e2f6958ea",
-          "title": "Pow Pow",
-          "isOpen": false,
-          "closedMessage": "Closed",
-          "location": {
-            "address": {
-              "address1": "1253 H St NE, Washington, DC 20002, USA",
-              "aptOrSuite": "",
-              "city": "Washington",
-              "country": "US",
-              "postalCode": "20002",
-              "region": "DC",
-              "title": null,
-              "uuid": "f7421339-086d-4a06-b2f7-b9270dbfa137",
-              "eaterFormattedAddress": "1253 H St NE, Washington, DC 20002, USA, Washington, DC 20002",
-              "subtitle": null,
-              "businessName": null,
-              "street": null,
-              "ugcAddressFieldInfo": null
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                name.push(c);
+                continue;
+            }
+            _ => {}
+        }
+        name.clear();
+    }
+    Ok(())
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Returns the arguments of a call, given the text after its opening parenthesis, up to the
+/// matching closing parenthesis
+fn call_arguments(rest: &str) -> Option<&str> {
+    let mut depth = 0;
+    let mut in_backticks = false;
+    let mut in_quotes: Option<char> = None;
+
+    for (i, ch) in rest.char_indices() {
+        match ch {
+            '\'' | '"' if !in_backticks => match in_quotes {
+                Some(quote) if quote == ch => in_quotes = None,
+                None => in_quotes = Some(ch),
+                _ => {}
             },
-            "latitude": 38.9001426,
-            "longitude": -76.988559,
-            "reference": null,
-            "type": null,
-            "placeReferences": null,
-            "interactionType": null,
-            "nickname": null
-          },
-          "storeStatus": {
-            "isActive": true,
-            "notActiveReason": "UNKNOWN"
-          },
-          "contact": {
-            "phoneNumber": "+xxxxxxxxxxx"
-          },
-          "territoryUUID": "d5f8a8fc-e1bb-4309-acb8-aac5b7c97260",
-          "rating": null,
-          "slug": "pow-pow-1253-h-st-ne"
-        },
-        "courierInfo": {
-          "name": ""
-        },
-        "fareInfo": {
-          "totalPrice": 3002.9999999999995,
-          "checkoutInfo": [
-            {
-              "label": "delivery.uber.service_fee",
-              "type": "credit",
-              "rawValue": 0.1,
-              "key": "delivery.uber.service_fee"
-            },
-            {
-              "label": "delivery.uber.service_fee.tax",
-              "type": "credit",
-              "rawValue": 0.01,
-              "key": "delivery.uber.service_fee.tax"
-            },
-            {
-              "label": "Tax on Delivery Fees",
-              "type": "credit",
-              "rawValue": 0.48,
-              "key": "eats.mp.charges.bundled_delivery_fee_taxes"
-            },
-            {
-              "label": "Special Offer",
-              "type": "debit",
-              "rawValue": -10.94,
-              "key": "eats.discounts.store_promotion"
-            },
-            {
-              "label": "Subtotal",
-              "type": "credit",
-              "rawValue": 31.25,
-              "key": "eats_fare.subtotal"
-            },
-            {
-              "label": "Tax",
-              "type": "credit",
-              "rawValue": 2.03,
-              "key": "eats.tax.base"
-            },
-            {
-              "label": "Service Fee and Other Fees",
-              "type": "credit",
-              "rawValue": 4.79,
-              "key": "eats.mp.charges.basket_dependent_fee"
-            },
-            {
-              "label": "Tip",
-              "type": "credit",
-              "rawValue": 2.31,
-              "key": "eats_fare.tip"
-            },
-            {
-              "label": "Total",
-              "type": "credit",
-              "rawValue": 30.029999999999998,
-              "key": "eats_fare.total"
+            _ if in_quotes.is_some() => {}
+            '`' => in_backticks = !in_backticks,
+            '(' if !in_backticks => depth += 1,
+            ')' if !in_backticks && depth == 0 => return Some(&rest[..i]),
+            ')' if !in_backticks => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn unknown_function_error(name: &str) -> String {
+    match suggest_function(name) {
+        Some(suggestion) => format!(
+            "unknown function '{}', did you mean '{}'?",
+            name, suggestion
+        ),
+        None => format!("unknown function '{}'", name),
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Returns the closest supported function name, if any is close enough to be a typo
+fn suggest_function(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    supported_functions()
+        .iter()
+        .map(|candidate| (*candidate, edit_distance(&name, candidate)))
+        .filter(|(_, distance)| *distance <= 2)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(candidate, _)| candidate)
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn evaluate_string_argument(
+    function: &str,
+    arg: &str,
+    data: &serde_json::Value,
+) -> Result<String, String> {
+    match evaluate_field_expression(arg, data)? {
+        serde_json::Value::String(s) => Ok(s),
+        other => Err(format!("{} expects a string, got {:?}", function, other)),
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn evaluate_pattern_argument(arg: &str) -> Result<String, String> {
+    match parse_literal_value(arg)? {
+        serde_json::Value::String(s) => Ok(s),
+        other => Ok(other.to_string()),
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Run a function with a pattern compiled from the shared cache
+fn with_compiled_pattern<T, F>(pattern: &str, f: F) -> Result<T, ProviderError>
+where
+    F: FnOnce(&Regex) -> T,
+{
+    let cached = COMPILED_PATTERN_CACHE.read().unwrap().get(pattern).cloned();
+    let regex = match cached {
+        Some(regex) => regex,
+        None => {
+            let regex = Regex::new(pattern)
+                .map_err(|e| ProviderError::InvalidRegex(pattern.to_string(), e))?;
+            COMPILED_PATTERN_CACHE
+                .write()
+                .unwrap()
+                .insert(pattern.to_string(), regex.clone());
+            regex
+        }
+    };
+    Ok(f(&regex))
+}
+
+/// Normalizes a TLS version name such as `TLSv1_2` or `TLS 1.2` to its number, e.g. `1.2`
+fn normalize_tls_version(version: &str) -> String {
+    let version = version.trim().to_ascii_lowercase().replace(['_', ' '], ".");
+    let version = version.strip_prefix("tls").unwrap_or(&version);
+    let version = version.trim_start_matches(['.', 'v']);
+    version.to_string()
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn find_operator_position(expr: &str, op: &str) -> Option<usize> {
+    let mut paren_count = 0;
+    let mut in_backticks = false;
+
+    for (i, ch) in expr.char_indices() {
+        match ch {
+            '`' => in_backticks = !in_backticks,
+            // Operators of a `[?condition]` filter belong to the filter
+            '(' | '[' if !in_backticks => paren_count += 1,
+            ')' | ']' if !in_backticks => paren_count -= 1,
+            _ if !in_backticks && paren_count == 0 => {
+                if expr[i..].starts_with(op) {
+                    return Some(i);
+                }
             }
-          ]
-        },
-        "ratingInfo": {
-          "isRatable": true,
-          "userRatings": []
-        },
-        "interactionType": "leave_in_lobby"
-      }
-    },
-    "orderUuids": [
-      "6a58f37d-5258-4ac7-902c-2c9c26d72259"
-    ],
-    "paginationData": {
-      "nextCursor": "{\"entity_type\":\"CONSUMER\",\"cursor\":\"MjAyMy0wMy0xMlQyMzowNjowOS43MDRa\"}"
-    },
-    "meta": {
-      "hasMore": true
+            _ => {}
+        }
     }
-  }
+    None
 }
-  "#;
 
-    const UBEREATS_PROVIDER_TEXT: &str = r#"
-       {
-      "id": 5,
-      "host": "ubereats.com",
-      "urlRegex":
-        "^https:\\/\\/www\\.ubereats\\.com\\/_p\\/api\\/getPastOrdersV1.*",
-      "targetUrl": "https://www.ubereats.com/orders",
-      "method": "POST",
-      "title": "Uber eats orders",
-      "description": "Go to your order history",
-      "icon": "https://i.pinimg.com/originals/a3/4a/8c/a34a8c234e27ac9476e7f010f750d136.jpg",
-      "responseType": "json",
-      "attributes": ["{usd_total: USD.totalPrice, usd_count: USD.orderCount}"],
-      "preprocess": "function process(jsonString) { const totals = Object.values(JSON.parse(jsonString).data.ordersMap).reduce((totals, order) => { const price = order.fareInfo.totalPrice; const currency = order.baseEaterOrder.currencyCode; if (typeof price === 'number' && currency) { if (!totals[currency]) { totals[currency] = { totalPrice: 0, orderCount: 0 }; } totals[currency].totalPrice += price; totals[currency].orderCount += 1; } return totals; }, {}); Object.keys(totals).forEach(currency => totals[currency].totalPrice = (totals[currency].totalPrice / 100).toFixed(2)); return totals; } "
-    }"#;
+/// Converts a number or a numeric string to a number
+///
+/// When `lenient` is set, currency symbols, thousands separators and whitespace are stripped from
+/// strings before parsing, so `"$1,234.50"` converts to `1234.5`.
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn value_to_number(value: serde_json::Value, lenient: bool) -> Result<serde_json::Value, String> {
+    let s = match &value {
+        serde_json::Value::Number(n) => return Ok(serde_json::Value::Number(n.clone())),
+        serde_json::Value::String(s) if lenient => s
+            .chars()
+            .filter(|c| !matches!(c, '$' | '€' | '£' | '¥' | ',') && !c.is_whitespace())
+            .collect(),
+        serde_json::Value::String(s) => s.clone(),
+        _ => return Err(format!("Cannot convert {:?} to number", value)),
+    };
+
+    if let Some(number) = parse_integer(&s) {
+        return Ok(serde_json::Value::Number(number));
+    }
+    if let Ok(f) = s.parse::<f64>() {
+        return serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| format!("Invalid number value: {} (NaN or infinite)", f));
+    }
+    Err(format!("Cannot convert {:?} to number", value))
+}
 
-    #[cfg(not(target_arch = "wasm32"))]
-    #[test]
-    fn test_ubereats_provider() {
-        let provider: Provider =
-            serde_json::from_str(UBEREATS_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&UBEREATS_RESPONSE_TEXT)
-            .expect("Failed to preprocess response");
-        let result = provider
-            .get_attributes(&result)
-            .expect("Failed to get attributes");
-        println!("{:?}", result);
-        assert!(result.contains(&"usd_total: \"30.03\"".to_string()));
-        assert!(result.contains(&"usd_count: 1".to_string()));
+/// Parses an integer literal, keeping the full precision of values above 2^53
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn parse_integer(value: &str) -> Option<serde_json::Number> {
+    if let Ok(n) = value.parse::<u64>() {
+        return Some(serde_json::Number::from(n));
     }
+    value.parse::<i64>().ok().map(serde_json::Number::from)
+}
 
-    const REDDIT_PROVIDER_TEXT: &str = r#"{
-      "id": 3,
-      "host": "reddit.com",
-      "urlRegex": "^https:\\/\\/www\\.reddit\\.com\\/user\\/[a-zA-Z0-9]+.*\\/about\\.json$",
-      "targetUrl": "https://www.reddit.com",
-      "method": "GET",
-      "title": "Reddit account",
-      "description": "Go to your profile",
-      "icon": "https://seeklogo.com/images/R/reddit-icon-new-2023-logo-3F12137D65-seeklogo.com.png",
-      "responseType": "html",
-      "actionSelectors": ["a[href^=\"/user/\"][href$=\"/\"]"],
-      "attributes": ["{karma: karma}"],
-      "preprocess": "function process(jsonString) { const data = JSON.parse(jsonString); return { karma: data.data.total_karma } }"
-    }"#;
+/// Compares two numbers, only falling back to f64 when one of them is fractional
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn compare_numbers(left: &serde_json::Number, right: &serde_json::Number) -> Ordering {
+    if let (Some(l), Some(r)) = (left.as_u64(), right.as_u64()) {
+        return l.cmp(&r);
+    }
+    if let (Some(l), Some(r)) = (left.as_i64(), right.as_i64()) {
+        return l.cmp(&r);
+    }
+    // Only one side is above i64::MAX and the other is negative
+    if left.is_u64() && right.is_i64() {
+        return Ordering::Greater;
+    }
+    if left.is_i64() && right.is_u64() {
+        return Ordering::Less;
+    }
+    match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l.partial_cmp(&r).unwrap_or(Ordering::Equal),
+        _ => Ordering::Equal,
+    }
+}
 
-    const REDDIT_RESPONSE_TEXT: &str = r#"{
-  "kind": "t2",
-  "data": {
-    "is_employee": false,
-    "has_visited_new_profile": false,
-    "is_friend": false,
-    "pref_no_profanity": true,
-    "has_external_account": false,
-    "pref_geopopular": "",
-    "pref_show_trending": true,
-    "subreddit": {
-      "default_set": true,
-      "user_is_contributor": false,
-      "banner_img": "",
-      "allowed_media_in_comments": [],
-      "user_is_banned": false,
-      "free_form_reports": true,
-      "community_icon": null,
-      "show_media": true,
-      "icon_color": "",
-      "user_is_muted": null,
-      "display_name": "u_xxxxxx",
-      "header_img": null,
-      "title": "",
-      "coins": 0,
-      "previous_names": [],
-      "over_18": false,
-      "icon_size": [
-        256,
-        256
-      ],
-      "primary_color": "",
-      "icon_img": "https://styles.redditmedia.com/t5_3w62uh/styles/profileIcon_ldu1icwig3c81.png?width=256&amp;height=256&amp;crop=256:256,smart&amp;s=15bd7ab8be642a980566f9075fc5815f508935e7",
-      "description": "",
-      "submit_link_label": "",
-      "header_size": null,
-      "restrict_posting": true,
-      "restrict_commenting": false,
-      "subscribers": 1,
-      "submit_text_label": "",
-      "is_default_icon": false,
-      "link_flair_position": "",
-      "display_name_prefixed": "u/xxxxxx",
-      "key_color": "",
-      "name": "t5_3w62uh",
-      "is_default_banner": true,
-      "url": "/user/xxxxxx/",
-      "quarantine": false,
-      "banner_size": null,
-      "user_is_moderator": true,
-      "accept_followers": true,
-      "public_description": "Ads and Premium services engineer @brave\n\nComputer languages enthusiast\nSwore oath to protect privacy",
-      "link_flair_enabled": false,
-      "disable_contributor_requests": false,
-      "subreddit_type": "user",
-      "user_is_subscriber": false
-    },
-    "pref_show_presence": true,
-    "snoovatar_img": "",
-    "snoovatar_size": null,
-    "gold_expiration": null,
-    "has_gold_subscription": false,
-    "is_sponsor": false,
-    "num_friends": 0,
-    "features": {
-      "modmail_harassment_filter": true,
-      "mod_service_mute_writes": true,
-      "promoted_trend_blanks": true,
-      "show_amp_link": true,
-      "chat": true,
-      "is_email_permission_required": false,
-      "mod_awards": true,
-      "mweb_xpromo_revamp_v3": {
-        "owner": "growth",
-        "variant": "control_1",
-        "experiment_id": 480
-      },
-      "mweb_xpromo_revamp_v2": {
-        "owner": "growth",
-        "variant": "treatment_4",
-        "experiment_id": 457
-      },
-      "awards_on_streams": true,
-      "mweb_xpromo_modal_listing_click_daily_dismissible_ios": true,
-      "chat_subreddit": true,
-      "cookie_consent_banner": true,
-      "modlog_copyright_removal": true,
-      "do_not_track": true,
-      "images_in_comments": true,
-      "mod_service_mute_reads": true,
-      "chat_user_settings": true,
-      "use_pref_account_deployment": true,
-      "mweb_xpromo_interstitial_comments_ios": true,
-      "mweb_xpromo_modal_listing_click_daily_dismissible_android": true,
-      "premium_subscriptions_table": true,
-      "mweb_xpromo_interstitial_comments_android": true,
-      "crowd_control_for_post": true,
-      "mweb_sharing_web_share_api": {
-        "owner": "growth",
-        "variant": "control_1",
-        "experiment_id": 314
-      },
-      "chat_group_rollout": true,
-      "resized_styles_images": true,
-      "noreferrer_to_noopener": true,
-      "expensive_coins_package": true
-    },
-    "can_edit_name": false,
-    "is_blocked": false,
-    "verified": true,
-    "new_modmail_exists": null,
-    "pref_autoplay": true,
-    "coins": 0,
-    "has_paypal_subscription": false,
-    "has_subscribed_to_premium": false,
-    "id": "a2gghybr",
-    "can_create_subreddit": true,
-    "over_18": true,
-    "is_gold": false,
-    "is_mod": false,
-    "awarder_karma": 0,
-    "suspension_expiration_utc": null,
-    "has_stripe_subscription": false,
-    "is_suspended": false,
-    "pref_video_autoplay": true,
-    "in_chat": true,
-    "has_android_subscription": false,
-    "in_redesign_beta": true,
-    "icon_img": "https://styles.redditmedia.com/t5_3w62uh/styles/profileIcon_ldu1icwig3c81.png?width=256&amp;height=256&amp;crop=256:256,smart&amp;s=15bd7ab8be642a980566f9075fc5815f508935e7",
-    "has_mod_mail": false,
-    "pref_nightmode": false,
-    "awardee_karma": 0,
-    "hide_from_robots": false,
-    "password_set": true,
-    "modhash": "hf34fcz1wh637b593a4a2303d3f9193e2b9446faf6c90dee7a",
-    "link_karma": 1,
-    "force_password_reset": false,
-    "total_karma": 1,
-    "inbox_count": 2,
-    "pref_top_karma_subreddits": true,
-    "has_mail": true,
-    "pref_show_snoovatar": false,
-    "name": "xxxxxx",
-    "pref_clickgadget": 5,
-    "created": 1612356751,
-    "has_verified_email": true,
-    "gold_creddits": 0,
-    "created_utc": 1612356751,
-    "has_ios_subscription": false,
-    "pref_show_twitter": false,
-    "in_beta": false,
-    "comment_karma": 0,
-    "accept_followers": true,
-    "has_subscribed": true
-  }
-}"#;
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+fn parse_literal_value(value_str: &str) -> Result<serde_json::Value, String> {
+    let value_str = value_str.trim();
 
-    #[test]
-    fn test_reddit_provider() {
-        let matchurl = "https://www.reddit.com/user/xxxxxx/about.json";
-        let provider: Provider =
-            serde_json::from_str(REDDIT_PROVIDER_TEXT).expect("Failed to parse provider");
-        provider
-            .check_url_method(matchurl, "POST")
-            .expect("Failed to check url method");
-        let result = provider
-            .preprocess_response(&REDDIT_RESPONSE_TEXT)
-            .expect("Failed to preprocess response");
-        let attributes = provider
-            .get_attributes(&result)
-            .expect("Failed to get attributes");
-        assert_eq!(attributes.len(), 1);
-        assert_eq!(attributes[0], "karma: 1");
+    if value_str.starts_with('`') && value_str.ends_with('`') {
+        let inner = &value_str[1..value_str.len() - 1];
+        if let Some(number) = parse_integer(inner) {
+            return Ok(serde_json::Value::Number(number));
+        }
+        if let Ok(num) = inner.parse::<f64>() {
+            if let Some(number) = serde_json::Number::from_f64(num) {
+                return Ok(serde_json::Value::Number(number));
+            } else {
+                return Err(format!(
+                    "Invalid number value in backticks: {} (NaN or infinite)",
+                    num
+                ));
+            }
+        } else {
+            return Ok(serde_json::Value::String(inner.to_string()));
+        }
     }
 
-    const ROBINHOOD_RESPONSE_TEXT: &str = r#"{
-  "title": null,
-  "weight": null,
-  "include_all_hours": true,
-  "line_ordering": [],
-  "performance_baseline": {
-    "currency_code": "USD",
-    "currency_id": "1072fc76-1862-41ab-82c2-485837590762",
-    "amount": "10245.5079335464"
-  }
+    if let Some(number) = parse_integer(value_str) {
+        return Ok(serde_json::Value::Number(number));
+    }
+
+    if let Ok(num) = value_str.parse::<f64>() {
+        if let Some(number) = serde_json::Number::from_f64(num) {
+            return Ok(serde_json::Value::Number(number));
+        } else {
+            return Err(format!("Invalid number value: {} (NaN or infinite)", num));
+        }
+    }
+
+    if (value_str.starts_with('"') && value_str.ends_with('"'))
+        || (value_str.starts_with('\'') && value_str.ends_with('\''))
+    {
+        let inner = &value_str[1..value_str.len() - 1];
+        return Ok(serde_json::Value::String(inner.to_string()));
+    }
+
+    if let Some(path) = value_str.strip_prefix('$') {
+        return attribute_output(path);
+    }
+
+    if let Some(value) = constant(value_str) {
+        return Ok(value);
+    }
+
+    Ok(serde_json::Value::String(value_str.to_string()))
 }
-    "#;
 
-    const ROBINHOOD_PROVIDER_TEXT: &str = r#"{
-      "id": 2,
-      "host": "robinhood.com",
-      "urlRegex":
-        "^https:\\/\\/bonfire\\.robinhood\\.com\\/portfolio\\/performance\\/[a-zA-Z0-9]+(\\?.*)?$",
-      "targetUrl": "https://robinhood.com/",
-      "method": "GET",
-      "title": "Robinhood balance greater than $10,000",
-      "description": "Go to your portfolio",
-      "icon": "https://upload.wikimedia.org/wikipedia/commons/b/b9/Robinhood_Logo.png",
-      "responseType": "json",
-      "attributes": ["{over_10k: to_number(performance_baseline.amount) >`10000` && performance_baseline.currency_code == 'USD'}"]
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Run `f` with the constants of a provider resolvable by [`constant`], restoring the constants
+/// in scope before
+fn with_constants<T>(constants: Option<&HashMap<String, Value>>, f: impl FnOnce() -> T) -> T {
+    let previous =
+        EXPRESSION_CONSTANTS.with(|scope| scope.replace(constants.cloned().unwrap_or_default()));
+    let result = f();
+    EXPRESSION_CONSTANTS.with(|scope| scope.replace(previous));
+    result
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Get the value of the constant of the provider being evaluated with the given name, if any
+fn constant(name: &str) -> Option<Value> {
+    EXPRESSION_CONSTANTS.with(|scope| scope.borrow().get(name).cloned())
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Run `f` with no attribute outputs, restoring the outputs of the attributes evaluated before
+fn with_attribute_outputs<T>(f: impl FnOnce() -> T) -> T {
+    let previous = ATTRIBUTE_OUTPUTS.with(|outputs| outputs.take());
+    let result = f();
+    ATTRIBUTE_OUTPUTS.with(|outputs| outputs.replace(previous));
+    result
+}
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+/// Evaluate a field path, e.g. `score` or `profile.name`, against the outputs of the attributes
+/// evaluated so far
+fn attribute_output(path: &str) -> Result<Value, String> {
+    let outputs = ATTRIBUTE_OUTPUTS.with(|outputs| Value::Object(outputs.borrow().clone()));
+    evaluate_field_expression(path, &outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(target_arch = "wasm32"))]
+    use tokio;
+
+    /// Next id of the providers built by [`test_provider`]
+    static NEXT_TEST_PROVIDER_ID: std::sync::atomic::AtomicU32 =
+        std::sync::atomic::AtomicU32::new(1000);
+
+    /// Build a GET json provider of `https://test.com/*` with the fields of `overrides`
+    ///
+    /// Each provider gets its own id, as the ids of the providers of a config must be unique.
+    fn test_provider(overrides: Value) -> Provider {
+        let mut provider = serde_json::json!({
+            "id": NEXT_TEST_PROVIDER_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            "host": "test.com",
+            "urlRegex": r"^https://test\.com/.*$",
+            "targetUrl": "https://test.com",
+            "method": "GET",
+            "title": "Test Provider",
+            "description": "Test provider",
+            "icon": "test",
+            "responseType": "json"
+        });
+        if let (Some(provider), Value::Object(overrides)) = (provider.as_object_mut(), overrides) {
+            provider.extend(overrides);
+        }
+        serde_json::from_value(provider).expect("Failed to parse provider")
+    }
+
+    /// Build a config of `providers` with the defaults of every other setting
+    fn test_config(providers: Vec<Provider>) -> Config {
+        Config {
+            version: "1".to_string(),
+            expected_pcrs: HashMap::new(),
+            providers,
+            deny_list: Vec::new(),
+            max_attributes: None,
+            allow_scripts: None,
+        }
+    }
+
+    /// The name and `name=value` fields of a span or event
+    type RecordedTrace = (String, Vec<String>);
+
+    /// The spans created and events logged in [`record_tracing`]
+    #[derive(Debug, Default)]
+    struct Recording {
+        spans: Vec<RecordedTrace>,
+        events: Vec<RecordedTrace>,
+    }
+
+    /// Collects the `name=value` fields of a span or event
+    struct FieldRecorder(Vec<String>);
+
+    impl tracing::field::Visit for FieldRecorder {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    /// Subscriber which records every span and event into a [`Recording`]
+    #[derive(Default)]
+    struct Recorder {
+        next_id: std::sync::atomic::AtomicU64,
+        recording: Arc<std::sync::Mutex<Recording>>,
+    }
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = FieldRecorder(Vec::new());
+            attrs.record(&mut fields);
+            let span = (attrs.metadata().name().to_string(), fields.0);
+            self.recording.lock().unwrap().spans.push(span);
+            let id = self
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            tracing::span::Id::from_u64(id + 1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = FieldRecorder(Vec::new());
+            event.record(&mut fields);
+            let event = (event.metadata().name().to_string(), fields.0);
+            self.recording.lock().unwrap().events.push(event);
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    /// Run `f` with a [`Recorder`] as the default subscriber, returning its result along with
+    /// the spans and events it traced
+    fn record_tracing<T>(f: impl FnOnce() -> T) -> (T, Recording) {
+        let recorder = Recorder::default();
+        let recording = recorder.recording.clone();
+        let result = tracing::subscriber::with_default(recorder, f);
+        let recording = std::mem::take(&mut *recording.lock().unwrap());
+        (result, recording)
+    }
+
+    const MISSING_ATTRIBUTES_PROVIDER_TEXT: &str = r#"{
+        "id": 7,
+        "host": "github.com",
+        "urlRegex": "^https:\\/\\/api\\.github\\.com\\/users\\/[a-zA-Z0-9]+(\\?.*)?$",
+        "targetUrl": "https://github.com",
+        "method": "GET",
+        "title": "Github profile",
+        "description": "Go to your profile",
+        "icon": "https://github.githubassets.com/images/modules/logos_page/GitHub-Mark.png",  
+        "responseType": "json"
     }"#;
 
     #[test]
-    fn test_robinhood_provider() {
-        let provider: Provider =
-            serde_json::from_str(ROBINHOOD_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&ROBINHOOD_RESPONSE_TEXT)
+    fn test_missing_attributes_provider() {
+        let provider: Provider = serde_json::from_str(MISSING_ATTRIBUTES_PROVIDER_TEXT)
+            .expect("Failed to parse provider");
+        let response_text = r#"{
+            "login": "xxxxxx",
+            "id": 4715448,    
+            "public_repos": 47,
+            "public_gists": 0
+    }"#;
+        let processed_response = provider
+            .preprocess_response(&response_text)
             .expect("Failed to preprocess response");
-        let attributes = provider
-            .get_attributes(&result)
+        let result = provider
+            .get_attributes(&processed_response)
             .expect("Failed to get attributes");
-        println!("{:?}", attributes);
-        assert_eq!(attributes.len(), 1);
-        assert_eq!(attributes[0], "over_10k: true");
+        assert_eq!(result.len(), 0);
     }
 
-    const TWITTER_BIO_RESPONSE_TEXT: &str = r#"{
-  "data": {
-    "user": {
-      "result": {
-        "__typename": "User",
-        "id": "VRNlcjoxNjc2OTI4NDU2MjI1ODk4NDk2",
-        "rest_id": "1676928456225898496",
-        "affiliates_highlighted_label": {},
-        "avatar": {
-          "image_url": "https://pbs.twimg.com/profile_images/1938401595659534336/4FfZgohs_normal.jpg"
-        },
-        "core": {
-          "created_at": "Thu Jan 06 12:18:01 +0000 2022",
-          "name": "fppp290",
-          "screen_name": "Johndoe93"
-        },
-        "dm_permissions": {
-          "can_dm": true
-        },
-        "has_graduated_access": true,
-        "is_blue_verified": true,
-        "legacy": {
-          "default_profile": true,
-          "default_profile_image": false,
-          "description": "It starts with freysa...",
-          "entities": {
-            "description": {
-              "urls": []
-            }
-          },
-          "fast_followers_count": 0,
-          "favourites_count": 182,
-          "followers_count": 35,
-          "friends_count": 180,
-          "has_custom_timelines": false,
-          "is_translator": false,
-          "listed_count": 1,
-          "media_count": 7,
-          "needs_phone_verification": false,
-          "normal_followers_count": 35,
-          "pinned_tweet_ids_str": [
-            "1875293532711186617"
-          ],
-          "possibly_sensitive": false,
-          "profile_banner_url": "https://pbs.twimg.com/profile_banners/1676928456225898496/1738727374",
-          "profile_interstitial_type": "",
-          "statuses_count": 565,
-          "translator_type": "none",
-          "want_retweets": false,
-          "withheld_in_countries": []
-        },
-        "location": {
-          "location": ""
-        },
-        "media_permissions": {
-          "can_media_tag": true
-        },
-        "parody_commentary_fan_label": "None",
-        "profile_image_shape": "Circle",
-        "privacy": {
-          "protected": false
-        },
-        "relationship_perspectives": {
-          "following": false
-        },
-        "tipjar_settings": {},
-        "verification": {
-          "verified": false
-        },
-        "legacy_extended_profile": {
-          "birthdate": {
-            "day": 12,
-            "month": 1,
-            "year": 1994,
-            "visibility": "Self",
-            "year_visibility": "Self"
-          }
-        },
-        "is_profile_translatable": false,
-        "has_hidden_subscriptions_on_profile": false,
-        "verification_info": {
-          "is_identity_verified": false,
-          "reason": {
-            "description": {
-              "text": "This account is verified. Learn more",
-              "entities": [
-                {
-                  "from_index": 26,
-                  "to_index": 36,
-                  "ref": {
-                    "url": "https://help.twitter.com/managing-your-account/about-twitter-verified-accounts",
-                    "url_type": "ExternalUrl"
-                  }
-                }
-              ]
-            },
-            "verified_since_msec": "1735851543944"
-          }
-        },
-        "highlights_info": {
-          "can_highlight_tweets": true,
-          "highlighted_tweets": "1"
-        },
-        "user_seed_tweet_count": 0,
-        "premium_gifting_eligible": false,
-        "business_account": {},
-        "creator_subscriptions_count": 0
-      }
-    }
-  }
-}"#;
-
-    const TWITTER_BIO_PROVIDER_TEXT: &str = r#"{
-        "id": 2,
-        "host": "x.com",
-        "urlRegex": "^https:\\/\\/x\\.com\\/i\\/api\\/graphql\\/[\\w\\d]+\\/UserByScreenName(\\?.*)?$",
-        "targetUrl": "https://www.x.com/home",
-        "method": "GET",
-        "title": "Verify X subscription",
-        "description": "",
-        "icon": "twitterPremium",
-        "responseType": "json",
-        "actionSelectors": ["div[data-testid^='UserAvatar-Container-'] a[role=\"link\"] img[alt][draggable=\"true\"]"],
-        "preprocess": "function process(jsonString) { const object = JSON.parse(jsonString); const parts = object.data.user.result.core.created_at.split(' '); const time = parts[3].split(':'); const months = { Jan: 0, Feb: 1, Mar: 2, Apr: 3, May: 4, Jun: 5, Jul: 6, Aug: 7, Sep: 8, Oct: 9, Nov: 10, Dec: 11 }; const createdAt = new Date(parseInt(parts[5]), months[parts[1]], parseInt(parts[2]), parseInt(time[0]), parseInt(time[1]), parseInt(time[2])); const targetDate = new Date('2023-03-01'); const PreGPT4 = createdAt < targetDate; const verified = object.data.user.result.is_blue_verified; if(!verified || !PreGPT4) throw new Error('Invalid account'); return { verified, PreGPT4 }; }",
-        "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
-    }"#;
+    const HTML_MISSING_ATTRIBUTES_PROVIDER_TEXT: &str = r#"{
+      "id": 7,
+      "host": "github.com",
+      "urlRegex": "^https:\\/\\/api\\.github\\.com\\/users\\/[a-zA-Z0-9]+(\\?.*)?$",
+      "targetUrl": "https://github.com",
+      "method": "GET",
+      "title": "Github profile",
+      "description": "Go to your profile",
+      "icon": "https://github.githubassets.com/images/modules/logos_page/GitHub-Mark.png",  
+      "responseType": "json",
+      "preprocess" : ""
+  }"#;
 
     #[test]
-    fn test_twitter_bio_provider() {
-        let provider: Provider =
-            serde_json::from_str(TWITTER_BIO_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&TWITTER_BIO_RESPONSE_TEXT)
+    fn test_html_missing_attributes_provider() {
+        let provider: Provider = serde_json::from_str(HTML_MISSING_ATTRIBUTES_PROVIDER_TEXT)
+            .expect("Failed to parse provider");
+        let response_text = r#"<html><body><h1 id="followers">94</h1><h1 id="following">80</h1><h1 id="public_repos">47</h1></body></html>"#;
+        let processed_response = provider
+            .preprocess_response(response_text)
             .expect("Failed to preprocess response");
-        let attributes = provider
-            .get_attributes(&result)
+        let result = provider
+            .get_attributes(&processed_response)
             .expect("Failed to get attributes");
-
-        println!("result: {:?}", result);
-        println!("attributes: {:?}", attributes);
-
-        assert_eq!(attributes.len(), 2);
-        assert!(attributes.contains(&"verified: true".to_string()));
-        assert!(attributes.contains(&"PreGPT4: true".to_string()));
+        assert_eq!(result.len(), 0);
     }
 
-    const CHATGPT_RESPONSE_TEXT: &str = r#"{
-  "persona": "chatgpt-paid",
-  "turnstile": {
-    "required": true,
-    "dx": "PBp5bWFze3lLcRxvbAxfaTdWVHRyemd5YAVJemZ5R3xfHFVuCHNAYEtXVm1dWFd/a25oZWMMWG0fZ09jcnlDcmRyVFZ/ellTNG50P3V+W2t/allvcGxkU3sRXRZ/aTEU"
-  },
-  "proofofwork": {
-    "required": true,
-    "seed": "0.3057354499477558",
-    "difficulty": "0227c7"
-  }
-}"#;
-
-    const CHATGPT_PROVIDER_TEXT: &str = r#"{
-        "id": 1,
-        "host": "chatgpt.com",
-        "urlRegex": "^https:\\/\\/chatgpt\\.com\\/backend-api\\/sentinel\\/chat-requirements(\\?.*)?$",
-        "targetUrl": "https://www.chatgpt.com/home",
-        "method": "GET",
-        "title": "ChatGPT Paid Account",
-        "description": "",
-        "icon": "https://utfs.io/f/taibMU1XxiEPtZlbWo1XxiEPsjzpNu8frqFdalI30V7yCJBO",
-        "responseType": "json",
-        "actionSelectors": ["a[role=\"link\"] img[alt][draggable=\"true\"]"],
-        "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); const paid = obj.persona === 'chatgpt-paid'; if(!paid) throw new Error('Invalid account'); return { paid: paid }; }",
-        "attributes": ["{paid: paid}"]
-      }"#;
+    const JSON_PROVIDER_TEXT: &str = r#"{
+      "id": 7,
+      "host": "github.com",
+      "urlRegex": "^https:\\/\\/chatgpt\\.com\\/backend-api\\/sentinel\\/chat-requirements(\\?.*)?$",
+      "targetUrl": "https://github.com",
+      "method": "GET",
+      "title": "Github profile",
+      "description": "Go to your profile",
+      "icon": "https://github.githubassets.com/images/modules/logos_page/GitHub-Mark.png",  
+      "responseType": "json",
+      "attributes": ["{followers: followers, following: following}", "{public_repos: public_repos}", "{is_active: followers + following > public_repos}"]
+    }"#;
 
     #[test]
-    fn test_chatgpt_provider() {
+    fn test_declared_attribute_names() {
         let provider: Provider =
-            serde_json::from_str(CHATGPT_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&CHATGPT_RESPONSE_TEXT)
-            .expect("Failed to preprocess response");
-        let attributes = provider
-            .get_attributes(&result)
-            .expect("Failed to get attributes");
-
-        println!("result: {:?}", result);
-        println!("{:?}", attributes);
-        assert_eq!(attributes.len(), 1);
-        assert_eq!(attributes[0], "paid: true");
+            serde_json::from_str(JSON_PROVIDER_TEXT).expect("Failed to parse provider");
+        assert_eq!(
+            provider.declared_attribute_names(),
+            vec!["followers", "following", "public_repos", "is_active"]
+        );
+
+        let provider = Provider {
+            attribute_groups: Some(vec![AttributeGroup {
+                when_status: Some("4xx".to_string()),
+                attributes: vec!["{error: message, followers: `0`}".to_string()],
+            }]),
+            ..provider
+        };
+        assert_eq!(
+            provider.declared_attribute_names(),
+            vec![
+                "followers",
+                "following",
+                "public_repos",
+                "is_active",
+                "error"
+            ]
+        );
     }
 
-    const CLAUDE_RESPONSE_TEXT: &str = r#"{
-  "status": "active",
-  "cancel_at_ts": null
-  }"#;
+    #[test]
+    fn test_check_url_method() {
+        let provider: Provider =
+            serde_json::from_str(JSON_PROVIDER_TEXT).expect("Failed to parse provider");
+        assert!(provider
+            .check_url_method(
+                "https://chatgpt.com/backend-api/sentinel/chat-requirements",
+                "GET"
+            )
+            .expect("Failed to check url method"));
+        assert!(!provider
+            .check_url_method("https://api.github.com/users/xxxxxx/followers", "GET")
+            .expect("Failed to check url method"));
+    }
 
-    const CLAUDE_PROVIDER_TEXT: &str = r#"{
-        "id": 0,
-        "host": "claude.ai",
-        "urlRegex": "^https:\\/\\/claude\\.ai(.*)?subscription_status(.*)?$",
-        "targetUrl": "https://claude.ai/",
+    const SSA_PROVIDER_TEXT: &str = r#"{
+        "id": 4,
+        "host": "secure.ssa.gov",
+        "urlRegex": "https://secure.ssa.gov/myssa/myprofile-api/profileInfo",
+        "targetUrl": "https://secure.ssa.gov/myssa/myprofile-ui/main",
         "method": "GET",
-        "title": "Claude Paid Account",
-        "description": "",
-        "icon": "https://utfs.io/f/taibMU1XxiEPtZlbWo1XxiEPsjzpNu8frqFdalI30V7yCJBO",
+        "title": "US SSA",
+        "description": "Go to your profile",
+        "icon": "https://brandslogos.com/wp-content/uploads/images/large/us-social-security-administration-logo-black-and-white.png",
         "responseType": "json",
-        "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); return { paid: obj.status === 'active' }; }",
-        "attributes": ["{paid: paid}"]
+        "attributes": ["{age: age, isValid: length(loggedInUserInfo.cossn) == `11` } "],
+        "preprocess": "function process(jsonString) { const startIndex = jsonString.indexOf('{'); const endIndex = jsonString.lastIndexOf('}') + 1; if (startIndex === -1 || endIndex === 0) { return {}; } try { const cleanedResponse = jsonString.slice(startIndex, endIndex); const s = JSON.parse(cleanedResponse); const currentDate = new Date(); const currentYear = currentDate.getFullYear(); let age = currentYear - s.loggedInUserInfo.dobYear; const currentMonth = currentDate.getMonth(); const currentDay = currentDate.getDate(); if (currentMonth === 0 && currentDay < 1) { age--; } s.age = age; return s; } catch (e) { return {}; }  }"
       }"#;
 
-    #[test]
-    fn test_claude_provider() {
-        let provider: Provider =
-            serde_json::from_str(CLAUDE_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&CLAUDE_RESPONSE_TEXT)
-            .expect("Failed to preprocess response");
-        let attributes = provider
-            .get_attributes(&result)
-            .expect("Failed to get attributes");
+    const SSA_RESPONSE_TEXT: &str = r#"
+          1e0
+          {
+              "responseStatus": {
+                "returnCode": "0000",
+                "reasonCode": "0000",
+                "reasonDescription": "Successfully obtained the profile info"
+              },
+              "urlPath": "/myssa/bec-plan-prep-ui/",
+              "loggedInUserInfo": {
+                  "cossn": "***-**-9999",
+                  "name": {
+                    "firstName": "JOHN",
+                    "middleName": "",
+                    "lastName": "DOE",
+                    "suffix": ""
+                  },
+                  "formattedName": "John Doe",
+                  "otherServicesInd": "N",
+                  "messageCount": "",
+                  "dobYear": "1999",
+                  "dobMonth": "09",
+                  "dobDay": "09",
+                  "contactDisplayInd": "N",
+                  "bankingDisplayInd": "N"
+              }
+          }
+          0"#;
+
+    #[test]
+    fn test_ssa_provider() {
+        let provider: Provider =
+            serde_json::from_str(SSA_PROVIDER_TEXT).expect("Failed to parse provider");
+        let processed_response = provider
+            .preprocess_response(&SSA_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let result = provider
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&"age: 26".to_string()));
+        assert!(result.contains(&"isValid: true".to_string()));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_processor() {
+        let processor = Processor::new(
+            "https://raw159opfy.ufs.sh/f/taibMU1XxiEPtYPjjZ1XxiEPsjzpNu8frqFdalI30V7yCJBO"
+                .to_string(),
+            "https://link.freysa.ai/provider-schema".to_string(),
+        )
+        .await
+        .expect("Failed to initialize processor");
+        let result = processor
+            .process(
+                "https://secure.ssa.gov/myssa/myprofile-api/profileInfo",
+                "GET",
+                SSA_RESPONSE_TEXT,
+            )
+            .expect("Failed to process");
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&"age: 25.0".to_string()));
+        assert!(result.contains(&"isValid: true".to_string()));
+    }
+
+    const CHASE_RESPONSE_TEXT: &str = r#"{
+        "creditScoreOutlineResponse": {
+            "creditBureauName": "EXPERIAN",
+            "creditScore": {
+                "currentCreditScoreSummary": {
+                    "creditRiskScore": 701,
+                    "creditScoreGradeName": "GOOD"
+                    },
+                "previousCreditScoreSummary": {
+                    "creditRiskScore": 123,
+                    "creditScoreGradeName": "GOOD"
+                },
+                "creditScoreModelIdentifier": {
+                    "riskModelName": "VantageScore",
+                    "riskModelVersionNumber": "1.2"
+                }
+            }
+        }
+    }"#;
+
+    const CHASE_PROVIDER_TEXT: &str = r#"{
+      "id": 6,
+      "host": "secure.chase.com",
+      "urlRegex":
+        "^https:\/\/secure.chase.com\/svc\/wr\/profile\/secure\/creditscore\/v2\/credit-journey\/servicing\/inquiry-maintenance\/v1\/customers\/credit-journey-insight-outlines.*",
+      "targetUrl": "https://secure.chase.com/web/auth/dashboard#/dashboard/overview",
+      "method": "GET",
+      "title": "Chase credit score",
+      "description": "Login to your chase account",
+      "icon": "https://download.logo.wine/logo/Chase_Bank/Chase_Bank-Logo.wine.png",
+      "responseType": "json",
+      "attributes": ["{creditScore: score, high_score: high_score, grade_name: grade_name}"],
+      "preprocess": "function process(jsonString) { const s =JSON.parse(jsonString); return {score: s.creditScoreOutlineResponse.creditScore.currentCreditScoreSummary.creditRiskScore, high_score: s.creditScoreOutlineResponse.creditScore.currentCreditScoreSummary.creditRiskScore > 700, grade_name: s.creditScoreOutlineResponse.creditScore.currentCreditScoreSummary.creditScoreGradeName} }"
+    }"#;
 
+    #[test]
+    fn test_chase_provider() {
+        let provider: Provider =
+            serde_json::from_str(CHASE_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&CHASE_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let result = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
         println!("result: {:?}", result);
-        println!("{:?}", attributes);
-        assert_eq!(attributes.len(), 1);
-        assert_eq!(attributes[0], "paid: true");
+        // assert_eq!(result.len(), 2);
     }
 
-    const X_FOLLOWERS_RESPONSE_TEXT: &str = r#"
-{
-    "data": {
-      "viewer_v2": {
-        "user_results": {
-          "result": {
-            "__typename": "User",
-            "verified_follower_count": "9",
-            "relationship_counts": {
-              "followers": 29
+    const UBEREATS_RESPONSE_TEXT: &str = r#"
+  {
+  "status": "success",
+  "data": {
+    "ordersMap": {
+      "6a58f37d-5258-4ac7-902c-2c9c26d72259": {
+        "baseEaterOrder": {
+          "uuid": "6a58f37d-5258-4ac7-902c-2c9c26d72259",
+          "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+          "isCancelled": false,
+          "isCompleted": true,
+          "completedAt": "2024-08-19T20:38:34.000Z",
+          "lastStateChangeAt": "2024-08-19T20:38:34.000Z",
+          "orderStateChanges": [
+            {
+              "stateChangeTime": "2024-08-19T19:31:56.000Z",
+              "type": "CREATED",
+              "changingEntityUuid": null
             },
-            "organic_metrics_time_series": [
+            {
+              "stateChangeTime": "2024-08-19T19:31:56.000Z",
+              "type": "OFFERED",
+              "changingEntityUuid": null
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:32:01.000Z",
+              "type": "ASSIGNED",
+              "changingEntityUuid": null
+            },
+            {
+              "stateChangeTime": "2024-08-19T20:38:34.000Z",
+              "type": "COMPLETED",
+              "changingEntityUuid": null
+            }
+          ],
+          "deliveryStateChanges": [
+            {
+              "stateChangeTime": "2024-08-19T19:41:49.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:43:03.000Z",
+              "type": "UNKNOWN"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:44:33.000Z",
+              "type": "CANCELED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:44:50.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:45:03.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:45:19.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:46:03.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:46:35.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:47:03.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:47:19.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:48:03.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:48:19.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:48:48.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:51:34.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:52:19.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:52:34.000Z",
+              "type": "DISPATCHED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:53:33.000Z",
+              "type": "UNKNOWN"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:55:39.000Z",
+              "type": "PICKUP_ARRIVED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T19:59:31.000Z",
+              "type": "BEGINTRIP"
+            },
+            {
+              "stateChangeTime": "2024-08-19T20:37:11.000Z",
+              "type": "PICKUP_ARRIVED"
+            },
+            {
+              "stateChangeTime": "2024-08-19T20:38:34.000Z",
+              "type": "COMPLETED"
+            }
+          ],
+          "shoppingCart": {
+            "cartUuid": "8b3a9eb6-d13a-42b8-b862-2a96c19e8d99",
+            "items": [
               {
-                "metric_values": [
-                  {
-                    "metric_value": 3,
-                    "metric_type": "Engagements"
-                  },
-                  {
-                    "metric_value": 62,
-                    "metric_type": "Impressions"
-                  },
-                  {
-                    "metric_type": "ProfileVisits"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "Follows"
-                  },
-                  {
-                    "metric_type": "Replies"
-                  },
-                  {
-                    "metric_value": 2,
-                    "metric_type": "Likes"
-                  },
-                  {
-                    "metric_type": "Retweets"
-                  },
-                  {
-                    "metric_type": "Bookmark"
-                  },
-                  {
-                    "metric_type": "Share"
-                  },
-                  {
-                    "metric_type": "UrlClicks"
-                  },
-                  {
-                    "metric_type": "CreateTweet"
-                  },
-                  {
-                    "metric_type": "CreateQuote"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "Unfollows"
+                "uuid": "c7ff3e87-932d-58cd-9f58-cd324172feb6",
+                "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+                "shoppingCartItemUuid": "7cec3d83-c757-410e-bb93-8a48fc6d5e58",
+                "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
+                "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
+                "title": "Thai Coconut Curry Soup",
+                "price": 875,
+                "quantity": 1,
+                "specialInstructions": "",
+                "customizations": [],
+                "cartItemCustomizations": {},
+                "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
+                "rating": null,
+                "itemQuantity": {
+                  "inSellableUnit": {
+                    "value": {
+                      "coefficient": 1,
+                      "exponent": 0
+                    },
+                    "measurementUnit": {
+                      "measurementType": "MEASUREMENT_TYPE_COUNT",
+                      "length": null,
+                      "weight": null,
+                      "volume": null
+                    },
+                    "measurementUnitAbbreviationText": null
                   },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "CreateReply"
-                  }
-                ],
-                "timestamp": {
-                  "iso8601_time": "2025-01-21T00:00:00Z"
+                  "inPriceableUnit": null
                 }
               },
               {
-                "metric_values": [
-                  {
-                    "metric_value": 4,
-                    "metric_type": "Engagements"
-                  },
-                  {
-                    "metric_value": 47,
-                    "metric_type": "Impressions"
-                  },
-                  {
-                    "metric_type": "ProfileVisits"
-                  },
-                  {
-                    "metric_type": "Follows"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "Replies"
-                  },
-                  {
-                    "metric_value": 3,
-                    "metric_type": "Likes"
-                  },
-                  {
-                    "metric_type": "Retweets"
-                  },
-                  {
-                    "metric_type": "Bookmark"
-                  },
-                  {
-                    "metric_type": "Share"
-                  },
-                  {
-                    "metric_type": "UrlClicks"
-                  },
-                  {
-                    "metric_type": "CreateTweet"
-                  },
-                  {
-                    "metric_type": "CreateQuote"
-                  },
-                  {
-                    "metric_type": "Unfollows"
-                  },
+                "uuid": "5151f82e-8b5a-5932-b50c-9be8d212c5f4",
+                "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+                "shoppingCartItemUuid": "c1f8bac1-c5be-4694-b661-178e9eb90af3",
+                "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
+                "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
+                "title": "Taiwanese Fried Cashew Mozzarella Croquettes",
+                "price": 1125,
+                "quantity": 1,
+                "specialInstructions": "",
+                "customizations": [
                   {
-                    "metric_value": 3,
-                    "metric_type": "CreateReply"
+                    "uuid": "c0d24887-fb53-5759-8725-9d3a8669ed97",
+                    "title": "Sauce (Mozzarella Balls)",
+                    "childOptions": {
+                      "options": [
+                        {
+                          "price": 0,
+                          "quantity": 1,
+                          "title": "Spicy Ketchup on the side",
+                          "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
+                          "childCustomizationList": []
+                        }
+                      ]
+                    },
+                    "groupId": 0
                   }
                 ],
-                "timestamp": {
-                  "iso8601_time": "2025-01-22T00:00:00Z"
+                "cartItemCustomizations": {
+                  "c0d24887-fb53-5759-8725-9d3a8669ed97+0": [
+                    {
+                      "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
+                      "price": 0,
+                      "quantity": 1,
+                      "title": "Spicy Ketchup on the side",
+                      "defaultQuantity": 0,
+                      "customizationMeta": {
+                        "title": "Sauce (Mozzarella Balls)",
+                        "isPickOne": false
+                      }
+                    }
+                  ]
+                },
+                "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
+                "rating": null,
+                "itemQuantity": {
+                  "inSellableUnit": {
+                    "value": {
+                      "coefficient": 1,
+                      "exponent": 0
+                    },
+                    "measurementUnit": {
+                      "measurementType": "MEASUREMENT_TYPE_COUNT",
+                      "length": null,
+                      "weight": null,
+                      "volume": null
+                    },
+                    "measurementUnitAbbreviationText": null
+                  },
+                  "inPriceableUnit": null
                 }
               },
               {
-                "metric_values": [
-                  {
-                    "metric_value": 2,
-                    "metric_type": "Engagements"
-                  },
-                  {
-                    "metric_value": 198,
-                    "metric_type": "Impressions"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "ProfileVisits"
-                  },
-                  {
-                    "metric_type": "Follows"
-                  },
-                  {
-                    "metric_type": "Replies"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "Likes"
-                  },
-                  {
-                    "metric_type": "Retweets"
-                  },
-                  {
-                    "metric_type": "Bookmark"
-                  },
-                  {
-                    "metric_type": "Share"
-                  },
-                  {
-                    "metric_type": "UrlClicks"
-                  },
-                  {
-                    "metric_type": "CreateTweet"
-                  },
-                  {
-                    "metric_type": "CreateQuote"
-                  },
-                  {
-                    "metric_type": "Unfollows"
-                  },
+                "uuid": "48a6aa8d-a703-5357-b873-772351b5fde8",
+                "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+                "shoppingCartItemUuid": "6aae0ca3-a184-4e47-a05c-8b10ee2d3563",
+                "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
+                "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
+                "title": "Firecracker Cauliflower",
+                "price": 1125,
+                "quantity": 1,
+                "specialInstructions": "",
+                "customizations": [
                   {
-                    "metric_value": 6,
-                    "metric_type": "CreateReply"
+                    "uuid": "ae63620e-de6d-51f6-bb25-a75e990d6ea2",
+                    "title": "Substitute Firecracker sauce",
+                    "childOptions": {
+                      "options": [
+                        {
+                          "price": 0,
+                          "quantity": 1,
+                          "title": "Sub for Teriyaki sauce",
+                          "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
+                          "childCustomizationList": []
+                        }
+                      ]
+                    },
+                    "groupId": 0
                   }
                 ],
-                "timestamp": {
-                  "iso8601_time": "2025-01-23T00:00:00Z"
-                }
-              },
-              {
-                "metric_values": [
-                  {
-                    "metric_value": 5,
-                    "metric_type": "Engagements"
-                  },
-                  {
-                    "metric_value": 118,
-                    "metric_type": "Impressions"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "ProfileVisits"
-                  },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "Follows"
-                  },
-                  {
-                    "metric_type": "Replies"
-                  },
-                  {
-                    "metric_value": 3,
-                    "metric_type": "Likes"
-                  },
-                  {
-                    "metric_type": "Retweets"
-                  },
-                  {
-                    "metric_type": "Bookmark"
-                  },
-                  {
-                    "metric_type": "Share"
-                  },
-                  {
-                    "metric_type": "UrlClicks"
-                  },
-                  {
-                    "metric_type": "CreateTweet"
+                "cartItemCustomizations": {
+                  "ae63620e-de6d-51f6-bb25-a75e990d6ea2+0": [
+                    {
+                      "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
+                      "price": 0,
+                      "quantity": 1,
+                      "title": "Sub for Teriyaki sauce",
+                      "defaultQuantity": 0,
+                      "customizationMeta": {
+                        "title": "Substitute Firecracker sauce",
+                        "isPickOne": false
+                      }
+                    }
+                  ]
+                },
+                "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
+                "rating": null,
+                "itemQuantity": {
+                  "inSellableUnit": {
+                    "value": {
+                      "coefficient": 1,
+                      "exponent": 0
+                    },
+                    "measurementUnit": {
+                      "measurementType": "MEASUREMENT_TYPE_COUNT",
+                      "length": null,
+                      "weight": null,
+                      "volume": null
+                    },
+                    "measurementUnitAbbreviationText": null
                   },
-                  {
-                    "metric_type": "CreateQuote"
+                  "inPriceableUnit": null
+                }
+              }
+            ],
+            "currencyCode": "USD"
+          },
+          "currencyCode": "USD",
+          "isScheduledOrder": false,
+          "numItems": 3,
+          "totalQuantity": 3,
+          "fulfillmentType": "DELIVERY",
+          "userGroupedItems": [
+            {
+              "items": [
+                {
+                  "uuid": "c7ff3e87-932d-58cd-9f58-cd324172feb6",
+                  "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+                  "shoppingCartItemUuid": "7cec3d83-c757-410e-bb93-8a48fc6d5e58",
+                  "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
+                  "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
+                  "title": "Thai Coconut Curry Soup",
+                  "price": 875,
+                  "quantity": 1,
+                  "specialInstructions": "",
+                  "customizations": [],
+                  "cartItemCustomizations": {},
+                  "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
+                  "rating": null,
+                  "itemQuantity": {
+                    "inSellableUnit": {
+                      "value": {
+                        "coefficient": 1,
+                        "exponent": 0
+                      },
+                      "measurementUnit": {
+                        "measurementType": "MEASUREMENT_TYPE_COUNT",
+                        "length": null,
+                        "weight": null,
+                        "volume": null
+                      },
+                      "measurementUnitAbbreviationText": null
+                    },
+                    "inPriceableUnit": null
+                  }
+                },
+                {
+                  "uuid": "5151f82e-8b5a-5932-b50c-9be8d212c5f4",
+                  "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+                  "shoppingCartItemUuid": "c1f8bac1-c5be-4694-b661-178e9eb90af3",
+                  "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
+                  "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
+                  "title": "Taiwanese Fried Cashew Mozzarella Croquettes",
+                  "price": 1125,
+                  "quantity": 1,
+                  "specialInstructions": "",
+                  "customizations": [
+                    {
+                      "uuid": "c0d24887-fb53-5759-8725-9d3a8669ed97",
+                      "title": "Sauce (Mozzarella Balls)",
+                      "childOptions": {
+                        "options": [
+                          {
+                            "price": 0,
+                            "quantity": 1,
+                            "title": "Spicy Ketchup on the side",
+                            "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
+                            "childCustomizationList": []
+                          }
+                        ]
+                      },
+                      "groupId": 0
+                    }
+                  ],
+                  "cartItemCustomizations": {
+                    "c0d24887-fb53-5759-8725-9d3a8669ed97+0": [
+                      {
+                        "uuid": "9e793c7e-abc1-5d64-a2bb-026fae6bddb2",
+                        "price": 0,
+                        "quantity": 1,
+                        "title": "Spicy Ketchup on the side",
+                        "defaultQuantity": 0,
+                        "customizationMeta": {
+                          "title": "Sauce (Mozzarella Balls)",
+                          "isPickOne": false
+                        }
+                      }
+                    ]
                   },
-                  {
-                    "metric_type": "Unfollows"
+                  "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
+                  "rating": null,
+                  "itemQuantity": {
+                    "inSellableUnit": {
+                      "value": {
+                        "coefficient": 1,
+                        "exponent": 0
+                      },
+                      "measurementUnit": {
+                        "measurementType": "MEASUREMENT_TYPE_COUNT",
+                        "length": null,
+                        "weight": null,
+                        "volume": null
+                      },
+                      "measurementUnitAbbreviationText": null
+                    },
+                    "inPriceableUnit": null
+                  }
+                },
+                {
+                  "uuid": "48a6aa8d-a703-5357-b873-772351b5fde8",
+                  "storeUuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+                  "shoppingCartItemUuid": "6aae0ca3-a184-4e47-a05c-8b10ee2d3563",
+                  "sectionUuid": "35272fb5-ad16-5375-919c-c20ec5f6b62d",
+                  "subsectionUuid": "b951886a-8e21-5c8a-a791-13790f4dffd3",
+                  "title": "Firecracker Cauliflower",
+                  "price": 1125,
+                  "quantity": 1,
+                  "specialInstructions": "",
+                  "customizations": [
+                    {
+                      "uuid": "ae63620e-de6d-51f6-bb25-a75e990d6ea2",
+                      "title": "Substitute Firecracker sauce",
+                      "childOptions": {
+                        "options": [
+                          {
+                            "price": 0,
+                            "quantity": 1,
+                            "title": "Sub for Teriyaki sauce",
+                            "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
+                            "childCustomizationList": []
+                          }
+                        ]
+                      },
+                      "groupId": 0
+                    }
+                  ],
+                  "cartItemCustomizations": {
+                    "ae63620e-de6d-51f6-bb25-a75e990d6ea2+0": [
+                      {
+                        "uuid": "16b44899-398e-5768-babb-f3048bdc63a8",
+                        "price": 0,
+                        "quantity": 1,
+                        "title": "Sub for Teriyaki sauce",
+                        "defaultQuantity": 0,
+                        "customizationMeta": {
+                          "title": "Substitute Firecracker sauce",
+                          "isPickOne": false
+                        }
+                      }
+                    ]
                   },
-                  {
-                    "metric_value": 1,
-                    "metric_type": "CreateReply"
+                  "consumerUuid": "baa82dfe-7833-4b34-b854-88684e38e81e",
+                  "rating": null,
+                  "itemQuantity": {
+                    "inSellableUnit": {
+                      "value": {
+                        "coefficient": 1,
+                        "exponent": 0
+                      },
+                      "measurementUnit": {
+                        "measurementType": "MEASUREMENT_TYPE_COUNT",
+                        "length": null,
+                        "weight": null,
+                        "volume": null
+                      },
+                      "measurementUnitAbbreviationText": null
+                    },
+                    "inPriceableUnit": null
                   }
-                ],
-                "timestamp": {
-                  "iso8601_time": "2025-01-24T00:00:00Z"
                 }
-              },
-              {
-                "metric_values": [
-                  {
-                    "metric_type": "Engagements"
-                  },
-                  {
-                    "metric_value": 20,
-                    "metric_type": "Impressions"
-                  },
-                  {
-                    "metric_type": "ProfileVisits"
-                  },
-                  {
-                    "metric_type": "Follows"
-                  },
-                  {
-                    "metric_type": "Replies"
-                  },
-                  {
+              ],
+              "quantity": 3
+            }
+          ],
+          "posType": "UNDEFINED",
+          "orderAppVariant": "UBEREATS",
+          "isBackfilledOrder": false,
+          "displayName": "",
+          "billSplitOption": "UNKNOWN_BILL_SPLIT_OPTION",
+          "isOrderCreator": true,
+          "creatorDisplayName": "xxxxxxxxx"
+        },
+        "storeInfo": {
+          "uuid": "484a901a-3750-49a0-8207-b4db729ba2d2",
+          "heroImageUrl": "https://duyt4h9nfnj50.cloudfront.net/sku/c5929f791f8cad88221c2f3e2f6958ea",
+          "title": "Pow Pow",
+          "isOpen": false,
+          "closedMessage": "Closed",
+          "location": {
+            "address": {
+              "address1": "1253 H St NE, Washington, DC 20002, USA",
+              "aptOrSuite": "",
+              "city": "Washington",
+              "country": "US",
+              "postalCode": "20002",
+              "region": "DC",
+              "title": null,
+              "uuid": "f7421339-086d-4a06-b2f7-b9270dbfa137",
+              "eaterFormattedAddress": "1253 H St NE, Washington, DC 20002, USA, Washington, DC 20002",
+              "subtitle": null,
+              "businessName": null,
+              "street": null,
+              "ugcAddressFieldInfo": null
+            },
+            "latitude": 38.9001426,
+            "longitude": -76.988559,
+            "reference": null,
+            "type": null,
+            "placeReferences": null,
+            "interactionType": null,
+            "nickname": null
+          },
+          "storeStatus": {
+            "isActive": true,
+            "notActiveReason": "UNKNOWN"
+          },
+          "contact": {
+            "phoneNumber": "+xxxxxxxxxxx"
+          },
+          "territoryUUID": "d5f8a8fc-e1bb-4309-acb8-aac5b7c97260",
+          "rating": null,
+          "slug": "pow-pow-1253-h-st-ne"
+        },
+        "courierInfo": {
+          "name": ""
+        },
+        "fareInfo": {
+          "totalPrice": 3002.9999999999995,
+          "checkoutInfo": [
+            {
+              "label": "delivery.uber.service_fee",
+              "type": "credit",
+              "rawValue": 0.1,
+              "key": "delivery.uber.service_fee"
+            },
+            {
+              "label": "delivery.uber.service_fee.tax",
+              "type": "credit",
+              "rawValue": 0.01,
+              "key": "delivery.uber.service_fee.tax"
+            },
+            {
+              "label": "Tax on Delivery Fees",
+              "type": "credit",
+              "rawValue": 0.48,
+              "key": "eats.mp.charges.bundled_delivery_fee_taxes"
+            },
+            {
+              "label": "Special Offer",
+              "type": "debit",
+              "rawValue": -10.94,
+              "key": "eats.discounts.store_promotion"
+            },
+            {
+              "label": "Subtotal",
+              "type": "credit",
+              "rawValue": 31.25,
+              "key": "eats_fare.subtotal"
+            },
+            {
+              "label": "Tax",
+              "type": "credit",
+              "rawValue": 2.03,
+              "key": "eats.tax.base"
+            },
+            {
+              "label": "Service Fee and Other Fees",
+              "type": "credit",
+              "rawValue": 4.79,
+              "key": "eats.mp.charges.basket_dependent_fee"
+            },
+            {
+              "label": "Tip",
+              "type": "credit",
+              "rawValue": 2.31,
+              "key": "eats_fare.tip"
+            },
+            {
+              "label": "Total",
+              "type": "credit",
+              "rawValue": 30.029999999999998,
+              "key": "eats_fare.total"
+            }
+          ]
+        },
+        "ratingInfo": {
+          "isRatable": true,
+          "userRatings": []
+        },
+        "interactionType": "leave_in_lobby"
+      }
+    },
+    "orderUuids": [
+      "6a58f37d-5258-4ac7-902c-2c9c26d72259"
+    ],
+    "paginationData": {
+      "nextCursor": "{\"entity_type\":\"CONSUMER\",\"cursor\":\"MjAyMy0wMy0xMlQyMzowNjowOS43MDRa\"}"
+    },
+    "meta": {
+      "hasMore": true
+    }
+  }
+}
+  "#;
+
+    const UBEREATS_PROVIDER_TEXT: &str = r#"
+       {
+      "id": 5,
+      "host": "ubereats.com",
+      "urlRegex":
+        "^https:\\/\\/www\\.ubereats\\.com\\/_p\\/api\\/getPastOrdersV1.*",
+      "targetUrl": "https://www.ubereats.com/orders",
+      "method": "POST",
+      "title": "Uber eats orders",
+      "description": "Go to your order history",
+      "icon": "https://i.pinimg.com/originals/a3/4a/8c/a34a8c234e27ac9476e7f010f750d136.jpg",
+      "responseType": "json",
+      "attributes": ["{usd_total: USD.totalPrice, usd_count: USD.orderCount}"],
+      "preprocess": "function process(jsonString) { const totals = Object.values(JSON.parse(jsonString).data.ordersMap).reduce((totals, order) => { const price = order.fareInfo.totalPrice; const currency = order.baseEaterOrder.currencyCode; if (typeof price === 'number' && currency) { if (!totals[currency]) { totals[currency] = { totalPrice: 0, orderCount: 0 }; } totals[currency].totalPrice += price; totals[currency].orderCount += 1; } return totals; }, {}); Object.keys(totals).forEach(currency => totals[currency].totalPrice = (totals[currency].totalPrice / 100).toFixed(2)); return totals; } "
+    }"#;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_ubereats_provider() {
+        let provider: Provider =
+            serde_json::from_str(UBEREATS_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&UBEREATS_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let result = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+        println!("{:?}", result);
+        assert!(result.contains(&"usd_total: \"30.03\"".to_string()));
+        assert!(result.contains(&"usd_count: 1".to_string()));
+
+        // The string returned by `toFixed(2)` is attested as a number with `normalizeNumbers`
+        let provider = Provider {
+            normalize_numbers: Some(true),
+            ..provider
+        };
+        let result = provider
+            .preprocess_response(&UBEREATS_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let result = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+        assert!(result.contains(&"usd_total: 30.03".to_string()));
+        assert!(result.contains(&"usd_count: 1".to_string()));
+    }
+
+    const REDDIT_PROVIDER_TEXT: &str = r#"{
+      "id": 3,
+      "host": "reddit.com",
+      "urlRegex": "^https:\\/\\/www\\.reddit\\.com\\/user\\/[a-zA-Z0-9]+.*\\/about\\.json$",
+      "targetUrl": "https://www.reddit.com",
+      "method": "GET",
+      "title": "Reddit account",
+      "description": "Go to your profile",
+      "icon": "https://seeklogo.com/images/R/reddit-icon-new-2023-logo-3F12137D65-seeklogo.com.png",
+      "responseType": "html",
+      "actionSelectors": ["a[href^=\"/user/\"][href$=\"/\"]"],
+      "attributes": ["{karma: karma}"],
+      "preprocess": "function process(jsonString) { const data = JSON.parse(jsonString); return { karma: data.data.total_karma } }"
+    }"#;
+
+    const REDDIT_RESPONSE_TEXT: &str = r#"{
+  "kind": "t2",
+  "data": {
+    "is_employee": false,
+    "has_visited_new_profile": false,
+    "is_friend": false,
+    "pref_no_profanity": true,
+    "has_external_account": false,
+    "pref_geopopular": "",
+    "pref_show_trending": true,
+    "subreddit": {
+      "default_set": true,
+      "user_is_contributor": false,
+      "banner_img": "",
+      "allowed_media_in_comments": [],
+      "user_is_banned": false,
+      "free_form_reports": true,
+      "community_icon": null,
+      "show_media": true,
+      "icon_color": "",
+      "user_is_muted": null,
+      "display_name": "u_xxxxxx",
+      "header_img": null,
+      "title": "",
+      "coins": 0,
+      "previous_names": [],
+      "over_18": false,
+      "icon_size": [
+        256,
+        256
+      ],
+      "primary_color": "",
+      "icon_img": "https://styles.redditmedia.com/t5_3w62uh/styles/profileIcon_ldu1icwig3c81.png?width=256&amp;height=256&amp;crop=256:256,smart&amp;s=15bd7ab8be642a980566f9075fc5815f508935e7",
+      "description": "",
+      "submit_link_label": "",
+      "header_size": null,
+      "restrict_posting": true,
+      "restrict_commenting": false,
+      "subscribers": 1,
+      "submit_text_label": "",
+      "is_default_icon": false,
+      "link_flair_position": "",
+      "display_name_prefixed": "u/xxxxxx",
+      "key_color": "",
+      "name": "t5_3w62uh",
+      "is_default_banner": true,
+      "url": "/user/xxxxxx/",
+      "quarantine": false,
+      "banner_size": null,
+      "user_is_moderator": true,
+      "accept_followers": true,
+      "public_description": "Ads and Premium services engineer @brave\n\nComputer languages enthusiast\nSwore oath to protect privacy",
+      "link_flair_enabled": false,
+      "disable_contributor_requests": false,
+      "subreddit_type": "user",
+      "user_is_subscriber": false
+    },
+    "pref_show_presence": true,
+    "snoovatar_img": "",
+    "snoovatar_size": null,
+    "gold_expiration": null,
+    "has_gold_subscription": false,
+    "is_sponsor": false,
+    "num_friends": 0,
+    "features": {
+      "modmail_harassment_filter": true,
+      "mod_service_mute_writes": true,
+      "promoted_trend_blanks": true,
+      "show_amp_link": true,
+      "chat": true,
+      "is_email_permission_required": false,
+      "mod_awards": true,
+      "mweb_xpromo_revamp_v3": {
+        "owner": "growth",
+        "variant": "control_1",
+        "experiment_id": 480
+      },
+      "mweb_xpromo_revamp_v2": {
+        "owner": "growth",
+        "variant": "treatment_4",
+        "experiment_id": 457
+      },
+      "awards_on_streams": true,
+      "mweb_xpromo_modal_listing_click_daily_dismissible_ios": true,
+      "chat_subreddit": true,
+      "cookie_consent_banner": true,
+      "modlog_copyright_removal": true,
+      "do_not_track": true,
+      "images_in_comments": true,
+      "mod_service_mute_reads": true,
+      "chat_user_settings": true,
+      "use_pref_account_deployment": true,
+      "mweb_xpromo_interstitial_comments_ios": true,
+      "mweb_xpromo_modal_listing_click_daily_dismissible_android": true,
+      "premium_subscriptions_table": true,
+      "mweb_xpromo_interstitial_comments_android": true,
+      "crowd_control_for_post": true,
+      "mweb_sharing_web_share_api": {
+        "owner": "growth",
+        "variant": "control_1",
+        "experiment_id": 314
+      },
+      "chat_group_rollout": true,
+      "resized_styles_images": true,
+      "noreferrer_to_noopener": true,
+      "expensive_coins_package": true
+    },
+    "can_edit_name": false,
+    "is_blocked": false,
+    "verified": true,
+    "new_modmail_exists": null,
+    "pref_autoplay": true,
+    "coins": 0,
+    "has_paypal_subscription": false,
+    "has_subscribed_to_premium": false,
+    "id": "a2gghybr",
+    "can_create_subreddit": true,
+    "over_18": true,
+    "is_gold": false,
+    "is_mod": false,
+    "awarder_karma": 0,
+    "suspension_expiration_utc": null,
+    "has_stripe_subscription": false,
+    "is_suspended": false,
+    "pref_video_autoplay": true,
+    "in_chat": true,
+    "has_android_subscription": false,
+    "in_redesign_beta": true,
+    "icon_img": "https://styles.redditmedia.com/t5_3w62uh/styles/profileIcon_ldu1icwig3c81.png?width=256&amp;height=256&amp;crop=256:256,smart&amp;s=15bd7ab8be642a980566f9075fc5815f508935e7",
+    "has_mod_mail": false,
+    "pref_nightmode": false,
+    "awardee_karma": 0,
+    "hide_from_robots": false,
+    "password_set": true,
+    "modhash": "hf34fcz1wh637b593a4a2303d3f9193e2b9446faf6c90dee7a",
+    "link_karma": 1,
+    "force_password_reset": false,
+    "total_karma": 1,
+    "inbox_count": 2,
+    "pref_top_karma_subreddits": true,
+    "has_mail": true,
+    "pref_show_snoovatar": false,
+    "name": "xxxxxx",
+    "pref_clickgadget": 5,
+    "created": 1612356751,
+    "has_verified_email": true,
+    "gold_creddits": 0,
+    "created_utc": 1612356751,
+    "has_ios_subscription": false,
+    "pref_show_twitter": false,
+    "in_beta": false,
+    "comment_karma": 0,
+    "accept_followers": true,
+    "has_subscribed": true
+  }
+}"#;
+
+    #[test]
+    fn test_reddit_provider() {
+        let matchurl = "https://www.reddit.com/user/xxxxxx/about.json";
+        let provider: Provider =
+            serde_json::from_str(REDDIT_PROVIDER_TEXT).expect("Failed to parse provider");
+        provider
+            .check_url_method(matchurl, "POST")
+            .expect("Failed to check url method");
+        let result = provider
+            .preprocess_response(&REDDIT_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0], "karma: 1");
+    }
+
+    const ROBINHOOD_RESPONSE_TEXT: &str = r#"{
+  "title": null,
+  "weight": null,
+  "include_all_hours": true,
+  "line_ordering": [],
+  "performance_baseline": {
+    "currency_code": "USD",
+    "currency_id": "1072fc76-1862-41ab-82c2-485837590762",
+    "amount": "10245.5079335464"
+  }
+}
+    "#;
+
+    const ROBINHOOD_PROVIDER_TEXT: &str = r#"{
+      "id": 2,
+      "host": "robinhood.com",
+      "urlRegex":
+        "^https:\\/\\/bonfire\\.robinhood\\.com\\/portfolio\\/performance\\/[a-zA-Z0-9]+(\\?.*)?$",
+      "targetUrl": "https://robinhood.com/",
+      "method": "GET",
+      "title": "Robinhood balance greater than $10,000",
+      "description": "Go to your portfolio",
+      "icon": "https://upload.wikimedia.org/wikipedia/commons/b/b9/Robinhood_Logo.png",
+      "responseType": "json",
+      "attributes": ["{over_10k: to_number(performance_baseline.amount) >`10000` && performance_baseline.currency_code == 'USD'}"]
+    }"#;
+
+    #[test]
+    fn test_robinhood_provider() {
+        let provider: Provider =
+            serde_json::from_str(ROBINHOOD_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&ROBINHOOD_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+        println!("{:?}", attributes);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0], "over_10k: true");
+    }
+
+    const TWITTER_BIO_RESPONSE_TEXT: &str = r#"{
+  "data": {
+    "user": {
+      "result": {
+        "__typename": "User",
+        "id": "VRNlcjoxNjc2OTI4NDU2MjI1ODk4NDk2",
+        "rest_id": "1676928456225898496",
+        "affiliates_highlighted_label": {},
+        "avatar": {
+          "image_url": "https://pbs.twimg.com/profile_images/1938401595659534336/4FfZgohs_normal.jpg"
+        },
+        "core": {
+          "created_at": "Thu Jan 06 12:18:01 +0000 2022",
+          "name": "fppp290",
+          "screen_name": "Johndoe93"
+        },
+        "dm_permissions": {
+          "can_dm": true
+        },
+        "has_graduated_access": true,
+        "is_blue_verified": true,
+        "legacy": {
+          "default_profile": true,
+          "default_profile_image": false,
+          "description": "It starts with freysa...",
+          "entities": {
+            "description": {
+              "urls": []
+            }
+          },
+          "fast_followers_count": 0,
+          "favourites_count": 182,
+          "followers_count": 35,
+          "friends_count": 180,
+          "has_custom_timelines": false,
+          "is_translator": false,
+          "listed_count": 1,
+          "media_count": 7,
+          "needs_phone_verification": false,
+          "normal_followers_count": 35,
+          "pinned_tweet_ids_str": [
+            "1875293532711186617"
+          ],
+          "possibly_sensitive": false,
+          "profile_banner_url": "https://pbs.twimg.com/profile_banners/1676928456225898496/1738727374",
+          "profile_interstitial_type": "",
+          "statuses_count": 565,
+          "translator_type": "none",
+          "want_retweets": false,
+          "withheld_in_countries": []
+        },
+        "location": {
+          "location": ""
+        },
+        "media_permissions": {
+          "can_media_tag": true
+        },
+        "parody_commentary_fan_label": "None",
+        "profile_image_shape": "Circle",
+        "privacy": {
+          "protected": false
+        },
+        "relationship_perspectives": {
+          "following": false
+        },
+        "tipjar_settings": {},
+        "verification": {
+          "verified": false
+        },
+        "legacy_extended_profile": {
+          "birthdate": {
+            "day": 12,
+            "month": 1,
+            "year": 1994,
+            "visibility": "Self",
+            "year_visibility": "Self"
+          }
+        },
+        "is_profile_translatable": false,
+        "has_hidden_subscriptions_on_profile": false,
+        "verification_info": {
+          "is_identity_verified": false,
+          "reason": {
+            "description": {
+              "text": "This account is verified. Learn more",
+              "entities": [
+                {
+                  "from_index": 26,
+                  "to_index": 36,
+                  "ref": {
+                    "url": "https://help.twitter.com/managing-your-account/about-twitter-verified-accounts",
+                    "url_type": "ExternalUrl"
+                  }
+                }
+              ]
+            },
+            "verified_since_msec": "1735851543944"
+          }
+        },
+        "highlights_info": {
+          "can_highlight_tweets": true,
+          "highlighted_tweets": "1"
+        },
+        "user_seed_tweet_count": 0,
+        "premium_gifting_eligible": false,
+        "business_account": {},
+        "creator_subscriptions_count": 0
+      }
+    }
+  }
+}"#;
+
+    const TWITTER_BIO_PROVIDER_TEXT: &str = r#"{
+        "id": 2,
+        "host": "x.com",
+        "urlRegex": "^https:\\/\\/x\\.com\\/i\\/api\\/graphql\\/[\\w\\d]+\\/UserByScreenName(\\?.*)?$",
+        "targetUrl": "https://www.x.com/home",
+        "method": "GET",
+        "title": "Verify X subscription",
+        "description": "",
+        "icon": "twitterPremium",
+        "responseType": "json",
+        "actionSelectors": ["div[data-testid^='UserAvatar-Container-'] a[role=\"link\"] img[alt][draggable=\"true\"]"],
+        "preprocess": "function process(jsonString) { const object = JSON.parse(jsonString); const parts = object.data.user.result.core.created_at.split(' '); const time = parts[3].split(':'); const months = { Jan: 0, Feb: 1, Mar: 2, Apr: 3, May: 4, Jun: 5, Jul: 6, Aug: 7, Sep: 8, Oct: 9, Nov: 10, Dec: 11 }; const createdAt = new Date(parseInt(parts[5]), months[parts[1]], parseInt(parts[2]), parseInt(time[0]), parseInt(time[1]), parseInt(time[2])); const targetDate = new Date('2023-03-01'); const PreGPT4 = createdAt < targetDate; const verified = object.data.user.result.is_blue_verified; if(!verified || !PreGPT4) throw new Error('Invalid account'); return { verified, PreGPT4 }; }",
+        "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
+    }"#;
+
+    #[test]
+    fn test_twitter_bio_provider() {
+        let provider: Provider =
+            serde_json::from_str(TWITTER_BIO_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&TWITTER_BIO_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+
+        println!("result: {:?}", result);
+        println!("attributes: {:?}", attributes);
+
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains(&"verified: true".to_string()));
+        assert!(attributes.contains(&"PreGPT4: true".to_string()));
+    }
+
+    const CHATGPT_RESPONSE_TEXT: &str = r#"{
+  "persona": "chatgpt-paid",
+  "turnstile": {
+    "required": true,
+    "dx": "PBp5bWFze3lLcRxvbAxfaTdWVHRyemd5YAVJemZ5R3xfHFVuCHNAYEtXVm1dWFd/a25oZWMMWG0fZ09jcnlDcmRyVFZ/ellTNG50P3V+W2t/allvcGxkU3sRXRZ/aTEU"
+  },
+  "proofofwork": {
+    "required": true,
+    "seed": "0.3057354499477558",
+    "difficulty": "0227c7"
+  }
+}"#;
+
+    const CHATGPT_PROVIDER_TEXT: &str = r#"{
+        "id": 1,
+        "host": "chatgpt.com",
+        "urlRegex": "^https:\\/\\/chatgpt\\.com\\/backend-api\\/sentinel\\/chat-requirements(\\?.*)?$",
+        "targetUrl": "https://www.chatgpt.com/home",
+        "method": "GET",
+        "title": "ChatGPT Paid Account",
+        "description": "",
+        "icon": "https://utfs.io/f/taibMU1XxiEPtZlbWo1XxiEPsjzpNu8frqFdalI30V7yCJBO",
+        "responseType": "json",
+        "actionSelectors": ["a[role=\"link\"] img[alt][draggable=\"true\"]"],
+        "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); const paid = obj.persona === 'chatgpt-paid'; if(!paid) throw new Error('Invalid account'); return { paid: paid }; }",
+        "attributes": ["{paid: paid}"]
+      }"#;
+
+    #[test]
+    fn test_chatgpt_provider() {
+        let provider: Provider =
+            serde_json::from_str(CHATGPT_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&CHATGPT_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+
+        println!("result: {:?}", result);
+        println!("{:?}", attributes);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0], "paid: true");
+    }
+
+    const CLAUDE_RESPONSE_TEXT: &str = r#"{
+  "status": "active",
+  "cancel_at_ts": null
+  }"#;
+
+    const CLAUDE_PROVIDER_TEXT: &str = r#"{
+        "id": 0,
+        "host": "claude.ai",
+        "urlRegex": "^https:\\/\\/claude\\.ai(.*)?subscription_status(.*)?$",
+        "targetUrl": "https://claude.ai/",
+        "method": "GET",
+        "title": "Claude Paid Account",
+        "description": "",
+        "icon": "https://utfs.io/f/taibMU1XxiEPtZlbWo1XxiEPsjzpNu8frqFdalI30V7yCJBO",
+        "responseType": "json",
+        "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); return { paid: obj.status === 'active' }; }",
+        "attributes": ["{paid: paid}"]
+      }"#;
+
+    #[test]
+    fn test_claude_provider() {
+        let provider: Provider =
+            serde_json::from_str(CLAUDE_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&CLAUDE_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+
+        println!("result: {:?}", result);
+        println!("{:?}", attributes);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0], "paid: true");
+    }
+
+    const X_FOLLOWERS_RESPONSE_TEXT: &str = r#"
+{
+    "data": {
+      "viewer_v2": {
+        "user_results": {
+          "result": {
+            "__typename": "User",
+            "verified_follower_count": "9",
+            "relationship_counts": {
+              "followers": 29
+            },
+            "organic_metrics_time_series": [
+              {
+                "metric_values": [
+                  {
+                    "metric_value": 3,
+                    "metric_type": "Engagements"
+                  },
+                  {
+                    "metric_value": 62,
+                    "metric_type": "Impressions"
+                  },
+                  {
+                    "metric_type": "ProfileVisits"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "Follows"
+                  },
+                  {
+                    "metric_type": "Replies"
+                  },
+                  {
+                    "metric_value": 2,
+                    "metric_type": "Likes"
+                  },
+                  {
+                    "metric_type": "Retweets"
+                  },
+                  {
+                    "metric_type": "Bookmark"
+                  },
+                  {
+                    "metric_type": "Share"
+                  },
+                  {
+                    "metric_type": "UrlClicks"
+                  },
+                  {
+                    "metric_type": "CreateTweet"
+                  },
+                  {
+                    "metric_type": "CreateQuote"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "Unfollows"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "CreateReply"
+                  }
+                ],
+                "timestamp": {
+                  "iso8601_time": "2025-01-21T00:00:00Z"
+                }
+              },
+              {
+                "metric_values": [
+                  {
+                    "metric_value": 4,
+                    "metric_type": "Engagements"
+                  },
+                  {
+                    "metric_value": 47,
+                    "metric_type": "Impressions"
+                  },
+                  {
+                    "metric_type": "ProfileVisits"
+                  },
+                  {
+                    "metric_type": "Follows"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "Replies"
+                  },
+                  {
+                    "metric_value": 3,
+                    "metric_type": "Likes"
+                  },
+                  {
+                    "metric_type": "Retweets"
+                  },
+                  {
+                    "metric_type": "Bookmark"
+                  },
+                  {
+                    "metric_type": "Share"
+                  },
+                  {
+                    "metric_type": "UrlClicks"
+                  },
+                  {
+                    "metric_type": "CreateTweet"
+                  },
+                  {
+                    "metric_type": "CreateQuote"
+                  },
+                  {
+                    "metric_type": "Unfollows"
+                  },
+                  {
+                    "metric_value": 3,
+                    "metric_type": "CreateReply"
+                  }
+                ],
+                "timestamp": {
+                  "iso8601_time": "2025-01-22T00:00:00Z"
+                }
+              },
+              {
+                "metric_values": [
+                  {
+                    "metric_value": 2,
+                    "metric_type": "Engagements"
+                  },
+                  {
+                    "metric_value": 198,
+                    "metric_type": "Impressions"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "ProfileVisits"
+                  },
+                  {
+                    "metric_type": "Follows"
+                  },
+                  {
+                    "metric_type": "Replies"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "Likes"
+                  },
+                  {
+                    "metric_type": "Retweets"
+                  },
+                  {
+                    "metric_type": "Bookmark"
+                  },
+                  {
+                    "metric_type": "Share"
+                  },
+                  {
+                    "metric_type": "UrlClicks"
+                  },
+                  {
+                    "metric_type": "CreateTweet"
+                  },
+                  {
+                    "metric_type": "CreateQuote"
+                  },
+                  {
+                    "metric_type": "Unfollows"
+                  },
+                  {
+                    "metric_value": 6,
+                    "metric_type": "CreateReply"
+                  }
+                ],
+                "timestamp": {
+                  "iso8601_time": "2025-01-23T00:00:00Z"
+                }
+              },
+              {
+                "metric_values": [
+                  {
+                    "metric_value": 5,
+                    "metric_type": "Engagements"
+                  },
+                  {
+                    "metric_value": 118,
+                    "metric_type": "Impressions"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "ProfileVisits"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "Follows"
+                  },
+                  {
+                    "metric_type": "Replies"
+                  },
+                  {
+                    "metric_value": 3,
+                    "metric_type": "Likes"
+                  },
+                  {
+                    "metric_type": "Retweets"
+                  },
+                  {
+                    "metric_type": "Bookmark"
+                  },
+                  {
+                    "metric_type": "Share"
+                  },
+                  {
+                    "metric_type": "UrlClicks"
+                  },
+                  {
+                    "metric_type": "CreateTweet"
+                  },
+                  {
+                    "metric_type": "CreateQuote"
+                  },
+                  {
+                    "metric_type": "Unfollows"
+                  },
+                  {
+                    "metric_value": 1,
+                    "metric_type": "CreateReply"
+                  }
+                ],
+                "timestamp": {
+                  "iso8601_time": "2025-01-24T00:00:00Z"
+                }
+              },
+              {
+                "metric_values": [
+                  {
+                    "metric_type": "Engagements"
+                  },
+                  {
+                    "metric_value": 20,
+                    "metric_type": "Impressions"
+                  },
+                  {
+                    "metric_type": "ProfileVisits"
+                  },
+                  {
+                    "metric_type": "Follows"
+                  },
+                  {
+                    "metric_type": "Replies"
+                  },
+                  {
                     "metric_type": "Likes"
                   },
                   {
@@ -2732,352 +5532,2783 @@ mod tests {
                 "timestamp": {
                   "iso8601_time": "2025-02-03T00:00:00Z"
                 }
-              }
-            ],
-            "id": "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"
-          },
-          "id": "YYYYYYYYYYYYYYYYYYYYYYYYYYYYYYYY"
+              }
+            ],
+            "id": "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"
+          },
+          "id": "YYYYYYYYYYYYYYYYYYYYYYYYYYYYYYYY"
+        }
+      }
+    }
+  }
+    "#;
+
+    const X_FOLLOWERS_PROVIDER_TEXT: &str = r#"{
+            "id": 0,
+            "host": "x.com",
+            "urlRegex": "^https:\\/\\/x\\.com\\/i\\/api\\/graphql\\/[\\w-]+\\/AccountOverviewQuery$",
+            "targetUrl": "https://x.com/i/account_analytics",
+            "method": "GET",
+            "title": "X Verified Followers",
+            "description": "",
+            "icon": "https://utfs.io/f/taibMU1XxiEPtZlbWo1XxiEPsjzpNu8frqFdalI30V7yCJBO",
+            "responseType": "json",
+            "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); return { id: obj.data.viewer_v2.user_results.result.id, verified_followers: obj.data.viewer_v2.user_results.result.verified_follower_count }; }",
+            "attributes": ["{id: id, verified_followers: verified_followers}"]
+          }"#;
+
+    #[test]
+    fn test_x_followers_provider() {
+        let provider: Provider =
+            serde_json::from_str(X_FOLLOWERS_PROVIDER_TEXT).expect("Failed to parse provider");
+        let result = provider
+            .preprocess_response(&X_FOLLOWERS_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&result)
+            .expect("Failed to get attributes");
+
+        println!("attributes: {:?}", attributes);
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains(&"verified_followers: \"9\"".to_string()));
+        assert!(attributes.contains(&"id: \"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX\"".to_string()));
+    }
+
+    #[test]
+    fn test_time_series_sum() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "host": "x.com",
+            "urlRegex": r"^https://x\.com/i/api/graphql/[\w-]+/AccountOverviewQuery$",
+            "targetUrl": "https://x.com/i/account_analytics",
+            "attributes": [
+                "{impressions: sum(data.viewer_v2.user_results.result.organic_metrics_time_series[*].metric_values[?metric_type=='Impressions'].metric_value)}",
+                "{over_700: sum(data.viewer_v2.user_results.result.organic_metrics_time_series[*].metric_values[?metric_type=='Impressions'].metric_value) > 700}",
+                "{replies: data.viewer_v2.user_results.result.organic_metrics_time_series[*].metric_values[?metric_type=='Replies'].metric_value}"
+            ]
+        }));
+
+        let response: Value =
+            serde_json::from_str(X_FOLLOWERS_RESPONSE_TEXT).expect("Failed to parse response");
+        let mut attributes = provider
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            vec![
+                "impressions: 785".to_string(),
+                "over_700: true".to_string(),
+                // Days without replies have no metric value and are skipped
+                "replies: [1,1,1]".to_string(),
+            ]
+        );
+
+        let data = json!({ "values": [1, 2.5, -1] });
+        assert_eq!(
+            evaluate_field_expression("sum(values)", &data),
+            Ok(json!(2.5))
+        );
+        assert_eq!(
+            evaluate_field_expression("sum(missing[*])", &json!({"missing": []})),
+            Ok(json!(0))
+        );
+        assert!(
+            evaluate_field_expression("sum(values[*].a)", &json!({"values": [{"a": "1"}]}))
+                .is_err()
+        );
+        assert!(evaluate_field_expression("sum(values[*]", &data).is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        use serde_json::json;
+
+        let response: Value =
+            serde_json::from_str(X_FOLLOWERS_RESPONSE_TEXT).expect("Failed to parse response");
+        let data = &response["data"]["viewer_v2"]["user_results"]["result"]
+            ["organic_metrics_time_series"][0];
+
+        assert_eq!(
+            evaluate_field_expression("find(metric_values, 'metric_type', 'Impressions')", data),
+            Ok(json!({"metric_value": 62, "metric_type": "Impressions"}))
+        );
+        assert_eq!(
+            evaluate_field_expression(
+                "find(metric_values, 'metric_type', 'Impressions').metric_value > `50`",
+                data
+            ),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            evaluate_field_expression("find(metric_values, 'metric_value', `1.0`)", data),
+            Ok(json!({"metric_value": 1, "metric_type": "Follows"}))
+        );
+        assert_eq!(
+            evaluate_field_expression("find(metric_values, 'metric_type', 'Quotes')", data),
+            Ok(Value::Null)
+        );
+        assert!(
+            evaluate_field_expression("find(timestamp, 'metric_type', 'Likes')", data).is_err()
+        );
+    }
+
+    #[test]
+    fn test_has_keys() {
+        use serde_json::json;
+
+        let data = json!({
+            "user": {"id": "t2_abc", "name": "spez", "karma": 10},
+            "banned": null,
+            "tags": ["a"]
+        });
+
+        assert_eq!(
+            evaluate_field_expression("has_keys(user, ['id', \"name\"])", &data),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            evaluate_field_expression("has_keys(user, ['id', 'created_utc'])", &data),
+            Ok(json!(false))
+        );
+        assert_eq!(
+            evaluate_field_expression("has_keys(@, ['user', 'banned'])", &data),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            evaluate_field_expression("has_keys(tags, ['a'])", &data),
+            Ok(json!(false))
+        );
+        assert_eq!(
+            evaluate_field_expression("has_keys(user, ['id']) && user.karma > `5`", &data),
+            Ok(json!(true))
+        );
+        assert!(evaluate_field_expression("has_keys(user, 'id')", &data).is_err());
+
+        let provider = test_provider(json!({
+            "attributes": ["{complete: has_keys(user, ['id', 'name', 'karma'])}"]
+        }));
+        assert_eq!(
+            provider
+                .get_attributes(&data)
+                .expect("Failed to get attributes"),
+            vec!["complete: true"]
+        );
+    }
+
+    #[test]
+    fn test_is_absent_and_is_present() {
+        use serde_json::json;
+
+        let response: Value =
+            serde_json::from_str(REDDIT_RESPONSE_TEXT).expect("Failed to parse response");
+        let data = &response["data"];
+
+        // `suspension_expiration_utc` is null and `suspension_reason` is missing
+        for field in ["suspension_expiration_utc", "suspension_reason"] {
+            assert_eq!(
+                evaluate_field_expression(&format!("is_absent({})", field), data),
+                Ok(json!(true))
+            );
+            assert_eq!(
+                evaluate_field_expression(&format!("is_present({})", field), data),
+                Ok(json!(false))
+            );
+        }
+        assert_eq!(
+            evaluate_field_expression("is_absent(is_suspended)", data),
+            Ok(json!(false))
+        );
+        assert_eq!(
+            evaluate_field_expression("is_present(subreddit.title)", data),
+            Ok(json!(true))
+        );
+        assert_eq!(
+            evaluate_field_expression("is_present(subreddit.ban.reason)", data),
+            Ok(json!(false))
+        );
+        assert!(evaluate_field_expression("is_absent(`1`)", data).is_err());
+
+        let provider = test_provider(json!({
+            "host": "reddit.com",
+            "urlRegex": r"^https://www\.reddit\.com/user/[a-zA-Z0-9]+.*/about\.json$",
+            "targetUrl": "https://www.reddit.com",
+            "attributes": ["{not_suspended: is_absent(suspension_expiration_utc), verified: is_present(has_verified_email)}"],
+            "preprocess": "function process(jsonString) { return JSON.parse(jsonString).data; }"
+        }));
+        let processed_response = provider
+            .preprocess_response(REDDIT_RESPONSE_TEXT)
+            .expect("Failed to preprocess response");
+        let mut attributes = provider
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+        attributes.sort();
+        assert_eq!(attributes, vec!["not_suspended: true", "verified: true"]);
+    }
+
+    #[test]
+    fn test_custom_evaluator_simple() {
+        use serde_json::json;
+        use std::collections::HashMap;
+
+        // Create a simple provider that doesn't use preprocessing
+        let provider_json = json!({
+            "id": 99,
+            "host": "test.com",
+            "urlRegex": r"^https://test\.com/.*$",
+            "targetUrl": "https://test.com",
+            "method": "GET",
+            "title": "Test Provider",
+            "description": "Simple test",
+            "icon": "test",
+            "responseType": "json",
+            "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        // Create test response data that matches what the preprocessor would output
+        let test_response = json!({
+            "verified": true,
+            "PreGPT4": true,
+            "bioMentionsFreysa": false
+        });
+
+        let attributes = provider
+            .get_attributes(&test_response)
+            .expect("Failed to get attributes");
+
+        println!("Custom evaluator test result: {:?}", attributes);
+
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains(&"verified: true".to_string()));
+        assert!(attributes.contains(&"PreGPT4: true".to_string()));
+    }
+
+    #[test]
+    fn test_custom_evaluator_complex() {
+        use serde_json::json;
+
+        // Test a more complex expression
+        let provider_json = json!({
+            "id": 100,
+            "host": "test2.com",
+            "urlRegex": r"^https://test2\.com/.*$",
+            "targetUrl": "https://test2.com",
+            "method": "GET",
+            "title": "Test Provider 2",
+            "description": "Complex test",
+            "icon": "test2",
+            "responseType": "json",
+            "attributes": ["{over_10k: to_number(performance_baseline.amount) >`10000` && performance_baseline.currency_code == 'USD'}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        // Create test response data
+        let test_response = json!({
+            "performance_baseline": {
+                "amount": "15000",
+                "currency_code": "USD"
+            }
+        });
+
+        let attributes = provider
+            .get_attributes(&test_response)
+            .expect("Failed to get attributes");
+
+        println!("Complex evaluator test result: {:?}", attributes);
+
+        assert_eq!(attributes.len(), 1);
+        assert!(attributes.contains(&"over_10k: true".to_string()));
+    }
+
+    #[test]
+    fn test_custom_evaluator_simple_fields() {
+        use serde_json::json;
+
+        // Test simple field access
+        let provider_json = json!({
+            "id": 101,
+            "host": "test3.com",
+            "urlRegex": r"^https://test3\.com/.*$",
+            "targetUrl": "https://test3.com",
+            "method": "GET",
+            "title": "Test Provider 3",
+            "description": "Simple fields test",
+            "icon": "test3",
+            "responseType": "json",
+            "preprocess": "response",
+            "attributes": ["{id: id, screen_name: screen_name}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        // Create test response data
+        let test_response = json!({
+            "id": "12345",
+            "screen_name": "testuser"
+        });
+
+        let attributes = provider
+            .get_attributes(&test_response)
+            .expect("Failed to get attributes");
+
+        println!("Simple fields test result: {:?}", attributes);
+
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains(&"id: \"12345\"".to_string()));
+        assert!(attributes.contains(&"screen_name: \"testuser\"".to_string()));
+    }
+
+    #[test]
+    fn test_x_provider_direct() {
+        use serde_json::json;
+
+        // Use the actual X provider config from providers.json (without JavaScript preprocessing)
+        let provider_json = json!({
+            "id": 2,
+            "host": "x.com",
+            "urlRegex": r"^https://x\.com/i/api/graphql/[\w\d]+/UserByScreenName(\?.*)?$",
+            "targetUrl": "https://www.x.com/home",
+            "method": "GET",
+            "title": "Verify X subscription",
+            "description": "",
+            "icon": "twitterPremium",
+            "responseType": "json",
+            "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        // Create test response data that matches what the preprocessor would output
+        // This simulates a pre-March 2023 verified account
+        let test_response = json!({
+            "verified": true,
+            "PreGPT4": true,
+            "bioMentionsFreysa": true
+        });
+
+        let attributes = provider
+            .get_attributes(&test_response)
+            .expect("Failed to get attributes");
+
+        println!("X provider direct test result: {:?}", attributes);
+
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains(&"verified: true".to_string()));
+        assert!(attributes.contains(&"PreGPT4: true".to_string()));
+
+        println!("✅ X provider custom evaluator test passed!");
+    }
+
+    #[test]
+    fn test_x_provider_logged_payload() {
+        use serde_json::json;
+
+        // This is the exact payload from the logs
+        let x_response_text = r#"{"data":{"user":{"result":{"__typename":"User","id":"VRNlcjoxNjc2OTI4NDU2MjI1ODk4NDk2","rest_id":"1676928456225898496","affiliates_highlighted_label":{},"avatar":{"image_url":"https://pbs.twimg.com/profile_images/1938401595659534336/4FfZgohs_normal.jpg"},"core":{"created_at":"Thu Jul 06 12:18:01 +0000 2023","name":"fppp290","screen_name":"Johndoe93"},"dm_permissions":{"can_dm":true},"has_graduated_access":true,"is_blue_verified":true,"legacy":{"default_profile":true,"default_profile_image":false,"description":"","entities":{"description":{"urls":[]}},"fast_followers_count":0,"favourites_count":182,"followers_count":35,"friends_count":180,"has_custom_timelines":false,"is_translator":false,"listed_count":1,"media_count":7,"needs_phone_verification":false,"normal_followers_count":35,"pinned_tweet_ids_str":["1875293532711186617"],"possibly_sensitive":false,"profile_banner_url":"https://pbs.twimg.com/profile_banners/1676928456225898496/1738727374","profile_interstitial_type":"","statuses_count":565,"translator_type":"none","want_retweets":false,"withheld_in_countries":[]},"location":{"location":""},"media_permissions":{"can_media_tag":true},"parody_commentary_fan_label":"None","profile_image_shape":"Circle","privacy":{"protected":false},"relationship_perspectives":{"following":false},"tipjar_settings":{},"verification":{"verified":false},"legacy_extended_profile":{"birthdate":{"day":12,"month":1,"year":1994,"visibility":"Self","year_visibility":"Self"}},"is_profile_translatable":false,"has_hidden_subscriptions_on_profile":false,"verification_info":{"is_identity_verified":false,"reason":{"description":{"text":"This account is verified. Learn more","entities":[{"from_index":26,"to_index":36,"ref":{"url":"https://help.twitter.com/managing-your-account/about-twitter-verified-accounts","url_type":"ExternalUrl"}}]},"verified_since_msec":"1735851543944"}},"highlights_info":{"can_highlight_tweets":true,"highlighted_tweets":"1"},"user_seed_tweet_count":0,"premium_gifting_eligible":false,"business_account":{},"creator_subscriptions_count":0}}}}"#;
+
+        // Create a cleaner, properly formatted preprocessing script
+        let provider_json = json!({
+            "id": 2,
+            "host": "x.com",
+            "urlRegex": r"^https://x\.com/i/api/graphql/[\w\d]+/UserByScreenName(\?.*)?$",
+            "targetUrl": "https://www.x.com/home",
+            "method": "GET",
+            "title": "Verify X subscription",
+            "description": "",
+            "icon": "twitterPremium",
+            "responseType": "json",
+            "preprocess": "function process(jsonString) {\n  const object = JSON.parse(jsonString);\n  const parts = object.data.user.result.core.created_at.split(' ');\n  const time = parts[3].split(':');\n  const months = {\n    Jan: 0, Feb: 1, Mar: 2, Apr: 3, May: 4, Jun: 5,\n    Jul: 6, Aug: 7, Sep: 8, Oct: 9, Nov: 10, Dec: 11\n  };\n  const createdAt = new Date(\n    parseInt(parts[5]),\n    months[parts[1]],\n    parseInt(parts[2]),\n    parseInt(time[0]),\n    parseInt(time[1]),\n    parseInt(time[2])\n  );\n  const targetDate = new Date('2023-03-01');\n  const PreGPT4 = createdAt < targetDate;\n  const verified = object.data.user.result.is_blue_verified;\n  const bio = object.data.user.result.legacy.description;\n  const bioMentionsFreysa = bio.toLowerCase().includes('it starts with freysa...');\n  if (!verified || !PreGPT4) {\n    throw new Error('Invalid account');\n  }\n  return {\n    verified: verified,\n    bioMentionsFreysa: bioMentionsFreysa,\n    PreGPT4: PreGPT4\n  };\n}",
+            "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        println!("Testing with URL check...");
+        let url_matches = provider.check_url_method(
+        "https://x.com/i/api/graphql/U15Q5V7hgjzCEg6WpSWhqg/UserByScreenName?variables=%7B%22screen_name%22%3A%22Johndoe93%22%7D",
+        "GET"
+    ).expect("Failed to check URL");
+
+        println!("URL matches: {}", url_matches);
+        assert!(url_matches);
+
+        println!("Testing preprocessing...");
+        let result = provider.preprocess_response(x_response_text);
+
+        match result {
+            Ok(processed) => {
+                println!("Preprocessing succeeded: {:?}", processed);
+                // This should fail because the account was created after March 1, 2023
+                panic!("Expected preprocessing to fail with 'Invalid account' but it succeeded");
+            }
+            Err(e) => {
+                println!("Preprocessing failed as expected: {:?}", e);
+                // Check if it's the expected "Invalid account" error or a syntax error
+                assert!(
+                    e.to_string().contains("Invalid account")
+                        || e.to_string().contains("Process script error"),
+                    "Expected 'Invalid account' error but got: {}",
+                    e
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_x_provider_simplified_script() {
+        use serde_json::json;
+
+        let x_response_text = r#"{"data":{"user":{"result":{"core":{"created_at":"Thu Jan 06 12:18:01 +0000 2022"},"is_blue_verified":true,"legacy":{"description":"it starts with freysa..."}}}}}"#;
+
+        // Try with a much simpler preprocessing script
+        let provider_json = json!({
+            "id": 999,
+            "host": "x.com",
+            "urlRegex": r"^https://x\.com/.*$",
+            "targetUrl": "https://www.x.com/home",
+            "method": "GET",
+            "title": "Simple X test",
+            "description": "",
+            "icon": "twitter",
+            "responseType": "json",
+            "preprocess": "function process(jsonString) { return JSON.parse(jsonString); }",
+            "attributes": ["{verified: true}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        let result = provider.preprocess_response(x_response_text);
+        println!("Simple script result: {:?}", result);
+
+        match result {
+            Ok(processed) => {
+                println!("Simple preprocessing succeeded: {:?}", processed);
+            }
+            Err(e) => {
+                println!("Simple preprocessing failed: {:?}", e);
+                panic!("Even simple script failed: {}", e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_literal_value_edge_cases() {
+        use serde_json::json;
+
+        // Test that NaN values are handled gracefully without panicking
+        let provider_json = json!({
+            "id": 999,
+            "host": "test.com",
+            "urlRegex": r"^https://test\.com/.*$",
+            "targetUrl": "https://test.com",
+            "method": "GET",
+            "title": "Test Provider",
+            "description": "Test edge cases",
+            "icon": "test",
+            "responseType": "json",
+            "attributes": ["{nan_value: to_number(invalid_field)}"]
+        });
+
+        let provider: Provider =
+            serde_json::from_value(provider_json).expect("Failed to parse provider");
+
+        // Test response with a string that would parse to NaN when converted to f64
+        let test_response = json!({
+            "invalid_field": "NaN"
+        });
+
+        // This should return an error, not panic
+        let result = provider.get_attributes(&test_response);
+
+        match result {
+            Err(e) => {
+                println!("Expected error for NaN: {}", e);
+                assert!(e.to_string().contains("Invalid number value"));
+            }
+            Ok(_) => panic!("Expected error but got success"),
+        }
+
+        // Test infinity case as well
+        let test_response_inf = json!({
+            "invalid_field": "Infinity"
+        });
+
+        let result_inf = provider.get_attributes(&test_response_inf);
+
+        match result_inf {
+            Err(e) => {
+                println!("Expected error for Infinity: {}", e);
+                assert!(e.to_string().contains("Invalid number value"));
+            }
+            Ok(_) => panic!("Expected error but got success"),
+        }
+    }
+
+    #[test]
+    fn test_regex_extract() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{karma: regex_extract(bio, '([0-9]+) karma', `1`), has_karma: regex_match(bio, 'karma$')}"]
+        }));
+
+        let attributes = provider
+            .get_attributes(&json!({ "bio": "Redditor with 1,204 karma" }))
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"karma: \"204\"".to_string()));
+        assert!(attributes.contains(&"has_karma: true".to_string()));
+
+        let attributes = provider
+            .get_attributes(&json!({ "bio": "No points yet" }))
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"karma: null".to_string()));
+        assert!(attributes.contains(&"has_karma: false".to_string()));
+    }
+
+    #[test]
+    fn test_text_response_type() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "responseType": "text",
+            "attributes": ["{premium: contains(text, 'plan: premium'), member_id: regex_match(text, 'id: [0-9]+')}"]
+        }));
+
+        let processed_response = provider
+            .preprocess_response("status: ok\nplan: premium\nid: 4815")
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+
+        assert_eq!(attributes.len(), 2);
+        assert!(attributes.contains(&"premium: true".to_string()));
+        assert!(attributes.contains(&"member_id: true".to_string()));
+    }
+
+    #[test]
+    fn test_html_selectors() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "responseType": "html",
+            "htmlSelectors": {"followers": "#followers", "karma": "span.karma"},
+            "attributes": ["{followers: followers, karma: karma}"]
+        }));
+        let response = r#"<html><body><h1 id="followers">94</h1><span class="user karma"> 1,024 </span></body></html>"#;
+
+        let (processed_response, metadata) = provider
+            .preprocess_response_with_metadata(response)
+            .expect("Failed to preprocess response");
+        assert_eq!(metadata.path, PreprocessPath::HtmlSelectors);
+        assert_eq!(
+            processed_response,
+            json!({"followers": "94", "karma": "1,024"})
+        );
+
+        // Streaming the page yields the same attributes
+        let mut extractor = provider.html_extractor().unwrap().unwrap();
+        for chunk in response.as_bytes().chunks(5) {
+            extractor.feed(chunk);
+        }
+        let sorted_attributes = |response: &Value| {
+            let mut attributes = provider.get_attributes(response).unwrap();
+            attributes.sort();
+            attributes
+        };
+        assert_eq!(
+            sorted_attributes(&extractor.finish()),
+            sorted_attributes(&processed_response)
+        );
+
+        // A preprocess script takes the page instead
+        let scripted = test_provider(json!({
+            "responseType": "html",
+            "htmlSelectors": {"followers": "#followers"},
+            "preprocess": "function process(html) { return { followers: html.length }; }"
+        }));
+        assert!(scripted.html_extractor().unwrap().is_none());
+
+        let broken = test_provider(json!({
+            "responseType": "html",
+            "htmlSelectors": {"followers": "div > #followers"}
+        }));
+        assert!(matches!(
+            test_config(vec![broken]).validate(),
+            Err(ProviderError::InvalidSelector(selector, _)) if selector == "div > #followers"
+        ));
+    }
+
+    #[test]
+    fn test_ndjson_response_type() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "responseType": "ndjson",
+            "attributes": ["{events: length(@)}"]
+        }));
+
+        let processed_response = provider
+            .preprocess_response(
+                "{\"event\": \"login\", \"at\": 1}\n{\"event\": \"trade\", \"at\": 2}\n\n{\"event\": \"logout\", \"at\": 3}\n",
+            )
+            .expect("Failed to preprocess response");
+        assert_eq!(processed_response.as_array().map(Vec::len), Some(3));
+
+        let attributes = provider
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+        assert_eq!(attributes, vec!["events: 3".to_string()]);
+
+        assert!(matches!(
+            provider.preprocess_response("{\"event\": \"login\"}\n{\"event\""),
+            Err(ProviderError::JsonParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_response_encoding() {
+        use serde_json::json;
+
+        let provider = |response_encoding: &str| -> Provider {
+            test_provider(json!({
+                "responseEncoding": response_encoding,
+                "attributes": ["{karma: karma, name: name}"]
+            }))
+        };
+
+        // {"karma": 42, "name": "a b"}
+        let base64 = provider("base64");
+        let processed_response = base64
+            .preprocess_response("eyJrYXJtYSI6IDQyLCAibmFtZSI6ICJhIGIifQ==\n")
+            .expect("Failed to preprocess response");
+        let attributes = base64
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"karma: 42".to_string()));
+        assert!(attributes.contains(&"name: \"a b\"".to_string()));
+        assert!(matches!(
+            base64.preprocess_response("not base64!"),
+            Err(ProviderError::ResponseDecodeError(_))
+        ));
+
+        let urlencoded = provider("urlencoded");
+        let processed_response = urlencoded
+            .preprocess_response("%7B%22karma%22%3A%2042%2C%20%22name%22%3A%20%22a+b%22%7D")
+            .expect("Failed to preprocess response");
+        let attributes = urlencoded
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"karma: 42".to_string()));
+        assert!(attributes.contains(&"name: \"a b\"".to_string()));
+
+        let none = provider("none");
+        let processed_response = none
+            .preprocess_response(r#"{"karma": 42, "name": "a+b"}"#)
+            .expect("Failed to preprocess response");
+        assert_eq!(processed_response, json!({"karma": 42, "name": "a+b"}));
+    }
+
+    #[test]
+    fn test_output_schema() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{karma: karma}"],
+            "outputSchema": {
+                "type": "object",
+                "properties": { "karma": { "type": "number" } },
+                "required": ["karma"]
+            }
+        }));
+        let processor = Processor::from_config(test_config(vec![provider]), String::new());
+
+        let attributes = processor
+            .process("https://test.com/me", "GET", r#"{"karma": 42}"#)
+            .expect("Failed to process response");
+        assert_eq!(attributes, vec!["karma: 42".to_string()]);
+
+        // The shape of the response drifted, so the attributes are not extracted
+        assert!(matches!(
+            processor.process("https://test.com/me", "GET", r#"{"karma": "42"}"#),
+            Err(ProviderError::ValidationError(_))
+        ));
+        assert!(matches!(
+            processor.process("https://test.com/me", "GET", r#"{"score": 42}"#),
+            Err(ProviderError::ValidationError(_))
+        ));
+
+        // The schema is compiled once and reused for every response
+        let key = processor.config.providers[0].cache_key();
+        assert!(COMPILED_SCHEMA_CACHE.read().unwrap().contains_key(&key));
+    }
+
+    #[test]
+    fn test_config_validate_rejects_invalid_output_schema() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{karma: karma}"],
+            "outputSchema": { "type": 42 }
+        }));
+        let config = test_config(vec![provider]);
+        assert!(matches!(
+            config.validate(),
+            Err(ProviderError::SchemaError(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_numbers() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{price: price, count: count, negative: negative, big: big, code: code, exponent: exponent, padded: padded, name: name, nested: nested}"]
+        }));
+        let response = json!({
+            "price": "30.03",
+            "count": 2,
+            "negative": "-4",
+            "big": "18446744073709551615",
+            "code": "007",
+            "exponent": "1e3",
+            "padded": "30.10",
+            "name": "abc",
+            "nested": {"totals": ["1.5", "x"]}
+        });
+
+        let attributes = provider
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"price: \"30.03\"".to_string()));
+        assert!(attributes.contains(&"count: 2".to_string()));
+
+        let provider = Provider {
+            normalize_numbers: Some(true),
+            ..provider
+        };
+        let mut attributes = provider
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            vec![
+                "big: 18446744073709551615",
+                "code: \"007\"",
+                "count: 2",
+                "exponent: \"1e3\"",
+                "name: \"abc\"",
+                "negative: -4",
+                "nested: {\"totals\":[1.5,\"x\"]}",
+                "padded: \"30.10\"",
+                "price: 30.03",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_function() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{followers: to_nubmer(count)}"]
+        }));
+
+        let error = provider
+            .get_attributes(&json!({ "count": "12" }))
+            .expect_err("Expected unknown function error")
+            .to_string();
+        assert!(error.contains("unknown function 'to_nubmer', did you mean 'to_number'?"));
+
+        assert!(supported_functions().contains(&"regex_extract"));
+        assert!(supported_operators().contains(&"=="));
+    }
+
+    #[test]
+    fn test_unsupported_function_call() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{x: toLowerCase(name), y: contains(name, 'toUpperCase(')}"]
+        }));
+
+        match provider.get_attributes(&json!({ "name": "Alice" })) {
+            Err(ProviderError::ProcessError(message)) => {
+                assert_eq!(message, "unknown function 'toLowerCase'")
+            }
+            other => panic!("Expected unknown function error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_function_table() {
+        use serde_json::json;
+
+        let provider =
+            |attribute: &str| test_provider(json!({ "attributes": [attribute], "partial": true }));
+        let data = json!({ "name": "Alice", "tags": ["a"] });
+        let error = |attribute: &str| match provider(attribute).get_attributes(&data) {
+            Err(ProviderError::ProcessError(message)) => message,
+            other => panic!("Expected function call error, got {:?}", other),
+        };
+
+        // Checked before evaluation, so partial mode does not skip them
+        assert_eq!(error("{n: length()}"), "length expects 1 argument, got 0");
+        assert_eq!(
+            error("{n: length(name, tags)}"),
+            "length expects 1 argument, got 2"
+        );
+        assert_eq!(
+            error("{n: to_number(length(name, tags))}"),
+            "length expects 1 argument, got 2"
+        );
+        assert_eq!(
+            error("{n: regex_extract(name, 'A(l)')}"),
+            "regex_extract expects 3 arguments, got 2"
+        );
+        assert_eq!(
+            error("{n: lenght(name)}"),
+            "unknown function 'lenght', did you mean 'length'?"
+        );
+
+        let attributes =
+            provider("{n: length(name), t: contains(tags, 'a'), q: contains(name, 'length(')}")
+                .get_attributes(&data)
+                .expect("Failed to get attributes");
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(supported_functions().len(), FUNCTIONS.len());
+    }
+
+    #[test]
+    fn test_nested_function_calls() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{digits: length(regex_extract(bio, '([0-9]+) karma', `1`)), karma: to_number(regex_extract(bio, '([0-9]+) karma', `1`)) > `100`}"]
+        }));
+
+        let attributes = provider
+            .get_attributes(&json!({ "bio": "Redditor with 204 karma" }))
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"digits: 3".to_string()));
+        assert!(attributes.contains(&"karma: true".to_string()));
+    }
+
+    #[test]
+    fn test_function_wrong_argument_count() {
+        use serde_json::json;
+
+        let data = json!({ "name": "Alice" });
+
+        let error = evaluate_field_expression("contains(name)", &data).unwrap_err();
+        assert_eq!(error, "contains expects 2 arguments, got 1");
+
+        let error = evaluate_field_expression("length(name, name)", &data).unwrap_err();
+        assert_eq!(error, "length expects 1 argument, got 2");
+    }
+
+    #[test]
+    fn test_first_last() {
+        use serde_json::json;
+
+        let data = json!({
+            "followers": [{"count": 10}, {"count": 12}, {"count": 15}],
+            "empty": [],
+            "name": "Alice"
+        });
+
+        assert_eq!(
+            evaluate_field_expression("first(followers)", &data),
+            Ok(json!({"count": 10}))
+        );
+        assert_eq!(
+            evaluate_field_expression("last(followers)", &data),
+            Ok(json!({"count": 15}))
+        );
+
+        let error = evaluate_field_expression("first(empty)", &data).unwrap_err();
+        assert_eq!(error, "Cannot get first of an empty array");
+        let error = evaluate_field_expression("last(empty)", &data).unwrap_err();
+        assert_eq!(error, "Cannot get last of an empty array");
+        assert!(evaluate_field_expression("last(name)", &data).is_err());
+    }
+
+    #[test]
+    fn test_quoted_key_with_dot() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{total: data[\"eats_fare.total\"], nested: data['eats_fare.tip'].amount, plain: data.currency}"]
+        }));
+
+        let attributes = provider
+            .get_attributes(&json!({
+                "data": {
+                    "eats_fare.total": 42.5,
+                    "eats_fare.tip": { "amount": 5 },
+                    "currency": "USD",
+                    "eats_fare": { "total": 0 }
+                }
+            }))
+            .expect("Failed to get attributes");
+        assert!(attributes.contains(&"total: 42.5".to_string()));
+        assert!(attributes.contains(&"nested: 5".to_string()));
+        assert!(attributes.contains(&"plain: \"USD\"".to_string()));
+    }
+
+    #[test]
+    fn test_audit_sink() {
+        use serde_json::json;
+        use std::sync::Mutex;
+
+        #[derive(Debug, Default)]
+        struct MockSink(Mutex<Vec<AuditRecord>>);
+
+        impl AuditSink for MockSink {
+            fn record(&self, record: AuditRecord) {
+                self.0.lock().unwrap().push(record);
+            }
         }
-      }
+
+        let provider = test_provider(json!({
+            "attributes": ["{karma: karma, verified: verified}"]
+        }));
+
+        let provider_id = provider.id;
+        let sink = Arc::new(MockSink::default());
+        let processor = Processor::from_config(test_config(vec![provider]), String::new())
+            .with_audit_sink(sink.clone());
+
+        processor
+            .process(
+                "https://test.com/me",
+                "GET",
+                r#"{"karma": 42, "verified": true, "secret": "token"}"#,
+            )
+            .expect("Failed to process response");
+        processor
+            .process_request(
+                &RequestContext::new("https://test.com/me", "GET")
+                    .with_headers(&Headers {
+                        code: Some(200),
+                        headers: Vec::new(),
+                    })
+                    .with_session_id("session-1"),
+                r#"{"karma": 7, "verified": false}"#,
+            )
+            .expect("Failed to process response");
+        assert!(processor
+            .process("https://other.com/me", "GET", r#"{"karma": 1}"#)
+            .is_err());
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].session_id, None);
+        assert_eq!(records[0].provider_id, provider_id);
+        assert_eq!(records[0].url_regex, r"^https://test\.com/.*$");
+        assert_eq!(records[0].method, "GET");
+        assert_eq!(records[0].attribute_count, 2);
+        assert!(records[0].timestamp > 0);
+        assert_eq!(records[1].session_id.as_deref(), Some("session-1"));
+        assert!(!serde_json::to_string(&*records).unwrap().contains("token"));
+    }
+
+    #[test]
+    fn test_warm_up() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{karma: karma}"],
+            "preprocess": "function process(jsonString) { return JSON.parse(jsonString); }"
+        }));
+
+        let processor = Processor::from_config(test_config(vec![provider.clone()]), String::new());
+        processor.warm_up().expect("Failed to warm up");
+
+        let key = provider.cache_key();
+        assert!(COMPILED_REGEX_CACHE.read().unwrap().contains_key(&key));
+        assert!(COMPILED_ATTRIBUTES_CACHE.read().unwrap().contains_key(&key));
+        // Workers other than the warming thread see the compiled artifacts too
+        std::thread::spawn(move || {
+            assert!(COMPILED_REGEX_CACHE.read().unwrap().contains_key(&key));
+            assert!(COMPILED_ATTRIBUTES_CACHE.read().unwrap().contains_key(&key));
+        })
+        .join()
+        .unwrap();
+
+        let broken = test_provider(json!({ "preprocess": "function process(jsonString) {" }));
+        let processor = Processor::from_config(test_config(vec![broken]), String::new());
+        assert!(matches!(
+            processor.warm_up(),
+            Err(ProviderError::PreProcessScriptError(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "host": "chatgpt.com",
+            "urlRegex": r"^https://chatgpt\.com/.*$",
+            "targetUrl": "https://chatgpt.com",
+            "attributes": ["{paid: paid}"],
+            "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); if (obj.persona !== 'chatgpt-paid') throw new Error('Invalid account'); if (!obj.verified) throw new Error('Not verified'); return { paid: true }; }",
+            "errorCodes": { "Invalid account": "ACCOUNT_NOT_PAID" }
+        }));
+
+        let error = provider
+            .preprocess_response(r#"{"persona": "chatgpt-free"}"#)
+            .expect_err("Expected error code");
+        assert_eq!(error.code(), Some("ACCOUNT_NOT_PAID"));
+        assert_eq!(
+            error.to_string(),
+            "Provider error ACCOUNT_NOT_PAID: Invalid account"
+        );
+
+        let error = provider
+            .preprocess_response(r#"{"persona": "chatgpt-paid", "verified": false}"#)
+            .expect_err("Expected preprocess error");
+        assert_eq!(error.code(), None);
+        assert!(error.to_string().contains("Not verified"));
+    }
+
+    #[test]
+    fn test_status_code_attribute() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{ok: status_code == `200`}"]
+        }));
+
+        let processor = Processor::from_config(test_config(vec![provider]), String::new());
+
+        let result = processor
+            .process_request(
+                &RequestContext::new("https://test.com/me", "GET").with_status_code(200),
+                r#"{"name": "Alice"}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(result, vec!["ok: true".to_string()]);
+
+        let result = processor
+            .process_request(
+                &RequestContext::new("https://test.com/me", "GET").with_status_code(403),
+                "<html>Forbidden</html>",
+            )
+            .expect("Failed to process");
+        assert_eq!(result, vec!["ok: false".to_string()]);
+    }
+
+    #[test]
+    fn test_processor_from_config() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "host": "github.com",
+            "urlRegex": r"^https://api\.github\.com/users/[a-zA-Z0-9]+$",
+            "targetUrl": "https://github.com",
+            "attributes": ["{followers: followers, popular: to_number(followers) > `100`}"]
+        }));
+
+        let config = test_config(vec![provider]);
+        let processor = Processor::from_config(config, String::new());
+
+        let result = processor
+            .process(
+                "https://api.github.com/users/octocat",
+                "GET",
+                r#"{"login": "octocat", "followers": 150}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&"followers: 150".to_string()));
+        assert!(result.contains(&"popular: true".to_string()));
+
+        assert!(processor
+            .process("https://api.github.com/repos/octocat", "GET", "{}")
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_hash() {
+        use serde_json::json;
+
+        let config = |attributes: &str, pcrs: &[(&str, &str)]| -> Processor {
+            // The id is hashed, so every config has the same one
+            let provider = test_provider(json!({
+                "id": 1,
+                "host": "github.com",
+                "urlRegex": r"^https://api\.github\.com/users/[a-zA-Z0-9]+$",
+                "targetUrl": "https://github.com",
+                "attributes": [attributes],
+                "errorCodes": { "Not found": "E404", "Suspended": "E403" }
+            }));
+            let config = Config {
+                expected_pcrs: pcrs
+                    .iter()
+                    .map(|(bank, pcr)| (bank.to_string(), pcr.to_string()))
+                    .collect(),
+                ..test_config(vec![provider])
+            };
+            Processor::from_config(config, String::new())
+        };
+
+        let processor = config("{followers: followers}", &[("PCR0", "a"), ("PCR2", "b")]);
+        let hash = processor.config_hash();
+        assert_eq!(hash.len(), 64);
+
+        // The order of the maps does not matter
+        assert_eq!(
+            config("{followers: followers}", &[("PCR2", "b"), ("PCR0", "a")]).config_hash(),
+            hash
+        );
+
+        // Neither does a round trip through JSON
+        let json = serde_json::to_string_pretty(&processor.config).unwrap();
+        let config_from_json: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            Processor::from_config(config_from_json, String::new()).config_hash(),
+            hash
+        );
+
+        assert_ne!(
+            config("{followers: following}", &[("PCR0", "a"), ("PCR2", "b")]).config_hash(),
+            hash
+        );
+    }
+
+    #[test]
+    fn test_compare_counts_above_f64_precision() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "host": "api.example.com",
+            "urlRegex": r"^https://api\.example\.com/stats$",
+            "targetUrl": "https://api.example.com",
+            "attributes": [
+                "{above: views > `9007199254740992`, exact: views == `9007199254740993`, rounded: views == `9007199254740992`}",
+                "{negative: balance > `-9223372036854775808`, fractional: views > `1.5`}"
+            ]
+        }));
+
+        // 2^53 + 1 cannot be represented as an f64
+        let response = json!({ "views": 9007199254740993u64, "balance": -1 });
+        let mut result = provider
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
+        result.sort();
+        assert_eq!(
+            result,
+            vec![
+                "above: true".to_string(),
+                "exact: true".to_string(),
+                "fractional: true".to_string(),
+                "negative: true".to_string(),
+                "rounded: false".to_string(),
+            ]
+        );
     }
-  }
-    "#;
 
-    const X_FOLLOWERS_PROVIDER_TEXT: &str = r#"{
-            "id": 0,
+    #[test]
+    fn test_graphql_operation() {
+        use serde_json::json;
+
+        let provider = |operation: &str| {
+            test_provider(json!({
+                "host": "example.com",
+                "urlRegex": r"^https://example\.com/graphql$",
+                "targetUrl": "https://example.com",
+                "method": "POST",
+                "attributes": [format!("{{{}: name}}", operation)],
+                "graphqlOperation": operation
+            }))
+        };
+        let viewer = provider("Viewer");
+        let processor = Processor::from_config(
+            test_config(vec![provider("UserByScreenName"), viewer.clone()]),
+            String::new(),
+        );
+        let url = "https://example.com/graphql";
+
+        let request_body = r#"{"operationName": "Viewer", "variables": {}}"#;
+        assert_eq!(
+            processor
+                .find_provider_for_request(url, "POST", Some(request_body))
+                .unwrap()
+                .map(|p| p.id),
+            Some(viewer.id)
+        );
+        assert_eq!(
+            processor
+                .process_request(
+                    &RequestContext::new(url, "POST")
+                        .with_request_body(request_body)
+                        .with_status_code(200),
+                    r#"{"name": "alice"}"#
+                )
+                .expect("Failed to process"),
+            vec!["Viewer: \"alice\"".to_string()]
+        );
+
+        // Without the request body the operation cannot be checked
+        assert!(processor.find_provider(url, "POST").unwrap().is_none());
+        assert!(processor
+            .find_provider_for_request(url, "POST", Some(r#"{"operationName": "Other"}"#))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_large_x_response_is_not_embedded() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
             "host": "x.com",
-            "urlRegex": "^https:\\/\\/x\\.com\\/i\\/api\\/graphql\\/[\\w-]+\\/AccountOverviewQuery$",
-            "targetUrl": "https://x.com/i/account_analytics",
-            "method": "GET",
-            "title": "X Verified Followers",
-            "description": "",
-            "icon": "https://utfs.io/f/taibMU1XxiEPtZlbWo1XxiEPsjzpNu8frqFdalI30V7yCJBO",
-            "responseType": "json",
-            "preprocess": "function process(jsonString) { const obj = JSON.parse(jsonString); return { id: obj.data.viewer_v2.user_results.result.id, verified_followers: obj.data.viewer_v2.user_results.result.verified_follower_count }; }",
-            "attributes": ["{id: id, verified_followers: verified_followers}"]
-          }"#;
+            "urlRegex": r"^https://x\.com/i/api/graphql/[\w-]+/Timeline$",
+            "targetUrl": "https://x.com",
+            "attributes": ["{count: count, last: last}"],
+            "preprocess": "function process(jsonString) { const data = JSON.parse(jsonString); const entries = data.data.entries; return { count: entries.length, last: entries[entries.length - 1].text }; }"
+        }));
+
+        let entries = (0..10_000)
+            .map(|i| json!({ "text": format!("it's tweet\n#{}", i) }))
+            .collect::<Vec<_>>();
+        let body = json!({ "data": { "entries": entries } }).to_string();
+        assert!(body.len() > MAX_EMBEDDED_RESPONSE_SIZE);
+        let response = format!("{:x}\r\n{}\r\n0\r\n\r\n", body.len(), body);
+
+        let (processed_response, metadata) = provider
+            .preprocess_response_with_metadata(&response)
+            .expect("Failed to preprocess response");
+        assert_eq!(metadata.path, PreprocessPath::XScript);
+        assert!(metadata.response_global);
+        let mut result = provider
+            .get_attributes(&processed_response)
+            .expect("Failed to get attributes");
+        result.sort();
+        assert_eq!(
+            result,
+            vec![
+                "count: 10000".to_string(),
+                "last: \"it's tweet\\n#9999\"".to_string(),
+            ]
+        );
+    }
 
     #[test]
-    fn test_x_followers_provider() {
-        let provider: Provider =
-            serde_json::from_str(X_FOLLOWERS_PROVIDER_TEXT).expect("Failed to parse provider");
-        let result = provider
-            .preprocess_response(&X_FOLLOWERS_RESPONSE_TEXT)
+    fn test_preprocess_metadata() {
+        use serde_json::json;
+
+        let provider = |preprocess: Option<&str>| {
+            test_provider(json!({ "attributes": ["{paid: paid}"], "preprocess": preprocess }))
+        };
+
+        let (processed_response, metadata) = provider(None)
+            .preprocess_response_with_metadata(r#"{"paid": true}"#)
             .expect("Failed to preprocess response");
-        let attributes = provider
-            .get_attributes(&result)
+        assert_eq!(processed_response, json!({"paid": true}));
+        assert_eq!(
+            metadata,
+            PreprocessMetadata {
+                path: PreprocessPath::NoScript,
+                parse_fallback: false,
+                response_global: false,
+            }
+        );
+
+        // An unparseable body silently becomes "{}", which the metadata records
+        let (processed_response, metadata) = provider(Some(""))
+            .preprocess_response_with_metadata("<html>Not found</html>")
+            .expect("Failed to preprocess response");
+        assert_eq!(processed_response, json!("{}"));
+        assert_eq!(metadata.path, PreprocessPath::EmptyScript);
+        assert!(metadata.parse_fallback);
+
+        let (processed_response, metadata) = provider(Some(
+            "function process(jsonString) { return { paid: JSON.parse(jsonString).paid }; }",
+        ))
+        .preprocess_response_with_metadata(r#"{"paid": true}"#)
+        .expect("Failed to preprocess response");
+        assert_eq!(processed_response, json!({"paid": true}));
+        assert_eq!(metadata.path, PreprocessPath::Script);
+        assert!(!metadata.parse_fallback && !metadata.response_global);
+    }
+
+    #[test]
+    fn test_query_params() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "host": "x.com",
+            "urlRegex": r"^https://x\.com/i/api/graphql/[\w-]+/UserByScreenName\?.*$",
+            "targetUrl": "https://x.com",
+            "attributes": ["{followers: followers}"],
+            "queryParams": {
+                "variables": r#""screen_name":"freysa_ai""#,
+                "lang": "^en$"
+            }
+        }));
+
+        let url = |variables: &str, lang: &str| {
+            format!(
+                "https://x.com/i/api/graphql/abc/UserByScreenName?variables={}&lang={}#top",
+                variables, lang
+            )
+        };
+        let freysa = "%7B%22screen_name%22%3A%22freysa_ai%22%7D";
+        let check = |url: &str| provider.check_request(url, "GET", None).unwrap();
+
+        assert!(check(&url(freysa, "en")));
+        assert!(!check(&url(
+            "%7B%22screen_name%22%3A%22someone_else%22%7D",
+            "en"
+        )));
+        assert!(!check(&url(freysa, "en-GB")));
+        assert!(!check(
+            "https://x.com/i/api/graphql/abc/UserByScreenName?lang=en"
+        ));
+
+        // An invalid pattern is rejected when the config is loaded rather than when a request
+        // is matched
+        let broken = test_provider(json!({
+            "attributes": ["{followers: followers}"],
+            "queryParams": { "lang": "^(en$" }
+        }));
+        let processor = Processor::from_config(test_config(vec![broken.clone()]), String::new());
+        assert!(matches!(
+            test_config(vec![broken]).validate(),
+            Err(ProviderError::InvalidRegex(pattern, _)) if pattern == "^(en$"
+        ));
+        assert!(matches!(
+            processor.find_provider("https://test.com/me?lang=en", "GET"),
+            Err(ProviderError::InvalidRegex(..))
+        ));
+    }
+
+    #[test]
+    fn test_deny_list() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "host": "169.254.169.254",
+            "urlRegex": r"^https?://.*$",
+            "targetUrl": "https://example.com",
+            "attributes": ["{id: id}"]
+        }));
+        let config: Config = serde_json::from_value(json!({
+            "version": "1",
+            "EXPECTED_PCRS": {},
+            "PROVIDERS": [provider],
+            "denyList": [r"^https?://(localhost|127\.0\.0\.1|169\.254\.\d+\.\d+)([:/]|$)"]
+        }))
+        .expect("Failed to parse config");
+        let processor = Processor::from_config(config, String::new());
+
+        let url = "http://169.254.169.254/latest/meta-data/";
+        assert!(matches!(
+            processor.find_provider(url, "GET"),
+            Err(ProviderError::UrlDenied(_))
+        ));
+        assert!(matches!(
+            processor.process(url, "GET", r#"{"id": 1}"#),
+            Err(ProviderError::UrlDenied(_))
+        ));
+        assert_eq!(
+            processor
+                .process("https://example.com/me", "GET", r#"{"id": 1}"#)
+                .expect("Failed to process"),
+            vec!["id: 1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_invalid_deny_list_pattern() {
+        use serde_json::json;
+
+        let config: Config = serde_json::from_value(json!({
+            "version": "1",
+            "EXPECTED_PCRS": {},
+            "PROVIDERS": [],
+            "denyList": [r"^https?://(localhost"]
+        }))
+        .expect("Failed to parse config");
+
+        assert!(matches!(
+            config.validate(),
+            Err(ProviderError::InvalidRegex(pattern, _)) if pattern == r"^https?://(localhost"
+        ));
+        assert!(matches!(
+            Processor::from_config(config, String::new()).warm_up(),
+            Err(ProviderError::InvalidRegex(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_to_number_lenient() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{total: to_number_lenient(total), over_1k: to_number_lenient(total) > `1000`}"]
+        }));
+
+        let mut result = provider
+            .get_attributes(&json!({ "total": "$1,234.50" }))
             .expect("Failed to get attributes");
+        result.sort();
+        assert_eq!(
+            result,
+            vec!["over_1k: true".to_string(), "total: 1234.5".to_string()]
+        );
+
+        let mut result = provider
+            .get_attributes(&json!({ "total": "42" }))
+            .expect("Failed to get attributes");
+        result.sort();
+        assert_eq!(
+            result,
+            vec!["over_1k: false".to_string(), "total: 42".to_string()]
+        );
+
+        let error = provider
+            .get_attributes(&json!({ "total": "pending" }))
+            .expect_err("Expected conversion error")
+            .to_string();
+        assert!(error.contains("Cannot convert"));
+
+        // The strict variant still rejects formatted numbers
+        assert!(value_to_number(json!("$1,234.50"), false).is_err());
+    }
 
-        println!("attributes: {:?}", attributes);
-        assert_eq!(attributes.len(), 2);
-        assert!(attributes.contains(&"verified_followers: \"9\"".to_string()));
-        assert!(attributes.contains(&"id: \"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX\"".to_string()));
+    #[test]
+    fn test_attribute_references_earlier_output() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": [
+                "{score: to_number(report.creditScore)}",
+                "{good_score: $score > `700`}"
+            ]
+        }));
+
+        let mut result = provider
+            .get_attributes(&json!({ "report": { "creditScore": "701" } }))
+            .expect("Failed to get attributes");
+        result.sort();
+        assert_eq!(
+            result,
+            vec!["good_score: true".to_string(), "score: 701".to_string()]
+        );
     }
 
     #[test]
-    fn test_custom_evaluator_simple() {
+    fn test_attribute_output_does_not_shadow_response() {
         use serde_json::json;
-        use std::collections::HashMap;
 
-        // Create a simple provider that doesn't use preprocessing
-        let provider_json = json!({
-            "id": 99,
-            "host": "test.com",
-            "urlRegex": r"^https://test\.com/.*$",
-            "targetUrl": "https://test.com",
-            "method": "GET",
-            "title": "Test Provider",
-            "description": "Simple test",
-            "icon": "test",
-            "responseType": "json",
-            "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
+        let provider = test_provider(json!({
+            "attributes": [
+                "{score: to_number(score)}",
+                "{raw_score: score, good_score: $score > `700`}",
+                "{band: $band}"
+            ],
+            "partial": true
+        }));
+
+        // `score` is the response field and `$score` the output, and `$band` is no output
+        let mut result = provider
+            .get_attributes(&json!({ "score": "701", "band": "good" }))
+            .expect("Failed to get attributes");
+        result.sort();
+        assert_eq!(
+            result,
+            vec![
+                "good_score: true".to_string(),
+                "raw_score: \"701\"".to_string(),
+                "score: 701".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_json_schema() {
+        let schema = Config::json_schema();
+        let validator = jsonschema::Validator::new(&schema).expect("Failed to compile schema");
+
+        let providers = [
+            JSON_PROVIDER_TEXT,
+            SSA_PROVIDER_TEXT,
+            CHASE_PROVIDER_TEXT,
+            UBEREATS_PROVIDER_TEXT,
+            REDDIT_PROVIDER_TEXT,
+            ROBINHOOD_PROVIDER_TEXT,
+            TWITTER_BIO_PROVIDER_TEXT,
+            CHATGPT_PROVIDER_TEXT,
+            CLAUDE_PROVIDER_TEXT,
+            X_FOLLOWERS_PROVIDER_TEXT,
+        ]
+        .iter()
+        .map(|text| serde_json::from_str::<Value>(text).expect("Failed to parse provider"))
+        .collect::<Vec<_>>();
+        let config = serde_json::json!({
+            "version": "1.0.0",
+            "EXPECTED_PCRS": { "1": "pcr" },
+            "PROVIDERS": providers,
         });
+        assert!(validator.is_valid(&config));
+
+        let mut invalid = config.clone();
+        invalid["PROVIDERS"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("urlRegex");
+        assert!(!validator.is_valid(&invalid));
+
+        // The schema accepts the methods `HttpMethod` deserializes, in any case
+        for (method, valid) in [("post", true), (" Get ", true), ("FETCH", false)] {
+            let mut config = config.clone();
+            config["PROVIDERS"][0]["method"] = Value::from(method);
+            assert_eq!(validator.is_valid(&config), valid, "{}", method);
+            assert_eq!(method.parse::<HttpMethod>().is_ok(), valid, "{}", method);
+        }
+    }
 
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
+    #[test]
+    fn test_cache_policy() {
+        use serde_json::json;
 
-        // Create test response data that matches what the preprocessor would output
-        let test_response = json!({
-            "verified": true,
-            "PreGPT4": true,
-            "bioMentionsFreysa": false
+        let provider = test_provider(json!({
+            "attributes": ["{balance: balance}"],
+            "cachePolicy": { "maxAge": 60 }
+        }));
+        let headers = |headers: &[(&str, &str)]| Headers {
+            code: Some(200),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+
+        assert!(provider.check_cache_policy(&headers(&[])).is_ok());
+        assert!(provider
+            .check_cache_policy(&headers(&[("Age", "30")]))
+            .is_ok());
+        assert!(matches!(
+            provider.check_cache_policy(&headers(&[("Age", "86400")])),
+            Err(ProviderError::StaleResponse(_))
+        ));
+
+        let provider = Provider {
+            cache_policy: Some(CachePolicy {
+                require_no_store: true,
+                max_age: None,
+            }),
+            ..provider
+        };
+        assert!(provider
+            .check_cache_policy(&headers(&[("Cache-Control", "private, no-store")]))
+            .is_ok());
+        assert!(matches!(
+            provider.check_cache_policy(&headers(&[("Cache-Control", "max-age=3600")])),
+            Err(ProviderError::StaleResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_method_is_case_insensitive() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "method": "post",
+            "attributes": ["{id: id}"]
+        }));
+        assert_eq!(provider.method, HttpMethod::Post);
+        assert_eq!(
+            serde_json::to_value(provider.method).unwrap(),
+            json!("POST")
+        );
+
+        assert!(provider
+            .check_url_method("https://test.com/me", "POST")
+            .expect("Failed to check url method"));
+        assert!(provider
+            .check_url_method("https://test.com/me", "Post")
+            .expect("Failed to check url method"));
+        assert!(!provider
+            .check_url_method("https://test.com/me", "GET")
+            .expect("Failed to check url method"));
+
+        let mut unknown = serde_json::to_value(&provider).unwrap();
+        unknown["method"] = json!("FETCH");
+        assert!(serde_json::from_value::<Provider>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_processing_emits_spans() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{id: id}"]
+        }));
+        let provider_id = provider.id;
+        let processor = Processor::from_config(test_config(vec![provider]), String::new());
+
+        let headers = Headers {
+            code: Some(200),
+            headers: Vec::new(),
+        };
+        let (result, recording) = record_tracing(|| {
+            processor.process_request(
+                &RequestContext::new("https://test.com/me", "GET")
+                    .with_headers(&headers)
+                    .with_session_id("session-1"),
+                r#"{"id": 1}"#,
+            )
         });
+        assert_eq!(
+            result.expect("Failed to process"),
+            vec!["id: 1".to_string()]
+        );
+
+        let spans = recording.spans;
+        let names: Vec<&str> = spans.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "select_provider",
+                "preprocess_response",
+                "extract_attributes"
+            ]
+        );
+        for (_, fields) in spans.iter() {
+            assert!(fields.contains(&r#"session_id="session-1""#.to_string()));
+        }
+        for (_, fields) in spans.iter().skip(1) {
+            assert!(fields.contains(&format!("provider_id={}", provider_id)));
+        }
+    }
+
+    #[test]
+    fn test_debug_bodies() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{karma: karma}"]
+        }));
+        let config = test_config(vec![provider]);
+
+        let logged_body = |debug_bodies: bool, response: &str| {
+            let processor = Processor::from_config(config.clone(), String::new())
+                .with_debug_bodies(debug_bodies);
+            let ((), recording) = record_tracing(|| {
+                let _ = processor.process("https://test.com/me", "GET", response);
+            });
+            recording.events.iter().any(|(_, fields)| {
+                fields
+                    .iter()
+                    .any(|field| field.starts_with("body=") && field.contains("secret-handle"))
+            })
+        };
+
+        assert!(logged_body(true, r#"{"name": "secret-handle"}"#));
+        assert!(!logged_body(false, r#"{"name": "secret-handle"}"#));
+        assert!(!logged_body(
+            true,
+            r#"{"name": "secret-handle", "karma": 1}"#
+        ));
+
+        assert_eq!(truncate_chars("日本語", 2), "日本...");
+        assert_eq!(truncate_chars("日本", 2), "日本");
+    }
+
+    #[test]
+    fn test_extract_json_next_to_multibyte_characters() {
+        assert_eq!(
+            Provider::extract_json_from_response("é{\"name\": \"日本\"}ü"),
+            "{\"name\": \"日本\"}"
+        );
+        assert_eq!(
+            Provider::extract_json_from_response("1f\r\n{\"city\": \"Zürich\"}\r\n0\r\n\r\n"),
+            "{\"city\": \"Zürich\"}"
+        );
+        assert_eq!(Provider::extract_json_from_response("}é{"), "}é{");
+        assert_eq!(Provider::extract_json_from_response("日本"), "日本");
+    }
+
+    #[test]
+    fn test_tls_versions() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{id: id}"],
+            "tlsVersions": ["TLSv1.2"]
+        }));
+
+        assert!(provider.check_tls_version(Some("TLSv1_2")).is_ok());
+        assert!(matches!(
+            provider.check_tls_version(Some("TLSv1_3")),
+            Err(ProviderError::TlsVersionNotAllowed(version, id))
+                if version == "TLSv1_3" && id == provider.id
+        ));
+        assert!(provider.check_tls_version(None).is_err());
+
+        let provider = Provider {
+            tls_versions: None,
+            ..provider
+        };
+        assert!(provider.check_tls_version(Some("TLSv1_3")).is_ok());
+        assert!(provider.check_tls_version(None).is_ok());
+    }
+
+    #[test]
+    fn test_expected_status() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{premium: premium}"],
+            "expectedStatus": "200-204, 304"
+        }));
+        let id = provider.id;
+        let processor = Processor::from_config(test_config(vec![provider]), String::new());
+
+        // The forbidden response matches the provider and has a parseable body, but is rejected
+        let provider = processor
+            .find_provider("https://test.com/account", "GET")
+            .expect("Failed to check providers")
+            .expect("Failed to find provider");
+        let request = RequestContext::new("https://test.com/account", "GET");
+        assert!(processor
+            .process_request(&request.with_status_code(200), r#"{"premium": false}"#)
+            .is_ok());
+        assert!(matches!(
+            processor.process_request(&request.with_status_code(403), r#"{"premium": false}"#),
+            Err(ProviderError::UnexpectedStatus(status, error_id))
+                if status == "403" && error_id == id
+        ));
+        // Without a status line the status can't be checked, so the response is rejected
+        assert!(matches!(
+            processor.process("https://test.com/account", "GET", r#"{"premium": false}"#),
+            Err(ProviderError::UnexpectedStatus(status, error_id))
+                if status == "unknown" && error_id == id
+        ));
+        assert!(matches!(
+            provider.check_status(Some(403)),
+            Err(ProviderError::UnexpectedStatus(status, error_id))
+                if status == "403" && error_id == id
+        ));
+        assert!(provider.check_status(None).is_err());
+        for status_code in [200, 204, 304] {
+            assert!(provider.check_status(Some(status_code)).is_ok());
+        }
+        assert!(provider.check_status(Some(205)).is_err());
+
+        let provider = Provider {
+            expected_status: None,
+            ..provider.clone()
+        };
+        assert!(provider.check_status(Some(403)).is_ok());
+    }
+
+    #[test]
+    fn test_attribute_groups_when_status() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{status: status_code}"],
+            "attributeGroups": [
+                {"whenStatus": "200", "attributes": ["{karma: data.karma}"]},
+                {"whenStatus": "401, 403", "attributes": ["{error: error}"]},
+                {"whenStatus": "5xx", "attributes": ["{outage: status_code > 499}"]}
+            ]
+        }));
+        let config = test_config(vec![provider]);
+        let processor = Processor::from_config(config, String::new());
+        let url = "https://test.com/me";
+
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET").with_status_code(200),
+                r#"{"data": {"karma": 10}}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["status: 200", "karma: 10"]);
+
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET").with_status_code(403),
+                r#"{"error": "forbidden"}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["status: 403", "error: \"forbidden\""]);
+
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET").with_status_code(503),
+                r#"{}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["status: 503", "outage: true"]);
+
+        // A status code field of the body does not select a group when the status is unknown
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET"),
+                r#"{"status_code": 403, "error": "forbidden"}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["status: 403"]);
+
+        // The groups apply to a response which is an array
+        let provider = test_provider(json!({
+            "attributes": ["{count: length(@)}"],
+            "attributeGroups": [
+                {"whenStatus": "200", "attributes": ["{first: [0].id}"]},
+                {"whenStatus": "4xx", "attributes": ["{error: [0].error}"]}
+            ]
+        }));
+        let processor = Processor::from_config(test_config(vec![provider]), String::new());
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET").with_status_code(200),
+                r#"[{"id": 1}, {"id": 2}]"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["count: 2", "first: 1"]);
+    }
+
+    #[test]
+    fn test_process_json() {
+        use serde_json::json;
+
+        let config = test_config(vec![test_provider(json!({
+            "attributes": ["{followers: followers, verified: verified, handle: handle, roles: roles}"]
+        }))]);
+        let processor = Processor::from_config(config, String::new());
+        let response =
+            r#"{"followers": 10, "verified": true, "handle": "freysa", "roles": ["admin", "mod"]}"#;
+
+        let attributes = processor
+            .process_json("https://test.com/me", "GET", response)
+            .expect("Failed to process response");
+        assert_eq!(
+            attributes,
+            json!({
+                "followers": 10,
+                "verified": true,
+                "handle": "freysa",
+                "roles": ["admin", "mod"]
+            })
+        );
+
+        // The strings are the same attributes, formatted for signing
+        let mut strings = processor
+            .process("https://test.com/me", "GET", response)
+            .expect("Failed to process response");
+        strings.sort();
+        assert_eq!(
+            strings,
+            vec![
+                "followers: 10",
+                "handle: \"freysa\"",
+                "roles: [\"admin\",\"mod\"]",
+                "verified: true"
+            ]
+        );
+        for attribute in strings {
+            let (key, value) = attribute.split_once(": ").unwrap();
+            assert_eq!(
+                attributes[key],
+                serde_json::from_str::<Value>(value).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample_response_validation() {
+        use serde_json::json;
+
+        let config = |expected_attributes: Option<Value>| {
+            test_config(vec![test_provider(json!({
+                "attributes": ["{followers: followers, verified: verified}"],
+                "partial": true,
+                "sampleResponse": {
+                    "body": r#"{"followers": 10, "verified": true}"#,
+                    "expectedAttributes": expected_attributes
+                }
+            }))])
+        };
+
+        assert!(config(None).validate().is_ok());
+        assert!(config(Some(json!(["verified: true", "followers: 10"])))
+            .validate()
+            .is_ok());
+
+        // The sample yields a different value than declared
+        let config_ = config(Some(json!(["followers: 11", "verified: true"])));
+        let id = config_.providers[0].id;
+        let error = config_
+            .validate()
+            .expect_err("Expected the mismatched sample to fail validation");
+        assert!(matches!(
+            error,
+            ProviderError::SampleResponseMismatch(error_id, _) if error_id == id
+        ));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Sample response of provider {} failed validation: expected attributes \
+                 [\"followers: 11\", \"verified: true\"], got [\"followers: 10\", \"verified: true\"]",
+                id
+            )
+        );
+
+        // The attributes do not match the fields of the sample, so none are extracted
+        let mut config = config(None);
+        config.providers[0].attributes = Some(vec!["{followers: follower_count}".to_string()]);
+        let id = config.providers[0].id;
+        assert!(matches!(
+            config.validate(),
+            Err(ProviderError::SampleResponseMismatch(error_id, reason))
+                if error_id == id && reason == "no attributes were extracted"
+        ));
+    }
+
+    #[test]
+    fn test_require_https_and_port() {
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "urlRegex": r"^https?://test\.com(:\d+)?/.*$",
+            "attributes": ["{followers: followers}"],
+            "requireHttps": true,
+            "allowedPorts": [443]
+        }));
+        let matches = |url: &str| {
+            provider
+                .check_request(url, "GET", None)
+                .expect("Failed to check request")
+        };
+
+        assert!(matches("https://test.com/user"));
+        assert!(matches("https://test.com:443/user"));
+        assert!(!matches("http://test.com/user"));
+        assert!(!matches("https://test.com:8443/user"));
+        assert_eq!(
+            provider
+                .transport_mismatch("http://test.com/user")
+                .as_deref(),
+            Some("scheme 'http' is not https")
+        );
+        assert_eq!(
+            provider
+                .transport_mismatch("https://test.com:8443/user")
+                .as_deref(),
+            Some("port 8443 is not one of [443]")
+        );
+        assert!(provider.transport_mismatch("/user").is_some());
+
+        assert_eq!(
+            scheme_and_port_of("https://user:pass@[::1]:8443/path?a=b:c"),
+            Some(("https", Some(8443)))
+        );
+        assert_eq!(
+            scheme_and_port_of("http://test.com/a:b"),
+            Some(("http", Some(80)))
+        );
+        assert_eq!(scheme_and_port_of("https://test.com:port/"), None);
+    }
+
+    #[test]
+    fn test_canonical_array_attribute() {
+        use p256::ecdsa::{
+            signature::{Signer, Verifier},
+            Signature, SigningKey,
+        };
+        use serde_json::json;
+
+        let provider = test_provider(json!({
+            "attributes": ["{roles: roles}"]
+        }));
+        let response: Value = serde_json::from_str(
+            r#"{"roles": [{"name": "admin", "id": 1, "scopes": {"write": true, "read": true}}, "guest"]}"#,
+        )
+        .unwrap();
+        let reordered: Value = serde_json::from_str(
+            r#"{"roles": [{"scopes": {"read": true, "write": true}, "id": 1, "name": "admin"}, "guest"]}"#,
+        )
+        .unwrap();
 
         let attributes = provider
-            .get_attributes(&test_response)
+            .get_attributes(&response)
             .expect("Failed to get attributes");
+        assert_eq!(
+            attributes,
+            vec![r#"roles: [{"id":1,"name":"admin","scopes":{"read":true,"write":true}},"guest"]"#]
+        );
+        assert_eq!(
+            provider
+                .get_attributes(&reordered)
+                .expect("Failed to get attributes"),
+            attributes
+        );
+
+        // A verifier rebuilding the attribute from the value reproduces the signed bytes
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let signature: Signature = signing_key.sign(attributes[0].as_bytes());
+        let mut rebuilt = String::new();
+        write_canonical_json(&reordered["roles"], &mut rebuilt);
+        assert!(signing_key
+            .verifying_key()
+            .verify(format!("roles: {}", rebuilt).as_bytes(), &signature)
+            .is_ok());
+    }
 
-        println!("Custom evaluator test result: {:?}", attributes);
+    #[test]
+    fn test_max_attribute_value_length() {
+        use serde_json::json;
+
+        let provider = |max_attribute_value_length: Option<usize>| -> Provider {
+            test_provider(json!({
+                "attributes": ["{title: title, body: body}"],
+                "partial": true,
+                "maxAttributeValueLength": max_attribute_value_length
+            }))
+        };
+        let response = json!({
+            "title": "Release notes",
+            "body": "x".repeat(DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH),
+        });
 
+        // The quotes of the string count toward the length
+        let error = provider(None)
+            .get_attributes(&response)
+            .expect_err("Expected the body to be rejected");
+        assert!(matches!(
+            error,
+            ProviderError::AttributeTooLong(key, length, max)
+                if key == "body"
+                    && length == DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH + 2
+                    && max == DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH
+        ));
+
+        let error = provider(Some(10))
+            .get_attributes(&json!({"title": "Release notes", "body": ""}))
+            .expect_err("Expected the title to be rejected");
+        assert_eq!(
+            error.to_string(),
+            "Attribute 'title' is 15 bytes long, more than the 10 allowed"
+        );
+
+        let attributes = provider(Some(DEFAULT_MAX_ATTRIBUTE_VALUE_LENGTH + 2))
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
         assert_eq!(attributes.len(), 2);
-        assert!(attributes.contains(&"verified: true".to_string()));
-        assert!(attributes.contains(&"PreGPT4: true".to_string()));
     }
 
     #[test]
-    fn test_custom_evaluator_complex() {
+    fn test_conditional_attributes() {
         use serde_json::json;
 
-        // Test a more complex expression
-        let provider_json = json!({
-            "id": 100,
-            "host": "test2.com",
-            "urlRegex": r"^https://test2\.com/.*$",
-            "targetUrl": "https://test2.com",
-            "method": "GET",
-            "title": "Test Provider 2",
-            "description": "Complex test",
-            "icon": "test2",
-            "responseType": "json",
-            "attributes": ["{over_10k: to_number(performance_baseline.amount) >`10000` && performance_baseline.currency_code == 'USD'}"]
-        });
+        let provider = test_provider(json!({
+            "attributes": ["{plan: plan}"],
+            "conditionalAttributes": [
+                {
+                    "when": "plan == 'paid'",
+                    "attributes": ["{tier: subscription.tier, renews: subscription.renews}"]
+                },
+                {
+                    "when": "trial.days_left > `0`",
+                    "attributes": ["{trial: trial.days_left}"]
+                },
+                {
+                    "when": "plan == 'free'",
+                    "attributes": ["{ads: ads}"]
+                }
+            ]
+        }));
+
+        let attributes = |response: Value| {
+            let mut attributes = provider
+                .get_attributes(&response)
+                .expect("Failed to get attributes");
+            attributes.sort();
+            attributes
+        };
+
+        assert_eq!(
+            attributes(json!({
+                "plan": "paid",
+                "subscription": { "tier": "pro", "renews": true }
+            })),
+            vec![
+                "plan: \"paid\"".to_string(),
+                "renews: true".to_string(),
+                "tier: \"pro\"".to_string(),
+            ]
+        );
+        // The trial condition errors on this shape and does not hold
+        assert_eq!(
+            attributes(json!({ "plan": "free", "ads": true })),
+            vec!["ads: true".to_string(), "plan: \"free\"".to_string()]
+        );
+        // Only the first matching set is extracted
+        assert_eq!(
+            attributes(json!({ "plan": "free", "ads": true, "trial": { "days_left": 3 } })),
+            vec!["plan: \"free\"".to_string(), "trial: 3".to_string()]
+        );
+        assert_eq!(
+            attributes(json!({ "plan": "team" })),
+            vec!["plan: \"team\"".to_string()]
+        );
+        assert_eq!(
+            provider.declared_attribute_names(),
+            vec!["plan", "tier", "renews", "trial", "ads"]
+        );
+    }
+
+    #[test]
+    fn test_include_headers() {
+        use serde_json::json;
 
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
+        let provider = test_provider(json!({
+            "attributes": [
+                "{ok: body.status == 'active' && headers.content_type == 'application/json'}",
+                "{code: status_code}"
+            ],
+            "includeHeaders": true
+        }));
+        let config = test_config(vec![provider]);
+        let processor = Processor::from_config(config, String::new());
+        let headers = |content_type: &str| Headers {
+            code: Some(200),
+            headers: vec![
+                ("Content-Type".to_string(), content_type.to_string()),
+                ("Content-Type".to_string(), "text/plain".to_string()),
+            ],
+        };
+        let url = "https://test.com/me";
 
-        // Create test response data
-        let test_response = json!({
-            "performance_baseline": {
-                "amount": "15000",
-                "currency_code": "USD"
-            }
-        });
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET").with_headers(&headers("application/json")),
+                r#"{"status": "active"}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["ok: true", "code: 200"]);
 
-        let attributes = provider
-            .get_attributes(&test_response)
-            .expect("Failed to get attributes");
+        let attributes = processor
+            .process_request(
+                &RequestContext::new(url, "GET").with_headers(&headers("text/html")),
+                r#"{"status": "active"}"#,
+            )
+            .expect("Failed to process");
+        assert_eq!(attributes, vec!["ok: false", "code: 200"]);
+    }
 
-        println!("Complex evaluator test result: {:?}", attributes);
+    #[test]
+    fn test_script_threw() {
+        use serde_json::json;
 
-        assert_eq!(attributes.len(), 1);
-        assert!(attributes.contains(&"over_10k: true".to_string()));
+        let provider = |preprocess: &str| -> Provider {
+            test_provider(json!({
+                "attributes": ["{paid: paid}"],
+                "preprocess": preprocess
+            }))
+        };
+
+        let error =
+            provider("function process(jsonString) { throw new Error('Invalid account'); }")
+                .preprocess_response("{}")
+                .expect_err("Expected the script to throw");
+        assert!(matches!(
+            error,
+            ProviderError::ScriptThrew { message } if message == "Invalid account"
+        ));
+
+        let error = provider("function process(jsonString) { return { paid: true }")
+            .preprocess_response("{}")
+            .expect_err("Expected a syntax error");
+        assert!(matches!(error, ProviderError::PreprocessError(_)));
+
+        let error = provider("function process(jsonString) { return JSON.parse(jsonString).a.b; }")
+            .preprocess_response("{}")
+            .expect_err("Expected a type error");
+        assert!(matches!(error, ProviderError::PreprocessError(_)));
     }
 
     #[test]
-    fn test_custom_evaluator_simple_fields() {
+    fn test_preprocess_returns_non_object() {
         use serde_json::json;
 
-        // Test simple field access
-        let provider_json = json!({
-            "id": 101,
-            "host": "test3.com",
-            "urlRegex": r"^https://test3\.com/.*$",
-            "targetUrl": "https://test3.com",
-            "method": "GET",
-            "title": "Test Provider 3",
-            "description": "Simple fields test",
-            "icon": "test3",
-            "responseType": "json",
-            "preprocess": "response",
-            "attributes": ["{id: id, screen_name: screen_name}"]
-        });
+        let provider = |preprocess: &str| -> Provider {
+            test_provider(json!({
+                "attributes": ["{total: total}"],
+                "preprocess": preprocess
+            }))
+        };
+
+        let error = provider("function process(jsonString) { return 'total'; }")
+            .preprocess_response("{}")
+            .expect_err("Expected a string to be rejected");
+        assert!(matches!(
+            error,
+            ProviderError::ProcessError(message)
+                if message == "preprocess must return an object or an array, got string"
+        ));
+
+        let error = provider("function process(jsonString) { return 3; }")
+            .preprocess_response("{}")
+            .expect_err("Expected a number to be rejected");
+        assert_eq!(
+            error.to_string(),
+            "Process script error: preprocess must return an object or an array, got number"
+        );
+    }
 
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
+    #[test]
+    fn test_preprocess_on_exiting_threads() {
+        use serde_json::json;
 
-        // Create test response data
-        let test_response = json!({
-            "id": "12345",
-            "screen_name": "testuser"
-        });
+        // Script engine objects kept alive past the end of a call used to be dropped at thread
+        // exit after the engine's own state, which panicked. Each thread preprocesses several
+        // responses, including one large enough to be passed as a global, and then exits.
+        let provider = test_provider(json!({
+            "host": "x.com",
+            "urlRegex": r"^https://x\.com/.*$",
+            "targetUrl": "https://x.com",
+            "attributes": ["{count: count}"],
+            "preprocess": "function process(jsonString) { return { count: JSON.parse(jsonString).items.length }; }"
+        }));
+        let large = format!(
+            r#"{{"items": [{}]}}"#,
+            vec!["\"item\""; MAX_EMBEDDED_RESPONSE_SIZE / 4].join(",")
+        );
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let provider = provider.clone();
+                let large = large.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..3 {
+                        let result = provider
+                            .preprocess_response(r#"{"items": [1, 2, 3]}"#)
+                            .expect("Failed to preprocess small response");
+                        assert_eq!(result, json!({"count": 3}));
+                    }
+                    let (result, metadata) = provider
+                        .preprocess_response_with_metadata(&large)
+                        .expect("Failed to preprocess large response");
+                    assert!(metadata.response_global);
+                    assert_eq!(result, json!({"count": MAX_EMBEDDED_RESPONSE_SIZE / 4}));
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("Preprocessing thread panicked");
+        }
+    }
 
-        let attributes = provider
-            .get_attributes(&test_response)
-            .expect("Failed to get attributes");
+    #[test]
+    fn test_preprocess_response_global() {
+        use serde_json::json;
 
-        println!("Simple fields test result: {:?}", attributes);
+        let provider = |host: &str, preprocess: &str| {
+            test_provider(json!({
+                "host": host,
+                "urlRegex": format!(r"^https://{}/.*$", host.replace('.', r"\.")),
+                "targetUrl": format!("https://{}", host),
+                "attributes": ["{count: count}"],
+                "preprocess": preprocess
+            }))
+        };
+        let script = "function process() { return { count: JSON.parse(response).items.length }; }";
+        let small = r#"{"items": [1, 2, 3]}"#;
+        let large = format!(
+            r#"{{"items": [{}]}}"#,
+            vec!["\"item\""; MAX_EMBEDDED_RESPONSE_SIZE / 4].join(",")
+        );
+
+        // The standard and x.com paths both bind the response, embedded argument or not
+        for host in ["test.com", "x.com"] {
+            let provider = provider(host, script);
+            let result = provider
+                .preprocess_response(small)
+                .expect("Failed to preprocess small response");
+            assert_eq!(result, json!({"count": 3}), "host {}", host);
+            let result = provider
+                .preprocess_response(&large)
+                .expect("Failed to preprocess large response");
+            assert_eq!(
+                result,
+                json!({"count": MAX_EMBEDDED_RESPONSE_SIZE / 4}),
+                "host {}",
+                host
+            );
+        }
 
-        assert_eq!(attributes.len(), 2);
-        assert!(attributes.contains(&"id: \"12345\"".to_string()));
-        assert!(attributes.contains(&"screen_name: \"testuser\"".to_string()));
+        for host in ["test.com", "x.com"] {
+            let error = provider(host, "function transform(json) { return json; }")
+                .preprocess_response(small)
+                .expect_err("Expected a script without process to be rejected");
+            assert_eq!(
+                error.to_string(),
+                "Preprocess script error: preprocess script must define a function named `process`"
+            );
+        }
     }
 
     #[test]
-    fn test_x_provider_direct() {
+    fn test_unparseable_preprocess_output() {
         use serde_json::json;
 
-        // Use the actual X provider config from providers.json (without JavaScript preprocessing)
-        let provider_json = json!({
-            "id": 2,
-            "host": "x.com",
-            "urlRegex": r"^https://x\.com/i/api/graphql/[\w\d]+/UserByScreenName(\?.*)?$",
-            "targetUrl": "https://www.x.com/home",
-            "method": "GET",
-            "title": "Verify X subscription",
-            "description": "",
-            "icon": "twitterPremium",
-            "responseType": "json",
-            "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
-        });
+        let provider = test_provider(json!({
+            "attributes": ["{paid: paid}"],
+            "preprocess": "function process(jsonString) { return undefined; }"
+        }));
 
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
+        let error = provider
+            .preprocess_response("{}")
+            .expect_err("Expected the output not to parse");
+        assert!(matches!(
+            error,
+            ProviderError::PreprocessError(message)
+                if message.starts_with("Failed to parse result JSON")
+                    && message.ends_with("(output: *********)")
+        ));
+
+        let snippet = Provider::error_snippet(&format!(
+            "{{\"card\": \"4111\", \"name\": [Ada{}",
+            "x".repeat(300)
+        ));
+        assert!(snippet.starts_with("{\"****\": \"****\", \"****\": [*****"));
+        assert!(!snippet.contains("4111") && !snippet.contains("Ada"));
+        assert!(snippet.ends_with("*..."));
+        assert_eq!(snippet.chars().count(), MAX_ERROR_SNIPPET_LEN + 3);
+    }
 
-        // Create test response data that matches what the preprocessor would output
-        // This simulates a pre-March 2023 verified account
-        let test_response = json!({
-            "verified": true,
-            "PreGPT4": true,
-            "bioMentionsFreysa": true
-        });
+    #[test]
+    fn test_partial_attributes() {
+        use serde_json::json;
 
+        let provider = test_provider(json!({
+            "attributes": [
+                "{karma: user.karma}",
+                "{followers: user.stats.followers}",
+                "{verified: user.verified}"
+            ]
+        }));
+        let response = json!({"user": {"karma": 10, "verified": true}});
+
+        assert!(matches!(
+            provider.get_attributes(&response),
+            Err(ProviderError::JsonpathError(_))
+        ));
+
+        let provider = Provider {
+            partial: Some(true),
+            ..provider
+        };
         let attributes = provider
-            .get_attributes(&test_response)
+            .get_attributes(&response)
             .expect("Failed to get attributes");
+        assert_eq!(attributes, vec!["karma: 10", "verified: true"]);
+    }
 
-        println!("X provider direct test result: {:?}", attributes);
+    #[test]
+    fn test_root_path() {
+        use serde_json::json;
 
-        assert_eq!(attributes.len(), 2);
-        assert!(attributes.contains(&"verified: true".to_string()));
-        assert!(attributes.contains(&"PreGPT4: true".to_string()));
+        let provider = test_provider(json!({
+            "host": "api.test.com",
+            "urlRegex": r"^https://api\.test\.com/.*$",
+            "targetUrl": "https://api.test.com",
+            "rootPath": "data.user",
+            "attributes": ["{karma: karma}", "{verified: verified && karma > `10`}"]
+        }));
 
-        println!("✅ X provider custom evaluator test passed!");
+        let response = provider
+            .preprocess_response(
+                r#"{"status": "ok", "data": {"user": {"karma": 42, "verified": true}}}"#,
+            )
+            .expect("Failed to preprocess response");
+        let attributes = provider
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
+        assert_eq!(attributes, vec!["karma: 42", "verified: true"]);
+
+        let error = provider
+            .get_attributes(&json!({"status": "error", "error": "unauthorized"}))
+            .expect_err("Expected a response without the root path to be rejected");
+        assert_eq!(
+            error.to_string(),
+            "Process script error: rootPath 'data.user' is not in the response: Field 'data' not found"
+        );
     }
 
     #[test]
-    fn test_x_provider_logged_payload() {
+    fn test_duplicate_provider_ids() {
         use serde_json::json;
 
-        // This is the exact payload from the logs
-        let x_response_text = r#"{"data":{"user":{"result":{"__typename":"User","id":"VRNlcjoxNjc2OTI4NDU2MjI1ODk4NDk2","rest_id":"1676928456225898496","affiliates_highlighted_label":{},"avatar":{"image_url":"https://pbs.twimg.com/profile_images/1938401595659534336/4FfZgohs_normal.jpg"},"core":{"created_at":"Thu Jul 06 12:18:01 +0000 2023","name":"fppp290","screen_name":"Johndoe93"},"dm_permissions":{"can_dm":true},"has_graduated_access":true,"is_blue_verified":true,"legacy":{"default_profile":true,"default_profile_image":false,"description":"","entities":{"description":{"urls":[]}},"fast_followers_count":0,"favourites_count":182,"followers_count":35,"friends_count":180,"has_custom_timelines":false,"is_translator":false,"listed_count":1,"media_count":7,"needs_phone_verification":false,"normal_followers_count":35,"pinned_tweet_ids_str":["1875293532711186617"],"possibly_sensitive":false,"profile_banner_url":"https://pbs.twimg.com/profile_banners/1676928456225898496/1738727374","profile_interstitial_type":"","statuses_count":565,"translator_type":"none","want_retweets":false,"withheld_in_countries":[]},"location":{"location":""},"media_permissions":{"can_media_tag":true},"parody_commentary_fan_label":"None","profile_image_shape":"Circle","privacy":{"protected":false},"relationship_perspectives":{"following":false},"tipjar_settings":{},"verification":{"verified":false},"legacy_extended_profile":{"birthdate":{"day":12,"month":1,"year":1994,"visibility":"Self","year_visibility":"Self"}},"is_profile_translatable":false,"has_hidden_subscriptions_on_profile":false,"verification_info":{"is_identity_verified":false,"reason":{"description":{"text":"This account is verified. Learn more","entities":[{"from_index":26,"to_index":36,"ref":{"url":"https://help.twitter.com/managing-your-account/about-twitter-verified-accounts","url_type":"ExternalUrl"}}]},"verified_since_msec":"1735851543944"}},"highlights_info":{"can_highlight_tweets":true,"highlighted_tweets":"1"},"user_seed_tweet_count":0,"premium_gifting_eligible":false,"business_account":{},"creator_subscriptions_count":0}}}}"#;
+        // The config is rejected before any provider is compiled, so the ids can be picked
+        let provider =
+            |id: u32| test_provider(json!({ "id": id, "attributes": ["{followers: followers}"] }));
+        let config = test_config(vec![
+            provider(2),
+            provider(1),
+            provider(2),
+            provider(3),
+            provider(1),
+        ]);
+
+        let error = config
+            .validate()
+            .expect_err("Expected duplicate ids to fail validation");
+        assert!(matches!(error, ProviderError::ValidationError(_)));
+        assert_eq!(
+            error.to_string(),
+            "JSON validation failed: duplicate provider ids: 1, 2"
+        );
+    }
 
-        // Create a cleaner, properly formatted preprocessing script
-        let provider_json = json!({
-            "id": 2,
-            "host": "x.com",
-            "urlRegex": r"^https://x\.com/i/api/graphql/[\w\d]+/UserByScreenName(\?.*)?$",
-            "targetUrl": "https://www.x.com/home",
-            "method": "GET",
-            "title": "Verify X subscription",
-            "description": "",
-            "icon": "twitterPremium",
-            "responseType": "json",
-            "preprocess": "function process(jsonString) {\n  const object = JSON.parse(jsonString);\n  const parts = object.data.user.result.core.created_at.split(' ');\n  const time = parts[3].split(':');\n  const months = {\n    Jan: 0, Feb: 1, Mar: 2, Apr: 3, May: 4, Jun: 5,\n    Jul: 6, Aug: 7, Sep: 8, Oct: 9, Nov: 10, Dec: 11\n  };\n  const createdAt = new Date(\n    parseInt(parts[5]),\n    months[parts[1]],\n    parseInt(parts[2]),\n    parseInt(time[0]),\n    parseInt(time[1]),\n    parseInt(time[2])\n  );\n  const targetDate = new Date('2023-03-01');\n  const PreGPT4 = createdAt < targetDate;\n  const verified = object.data.user.result.is_blue_verified;\n  const bio = object.data.user.result.legacy.description;\n  const bioMentionsFreysa = bio.toLowerCase().includes('it starts with freysa...');\n  if (!verified || !PreGPT4) {\n    throw new Error('Invalid account');\n  }\n  return {\n    verified: verified,\n    bioMentionsFreysa: bioMentionsFreysa,\n    PreGPT4: PreGPT4\n  };\n}",
-            "attributes": ["{verified: verified, PreGPT4: PreGPT4}"]
-        });
+    #[test]
+    fn test_normalize_url() {
+        use serde_json::json;
 
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
+        assert_eq!(
+            normalize_url("HTTPS://API.Test.com:443/v1/%7euser/a%2fb?b=2&a=1&b=1#Top"),
+            "https://api.test.com/v1/~user/a%2Fb?a=1&b=2&b=1#Top"
+        );
+        assert_eq!(normalize_url("http://test.com:80"), "http://test.com/");
+        assert_eq!(
+            normalize_url("http://[::1]:8080/%zz"),
+            "http://[::1]:8080/%zz"
+        );
+        assert_eq!(normalize_url("/relative?b&a"), "/relative?b&a");
+
+        let provider = |normalize_url: bool| -> Provider {
+            test_provider(json!({
+                "host": "api.test.com",
+                "urlRegex": r"^https://api\.test\.com/v1/~me\?fields=karma&id=\d+$",
+                "targetUrl": "https://api.test.com",
+                "attributes": ["{karma: karma}"],
+                "normalizeUrl": normalize_url
+            }))
+        };
+        let url = "https://API.test.com:443/v1/%7Eme?id=42&fields=karma";
+        assert!(!provider(false)
+            .check_url_method(url, "GET")
+            .expect("Failed to check url"));
+        assert!(provider(true)
+            .check_url_method(url, "GET")
+            .expect("Failed to check url"));
+        assert!(!provider(true)
+            .check_url_method("https://api.test.com:8443/v1/~me?id=42&fields=karma", "GET")
+            .expect("Failed to check url"));
+    }
 
-        println!("Testing with URL check...");
-        let url_matches = provider.check_url_method(
-        "https://x.com/i/api/graphql/U15Q5V7hgjzCEg6WpSWhqg/UserByScreenName?variables=%7B%22screen_name%22%3A%22Johndoe93%22%7D",
-        "GET"
-    ).expect("Failed to check URL");
+    #[test]
+    fn test_max_attributes() {
+        use serde_json::json;
 
-        println!("URL matches: {}", url_matches);
-        assert!(url_matches);
+        let config = |max_attributes: Option<usize>| -> Config {
+            serde_json::from_value(json!({
+                "version": "1.0.0",
+                "EXPECTED_PCRS": {},
+                "MAX_ATTRIBUTES": max_attributes,
+                "PROVIDERS": [test_provider(json!({
+                    "attributes": ["{followers: followers, verified: verified}", "{karma: karma}"],
+                    "attributeGroups": [{"whenStatus": "4xx", "attributes": ["{error: error}"]}],
+                    "conditionalAttributes": [
+                        {"when": "plan == 'paid'", "attributes": ["{plan: plan, seats: seats}"]},
+                        {"when": "plan == 'free'", "attributes": ["{plan: plan}"]}
+                    ]
+                }))]
+            }))
+            .expect("Failed to parse config")
+        };
+
+        assert_eq!(config(None).providers[0].declared_attribute_count(), 6);
+        assert_eq!(config(None).max_attributes(), DEFAULT_MAX_ATTRIBUTES);
+        assert!(config(None).validate().is_ok());
+        assert!(config(Some(6)).validate().is_ok());
+
+        let capped = config(Some(5));
+        let id = capped.providers[0].id;
+        let error = capped
+            .validate()
+            .expect_err("Expected a provider over the cap to be rejected");
+        assert!(
+            matches!(error, ProviderError::TooManyAttributes(error_id, 6, 5) if error_id == id)
+        );
+        assert_eq!(
+            error.to_string(),
+            format!("Provider {} has 6 attributes, more than the 5 allowed", id)
+        );
+    }
 
-        println!("Testing preprocessing...");
-        let result = provider.preprocess_response(x_response_text);
+    #[test]
+    fn test_allow_scripts() {
+        use serde_json::json;
 
-        match result {
-            Ok(processed) => {
-                println!("Preprocessing succeeded: {:?}", processed);
-                // This should fail because the account was created after March 1, 2023
-                panic!("Expected preprocessing to fail with 'Invalid account' but it succeeded");
-            }
-            Err(e) => {
-                println!("Preprocessing failed as expected: {:?}", e);
-                // Check if it's the expected "Invalid account" error or a syntax error
-                assert!(
-                    e.to_string().contains("Invalid account")
-                        || e.to_string().contains("Process script error"),
-                    "Expected 'Invalid account' error but got: {}",
-                    e
-                );
-            }
-        }
+        let config = |allow_scripts: Option<bool>, preprocess: &str| -> Config {
+            serde_json::from_value(json!({
+                "version": "1.0.0",
+                "EXPECTED_PCRS": {},
+                "ALLOW_SCRIPTS": allow_scripts,
+                "PROVIDERS": [test_provider(json!({
+                    "attributes": ["{karma: karma}"],
+                    "preprocess": preprocess
+                }))]
+            }))
+            .expect("Failed to parse config")
+        };
+        let script = "function process(body) { return JSON.parse(body); }";
+
+        assert!(config(None, script).validate().is_ok());
+        assert!(config(Some(true), script).validate().is_ok());
+        assert!(config(Some(false), "").validate().is_ok());
+        let disallowed = config(Some(false), script);
+        let id = disallowed.providers[0].id;
+        let error = disallowed
+            .validate()
+            .expect_err("Expected a scripted provider to be rejected");
+        assert!(matches!(error, ProviderError::ScriptsNotAllowed(error_id) if error_id == id));
+
+        // A processor built from an unvalidated config refuses to run the script
+        let processor = Processor::from_config(disallowed, String::new());
+        let error = processor
+            .process_json("https://test.com/me", "GET", r#"{"karma": 42}"#)
+            .expect_err("Expected the script not to run");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Provider {} has a preprocess script but scripts are not allowed",
+                id
+            )
+        );
+        // The provider of such a processor refuses to run the script when called directly
+        let provider = &processor.config.providers[0];
+        assert!(matches!(
+            provider.preprocess_response(r#"{"karma": 42}"#),
+            Err(ProviderError::ScriptsNotAllowed(error_id)) if error_id == id
+        ));
+        // A provider which was not loaded by a processor runs it
+        let provider = &config(Some(false), script).providers[0];
+        assert!(provider.preprocess_response(r#"{"karma": 42}"#).is_ok());
+
+        // The setting is read and written as `allowScripts`, `ALLOW_SCRIPTS` is an alias
+        let parsed: Config = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "EXPECTED_PCRS": {},
+            "allowScripts": false,
+            "PROVIDERS": []
+        }))
+        .expect("Failed to parse config");
+        assert_eq!(parsed.allow_scripts, Some(false));
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap()["allowScripts"],
+            json!(false)
+        );
+
+        let processor = Processor::from_config(config(Some(false), ""), String::new());
+        assert_eq!(
+            processor
+                .process_json("https://test.com/me", "GET", r#"{"karma": 42}"#)
+                .expect("Failed to process response"),
+            json!({"karma": 42})
+        );
     }
 
     #[test]
-    fn test_x_provider_simplified_script() {
+    fn test_cache_key_changes_with_provider() {
         use serde_json::json;
 
-        let x_response_text = r#"{"data":{"user":{"result":{"core":{"created_at":"Thu Jan 06 12:18:01 +0000 2022"},"is_blue_verified":true,"legacy":{"description":"it starts with freysa..."}}}}}"#;
-
-        // Try with a much simpler preprocessing script
-        let provider_json = json!({
-            "id": 999,
-            "host": "x.com",
-            "urlRegex": r"^https://x\.com/.*$",
-            "targetUrl": "https://www.x.com/home",
-            "method": "GET",
-            "title": "Simple X test",
-            "description": "",
-            "icon": "twitter",
-            "responseType": "json",
-            "preprocess": "function process(jsonString) { return JSON.parse(jsonString); }",
-            "attributes": ["{verified: true}"]
+        let provider = test_provider(json!({
+            "urlRegex": r"^https://test\.com/v1/.*$",
+            "attributes": ["{karma: karma}"]
+        }));
+        let matches = |provider: &Provider, url: &str| {
+            provider
+                .check_url_method(url, "GET")
+                .expect("Failed to check url")
+        };
+        assert!(matches(&provider, "https://test.com/v1/me"));
+        assert!(!matches(&provider, "https://test.com/v2/me"));
+
+        // The reloaded provider keeps its id but not its regex
+        let reloaded = Provider {
+            url_regex: r"^https://test\.com/v2/.*$".to_string(),
+            ..provider.clone()
+        };
+        assert_ne!(reloaded.cache_key(), provider.cache_key());
+        assert!(matches(&reloaded, "https://test.com/v2/me"));
+        assert!(!matches(&reloaded, "https://test.com/v1/me"));
+
+        // Both versions stay cached, so processors holding each of them do not evict the other
+        let processors = [&provider, &reloaded].map(|provider| {
+            Processor::from_config(test_config(vec![provider.clone()]), String::new())
         });
-
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
-
-        let result = provider.preprocess_response(x_response_text);
-        println!("Simple script result: {:?}", result);
-
-        match result {
-            Ok(processed) => {
-                println!("Simple preprocessing succeeded: {:?}", processed);
-            }
-            Err(e) => {
-                println!("Simple preprocessing failed: {:?}", e);
-                panic!("Even simple script failed: {}", e);
+        for _ in 0..2 {
+            for (processor, url) in processors.iter().zip(["v1", "v2"]) {
+                let provider = &processor.config.providers[0];
+                assert_eq!(provider.cache_key, Some(provider.compute_cache_key()));
+                assert!(matches(provider, &format!("https://test.com/{}/me", url)));
             }
         }
+        let mut cached = COMPILED_REGEX_CACHE
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|(id, _)| *id == provider.id)
+            .copied()
+            .collect::<Vec<_>>();
+        cached.sort_unstable();
+        let mut expected = vec![provider.cache_key(), reloaded.cache_key()];
+        expected.sort_unstable();
+        assert_eq!(cached, expected);
+
+        let renamed = Provider {
+            attributes: Some(vec!["{score: karma}".to_string()]),
+            ..reloaded.clone()
+        };
+        assert_ne!(renamed.cache_key(), reloaded.cache_key());
+        assert_eq!(reloaded.clone().cache_key(), reloaded.cache_key());
     }
 
     #[test]
-    fn test_parse_literal_value_edge_cases() {
+    fn test_between() {
         use serde_json::json;
 
-        // Test that NaN values are handled gracefully without panicking
-        let provider_json = json!({
-            "id": 999,
-            "host": "test.com",
-            "urlRegex": r"^https://test\.com/.*$",
-            "targetUrl": "https://test.com",
-            "method": "GET",
-            "title": "Test Provider",
-            "description": "Test edge cases",
-            "icon": "test",
-            "responseType": "json",
-            "attributes": ["{nan_value: to_number(invalid_field)}"]
-        });
-
-        let provider: Provider =
-            serde_json::from_value(provider_json).expect("Failed to parse provider");
+        let data = json!({"score": 742, "age": 18, "balance": 1000.5, "name": "spez"});
+        let between = |expr: &str| evaluate_field_expression(expr, &data);
+
+        // Inside the range
+        assert_eq!(between("between(score, `700`, `800`)"), Ok(json!(true)));
+        assert_eq!(between("between(balance, `0`, `1000.75`)"), Ok(json!(true)));
+        // On the boundaries
+        assert_eq!(between("between(age, `18`, `65`)"), Ok(json!(true)));
+        assert_eq!(between("between(age, `0`, `18`)"), Ok(json!(true)));
+        assert_eq!(
+            between("between(balance, `1000.5`, `1000.5`)"),
+            Ok(json!(true))
+        );
+        // Outside the range
+        assert_eq!(between("between(score, `750`, `800`)"), Ok(json!(false)));
+        assert_eq!(between("between(age, `21`, `65`)"), Ok(json!(false)));
+        assert_eq!(between("between(balance, `0`, `1000`)"), Ok(json!(false)));
+
+        assert_eq!(
+            between("between(score, `700`, `800`) && age > `17`"),
+            Ok(json!(true))
+        );
+        assert!(between("between(name, `0`, `1`)").is_err());
+        assert!(between("between(score, 'low', `800`)").is_err());
+        assert!(between("between(score, `700`)").is_err());
+
+        // 2^53 + 1 cannot be represented as an f64
+        let data = json!({"views": 9007199254740993u64});
+        let between = |expr: &str| evaluate_field_expression(expr, &data);
+        assert_eq!(
+            between("between(views, `9007199254740992`, `9007199254740992`)"),
+            Ok(json!(false))
+        );
+        assert_eq!(
+            between("between(views, `9007199254740993`, `9007199254740993`)"),
+            Ok(json!(true))
+        );
+    }
 
-        // Test response with a string that would parse to NaN when converted to f64
-        let test_response = json!({
-            "invalid_field": "NaN"
-        });
+    #[test]
+    fn test_top_level_array_response() {
+        use serde_json::json;
 
-        // This should return an error, not panic
-        let result = provider.get_attributes(&test_response);
+        let provider = test_provider(json!({
+            "host": "api.test.com",
+            "urlRegex": r"^https://api\.test\.com/.*$",
+            "targetUrl": "https://api.test.com",
+            "attributes": [
+                "{first_id: [0].id, repos: length(@)}",
+                "{stars: [*].stars, total_stars: sum([*].stars)}"
+            ]
+        }));
+        let response = r#"[{"id": 7, "stars": 3}, {"id": 9, "stars": 5}]"#;
 
-        match result {
-            Err(e) => {
-                println!("Expected error for NaN: {}", e);
-                assert!(e.to_string().contains("Invalid number value"));
-            }
-            Ok(_) => panic!("Expected error but got success"),
-        }
+        let processed = provider
+            .preprocess_response(response)
+            .expect("Failed to preprocess response");
+        assert_eq!(
+            processed,
+            json!([{"id": 7, "stars": 3}, {"id": 9, "stars": 5}])
+        );
+
+        // The status code of the session does not replace the array
+        let processor = Processor::from_config(test_config(vec![provider]), String::new());
+        let mut attributes = processor
+            .process_request(
+                &RequestContext::new("https://api.test.com/repos", "GET").with_status_code(200),
+                response,
+            )
+            .expect("Failed to process response");
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            vec!["first_id: 7", "repos: 2", "stars: [3,5]", "total_stars: 8"]
+        );
+
+        // A preprocess script may return an array, which the attributes index the same way
+        let scripted = test_provider(json!({
+            "attributes": ["{first_total: [0], totals: length(@)}"],
+            "preprocess": "function process(jsonString) { return JSON.parse(jsonString).totals.map(t => t.total); }"
+        }));
+        let processed = scripted
+            .preprocess_response(r#"{"totals": [{"total": 1}, {"total": 2}]}"#)
+            .expect("Failed to preprocess response");
+        assert_eq!(processed, json!([1, 2]));
+        let mut attributes = scripted
+            .get_attributes(&processed)
+            .expect("Failed to get attributes");
+        attributes.sort();
+        assert_eq!(attributes, vec!["first_total: 1", "totals: 2"]);
+    }
 
-        // Test infinity case as well
-        let test_response_inf = json!({
-            "invalid_field": "Infinity"
-        });
+    #[test]
+    fn test_provider_constants() {
+        use serde_json::json;
 
-        let result_inf = provider.get_attributes(&test_response_inf);
+        let provider = test_provider(json!({
+            "constants": {"MIN_FOLLOWERS": 100, "PLAN": "pro"},
+            "attributes": [
+                "{big: followers > MIN_FOLLOWERS}",
+                "{big_fans: fans > MIN_FOLLOWERS, pro: plan == PLAN}",
+                "{min: MIN_FOLLOWERS}"
+            ]
+        }));
 
-        match result_inf {
-            Err(e) => {
-                println!("Expected error for Infinity: {}", e);
-                assert!(e.to_string().contains("Invalid number value"));
-            }
-            Ok(_) => panic!("Expected error but got success"),
-        }
+        // The constant takes precedence over the response field of the same name
+        let response = json!({"followers": 150, "fans": 50, "plan": "pro", "MIN_FOLLOWERS": 1});
+        let mut attributes = provider
+            .get_attributes(&response)
+            .expect("Failed to get attributes");
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            vec!["big: true", "big_fans: false", "min: 100", "pro: true"]
+        );
+
+        // Constants are only in scope while their provider is evaluated
+        assert_eq!(
+            evaluate_field_expression("followers > MIN_FOLLOWERS", &response),
+            Err("Cannot compare Number(150) > String(\"MIN_FOLLOWERS\")".to_string())
+        );
     }
 }
@@ -0,0 +1,485 @@
+//! This module verifies the signed sessions a notary returns.
+//!
+//! Sessions can be assembled without running a notarization with [`SignedSession::builder`],
+//! e.g. to test verification code.
+
+use sha2::{Digest, Sha256};
+use tls_core::msgs::enums::{CipherSuite, ProtocolVersion};
+use tlsn_core::{attribute_tree::AttributeTree, NotaryPublicKey, SignatureVerifyError};
+
+pub use tlsn_core::msg::{
+    CommittedBody, SignedSession, SignedSessionBuilder, SignedSessionBuilderError, TranscriptForm,
+};
+
+/// An error that can occur when verifying a signed session.
+#[derive(Debug, thiserror::Error)]
+pub enum SessionVerifyError {
+    /// The session is malformed, e.g. its application data is not hex encoded.
+    #[error("invalid session: {0}")]
+    InvalidSession(String),
+    /// The signed application data is not the hash of the application data of the session.
+    #[error("signed application data does not match the application data")]
+    ApplicationDataMismatch,
+    /// A signature of the session does not verify with the notary public key.
+    #[error("invalid {0} signature: {1}")]
+    InvalidSignature(String, SignatureVerifyError),
+}
+
+/// Verifies a signed session as a prover or relying party receives it from the notary.
+///
+/// This checks that the scheme of the session is the one of the notary key, that the signed
+/// application data is the hash of the application data and the recorded session parameters,
+/// and the notary signatures over it, over each attestation, over the attestation set and over
+/// the root of the attribute tree if the session has one.
+///
+/// # Arguments
+///
+/// * `session` - The signed session.
+/// * `notary_public_key` - The public key of the notary.
+pub fn verify_session(
+    session: &SignedSession,
+    notary_public_key: impl Into<NotaryPublicKey>,
+) -> Result<(), SessionVerifyError> {
+    let notary_public_key = notary_public_key.into();
+    session
+        .check_scheme(&notary_public_key)
+        .map_err(invalid_signature("session"))?;
+
+    let application_data = hex::decode(&session.application_data)
+        .map_err(|e| SessionVerifyError::InvalidSession(format!("application data: {e}")))?;
+    let server_cert_fingerprint = session
+        .server_cert_fingerprint
+        .as_ref()
+        .map(|fingerprint| {
+            hex::decode(fingerprint)
+                .ok()
+                .and_then(|fingerprint| <[u8; 32]>::try_from(fingerprint).ok())
+                .ok_or_else(|| {
+                    SessionVerifyError::InvalidSession(format!(
+                        "server certificate fingerprint: {fingerprint}"
+                    ))
+                })
+        })
+        .transpose()?;
+    let hash = signed_data_hash(
+        &application_data,
+        &SessionParameters {
+            server_cert_fingerprint: server_cert_fingerprint.as_ref(),
+            protocol_version: session.tls_version,
+            cipher_suite: session.cipher_suite,
+            signed_at: session.signed_at,
+            transcript_form: session.transcript_form,
+            content_encoding: session.content_encoding.as_deref(),
+            committed_body: session.committed_body,
+        },
+    );
+    if !session
+        .application_signed_data
+        .eq_ignore_ascii_case(&hex::encode(hash))
+    {
+        return Err(SessionVerifyError::ApplicationDataMismatch);
+    }
+    session
+        .signature
+        .verify(&hash, notary_public_key.clone())
+        .map_err(invalid_signature("session"))?;
+
+    let mut attributes = session.attestations.keys().collect::<Vec<_>>();
+    attributes.sort();
+    for attribute in attributes {
+        session.attestations[attribute]
+            .verify(attribute.as_bytes(), notary_public_key.clone())
+            .map_err(|e| {
+                SessionVerifyError::InvalidSignature(format!("attestation '{attribute}'"), e)
+            })?;
+    }
+    session
+        .verify_attestations(notary_public_key.clone())
+        .map_err(invalid_signature("attestation set"))?;
+
+    if let Some(root_signature) = &session.attributes_root_signature {
+        let root_message = AttributeTree::new(session.attestations.keys())
+            .ok_or_else(|| {
+                SessionVerifyError::InvalidSession(
+                    "attributes root signature without attestations".to_string(),
+                )
+            })?
+            .root_message(&hash);
+        root_signature
+            .verify(&root_message, notary_public_key)
+            .map_err(invalid_signature("attributes root"))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the mapping of a signature verification error of the named signature of a session.
+fn invalid_signature(
+    name: &'static str,
+) -> impl FnOnce(SignatureVerifyError) -> SessionVerifyError {
+    move |e| SessionVerifyError::InvalidSignature(name.to_string(), e)
+}
+
+/// The domain and version of the preimage of [`signed_data_hash`], so the hash cannot collide
+/// with another message signed by the notary nor with another version of the encoding.
+pub const SIGNED_DATA_DOMAIN: &[u8] = b"tlsn-tee/signed-session/v1";
+
+/// The parameters of a session which are recorded in the signed session and bound to its
+/// signature, unset if the notary does not record them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionParameters<'a> {
+    /// The sha256 fingerprint of the server's end-entity certificate
+    pub server_cert_fingerprint: Option<&'a [u8; 32]>,
+    /// The TLS protocol version negotiated with the server
+    pub protocol_version: Option<ProtocolVersion>,
+    /// The cipher suite negotiated with the server
+    pub cipher_suite: Option<CipherSuite>,
+    /// The unix time in seconds at which the notary signed the session
+    pub signed_at: Option<u64>,
+    /// The form of the request in the application data
+    pub transcript_form: TranscriptForm,
+    /// The `Content-Encoding` of the response, if it was encoded
+    pub content_encoding: Option<&'a str>,
+    /// The form of the response body in the application data
+    pub committed_body: CommittedBody,
+}
+
+/// Returns the hash of the application data and the recorded session parameters which is signed
+/// by the notary.
+///
+/// The preimage is [`SIGNED_DATA_DOMAIN`] and the application data, each prefixed with its
+/// big-endian u64 length, followed by the server certificate fingerprint, the big-endian TLS
+/// version, cipher suite and signing time in this order. Each of those starts with a presence
+/// tag, `0x00` if it is not recorded or `0x01` followed by its length-prefixed bytes, so no two
+/// sets of parameters share a preimage.
+///
+/// The parameters recorded since are appended after those, each only if it is not its default,
+/// as its one byte tag followed by its length-prefixed bytes, so sessions signed before a
+/// parameter was recorded keep their hash:
+///
+/// * `0x01`: the transcript form, `canonical` for [`TranscriptForm::Canonical`].
+/// * `0x02`: the `Content-Encoding` of the response, if it was encoded.
+/// * `0x03`: the form of the response body, `decoded` for [`CommittedBody::Decoded`].
+pub fn signed_data_hash(data: &[u8], parameters: &SessionParameters) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    update_length_prefixed(&mut hasher, SIGNED_DATA_DOMAIN);
+    update_length_prefixed(&mut hasher, data);
+    // Bind the server identity to the signature over the transcript
+    update_optional(
+        &mut hasher,
+        parameters.server_cert_fingerprint.map(|f| f.as_slice()),
+    );
+    // Bind the negotiated TLS parameters so they cannot be swapped by the prover
+    let protocol_version = parameters
+        .protocol_version
+        .map(|v| v.get_u16().to_be_bytes());
+    update_optional(&mut hasher, protocol_version.as_ref().map(|v| v.as_slice()));
+    let cipher_suite = parameters.cipher_suite.map(|s| s.get_u16().to_be_bytes());
+    update_optional(&mut hasher, cipher_suite.as_ref().map(|s| s.as_slice()));
+    // Bind the signing time so the session can be expired
+    let signed_at = parameters.signed_at.map(u64::to_be_bytes);
+    update_optional(&mut hasher, signed_at.as_ref().map(|t| t.as_slice()));
+    // Bind the form of the request so a raw transcript cannot pass as a canonical one
+    if parameters.transcript_form == TranscriptForm::Canonical {
+        update_tagged(&mut hasher, TRANSCRIPT_FORM_TAG, b"canonical");
+    }
+    // Bind how the body was encoded and whether it was committed to before or after decoding
+    if let Some(content_encoding) = parameters.content_encoding {
+        update_tagged(
+            &mut hasher,
+            CONTENT_ENCODING_TAG,
+            content_encoding.as_bytes(),
+        );
+    }
+    if parameters.committed_body == CommittedBody::Decoded {
+        update_tagged(&mut hasher, COMMITTED_BODY_TAG, b"decoded");
+    }
+    hasher.finalize().into()
+}
+
+/// The domain and version of the preimage of [`session_chunk_hash`], distinct from
+/// [`SIGNED_DATA_DOMAIN`] so the signature of a chunk never verifies as the signature of a session.
+pub const SESSION_CHUNK_DOMAIN: &[u8] = b"tlsn-tee/session-chunk/v1";
+
+/// Returns the hash of the transcript streamed as chunk `index` of `count` chunks which is signed
+/// by the notary.
+///
+/// The preimage is [`SESSION_CHUNK_DOMAIN`] prefixed with its big-endian u64 length, the
+/// big-endian u32 index and count, and the transcript prefixed with its big-endian u64 length, so
+/// a chunk cannot be moved to another position or into a session of another length.
+pub fn session_chunk_hash(data: &[u8], index: u32, count: u32) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    update_length_prefixed(&mut hasher, SESSION_CHUNK_DOMAIN);
+    hasher.update(index.to_be_bytes());
+    hasher.update(count.to_be_bytes());
+    update_length_prefixed(&mut hasher, data);
+    hasher.finalize().into()
+}
+
+/// The tag of the transcript form in the preimage of [`signed_data_hash`]
+const TRANSCRIPT_FORM_TAG: u8 = 0x01;
+/// The tag of the content encoding of the response in the preimage of [`signed_data_hash`]
+const CONTENT_ENCODING_TAG: u8 = 0x02;
+/// The tag of the form of the response body in the preimage of [`signed_data_hash`]
+const COMMITTED_BODY_TAG: u8 = 0x03;
+
+fn update_length_prefixed(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(bytes);
+}
+
+fn update_tagged(hasher: &mut Sha256, tag: u8, bytes: &[u8]) {
+    hasher.update([tag]);
+    update_length_prefixed(hasher, bytes);
+}
+
+fn update_optional(hasher: &mut Sha256, bytes: Option<&[u8]>) {
+    match bytes {
+        Some(bytes) => {
+            hasher.update([1]);
+            update_length_prefixed(hasher, bytes);
+        }
+        None => hasher.update([0]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use std::collections::HashMap;
+
+    fn sign(signing_key: &SigningKey, message: &[u8]) -> tlsn_core::Signature {
+        let signature: Signature = signing_key.sign(message);
+        signature.into()
+    }
+
+    #[test]
+    fn test_verify_built_session() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let notary_public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        let application_data =
+            b"GET /profile HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\n\r\n{\"followers\":10}";
+        let signed_at = 1735613754;
+        let hash = signed_data_hash(
+            application_data,
+            &SessionParameters {
+                signed_at: Some(signed_at),
+                ..Default::default()
+            },
+        );
+        let attestations = ["followers: 10", "verified: true"]
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute.to_string(),
+                    sign(&signing_key, attribute.as_bytes()),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let root_message = AttributeTree::new(attestations.keys())
+            .unwrap()
+            .root_message(&hash);
+
+        let session = SignedSession::builder()
+            .application_data(hex::encode(application_data))
+            .application_signed_data(hex::encode(hash))
+            .signature(sign(&signing_key, &hash))
+            .attestations_signature(sign(
+                &signing_key,
+                &SignedSession::attestations_message(&hash, &attestations),
+            ))
+            .attestations(attestations)
+            .attributes_root_signature(sign(&signing_key, &root_message))
+            .signed_at(signed_at)
+            .build()
+            .unwrap();
+        verify_session(&session, notary_public_key).unwrap();
+
+        // An attestation whose value was changed after signing
+        let mut tampered = session.clone();
+        let signature = tampered.attestations.remove("followers: 10").unwrap();
+        tampered
+            .attestations
+            .insert("followers: 10000".to_string(), signature);
+        assert!(matches!(
+            verify_session(&tampered, notary_public_key),
+            Err(SessionVerifyError::InvalidSignature(name, _)) if name == "attestation 'followers: 10000'"
+        ));
+
+        // A root signature of the same attributes in another session
+        let mut tampered = session.clone();
+        let root_message = AttributeTree::new(session.attestations.keys())
+            .unwrap()
+            .root_message(&[0; 32]);
+        tampered.attributes_root_signature = Some(sign(&signing_key, &root_message));
+        assert!(matches!(
+            verify_session(&tampered, notary_public_key),
+            Err(SessionVerifyError::InvalidSignature(name, _)) if name == "attributes root"
+        ));
+
+        // A signing time which was changed after signing
+        let mut tampered = session.clone();
+        tampered.signed_at = Some(signed_at + 3600);
+        assert!(matches!(
+            verify_session(&tampered, notary_public_key),
+            Err(SessionVerifyError::ApplicationDataMismatch)
+        ));
+
+        // A raw transcript which claims to be canonical
+        let mut tampered = session.clone();
+        tampered.transcript_form = TranscriptForm::Canonical;
+        assert!(matches!(
+            verify_session(&tampered, notary_public_key),
+            Err(SessionVerifyError::ApplicationDataMismatch)
+        ));
+
+        // A received body which claims to be decoded, or to have been encoded
+        let mut tampered = session.clone();
+        tampered.committed_body = CommittedBody::Decoded;
+        assert!(matches!(
+            verify_session(&tampered, notary_public_key),
+            Err(SessionVerifyError::ApplicationDataMismatch)
+        ));
+        let mut tampered = session;
+        tampered.content_encoding = Some("gzip".to_string());
+        assert!(matches!(
+            verify_session(&tampered, notary_public_key),
+            Err(SessionVerifyError::ApplicationDataMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_verify_built_session_with_encoding() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let notary_public_key = p256::PublicKey::from(signing_key.verifying_key());
+
+        let application_data =
+            b"GET /profile HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n{\"followers\":10}";
+        let hash = signed_data_hash(
+            application_data,
+            &SessionParameters {
+                content_encoding: Some("gzip"),
+                committed_body: CommittedBody::Decoded,
+                ..Default::default()
+            },
+        );
+        let attestations = HashMap::from([(
+            "followers: 10".to_string(),
+            sign(&signing_key, b"followers: 10"),
+        )]);
+        let builder = || {
+            SignedSession::builder()
+                .application_data(hex::encode(application_data))
+                .application_signed_data(hex::encode(hash))
+                .signature(sign(&signing_key, &hash))
+                .attestations_signature(sign(
+                    &signing_key,
+                    &SignedSession::attestations_message(&hash, &attestations),
+                ))
+                .attestations(attestations.clone())
+        };
+
+        let session = builder()
+            .content_encoding("gzip")
+            .committed_body(CommittedBody::Decoded)
+            .build()
+            .unwrap();
+        assert_eq!(session.content_encoding.as_deref(), Some("gzip"));
+        verify_session(&session, notary_public_key).unwrap();
+
+        // The same session without the encoding it was signed with
+        let session = builder().build().unwrap();
+        assert!(matches!(
+            verify_session(&session, notary_public_key),
+            Err(SessionVerifyError::ApplicationDataMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_signed_data_hash_is_unambiguous() {
+        let fingerprint = [3; 32];
+        let hash = |data: &[u8], parameters: SessionParameters| signed_data_hash(data, &parameters);
+
+        // A fingerprint is not confused with application data ending in the same bytes
+        assert_ne!(
+            hash(
+                b"data",
+                SessionParameters {
+                    server_cert_fingerprint: Some(&fingerprint),
+                    ..Default::default()
+                }
+            ),
+            hash(
+                &[b"data".as_slice(), &fingerprint].concat(),
+                Default::default()
+            )
+        );
+        // A raw transcript keeps the hash of sessions signed before the form was recorded
+        let mut legacy = Sha256::new();
+        update_length_prefixed(&mut legacy, SIGNED_DATA_DOMAIN);
+        update_length_prefixed(&mut legacy, b"data");
+        legacy.update([0; 4]);
+        assert_eq!(
+            hash(b"data", Default::default()),
+            <[u8; 32]>::from(legacy.finalize())
+        );
+        assert_ne!(
+            hash(b"data", Default::default()),
+            hash(
+                b"data",
+                SessionParameters {
+                    transcript_form: TranscriptForm::Canonical,
+                    ..Default::default()
+                }
+            )
+        );
+        // Each parameter is tagged, so one cannot stand in for another of the same bytes
+        assert_ne!(
+            hash(
+                b"data",
+                SessionParameters {
+                    content_encoding: Some("decoded"),
+                    ..Default::default()
+                }
+            ),
+            hash(
+                b"data",
+                SessionParameters {
+                    committed_body: CommittedBody::Decoded,
+                    ..Default::default()
+                }
+            )
+        );
+        // Nor is a TLS version with a cipher suite of the same value
+        assert_ne!(
+            hash(
+                b"data",
+                SessionParameters {
+                    protocol_version: Some(ProtocolVersion::TLSv1_2),
+                    ..Default::default()
+                }
+            ),
+            hash(
+                b"data",
+                SessionParameters {
+                    cipher_suite: Some(CipherSuite::Unknown(0x0303)),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_build_session_missing_field() {
+        let error = SignedSession::builder()
+            .application_data("")
+            .application_signed_data("")
+            .build()
+            .err()
+            .expect("Expected the build to fail without a signature");
+        assert_eq!(error.to_string(), "missing field: signature");
+    }
+}
@@ -0,0 +1,179 @@
+//! This module contains the signer abstraction used to sign notarization results.
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use p256::ecdsa::{signature::Signer, SigningKey};
+use tlsn_core::{NotaryPublicKey, Signature};
+
+/// NotarySignerError is the error that is returned when a notary signer fails to sign a message
+#[derive(Debug, thiserror::Error)]
+#[error("notary signer error: {0}")]
+pub struct NotarySignerError(pub String);
+
+/// NotarySigner signs notarization results with the notary key
+///
+/// The key may be held in memory or in a KMS or HSM, in which case signing can involve a
+/// request to the key store, so signing is async.
+#[async_trait]
+pub trait NotarySigner: Debug + Send + Sync {
+    /// Sign a message with the notary key
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, NotarySignerError>;
+
+    /// Get the public key that verifies the signatures
+    fn public_key(&self) -> NotaryPublicKey;
+
+    /// Get the hex encoded SEC1 form of the public key, as consumed by the wasm verifier, or
+    /// `None` if the key has no SEC1 form, i.e. it is an Ed25519 key
+    fn public_key_sec1_hex(&self) -> Option<String> {
+        self.public_key().to_sec1_bytes().map(hex::encode)
+    }
+}
+
+/// An in-memory P-256 notary key
+#[async_trait]
+impl NotarySigner for SigningKey {
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, NotarySignerError> {
+        let signature: p256::ecdsa::Signature = self
+            .try_sign(msg)
+            .map_err(|e| NotarySignerError(e.to_string()))?;
+        Ok(signature.into())
+    }
+
+    fn public_key(&self) -> NotaryPublicKey {
+        p256::PublicKey::from(self.verifying_key()).into()
+    }
+}
+
+/// An in-memory Ed25519 notary key
+#[async_trait]
+impl NotarySigner for ed25519_dalek::SigningKey {
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, NotarySignerError> {
+        let signature: ed25519_dalek::Signature = self
+            .try_sign(msg)
+            .map_err(|e| NotarySignerError(e.to_string()))?;
+        Ok(signature.into())
+    }
+
+    fn public_key(&self) -> NotaryPublicKey {
+        self.verifying_key().into()
+    }
+}
+
+/// An in-memory secp256k1 notary key, which signs the Keccak-256 hash of the message
+#[cfg(feature = "eth")]
+#[async_trait]
+impl NotarySigner for k256::ecdsa::SigningKey {
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, NotarySignerError> {
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+
+        let signature: k256::ecdsa::Signature = self
+            .sign_prehash(&tlsn_core::eip712::keccak256(msg))
+            .map_err(|e| NotarySignerError(e.to_string()))?;
+        Ok(signature.into())
+    }
+
+    fn public_key(&self) -> NotaryPublicKey {
+        k256::PublicKey::from(self.verifying_key()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signing_key_signer() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let signer: &dyn NotarySigner = &signing_key;
+
+        let signature = signer.sign(b"followers: 10").await.unwrap();
+        assert!(signature
+            .verify(b"followers: 10", signer.public_key())
+            .is_ok());
+        assert!(signature
+            .verify(b"followers: 11", signer.public_key())
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ed25519_signer() {
+        use std::collections::HashMap;
+        use tlsn_core::{msg::SignedSession, SignatureScheme};
+
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let signer: &dyn NotarySigner = &signing_key;
+
+        let mut attestations = HashMap::new();
+        for attestation in ["followers: 10", "verified: true"] {
+            let signature = signer.sign(attestation.as_bytes()).await.unwrap();
+            attestations.insert(attestation.to_string(), signature);
+        }
+        let session_hash = [7; 32];
+        let attestations_signature = signer
+            .sign(&SignedSession::attestations_message(
+                &session_hash,
+                &attestations,
+            ))
+            .await
+            .unwrap();
+        let signature = signer.sign(&session_hash).await.unwrap();
+        let session = SignedSession::new(
+            hex::encode(b"application data"),
+            hex::encode(session_hash),
+            signature,
+            attestations,
+            attestations_signature,
+        );
+
+        assert_eq!(session.scheme, SignatureScheme::Ed25519);
+        assert!(session.verify_attestations(signer.public_key()).is_ok());
+
+        // A P-256 notary key does not verify the session
+        let p256_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        assert!(session
+            .verify_attestations(NotarySigner::public_key(&p256_key))
+            .is_err());
+    }
+
+    #[cfg(feature = "eth")]
+    #[tokio::test]
+    async fn test_secp256k1_signer() {
+        let signing_key = k256::ecdsa::SigningKey::from_slice(&[1; 32]).unwrap();
+        let signer: &dyn NotarySigner = &signing_key;
+
+        let signature = signer.sign(b"followers: 10").await.unwrap();
+        assert_eq!(
+            signature.scheme(),
+            tlsn_core::SignatureScheme::EcdsaSecp256k1Keccak256
+        );
+        assert!(signature
+            .verify(b"followers: 10", signer.public_key())
+            .is_ok());
+        assert!(signature
+            .verify(
+                b"followers: 10",
+                NotarySigner::public_key(&SigningKey::from_slice(&[1; 32]).unwrap())
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_public_key_sec1_hex() {
+        use p256::ecdsa::signature::Verifier;
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let signer: &dyn NotarySigner = &signing_key;
+
+        let signature = signer.sign(b"followers: 10").await.unwrap();
+        let public_key = hex::decode(signer.public_key_sec1_hex().unwrap()).unwrap();
+        let verifying_key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&public_key).unwrap();
+        let signature = p256::ecdsa::Signature::from_slice(&signature.to_bytes()).unwrap();
+
+        assert!(verifying_key.verify(b"followers: 10", &signature).is_ok());
+
+        // An Ed25519 key has no SEC1 form
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        assert!(NotarySigner::public_key_sec1_hex(&signing_key).is_none());
+    }
+}
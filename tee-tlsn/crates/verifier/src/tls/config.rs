@@ -1,6 +1,32 @@
-use std::fmt::{Debug, Formatter, Result};
+use std::{
+    collections::HashSet,
+    fmt::{Debug, Formatter, Result},
+};
 use tls_tee::{TeeTlsCommonConfig, TeeTlsFollowerConfig};
 use tlsn_common::config::{DEFAULT_MAX_RECV_LIMIT, DEFAULT_MAX_SENT_LIMIT};
+#[cfg(feature = "eth")]
+use tlsn_core::eip712::Eip712Domain;
+
+/// A secp256k1 key which additionally signs every attestation over its EIP-712 digest, so the
+/// attestations can be checked on-chain with `ecrecover`.
+#[cfg(feature = "eth")]
+#[derive(Clone)]
+pub struct Eip712Signer {
+    /// The EIP-712 domain of the verifying contract.
+    pub domain: Eip712Domain,
+    /// The secp256k1 key whose address is trusted by the verifying contract.
+    pub signing_key: k256::ecdsa::SigningKey,
+}
+
+#[cfg(feature = "eth")]
+impl Debug for Eip712Signer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("Eip712Signer")
+            .field("domain", &self.domain)
+            .field("signing_key", &"_")
+            .finish()
+    }
+}
 
 /// Configuration for the [`Verifier`](crate::tls::Verifier).
 #[allow(missing_docs)]
@@ -15,16 +41,61 @@ pub struct VerifierConfig {
     /// Maximum number of bytes that can be received.
     #[builder(default = "DEFAULT_MAX_RECV_LIMIT")]
     max_recv_data: usize,
+    /// Maximum number of record bytes read from the prover ahead of processing, the TEE-TLS
+    /// default is used if unset.
+    #[builder(default, setter(strip_option))]
+    max_buffered_bytes: Option<usize>,
+    /// Ids of the providers that may be attested in this session, all providers are
+    /// allowed if unset.
+    #[builder(default, setter(strip_option))]
+    allowed_provider_ids: Option<HashSet<u32>>,
+    /// Whether to record the server certificate fingerprint in the signed session.
+    #[builder(default)]
+    include_server_cert_fingerprint: bool,
+    /// Whether to record the negotiated TLS version and cipher suite in the signed session.
+    #[builder(default)]
+    include_tls_parameters: bool,
+    /// Whether to record the time of signing in the signed session.
+    #[builder(default)]
+    include_signed_at: bool,
+    /// Whether to sign the root of the Merkle tree of the attestations.
+    #[builder(default)]
+    include_attributes_root: bool,
+    /// Whether to sign the request in its canonical form, see
+    /// [`canonical_request`](crate::http::canonical_request), instead of as received.
+    #[builder(default)]
+    canonical_request: bool,
+    /// Whether to stream the session to the prover as one signed chunk per HTTP exchange, followed
+    /// by a commitment covering them, before the signed session.
+    #[builder(default)]
+    stream_session: bool,
+    /// Signs the attestations over their EIP-712 digest in addition to the notary signature.
+    #[cfg(feature = "eth")]
+    #[builder(default, setter(strip_option))]
+    eip712_signer: Option<Eip712Signer>,
 }
 
 impl Debug for VerifierConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        f.debug_struct("VerifierConfig")
+        let mut debug = f.debug_struct("VerifierConfig");
+        debug
             .field("id", &self.id)
             .field("max_sent_data", &self.max_sent_data)
             .field("max_recv_data", &self.max_recv_data)
-            .field("cert_verifier", &"_")
-            .finish()
+            .field("max_buffered_bytes", &self.max_buffered_bytes)
+            .field("allowed_provider_ids", &self.allowed_provider_ids)
+            .field(
+                "include_server_cert_fingerprint",
+                &self.include_server_cert_fingerprint,
+            )
+            .field("include_tls_parameters", &self.include_tls_parameters)
+            .field("include_signed_at", &self.include_signed_at)
+            .field("include_attributes_root", &self.include_attributes_root)
+            .field("canonical_request", &self.canonical_request)
+            .field("stream_session", &self.stream_session);
+        #[cfg(feature = "eth")]
+        debug.field("eip712_signer", &self.eip712_signer);
+        debug.field("cert_verifier", &"_").finish()
     }
 }
 
@@ -49,15 +120,72 @@ impl VerifierConfig {
         self.max_recv_data
     }
 
+    /// Returns the maximum number of record bytes read from the prover ahead of processing, if
+    /// set.
+    pub fn max_buffered_bytes(&self) -> Option<usize> {
+        self.max_buffered_bytes
+    }
+
+    /// Returns the ids of the providers that may be attested, if restricted.
+    pub fn allowed_provider_ids(&self) -> Option<&HashSet<u32>> {
+        self.allowed_provider_ids.as_ref()
+    }
+
+    /// Returns whether the provider with the given id may be attested.
+    pub fn is_provider_allowed(&self, provider_id: u32) -> bool {
+        self.allowed_provider_ids
+            .as_ref()
+            .map_or(true, |ids| ids.contains(&provider_id))
+    }
+
+    /// Returns whether the server certificate fingerprint is recorded in the signed session.
+    pub fn include_server_cert_fingerprint(&self) -> bool {
+        self.include_server_cert_fingerprint
+    }
+
+    /// Returns whether the negotiated TLS version and cipher suite are recorded in the signed
+    /// session.
+    pub fn include_tls_parameters(&self) -> bool {
+        self.include_tls_parameters
+    }
+
+    /// Returns whether the time of signing is recorded in the signed session.
+    pub fn include_signed_at(&self) -> bool {
+        self.include_signed_at
+    }
+
+    /// Returns whether the root of the Merkle tree of the attestations is signed.
+    pub fn include_attributes_root(&self) -> bool {
+        self.include_attributes_root
+    }
+
+    /// Returns whether the request is signed in its canonical form.
+    pub fn canonical_request(&self) -> bool {
+        self.canonical_request
+    }
+
+    /// Returns whether the session is streamed to the prover as signed chunks.
+    pub fn stream_session(&self) -> bool {
+        self.stream_session
+    }
+
+    /// Returns the signer of the EIP-712 attestation signatures, if enabled.
+    #[cfg(feature = "eth")]
+    pub fn eip712_signer(&self) -> Option<&Eip712Signer> {
+        self.eip712_signer.as_ref()
+    }
+
     pub(crate) fn build_tee_tls_config(&self) -> TeeTlsFollowerConfig {
+        let mut common = TeeTlsCommonConfig::builder();
+        common
+            .id(format!("{}/tee_tls", &self.id))
+            .handshake_commit(true);
+        if let Some(max_buffered_bytes) = self.max_buffered_bytes {
+            common.max_buffered_bytes(max_buffered_bytes);
+        }
+
         TeeTlsFollowerConfig::builder()
-            .common(
-                TeeTlsCommonConfig::builder()
-                    .id(format!("{}/tee_tls", &self.id))
-                    .handshake_commit(true)
-                    .build()
-                    .unwrap(),
-            )
+            .common(common.build().unwrap())
             .build()
             .unwrap()
     }
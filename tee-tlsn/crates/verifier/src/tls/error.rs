@@ -1,7 +1,7 @@
 use std::error::Error;
 use tls_tee::TeeTlsError;
 
-use crate::provider::ProviderError;
+use crate::{provider::ProviderError, signer::NotarySignerError};
 
 /// An error that can occur during TLS verification.
 #[derive(Debug, thiserror::Error)]
@@ -15,6 +15,14 @@ pub enum VerifierError {
     InvalidRange,
     #[error("error occurred in provider: {0}")]
     ProviderError(ProviderError),
+    #[error("failed to sign attestation: {0}")]
+    SigningError(String),
+    #[error("failed to parse HTTP transcript: {0}")]
+    HttpParseError(String),
+    #[error("incomplete HTTP response head")]
+    IncompleteResponseHead,
+    #[error("failed to decode response body: {0}")]
+    BodyDecodeError(String),
 }
 
 impl From<uid_mux::yamux::ConnectionError> for VerifierError {
@@ -26,6 +34,12 @@ impl From<uid_mux::yamux::ConnectionError> for VerifierError {
     }
 }
 
+impl From<NotarySignerError> for VerifierError {
+    fn from(e: NotarySignerError) -> Self {
+        Self::SigningError(e.to_string())
+    }
+}
+
 impl From<TeeTlsError> for VerifierError {
     fn from(e: TeeTlsError) -> Self {
         Self::TeeError(Box::new(e))
@@ -6,26 +6,28 @@ mod notarize;
 pub mod state;
 mod verify;
 
+#[cfg(feature = "eth")]
+pub use config::Eip712Signer;
 pub use config::{VerifierConfig, VerifierConfigBuilder, VerifierConfigBuilderError};
 pub use error::VerifierError;
+pub use notarize::send_session_chunks;
 use prometheus::{register_histogram, Histogram};
 use serio::StreamExt;
 use uid_mux::FramedUidMux;
 
 use futures::{AsyncRead, AsyncWrite, TryFutureExt};
-use signature::Signer;
 use state::{Notarize, Verify};
 use tls_tee::{TeeTlsFollower, TeeTlsFollowerData};
 use tlsn_common::{
     mux::{attach_mux, MuxControl},
     Role,
 };
-use tlsn_core::{msg::SignedSession, Signature};
+use tlsn_core::msg::SignedSession;
 
 use lazy_static::lazy_static;
 use tracing::{debug, info, info_span, instrument, Span};
 
-use crate::provider::Processor;
+use crate::{provider::Processor, signer::NotarySigner};
 
 lazy_static! {
     static ref TLS_SESSION_HISTOGRAM: Histogram = register_histogram!(
@@ -107,17 +109,14 @@ impl Verifier<state::Initialized> {
     /// * `socket` - The socket to the prover.
     /// * `signer` - The signer used to sign the notarization result.
     #[instrument(parent = &self.span, level = "info", skip_all, err)]
-    pub async fn notarize<S: AsyncWrite + AsyncRead + Send + Unpin + 'static, T>(
+    pub async fn notarize<S: AsyncWrite + AsyncRead + Send + Unpin + 'static>(
         self,
         socket: S,
-        signer: &impl Signer<T>,
+        signer: &dyn NotarySigner,
         provider: &Processor,
         session_id: String,
         posthog_key: String,
-    ) -> Result<SignedSession, VerifierError>
-    where
-        T: Into<Signature>,
-    {
+    ) -> Result<SignedSession, VerifierError> {
         self.setup(socket)
             .await?
             .run()
@@ -164,6 +163,9 @@ impl Verifier<state::Setup> {
         let TeeTlsFollowerData {
             response_data,
             request_data,
+            server_cert_fingerprint,
+            protocol_version,
+            cipher_suite,
         } = mux_fut
             .poll_with(tee_tls.run().1.map_err(VerifierError::from))
             .await?;
@@ -184,6 +186,9 @@ impl Verifier<state::Setup> {
                 mux_fut,
                 response_data,
                 request_data,
+                server_cert_fingerprint,
+                protocol_version,
+                cipher_suite,
             },
         })
     }
@@ -223,10 +228,11 @@ async fn setup_tee_backend(
 ) -> Result<TeeTlsFollower, VerifierError> {
     debug!("starting TEE backend setup");
 
-    let _tee_tls_config = config.build_tee_tls_config();
+    let tee_tls_config = config.build_tee_tls_config();
 
     let channel = mux.open_framed(b"tee_tls").await?;
-    let mut tee_tls = TeeTlsFollower::new(Box::new(StreamExt::compat_stream(channel)));
+    let mut tee_tls = TeeTlsFollower::new(Box::new(StreamExt::compat_stream(channel)))
+        .with_max_buffered_bytes(tee_tls_config.common().max_buffered_bytes());
 
     tee_tls.setup().await?;
 
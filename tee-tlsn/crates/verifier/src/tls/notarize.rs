@@ -2,20 +2,35 @@
 //!
 //! The TLS verifier is only a notary.
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::util::{log_event, LogEvent};
 use crate::{
-    provider::Processor,
-    util::{log_event, LogEvent},
+    http::{canonical_request, decode_body, split_http_body, split_http_exchanges, Headers},
+    provider::{Processor, ProviderError, RequestContext},
+    session::{session_chunk_hash, signed_data_hash, SessionParameters},
+    signer::NotarySigner,
 };
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-use super::{state::Notarize, Verifier, VerifierError};
-use httparse::{Request, Response, Status};
-use serio::SinkExt;
-use signature::Signer;
-use tlsn_core::{msg::SignedSession, Signature};
+#[cfg(feature = "eth")]
+use super::Eip712Signer;
+use super::{state::Notarize, Verifier, VerifierConfig, VerifierError};
+use httparse::{Request, Status};
+use serio::{IoSink, SinkExt};
+use tls_core::msgs::enums::{CipherSuite, ProtocolVersion};
+#[cfg(feature = "eth")]
+use tlsn_core::eip712::sign_digest;
+use tlsn_core::{
+    attribute_tree::AttributeTree,
+    msg::{
+        CommittedBody, SessionChunkMessage, SignedSession, SignedSessionChunk,
+        SignedSessionCommitment, TranscriptForm,
+    },
+    Signature,
+};
 
-use sha2::{Digest, Sha256};
-use tracing::{debug, info, instrument};
+use tracing::{debug, debug_span, field, info, instrument};
+use web_time::{SystemTime, UNIX_EPOCH};
 use zeroize::Zeroize;
 
 use lazy_static::lazy_static;
@@ -37,16 +52,13 @@ impl Verifier<Notarize> {
     ///
     /// * `signer` - The signer used to sign the notarization result.
     #[instrument(parent = &self.span, level = "debug", skip_all, err, err(Debug))]
-    pub async fn finalize<T>(
+    pub async fn finalize(
         self,
-        signer: &impl Signer<T>,
+        signer: &dyn NotarySigner,
         provider: &Processor,
         session_id: String,
         posthog_key: String,
-    ) -> Result<SignedSession, VerifierError>
-    where
-        T: Into<Signature>,
-    {
+    ) -> Result<SignedSession, VerifierError> {
         debug!("starting finalization");
         let timer = FINALIZATION_HISTOGRAM.start_timer();
         let Notarize {
@@ -55,93 +67,34 @@ impl Verifier<Notarize> {
             mut mux_fut,
             mut response_data,
             mut request_data,
+            server_cert_fingerprint,
+            protocol_version,
+            cipher_suite,
             ..
         } = self.state;
-
-        let mut request_headers = [httparse::EMPTY_HEADER; 64];
-        let mut request = Request::new(&mut request_headers);
-        let request_data_mut = request_data.to_owned();
-        let req_bytes = request_data_mut.as_bytes();
-        let _req_result = request.parse(&req_bytes).unwrap();
-
-        let mut response_headers = [httparse::EMPTY_HEADER; 64];
-        let mut response = Response::new(&mut response_headers);
-        let response_data_mut = response_data.to_owned();
-        let resp_bytes = response_data_mut.as_bytes();
-        let resp_size = match response.parse(resp_bytes).unwrap() {
-            Status::Complete(size) => {
-                info!("response parsed");
-                size
-            }
-            Status::Partial => {
-                info!("response partial");
-                0
-            }
+        let transcript = SessionTranscript {
+            request: request_data.as_bytes(),
+            response: response_data.as_bytes(),
+            server_cert_fingerprint,
+            protocol_version,
+            cipher_suite,
         };
-        let body = String::from_utf8_lossy(&resp_bytes[resp_size..]).to_string();
-        let mut attestations: HashMap<String, Signature> = HashMap::new();
-
-        #[cfg(not(target_arch = "wasm32"))]
-        match request.path {
-            Some(path) => {
-                info!("request path: {:?}", path);
-                let provider_ = provider
-                    .find_provider(path, request.method.expect("method not found"))
-                    .expect("provider not found");
-                info!("provider: {:?}", provider_.url_regex);
-
-                log_event(
-                    LogEvent {
-                        event_type: "new_attestation".to_string(),
-                        event_subtype: "processing_provider".to_string(),
-                        session_id: session_id.to_string(),
-                        debug: false,
-                        misc_property_name: "website_url_regex".to_string(),
-                        misc_property_value: provider_.url_regex.to_string(),
-                    },
-                    posthog_key.to_string(),
-                )
-                .await;
-
-                let attributes = match provider.process(
-                    path,
-                    request.method.expect("method not found"),
-                    &body,
-                ) {
-                    Ok(attributes) => attributes,
-                    Err(e) => {
-                        return Err(VerifierError::ProviderError(e));
-                    }
-                };
-                for attribute in attributes {
-                    let signature = signer.sign(attribute.as_bytes());
-                    attestations.insert(attribute, signature.into());
-                }
-            }
-            None => {
-                info!("request path not found");
-            }
-        }
 
         let session_header = mux_fut
             .poll_with(async {
-                let mut data = Vec::new();
-                data.extend_from_slice(req_bytes);
-                data.extend_from_slice(resp_bytes);
-                let mut hasher = Sha256::new();
-                hasher.update(&data);
-                let hash = hasher.finalize();
-                let signature = signer.sign(&hash);
-                info!("signing session");
-                let signed_session = SignedSession {
-                    application_signed_data: hex::encode(hash),
-                    signature: signature.into(),
-                    attestations,
-                    application_data: hex::encode(data),
-                };
+                let signed_session = sign_session(
+                    &self.config,
+                    signer,
+                    provider,
+                    &session_id,
+                    &posthog_key,
+                    &transcript,
+                )
+                .await?;
                 info!("sending signed session");
 
-                io.send(signed_session.clone()).await?;
+                send_signed_session(&mut io, &self.config, signer, &transcript, &signed_session)
+                    .await?;
                 info!(
                     "sent signed session {:?}",
                     signed_session.attestations.keys()
@@ -154,8 +107,6 @@ impl Verifier<Notarize> {
 
         request_data.zeroize();
         response_data.zeroize();
-        drop(response);
-        drop(request);
 
         if !mux_fut.is_complete() {
             mux_ctrl.mux().close();
@@ -168,3 +119,785 @@ impl Verifier<Notarize> {
         Ok(session_header)
     }
 }
+
+/// The transcript of a closed TLS session along with the parameters negotiated with the server.
+struct SessionTranscript<'a> {
+    request: &'a [u8],
+    response: &'a [u8],
+    server_cert_fingerprint: Option<[u8; 32]>,
+    protocol_version: Option<ProtocolVersion>,
+    cipher_suite: Option<CipherSuite>,
+}
+
+/// Processes the transcript with the provider matching its request and signs the session.
+///
+/// This is the notarization of [`Verifier::finalize`], without the transport to the prover.
+async fn sign_session(
+    config: &VerifierConfig,
+    signer: &dyn NotarySigner,
+    provider: &Processor,
+    session_id: &str,
+    posthog_key: &str,
+    transcript: &SessionTranscript<'_>,
+) -> Result<SignedSession, VerifierError> {
+    let server_cert_fingerprint = transcript
+        .server_cert_fingerprint
+        .filter(|_| config.include_server_cert_fingerprint());
+    // The provider check uses the negotiated version even if it is not recorded in the session
+    let negotiated_version = transcript
+        .protocol_version
+        .map(|version| format!("{:?}", version));
+    let (protocol_version, cipher_suite) = if config.include_tls_parameters() {
+        (transcript.protocol_version, transcript.cipher_suite)
+    } else {
+        (None, None)
+    };
+    let signed_at = if config.include_signed_at() {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs())
+    } else {
+        None
+    };
+
+    let req_bytes = transcript.request;
+    let resp_bytes = transcript.response;
+    let mut request_headers = [httparse::EMPTY_HEADER; 64];
+    let mut request = Request::new(&mut request_headers);
+    let request_body = match request.parse(req_bytes).unwrap() {
+        Status::Complete(size) => String::from_utf8_lossy(&req_bytes[size..]).to_string(),
+        Status::Partial => String::new(),
+    };
+
+    // A response whose head is cut off is processed as a body without a status code, as before
+    // the head was parsed with `split_http_body`
+    let (response_headers, body) = match split_http_body(resp_bytes) {
+        Err(VerifierError::IncompleteResponseHead) => {
+            info!("response partial");
+            let headers = Headers {
+                code: None,
+                headers: Vec::new(),
+            };
+            (headers, resp_bytes)
+        }
+        split => split?,
+    };
+    // A body in a supported encoding is decoded, and committed to as decoded so the attributes
+    // can be checked against the application data, any other is committed to as received
+    let content_encoding = content_encoding_of(&response_headers);
+    let decoded_body = match content_encoding.as_deref() {
+        Some(encoding) => decode_body(body, encoding, response_headers.is_chunked())?,
+        None => None,
+    };
+    let committed_body = if decoded_body.is_some() {
+        CommittedBody::Decoded
+    } else {
+        CommittedBody::Received
+    };
+    let committed_response = match &decoded_body {
+        Some(decoded_body) => {
+            Cow::Owned([&resp_bytes[..resp_bytes.len() - body.len()], decoded_body].concat())
+        }
+        None => Cow::Borrowed(resp_bytes),
+    };
+    info!("response parsed");
+    let body = String::from_utf8_lossy(decoded_body.as_deref().unwrap_or(body)).to_string();
+    let mut attestations: HashMap<String, Signature> = HashMap::new();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    match request.path {
+        Some(path) => {
+            info!("request path: {:?}", path);
+            let selection_span = debug_span!(
+                "select_provider",
+                session_id = %session_id,
+                provider_id = field::Empty
+            );
+            let provider_ = selection_span.in_scope(|| {
+                let provider_ = provider
+                    .find_provider_for_request(
+                        path,
+                        request.method.expect("method not found"),
+                        Some(&request_body),
+                    )
+                    .map_err(VerifierError::ProviderError)?
+                    .ok_or_else(|| {
+                        VerifierError::ProviderError(ProviderError::UnsupportedTarget(
+                            path.to_string(),
+                        ))
+                    })?;
+                selection_span.record("provider_id", provider_.id);
+                info!("provider: {:?}", provider_.url_regex);
+
+                if !config.is_provider_allowed(provider_.id) {
+                    return Err(VerifierError::ProviderError(
+                        ProviderError::ProviderNotAllowed(provider_.id),
+                    ));
+                }
+                provider_
+                    .check_cache_policy(&response_headers)
+                    .map_err(VerifierError::ProviderError)?;
+                provider_
+                    .check_tls_version(negotiated_version.as_deref())
+                    .map_err(VerifierError::ProviderError)?;
+                provider_
+                    .check_status(response_headers.code)
+                    .map_err(VerifierError::ProviderError)?;
+                Ok::<_, VerifierError>(provider_)
+            })?;
+
+            log_event(
+                LogEvent {
+                    event_type: "new_attestation".to_string(),
+                    event_subtype: "processing_provider".to_string(),
+                    session_id: session_id.to_string(),
+                    debug: false,
+                    misc_property_name: "website_url_regex".to_string(),
+                    misc_property_value: provider_.url_regex.to_string(),
+                },
+                posthog_key.to_string(),
+            )
+            .await;
+
+            let method = request.method.expect("method not found");
+            let processed = debug_span!(
+                "process_response",
+                session_id = %session_id,
+                provider_id = provider_.id
+            )
+            .in_scope(|| {
+                provider.process_request(
+                    &RequestContext::new(path, method)
+                        .with_request_body(&request_body)
+                        .with_headers(&response_headers)
+                        .with_session_id(session_id),
+                    &body,
+                )
+            });
+            let attributes = match processed {
+                Ok(attributes) => attributes,
+                Err(e) => {
+                    return Err(VerifierError::ProviderError(e));
+                }
+            };
+            // Each attribute costs a signature, so the cap bounds the signing per session
+            if attributes.len() > provider.max_attributes() {
+                return Err(VerifierError::ProviderError(
+                    ProviderError::TooManyAttributes(
+                        provider_.id,
+                        attributes.len(),
+                        provider.max_attributes(),
+                    ),
+                ));
+            }
+            attestations = sign_attributes(signer, attributes).await?;
+        }
+        None => {
+            info!("request path not found");
+        }
+    }
+
+    #[cfg(feature = "eth")]
+    let eip712_signatures = config
+        .eip712_signer()
+        .map(|eip712_signer| sign_eip712(eip712_signer, attestations.keys()))
+        .transpose()?;
+    #[cfg(not(feature = "eth"))]
+    let eip712_signatures = None;
+
+    let transcript_form = if config.canonical_request() {
+        TranscriptForm::Canonical
+    } else {
+        TranscriptForm::Raw
+    };
+    let data = signed_transcript(req_bytes, &committed_response, transcript_form)?;
+    let hash = signed_data_hash(
+        &data,
+        &SessionParameters {
+            server_cert_fingerprint: server_cert_fingerprint.as_ref(),
+            protocol_version,
+            cipher_suite,
+            signed_at,
+            transcript_form,
+            content_encoding: content_encoding.as_deref(),
+            committed_body,
+        },
+    );
+    let signature = signer.sign(&hash).await?;
+    let attestations_signature = signer
+        .sign(&SignedSession::attestations_message(&hash, &attestations))
+        .await?;
+    let attributes_root_signature = match config
+        .include_attributes_root()
+        .then(|| AttributeTree::new(attestations.keys()))
+        .flatten()
+    {
+        Some(tree) => Some(signer.sign(&tree.root_message(&hash)).await?),
+        None => None,
+    };
+    info!("signing session");
+    let scheme = signature.scheme();
+    Ok(SignedSession {
+        application_signed_data: hex::encode(hash),
+        signature,
+        attestations,
+        attestations_signature,
+        application_data: hex::encode(data),
+        server_cert_fingerprint: server_cert_fingerprint.map(hex::encode),
+        tls_version: protocol_version,
+        cipher_suite,
+        signed_at,
+        attributes_root_signature,
+        eip712_signatures,
+        scheme,
+        content_encoding,
+        committed_body,
+        transcript_form,
+    })
+}
+
+/// Sends the signed session to the prover.
+///
+/// If [`VerifierConfig::stream_session`] is set, each HTTP exchange of the transcript is first
+/// streamed with [`send_session_chunks`], otherwise the signed session is sent alone.
+async fn send_signed_session<S: IoSink + Unpin>(
+    io: &mut S,
+    config: &VerifierConfig,
+    signer: &dyn NotarySigner,
+    transcript: &SessionTranscript<'_>,
+    signed_session: &SignedSession,
+) -> Result<(), VerifierError> {
+    if config.stream_session() {
+        let mut exchanges = split_http_exchanges(transcript.request, transcript.response)?;
+        let result = send_session_chunks(io, signer, &exchanges).await;
+        exchanges.iter_mut().for_each(Zeroize::zeroize);
+        result?;
+    }
+    io.send(signed_session.clone()).await?;
+    Ok(())
+}
+
+/// Streams the transcripts of a session to the prover, one [`SignedSessionChunk`] per
+/// transcript in order, followed by the [`SignedSessionCommitment`] covering all of them, so the
+/// prover can report progress on long sessions.
+///
+/// Each chunk is signed over its [`session_chunk_hash`], which binds its position in the session.
+pub async fn send_session_chunks<S: IoSink + Unpin>(
+    io: &mut S,
+    signer: &dyn NotarySigner,
+    transcripts: &[Vec<u8>],
+) -> Result<SignedSessionCommitment, VerifierError> {
+    let count = transcripts.len() as u32;
+    let mut chunks = Vec::with_capacity(transcripts.len());
+    for (index, transcript) in (0..count).zip(transcripts) {
+        let hash = session_chunk_hash(transcript, index, count);
+        let chunk = SignedSessionChunk {
+            index,
+            application_data: hex::encode(transcript),
+            application_signed_data: hex::encode(hash),
+            signature: signer.sign(&hash).await?,
+        };
+        io.send(SessionChunkMessage::Chunk(chunk.clone())).await?;
+        debug!("sent session chunk {}", index);
+        chunks.push(chunk);
+    }
+
+    let commitment = SignedSessionCommitment {
+        chunk_count: chunks.len() as u32,
+        signature: signer
+            .sign(&SignedSessionCommitment::commitment_message(&chunks))
+            .await?,
+    };
+    io.send(SessionChunkMessage::Commitment(commitment.clone()))
+        .await?;
+    Ok(commitment)
+}
+
+/// Signs each attribute with the notary signer.
+async fn sign_attributes(
+    signer: &dyn NotarySigner,
+    attributes: Vec<String>,
+) -> Result<HashMap<String, Signature>, VerifierError> {
+    let mut attestations = HashMap::new();
+    for attribute in attributes {
+        let signature = signer.sign(attribute.as_bytes()).await?;
+        attestations.insert(attribute, signature);
+    }
+    Ok(attestations)
+}
+
+/// Signs each attribute over its EIP-712 digest with the EIP-712 signer, returning the hex
+/// encoded signatures by attribute.
+#[cfg(feature = "eth")]
+fn sign_eip712<'a>(
+    eip712_signer: &Eip712Signer,
+    attributes: impl Iterator<Item = &'a String>,
+) -> Result<HashMap<String, String>, VerifierError> {
+    attributes
+        .map(|attribute| {
+            let digest = eip712_signer.domain.attestation_digest(attribute);
+            let signature = sign_digest(&eip712_signer.signing_key, &digest)
+                .map_err(|e| VerifierError::SigningError(e.to_string()))?;
+            Ok((attribute.clone(), hex::encode(signature)))
+        })
+        .collect()
+}
+
+/// Returns the `Content-Encoding` of a response, lowercased, or `None` if its body is not
+/// encoded.
+fn content_encoding_of(headers: &Headers) -> Option<String> {
+    headers
+        .get("content-encoding")
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty() && encoding != "identity")
+}
+
+/// Returns the transcript which is hashed and recorded as the application data of the session,
+/// the request in the given form followed by the response as received.
+fn signed_transcript(
+    request: &[u8],
+    response: &[u8],
+    form: TranscriptForm,
+) -> Result<Vec<u8>, VerifierError> {
+    let mut data = match form {
+        TranscriptForm::Canonical => canonical_request(request)?,
+        TranscriptForm::Raw => request.to_vec(),
+    };
+    data.extend_from_slice(response);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::signer::NotarySignerError;
+    use async_trait::async_trait;
+    use p256::ecdsa::SigningKey;
+    use std::sync::Mutex;
+    use tlsn_core::NotaryPublicKey;
+
+    /// Signs with an in-memory key and records every message it signed.
+    #[derive(Debug)]
+    struct RecordingSigner {
+        signing_key: SigningKey,
+        signed: Mutex<Vec<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl NotarySigner for RecordingSigner {
+        async fn sign(&self, msg: &[u8]) -> Result<Signature, NotarySignerError> {
+            self.signed.lock().unwrap().push(msg.to_vec());
+            NotarySigner::sign(&self.signing_key, msg).await
+        }
+
+        fn public_key(&self) -> NotaryPublicKey {
+            NotarySigner::public_key(&self.signing_key)
+        }
+    }
+
+    fn x_processor() -> Processor {
+        let config = serde_json::from_value(serde_json::json!({
+            "version": "1.0.0",
+            "EXPECTED_PCRS": {},
+            "PROVIDERS": [{
+                "id": 1,
+                "host": "api.x.com",
+                "urlRegex": r"^https://api\.x\.com/1\.1/account/settings\.json(\?.*)?$",
+                "targetUrl": "https://x.com",
+                "method": "GET",
+                "title": "X account",
+                "description": "Finalize test",
+                "icon": "x",
+                "responseType": "json",
+                "attributes": ["{screen_name: screen_name}"]
+            }]
+        }))
+        .unwrap();
+        Processor::from_config(config, String::new())
+    }
+
+    fn x_transcript() -> SessionTranscript<'static> {
+        SessionTranscript {
+            request: b"GET https://api.x.com/1.1/account/settings.json HTTP/1.1\r\nHost: api.x.com\r\n\r\n",
+            response: b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"screen_name\":\"alice\"}",
+            server_cert_fingerprint: None,
+            protocol_version: None,
+            cipher_suite: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_session_for_allowed_providers() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let config = |allowed_provider_ids: &[u32]| {
+            VerifierConfig::builder()
+                .id("test")
+                .allowed_provider_ids(allowed_provider_ids.iter().copied().collect())
+                .build()
+                .unwrap()
+        };
+
+        let session = sign_session(
+            &config(&[1, 2]),
+            &signing_key,
+            &x_processor(),
+            "session",
+            "",
+            &x_transcript(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(session.attestations.len(), 1);
+
+        let result = sign_session(
+            &config(&[2]),
+            &signing_key,
+            &x_processor(),
+            "session",
+            "",
+            &x_transcript(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(VerifierError::ProviderError(
+                ProviderError::ProviderNotAllowed(1)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sign_session_with_ed25519_signer() {
+        use crate::session::verify_session;
+        use tlsn_core::SignatureScheme;
+
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let config = VerifierConfig::builder().id("test").build().unwrap();
+
+        let session = sign_session(
+            &config,
+            &signing_key,
+            &x_processor(),
+            "session",
+            "",
+            &x_transcript(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(session.scheme, SignatureScheme::Ed25519);
+        assert!(verify_session(&session, NotarySigner::public_key(&signing_key)).is_ok());
+
+        // The session does not verify with a P-256 key
+        let p256_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        assert!(verify_session(&session, NotarySigner::public_key(&p256_key)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sign_session_with_partial_response_head() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let config = VerifierConfig::builder().id("test").build().unwrap();
+        let transcript = SessionTranscript {
+            response: b"HTTP/1.1 200 OK\r\nContent-Type: appl",
+            ..x_transcript()
+        };
+
+        // The whole response is handed to the provider as the body, rather than failing to parse
+        let result = sign_session(
+            &config,
+            &signing_key,
+            &x_processor(),
+            "session",
+            "",
+            &transcript,
+        )
+        .await;
+        assert!(matches!(result, Err(VerifierError::ProviderError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sign_attributes_with_mock_signer() {
+        let signer = RecordingSigner {
+            signing_key: SigningKey::from_slice(&[1; 32]).unwrap(),
+            signed: Mutex::new(Vec::new()),
+        };
+        let attributes = vec!["followers: 10".to_string(), "verified: true".to_string()];
+
+        let attestations = sign_attributes(&signer, attributes).await.unwrap();
+
+        assert_eq!(
+            *signer.signed.lock().unwrap(),
+            vec![b"followers: 10".to_vec(), b"verified: true".to_vec()]
+        );
+        for (attribute, signature) in &attestations {
+            assert!(signature
+                .verify(attribute.as_bytes(), signer.public_key())
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_session_chunks() {
+        use serio::stream::IoStreamExt;
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let transcripts = vec![
+            b"GET /page/1 HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\n\r\n[1]".to_vec(),
+            b"GET /page/2 HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\n\r\n[2]".to_vec(),
+            b"GET /page/3 HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\n\r\n[]".to_vec(),
+        ];
+        let (mut notary_io, mut prover_io) = serio::channel::duplex(8);
+
+        let sent = send_session_chunks(&mut notary_io, &signing_key, &transcripts)
+            .await
+            .unwrap();
+        assert_eq!(sent.chunk_count, 3);
+
+        let mut chunks = Vec::new();
+        let commitment = loop {
+            match prover_io
+                .expect_next::<SessionChunkMessage>()
+                .await
+                .unwrap()
+            {
+                SessionChunkMessage::Chunk(chunk) => chunks.push(chunk),
+                SessionChunkMessage::Commitment(commitment) => break commitment,
+            }
+        };
+        let public_key = NotarySigner::public_key(&signing_key);
+        for (index, (chunk, transcript)) in chunks.iter().zip(&transcripts).enumerate() {
+            assert_eq!(chunk.index as usize, index);
+            assert_eq!(chunk.application_data, hex::encode(transcript));
+            let hash = hex::decode(&chunk.application_signed_data).unwrap();
+            assert_eq!(hash, session_chunk_hash(transcript, index as u32, 3));
+            assert!(chunk.signature.verify(&hash, public_key.clone()).is_ok());
+            // A chunk signature does not verify as the signature of a session over the transcript
+            let session_hash = signed_data_hash(transcript, &SessionParameters::default());
+            assert!(chunk
+                .signature
+                .verify(&session_hash, public_key.clone())
+                .is_err());
+        }
+        assert!(commitment.verify(&chunks, public_key.clone()).is_ok());
+
+        // The commitment does not cover a subset or a reordering of the chunks
+        assert!(commitment.verify(&chunks[..2], public_key.clone()).is_err());
+        chunks.swap(0, 1);
+        assert!(commitment.verify(&chunks, public_key).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_streamed_session() {
+        use serio::stream::IoStreamExt;
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let config = |stream_session: bool| {
+            VerifierConfig::builder()
+                .id("test")
+                .stream_session(stream_session)
+                .build()
+                .unwrap()
+        };
+        let signed_session = sign_session(
+            &config(false),
+            &signing_key,
+            &x_processor(),
+            "test",
+            "",
+            &x_transcript(),
+        )
+        .await
+        .unwrap();
+        let transcript = SessionTranscript {
+            request: b"GET /page/1 HTTP/1.1\r\n\r\nGET /page/2 HTTP/1.1\r\n\r\n",
+            response: b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n[1]HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n[2]",
+            ..x_transcript()
+        };
+
+        // The signed session is sent alone unless streaming was negotiated
+        let (mut notary_io, mut prover_io) = serio::channel::duplex(8);
+        send_signed_session(
+            &mut notary_io,
+            &config(false),
+            &signing_key,
+            &transcript,
+            &signed_session,
+        )
+        .await
+        .unwrap();
+        let received: SignedSession = prover_io.expect_next().await.unwrap();
+        assert_eq!(
+            received.application_signed_data,
+            signed_session.application_signed_data
+        );
+
+        // Otherwise each exchange is streamed in order, as `Prover::finalize_streaming` reads
+        // them, before the signed session
+        let (mut notary_io, mut prover_io) = serio::channel::duplex(8);
+        send_signed_session(
+            &mut notary_io,
+            &config(true),
+            &signing_key,
+            &transcript,
+            &signed_session,
+        )
+        .await
+        .unwrap();
+        let mut chunks = Vec::new();
+        let commitment = loop {
+            match prover_io
+                .expect_next::<SessionChunkMessage>()
+                .await
+                .unwrap()
+            {
+                SessionChunkMessage::Chunk(chunk) => chunks.push(chunk),
+                SessionChunkMessage::Commitment(commitment) => break commitment,
+            }
+        };
+        let received: SignedSession = prover_io.expect_next().await.unwrap();
+        assert_eq!(
+            received.application_signed_data,
+            signed_session.application_signed_data
+        );
+
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| hex::decode(&chunk.application_data).unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                b"GET /page/1 HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n[1]"
+                    .to_vec(),
+                b"GET /page/2 HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n[2]"
+                    .to_vec(),
+            ]
+        );
+        assert!(commitment
+            .verify(&chunks, NotarySigner::public_key(&signing_key))
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_gzip_content_encoding() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"{\"screen_name\":\"alice\"}").unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let head =
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: GZIP\r\n\r\n";
+        let response = [head.as_slice(), &gzipped].concat();
+        let (headers, _) = split_http_body(&response).unwrap();
+        assert_eq!(content_encoding_of(&headers).as_deref(), Some("gzip"));
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let session = sign_session(
+            &VerifierConfig::builder().id("test").build().unwrap(),
+            &signing_key,
+            &x_processor(),
+            "session",
+            "",
+            &SessionTranscript {
+                response: &response,
+                ..x_transcript()
+            },
+        )
+        .await
+        .unwrap();
+
+        // The decoded body is committed to after the head as received
+        assert_eq!(session.content_encoding.as_deref(), Some("gzip"));
+        assert_eq!(session.committed_body, CommittedBody::Decoded);
+        assert_eq!(
+            hex::decode(&session.application_data).unwrap(),
+            [
+                x_transcript().request,
+                head,
+                b"{\"screen_name\":\"alice\"}".as_slice()
+            ]
+            .concat()
+        );
+        assert_eq!(session.attestations.len(), 1);
+        assert!(
+            crate::session::verify_session(&session, NotarySigner::public_key(&signing_key))
+                .is_ok()
+        );
+
+        // The encoding and the committed form are bound to the signature
+        let mut tampered = session.clone();
+        tampered.committed_body = CommittedBody::Received;
+        assert!(
+            crate::session::verify_session(&tampered, NotarySigner::public_key(&signing_key))
+                .is_err()
+        );
+        let mut tampered = session;
+        tampered.content_encoding = Some("deflate".to_string());
+        assert!(
+            crate::session::verify_session(&tampered, NotarySigner::public_key(&signing_key))
+                .is_err()
+        );
+
+        for identity in [
+            "HTTP/1.1 200 OK\r\nContent-Encoding: identity\r\n\r\n{}",
+            "HTTP/1.1 200 OK\r\n\r\n{}",
+        ] {
+            let (headers, _) = split_http_body(identity.as_bytes()).unwrap();
+            assert_eq!(content_encoding_of(&headers), None);
+        }
+    }
+
+    #[test]
+    fn test_canonical_request_hash() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+        let request = b"GET /me HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n";
+        let reordered = b"GET /me HTTP/1.1\naccept:   */*\nHOST: example.com\n\n";
+        let hash = |request: &[u8], transcript_form: TranscriptForm| {
+            let data = signed_transcript(request, response, transcript_form).unwrap();
+            signed_data_hash(
+                &data,
+                &SessionParameters {
+                    transcript_form,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(
+            hash(request, TranscriptForm::Canonical),
+            hash(reordered, TranscriptForm::Canonical)
+        );
+        assert_ne!(
+            hash(request, TranscriptForm::Raw),
+            hash(reordered, TranscriptForm::Raw)
+        );
+        assert_eq!(
+            signed_transcript(request, response, TranscriptForm::Raw).unwrap(),
+            [request.as_slice(), response.as_slice()].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signed_at_is_covered_by_signature() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let data = b"GET / HTTP/1.1\r\n\r\nHTTP/1.1 200 OK\r\n\r\n";
+
+        let signed_at = |signed_at| SessionParameters {
+            signed_at,
+            ..Default::default()
+        };
+        let hash = signed_data_hash(data, &signed_at(Some(1735613754)));
+        let signature = NotarySigner::sign(&signing_key, &hash).await.unwrap();
+        assert!(signature
+            .verify(&hash, NotarySigner::public_key(&signing_key))
+            .is_ok());
+
+        for parameters in [signed_at(Some(1735613755)), signed_at(None)] {
+            let hash = signed_data_hash(data, &parameters);
+            assert!(signature
+                .verify(&hash, NotarySigner::public_key(&signing_key))
+                .is_err());
+        }
+    }
+}
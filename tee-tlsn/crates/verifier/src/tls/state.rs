@@ -1,5 +1,6 @@
 //! TLS Verifier state.
 
+use tls_core::msgs::enums::{CipherSuite, ProtocolVersion};
 use tls_tee::TeeTlsFollower;
 use tlsn_common::{
     mux::{MuxControl, MuxFuture},
@@ -30,6 +31,9 @@ pub struct Closed {
     pub(crate) mux_fut: MuxFuture,
     pub(crate) response_data: String,
     pub(crate) request_data: String,
+    pub(crate) server_cert_fingerprint: Option<[u8; 32]>,
+    pub(crate) protocol_version: Option<ProtocolVersion>,
+    pub(crate) cipher_suite: Option<CipherSuite>,
 }
 
 opaque_debug::implement!(Closed);
@@ -41,6 +45,9 @@ pub struct Notarize {
     pub(crate) mux_fut: MuxFuture,
     pub(crate) response_data: String,
     pub(crate) request_data: String,
+    pub(crate) server_cert_fingerprint: Option<[u8; 32]>,
+    pub(crate) protocol_version: Option<ProtocolVersion>,
+    pub(crate) cipher_suite: Option<CipherSuite>,
 }
 
 opaque_debug::implement!(Notarize);
@@ -50,6 +57,9 @@ impl From<Closed> for Notarize {
         Self {
             response_data: value.response_data,
             request_data: value.request_data,
+            server_cert_fingerprint: value.server_cert_fingerprint,
+            protocol_version: value.protocol_version,
+            cipher_suite: value.cipher_suite,
             io: value.io,
             mux_ctrl: value.mux_ctrl,
             mux_fut: value.mux_fut,
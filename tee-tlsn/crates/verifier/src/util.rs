@@ -1,9 +1,13 @@
 //! This module contains the utility functions for logging events to PostHog.
 
+use std::collections::HashMap;
+
 use posthog_rs::Event;
 use serde::Serialize;
 
 use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+use tlsn_core::NotaryPublicKey;
 
 #[derive(Debug, Serialize)]
 /// LogEvent is the event that is logged to PostHog
@@ -77,13 +81,51 @@ pub async fn log_event(log_event: LogEvent, posthog_key: String) {
     }
 }
 
+/// The notary whose code attestation is fetched by default
+pub const DEFAULT_NOTARY_URL: &str = "https://notary.freysa.ai";
+
+/// Multihash prefix of a SHA-256 digest: the `sha2-256` code followed by the digest length
+pub const SHA256_MULTIHASH_PREFIX: [u8; 2] = [0x12, 0x20];
+
+/// Returns the user data which binds a notary deployment to the attestation documents of its
+/// enclave, as `verify_notary_deployment` in the wasm verifier checks it
+///
+/// The user data is the SHA-256 multihash of the SEC1 public key of the notary followed by the
+/// SHA-256 multihash whose digest is the hex encoded `config_hash`, see
+/// [`Processor::config_hash`](crate::provider::Processor::config_hash).
+pub fn deployment_user_data(
+    public_key_sec1: &[u8],
+    config_hash: &str,
+) -> Result<Vec<u8>, hex::FromHexError> {
+    let mut config_digest = [0; 32];
+    hex::decode_to_slice(config_hash, &mut config_digest)?;
+    Ok([
+        SHA256_MULTIHASH_PREFIX.as_slice(),
+        Sha256::digest(public_key_sec1).as_slice(),
+        SHA256_MULTIHASH_PREFIX.as_slice(),
+        &config_digest,
+    ]
+    .concat())
+}
+
 /// Retrieves the signed code attestation from AWS
 /// This attestation is fetched by calling nitriding server from within the TEE
-pub async fn get_code_attestation(nonce: String) -> String {
-    let url = format!(
-        "https://notary.freysa.ai/enclave/attestation?nonce={}",
+///
+/// The `user_data`, e.g. the [`deployment_user_data`] of the notary, is passed hex encoded and
+/// recorded in the attestation document.
+pub async fn get_code_attestation(
+    notary_url: &str,
+    nonce: String,
+    user_data: Option<&[u8]>,
+) -> String {
+    let mut url = format!(
+        "{}/enclave/attestation?nonce={}",
+        notary_url.trim_end_matches('/'),
         nonce
     );
+    if let Some(user_data) = user_data {
+        url.push_str(&format!("&user_data={}", hex::encode(user_data)));
+    }
 
     let client = reqwest::Client::builder()
         .danger_accept_invalid_certs(true)
@@ -141,3 +183,109 @@ pub async fn verify_signature(
 
     verifying_key.verify(&application_data, &signature).is_ok()
 }
+
+/// Verify the notary signature of every attribute of an attestation set, as `finalize` signs
+/// them over the bytes of the `name: value` attribute
+///
+/// Returns each attribute with whether its signature is valid, sorted by attribute.
+pub fn verify_attestations(
+    attestations: &HashMap<String, tlsn_core::Signature>,
+    notary_pubkey: impl Into<NotaryPublicKey>,
+) -> Vec<(String, bool)> {
+    let notary_pubkey = notary_pubkey.into();
+    let mut results = attestations
+        .iter()
+        .map(|(attribute, signature)| {
+            let valid = signature
+                .verify(attribute.as_bytes(), notary_pubkey.clone())
+                .is_ok();
+            (attribute.clone(), valid)
+        })
+        .collect::<Vec<_>>();
+    results.sort();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    #[test]
+    fn test_verify_attestations() {
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let sign = |message: &str| -> tlsn_core::Signature {
+            let signature: Signature = signing_key.sign(message.as_bytes());
+            signature.into()
+        };
+
+        let attestations = HashMap::from([
+            ("followers: 10".to_string(), sign("followers: 10")),
+            // The value was changed after signing
+            ("verified: true".to_string(), sign("verified: false")),
+            ("karma: 42".to_string(), sign("karma: 42")),
+        ]);
+        assert_eq!(
+            verify_attestations(
+                &attestations,
+                p256::PublicKey::from(signing_key.verifying_key())
+            ),
+            vec![
+                ("followers: 10".to_string(), true),
+                ("karma: 42".to_string(), true),
+                ("verified: true".to_string(), false),
+            ]
+        );
+
+        // Signatures of another key are all invalid
+        let other_key = SigningKey::from_slice(&[2; 32]).unwrap();
+        assert!(verify_attestations(
+            &attestations,
+            p256::PublicKey::from(other_key.verifying_key())
+        )
+        .iter()
+        .all(|(_, valid)| !valid));
+    }
+
+    #[test]
+    fn test_deployment_user_data() {
+        let public_key = hex::decode("04ab").unwrap();
+        let config_hash = hex::encode(Sha256::digest(b"config"));
+
+        let user_data = deployment_user_data(&public_key, &config_hash).unwrap();
+        assert_eq!(user_data.len(), 2 * 34);
+        assert_eq!(user_data[..2], SHA256_MULTIHASH_PREFIX);
+        assert_eq!(user_data[2..34], *Sha256::digest(&public_key));
+        assert_eq!(user_data[34..36], SHA256_MULTIHASH_PREFIX);
+        assert_eq!(hex::encode(&user_data[36..]), config_hash);
+
+        assert!(deployment_user_data(&public_key, "abcd").is_err());
+        assert!(deployment_user_data(&public_key, "not hex").is_err());
+    }
+
+    #[test]
+    fn test_verify_ed25519_attestations() {
+        use ed25519_dalek::Signer as _;
+
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let attestations = HashMap::from([(
+            "followers: 10".to_string(),
+            signing_key.sign(b"followers: 10").into(),
+        )]);
+        assert_eq!(
+            verify_attestations(&attestations, signing_key.verifying_key()),
+            vec![("followers: 10".to_string(), true)]
+        );
+
+        // A P-256 key of the same bytes does not verify them
+        let p256_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        assert_eq!(
+            verify_attestations(
+                &attestations,
+                p256::PublicKey::from(p256_key.verifying_key())
+            ),
+            vec![("followers: 10".to_string(), false)]
+        );
+    }
+}
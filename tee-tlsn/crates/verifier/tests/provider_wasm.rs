@@ -0,0 +1,41 @@
+//! Provider evaluation on wasm.
+//!
+//! Run with `wasm-pack test --headless --chrome -- --features wasm --test provider_wasm`.
+
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use serde_json::json;
+use tlsn_verifier::provider::Provider;
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn test_get_attributes() {
+    let provider: Provider = serde_json::from_value(json!({
+        "id": 1,
+        "host": "test.com",
+        "urlRegex": r"^https://test\.com/.*$",
+        "targetUrl": "https://test.com",
+        "method": "GET",
+        "title": "Test Provider",
+        "description": "Wasm test",
+        "icon": "test",
+        "responseType": "json",
+        "preprocess": "function process(jsonString) { const data = JSON.parse(jsonString); return { karma: data.user.karma }; }",
+        "attributes": ["{karma: karma, trusted: karma > `100`}"]
+    }))
+    .expect("Failed to parse provider");
+
+    assert!(provider
+        .check_url_method("https://test.com/me", "GET")
+        .unwrap());
+    let processed = provider
+        .preprocess_response(r#"{"user": {"karma": 142}}"#)
+        .expect("Failed to preprocess");
+    let mut attributes = provider
+        .get_attributes(&processed)
+        .expect("Failed to get attributes");
+    attributes.sort();
+    assert_eq!(attributes, vec!["karma: 142", "trusted: true"]);
+}
@@ -15,6 +15,7 @@ pub mod verifier;
 use base64::engine::{general_purpose, Engine};
 use hex;
 use log::LoggingConfig;
+use std::ops::RangeInclusive;
 use tee_attestation_verifier::{parse_verify_with, Payload};
 #[cfg(feature = "test")]
 pub use tests::*;
@@ -27,6 +28,7 @@ use tracing_subscriber::{
 };
 use tracing_web::MakeWebConsoleWriter;
 use wasm_bindgen::prelude::*;
+use x509_cert::{der::Decode, Certificate};
 
 use p256::{
     ecdsa::{
@@ -88,34 +90,424 @@ pub struct AttestationDocument {
     pub certificate: Option<String>,
 }
 
+/// Optional checks of an attestation document, on top of the expected nonce and PCR2.
+#[derive(Debug, Default, Tsify, Deserialize)]
+#[tsify(from_wasm_abi)]
+pub struct AttestationOptions {
+    /// The enclave instances the document must come from, e.g.
+    /// `i-0fe9a96ed626c76df-enc01940b0d332c6b53`, rather than any enclave with a matching PCR2
+    pub allowed_module_ids: Option<Vec<String>>,
+    /// How far the document timestamp may be behind the trusted current time, so a stale
+    /// document cannot be replayed
+    pub max_age_secs: Option<u64>,
+    /// How far the document timestamp may be ahead of the trusted current time,
+    /// [`DEFAULT_MAX_CLOCK_SKEW_SECS`] by default when `max_age_secs` is given
+    pub max_clock_skew_secs: Option<u64>,
+}
+
+impl AttestationOptions {
+    /// Returns the window, in unix milliseconds, the document timestamp must fall in given the
+    /// trusted current time `now` in unix seconds, or `None` if neither `max_age_secs` nor
+    /// `max_clock_skew_secs` is given and the timestamp is not checked.
+    fn validity_window(&self, now: u64) -> Option<RangeInclusive<u64>> {
+        if self.max_age_secs.is_none() && self.max_clock_skew_secs.is_none() {
+            return None;
+        }
+        let now_ms = now.saturating_mul(1000);
+        let not_before_ms = self.max_age_secs.map_or(0, |max_age_secs| {
+            now_ms.saturating_sub(max_age_secs.saturating_mul(1000))
+        });
+        let max_clock_skew_secs = self
+            .max_clock_skew_secs
+            .unwrap_or(DEFAULT_MAX_CLOCK_SKEW_SECS);
+        let not_after_ms = now_ms.saturating_add(max_clock_skew_secs.saturating_mul(1000));
+        Some(not_before_ms..=not_after_ms)
+    }
+}
+
+/// Verifies the attestation document against the expected nonce and PCR2.
+///
+/// `options` adds an allowlist of enclave instances and bounds on the document timestamp, see
+/// [`AttestationOptions`]. When `max_age_secs` or `max_clock_skew_secs` is given, `timestamp`
+/// must be a trusted current time in unix seconds, and the document timestamp must fall within
+/// `max_age_secs` before it and `max_clock_skew_secs` after it, as a document from the future
+/// indicates a manipulated clock.
+///
+/// See [`check_attestation_document`] for the reason a document does not verify.
 #[wasm_bindgen]
 pub fn verify_attestation_document(
     attestation_document: String,
     nonce_expected: String,
     pcr_expected: String,
     timestamp: u64,
+    options: Option<AttestationOptions>,
+) -> bool {
+    verify_attestation_payload(
+        attestation_document,
+        nonce_expected,
+        std::slice::from_ref(&pcr_expected),
+        timestamp,
+        &options.unwrap_or_default(),
+    )
+    .is_ok()
+}
+
+/// Verifies the attestation document as [`verify_attestation_document`] does, throwing the
+/// reason it does not verify, e.g. a module id mismatch or a timestamp outside the validity
+/// window, instead of returning `false`.
+#[wasm_bindgen]
+pub fn check_attestation_document(
+    attestation_document: String,
+    nonce_expected: String,
+    pcr_expected: String,
+    timestamp: u64,
+    options: Option<AttestationOptions>,
+) -> Result<(), JsValue> {
+    verify_attestation_payload(
+        attestation_document,
+        nonce_expected,
+        std::slice::from_ref(&pcr_expected),
+        timestamp,
+        &options.unwrap_or_default(),
+    )
+    .map(|_| ())
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Verifies the attestation document as [`verify_attestation_document`] does, accepting any of
+/// several enclave images, e.g. the old and new image during a rolling upgrade.
+///
+/// Returns the index in `pcrs_expected` of the PCR2 the document matched, or `None` if the
+/// document does not verify or matches none of them.
+#[wasm_bindgen]
+pub fn verify_attestation_document_any(
+    attestation_document: String,
+    nonce_expected: String,
+    pcrs_expected: Vec<String>,
+    timestamp: u64,
+    options: Option<AttestationOptions>,
+) -> Option<usize> {
+    verify_attestation_payload(
+        attestation_document,
+        nonce_expected,
+        &pcrs_expected,
+        timestamp,
+        &options.unwrap_or_default(),
+    )
+    .ok()
+    .map(|(_, matched)| matched)
+}
+
+/// Verifies the attestation document as [`verify_attestation_document`] does, and additionally
+/// checks that it was produced within `window_secs` of the session it vouches for, so an old
+/// attestation cannot be paired with a new session.
+///
+/// `session_timestamp` is the notarization time of the session in unix seconds.
+#[wasm_bindgen]
+pub fn verify_attestation_document_for_session(
+    attestation_document: String,
+    nonce_expected: String,
+    pcr_expected: String,
+    timestamp: u64,
+    session_timestamp: u64,
+    window_secs: u64,
+    options: Option<AttestationOptions>,
+) -> bool {
+    match verify_attestation_payload(
+        attestation_document,
+        nonce_expected,
+        std::slice::from_ref(&pcr_expected),
+        timestamp,
+        &options.unwrap_or_default(),
+    ) {
+        Ok((payload, _)) => {
+            is_attestation_timestamp_within(payload.timestamp, session_timestamp, window_secs)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Returns whether an attestation document timestamp, in unix milliseconds as recorded by the
+/// enclave, is within `window_secs` of a reference timestamp in unix seconds.
+fn is_attestation_timestamp_within(
+    attestation_timestamp_ms: u64,
+    reference_timestamp: u64,
+    window_secs: u64,
 ) -> bool {
+    let skew_ms = attestation_timestamp_ms.abs_diff(reference_timestamp.saturating_mul(1000));
+    info!("attestation timestamp skew: {}ms", skew_ms);
+    skew_ms <= window_secs.saturating_mul(1000)
+}
+
+/// The clock skew allowed by default between the timestamp of an attestation document and the
+/// trusted current time of the verifier when the document age is bounded, in seconds
+pub const DEFAULT_MAX_CLOCK_SKEW_SECS: u64 = 60;
+
+/// An error that can occur while verifying an attestation document.
+#[derive(Debug, thiserror::Error)]
+pub enum AttestationError {
+    /// The document could not be decoded or its signature chain is invalid.
+    #[error("invalid attestation document: {0}")]
+    InvalidDocument(String),
+    /// The PCR2 of the document is not one of the expected ones.
+    #[error("PCR2 {0} does not match the expected PCR2")]
+    PcrMismatch(String),
+    /// The module id of the document is not one of the allowed module ids.
+    #[error("module id '{0}' is not allowed")]
+    ModuleIdNotAllowed(String),
+    /// The subject of the enclave certificate is not the module id claimed by the document.
+    #[error("certificate subject '{subject}' does not match module id '{module_id}'")]
+    ModuleIdMismatch {
+        /// The module id of the document
+        module_id: String,
+        /// The common name of the certificate subject
+        subject: String,
+    },
+    /// The timestamp of the document is outside the validity window around the trusted current
+    /// time, i.e. the document is stale or from the future.
+    #[error(
+        "attestation timestamp {timestamp_ms}ms is outside [{not_before_ms}ms, {not_after_ms}ms]"
+    )]
+    TimestampOutsideWindow {
+        /// The timestamp recorded in the document, in unix milliseconds
+        timestamp_ms: u64,
+        /// The earliest allowed timestamp, in unix milliseconds
+        not_before_ms: u64,
+        /// The latest allowed timestamp, in unix milliseconds
+        not_after_ms: u64,
+    },
+    /// The user data of the document is not a notary key digest followed by a config hash.
+    #[error("invalid deployment user data: {0}")]
+    InvalidUserData(String),
+    /// The user data of the document binds another notary key than the expected one.
+    #[error("attested notary key digest {0} does not match the expected key")]
+    NotaryKeyMismatch(String),
+    /// The user data of the document binds another config than the expected one.
+    #[error("attested config hash {0} does not match the expected config hash")]
+    ConfigHashMismatch(String),
+}
+
+/// Decodes and verifies the attestation document, returning its payload and the index of the
+/// matching PCR if the PCR2 is one of `pcrs_expected`, the module id is allowed, the enclave
+/// certificate belongs to the module the document claims and the document timestamp falls within
+/// the validity window of `options` around `timestamp`, if any.
+fn verify_attestation_payload(
+    attestation_document: String,
+    nonce_expected: String,
+    pcrs_expected: &[String],
+    timestamp: u64,
+    options: &AttestationOptions,
+) -> Result<(Payload, usize), AttestationError> {
     info!("🔍 Starting verification..");
 
     let attestation_document = general_purpose::STANDARD
         .decode(attestation_document)
-        .expect("failed to decode document");
+        .map_err(|e| AttestationError::InvalidDocument(format!("invalid base64: {}", e)))?;
+
+    let nonce = hex::decode(nonce_expected)
+        .map_err(|e| AttestationError::InvalidDocument(format!("invalid nonce: {}", e)))?;
+
+    let (payload, _) = parse_verify_with(attestation_document, nonce, timestamp).map_err(|_| {
+        AttestationError::InvalidDocument("failed to verify the document".to_string())
+    })?;
+
+    let pcr_2 = base64::encode(payload.pcrs[2].clone());
+    info!("pcr_2: {:?}", pcr_2);
+    info!("pcrs: {:?}", pcrs_expected);
+    let matched = pcrs_expected
+        .iter()
+        .position(|pcr_expected| *pcr_expected == pcr_2)
+        .ok_or(AttestationError::PcrMismatch(pcr_2))?;
+
+    check_module_id_allowed(&payload.module_id, options.allowed_module_ids.as_deref())?;
+    check_certificate_module_id(&payload.certificate, &payload.module_id)?;
+    if let Some(window) = options.validity_window(timestamp) {
+        check_timestamp_within(payload.timestamp, window)?;
+    }
+    Ok((payload, matched))
+}
 
-    let nonce = hex::decode(nonce_expected).expect("decode nonce failed");
+/// Checks that the document timestamp, in unix milliseconds, falls within the validity window.
+fn check_timestamp_within(
+    timestamp_ms: u64,
+    window: RangeInclusive<u64>,
+) -> Result<(), AttestationError> {
+    if window.contains(&timestamp_ms) {
+        Ok(())
+    } else {
+        error!(
+            "attestation timestamp {}ms is outside [{}ms, {}ms]",
+            timestamp_ms,
+            window.start(),
+            window.end()
+        );
+        Err(AttestationError::TimestampOutsideWindow {
+            timestamp_ms,
+            not_before_ms: *window.start(),
+            not_after_ms: *window.end(),
+        })
+    }
+}
 
-    let verify_result = parse_verify_with(attestation_document, nonce, timestamp);
+/// Checks that the module id is one of the allowed module ids, if an allowlist is given.
+fn check_module_id_allowed(
+    module_id: &str,
+    allowed_module_ids: Option<&[String]>,
+) -> Result<(), AttestationError> {
+    match allowed_module_ids {
+        Some(allowed) if !allowed.iter().any(|allowed| allowed == module_id) => {
+            error!("module id {} is not allowed", module_id);
+            Err(AttestationError::ModuleIdNotAllowed(module_id.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
 
-    if (!verify_result.is_ok()) {
-        return false;
+/// Checks that the common name of the enclave certificate subject is the module id, e.g.
+/// `i-0fe9a96ed626c76df-enc01940b0d332c6b53.us-east-2.aws` for the module
+/// `i-0fe9a96ed626c76df-enc01940b0d332c6b53`, so a valid certificate cannot be paired with the
+/// module id of another enclave.
+fn check_certificate_module_id(
+    certificate: &[u8],
+    module_id: &str,
+) -> Result<(), AttestationError> {
+    let certificate = Certificate::from_der(certificate)
+        .map_err(|e| AttestationError::InvalidDocument(e.to_string()))?;
+    let subject = certificate.tbs_certificate.subject.to_string();
+    let common_name = subject
+        .split(',')
+        .find_map(|rdn| rdn.trim().strip_prefix("CN="))
+        .unwrap_or_default();
+
+    let matches = common_name
+        .strip_prefix(module_id)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'));
+    if matches {
+        Ok(())
     } else {
-        let (payload, _) = verify_result.unwrap();
+        error!(
+            "certificate subject {} does not match module id {}",
+            common_name, module_id
+        );
+        Err(AttestationError::ModuleIdMismatch {
+            module_id: module_id.to_string(),
+            subject: common_name.to_string(),
+        })
+    }
+}
+
+/// Length in bytes of a Nitro PCR, the size of a SHA-384 digest
+const PCR_LEN: usize = 48;
 
-        let pcr_2 = base64::encode(payload.pcrs[2].clone());
-        info!("pcr_2: {:?}", pcr_2);
-        info!("pcr: {:?}", pcr_expected);
+/// Computes the PCR expected after extending an initially zeroed PCR with `measurement`, as the
+/// Nitro hypervisor does, i.e. `SHA-384(0^48 || measurement)`, returned base64 encoded so it
+/// can be compared with `pcr_expected` of [`verify_attestation_document`].
+#[wasm_bindgen]
+pub fn compute_expected_pcr(measurement: Vec<u8>) -> String {
+    use sha2::{Digest, Sha384};
+
+    let mut hasher = Sha384::new();
+    hasher.update([0u8; PCR_LEN]);
+    hasher.update(&measurement);
+    general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+/// Multihash prefix of a SHA-256 digest: the `sha2-256` code followed by the digest length
+const SHA256_MULTIHASH_PREFIX: [u8; 2] = [0x12, 0x20];
 
-        return pcr_2 == pcr_expected;
+/// Verifies that an attestation document establishes trust in a whole notary deployment, i.e.
+/// that the document verifies as [`verify_attestation_document_any`] does, and that its user
+/// data binds both `expected_key` and `expected_config_hash`.
+///
+/// `expected_key` is the hex encoded SEC1 public key of the notary, as passed to
+/// [`verify_attestation_signature`], and `expected_config_hash` the hex encoded canonical hash
+/// of the notary config. `options` are checked as by [`verify_attestation_document`].
+#[wasm_bindgen]
+pub fn verify_notary_deployment(
+    attestation_document: String,
+    nonce_expected: String,
+    pcrs_expected: Vec<String>,
+    timestamp: u64,
+    expected_config_hash: String,
+    expected_key: String,
+    options: Option<AttestationOptions>,
+) -> Result<(), JsValue> {
+    check_notary_deployment(
+        attestation_document,
+        nonce_expected,
+        &pcrs_expected,
+        timestamp,
+        &expected_config_hash,
+        &expected_key,
+        &options.unwrap_or_default(),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Verifies the attestation document and the deployment it binds, see
+/// [`verify_notary_deployment`].
+fn check_notary_deployment(
+    attestation_document: String,
+    nonce_expected: String,
+    pcrs_expected: &[String],
+    timestamp: u64,
+    expected_config_hash: &str,
+    expected_key: &str,
+    options: &AttestationOptions,
+) -> Result<(), AttestationError> {
+    let (payload, _) = verify_attestation_payload(
+        attestation_document,
+        nonce_expected,
+        pcrs_expected,
+        timestamp,
+        options,
+    )?;
+    check_deployment_binding(&payload.user_data, expected_config_hash, expected_key)
+}
+
+/// Checks that the user data of an attestation document binds the notary key and config, i.e.
+/// that it is the SHA-256 multihash of the decoded `expected_key` followed by the SHA-256
+/// multihash whose digest is `expected_config_hash`.
+fn check_deployment_binding(
+    user_data: &[u8],
+    expected_config_hash: &str,
+    expected_key: &str,
+) -> Result<(), AttestationError> {
+    use sha2::{Digest, Sha256};
+
+    let mut digests = user_data.chunks(SHA256_MULTIHASH_PREFIX.len() + 32);
+    let (Some(key_digest), Some(config_hash), None) =
+        (digests.next(), digests.next(), digests.next())
+    else {
+        return Err(AttestationError::InvalidUserData(format!(
+            "expected two SHA-256 multihashes, got {} bytes",
+            user_data.len()
+        )));
+    };
+    let key_digest = key_digest
+        .strip_prefix(SHA256_MULTIHASH_PREFIX.as_slice())
+        .filter(|digest| digest.len() == 32)
+        .ok_or_else(|| {
+            AttestationError::InvalidUserData("malformed notary key digest".to_string())
+        })?;
+    let config_hash = config_hash
+        .strip_prefix(SHA256_MULTIHASH_PREFIX.as_slice())
+        .filter(|digest| digest.len() == 32)
+        .ok_or_else(|| AttestationError::InvalidUserData("malformed config hash".to_string()))?;
+
+    let expected_key = hex::decode(expected_key)
+        .map_err(|e| AttestationError::InvalidUserData(format!("invalid expected key: {}", e)))?;
+    if key_digest != Sha256::digest(&expected_key).as_slice() {
+        error!("attested notary key digest does not match the expected key");
+        return Err(AttestationError::NotaryKeyMismatch(hex::encode(key_digest)));
     }
+    if !hex::encode(config_hash).eq_ignore_ascii_case(expected_config_hash) {
+        error!("attested config hash does not match the expected config hash");
+        return Err(AttestationError::ConfigHashMismatch(hex::encode(
+            config_hash,
+        )));
+    }
+    Ok(())
 }
 
 #[wasm_bindgen]
@@ -133,8 +525,13 @@ pub fn verify_attestation_signature(
     let bytes_public_key = hex::decode(hex_raw_public_key).expect("decode public key failed");
 
     println!("bytes_public_key: {:?}", bytes_public_key);
-    let verifying_key = VerifyingKey::from_sec1_bytes(bytes_public_key.as_slice())
-        .expect("decode P256 public key failed");
+    let verifying_key = match VerifyingKey::from_sec1_bytes(bytes_public_key.as_slice()) {
+        Ok(verifying_key) => verifying_key,
+        Err(e) => {
+            error!("decode P256 public key failed: {}", e);
+            return false;
+        }
+    };
 
     //signature
     let signature_bytes = hex::decode(hex_raw_signature).expect("decode signature failed");
@@ -156,9 +553,48 @@ pub fn verify_attestation_signature(
     verifying_key.verify(&application_data, &signature).is_ok()
 }
 
+/// The notary whose code attestation is fetched when no URL is given
+pub const DEFAULT_NOTARY_URL: &str = "https://notary.freysa.ai";
+
+/// Fetches the code attestation of a notary enclave for the given nonce.
+///
+/// `notary_url` is the base URL of the notary, e.g. a staging or self-hosted deployment, and
+/// defaults to [`DEFAULT_NOTARY_URL`].
+#[wasm_bindgen]
+pub async fn fetch_code_attestation(
+    nonce: String,
+    notary_url: Option<String>,
+) -> Result<String, JsValue> {
+    let url = code_attestation_url(notary_url.as_deref(), &nonce);
+    info!("fetching code attestation from {}", url);
+
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| JsValue::from_str(&format!("failed to fetch code attestation: {}", e)))?;
+    response
+        .text()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("failed to read code attestation: {}", e)))
+}
+
+/// Returns the URL of the code attestation endpoint of a notary.
+fn code_attestation_url(notary_url: Option<&str>, nonce: &str) -> String {
+    format!(
+        "{}/enclave/attestation?nonce={}",
+        notary_url
+            .unwrap_or(DEFAULT_NOTARY_URL)
+            .trim_end_matches('/'),
+        nonce
+    )
+}
+
+#[cfg(test)]
 mod test {
     use crate::*;
 
+    /// An attestation document of the notary enclave, produced at 2024-12-31T02:55:54Z
+    const ATTESTATION_DOCUMENT: &str = "hEShATgioFkRXqlpbW9kdWxlX2lkeCdpLTBmZTlhOTZlZDYyNmM3NmRmLWVuYzAxOTQwYjBkMzMyYzZiNTNmZGlnZXN0ZlNIQTM4NGl0aW1lc3RhbXAbAAABlBqkLPdkcGNyc7AAWDBqayfwH0L+yJw/GE7G+egQh6+OxInfMClAmcC5MFoa1u3e+ZvXHGISxcnVS3nYDB0BWDBLTVs2YbPvwSkgkAyA4Sbkzng8Ui3mwCoqW/evOiuTJ7hndvGI5L4cHEBKEp29pJMCWDC8bcpDk1ZDBcUYwjlcTirF/BGGtAkKEJfwyHvaVxV+u/vlG6rh4vj2tu5++nAeLJIDWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAEWDCIPn1REwkIhCnSQOmdcrRV2ijE8/ylUzLyNYuVW12HDGdHpHMWaU989Mr4bmspc20FWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAGWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAHWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAIWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAJWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAKWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAALWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAMWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAANWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAOWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAPWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABrY2VydGlmaWNhdGVZAoAwggJ8MIICAaADAgECAhABlAsNMyxrUwAAAABnc106MAoGCCqGSM49BAMDMIGOMQswCQYDVQQGEwJVUzETMBEGA1UECAwKV2FzaGluZ3RvbjEQMA4GA1UEBwwHU2VhdHRsZTEPMA0GA1UECgwGQW1hem9uMQwwCgYDVQQLDANBV1MxOTA3BgNVBAMMMGktMGZlOWE5NmVkNjI2Yzc2ZGYudXMtZWFzdC0yLmF3cy5uaXRyby1lbmNsYXZlczAeFw0yNDEyMzEwMjU1NTFaFw0yNDEyMzEwNTU1NTRaMIGTMQswCQYDVQQGEwJVUzETMBEGA1UECAwKV2FzaGluZ3RvbjEQMA4GA1UEBwwHU2VhdHRsZTEPMA0GA1UECgwGQW1hem9uMQwwCgYDVQQLDANBV1MxPjA8BgNVBAMMNWktMGZlOWE5NmVkNjI2Yzc2ZGYtZW5jMDE5NDBiMGQzMzJjNmI1My51cy1lYXN0LTIuYXdzMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEvPqWS5P94NKO0hFpkeKsKcsZ4EJv36Z5V3i0ozlTfBeRlQa2nDZ/FI5ihhlRCj+eaon7GtEN+gtpNzhCr5I/BlmMBs4hABT8oX8Uo7P0uec/At0bUzcQ8cCGISzohF4Sox0wGzAMBgNVHRMBAf8EAjAAMAsGA1UdDwQEAwIGwDAKBggqhkjOPQQDAwNpADBmAjEAm1J4QIiUJIE/IXejgxI8sdqBghYV2m9xNFVUnL7fiyfGCbKqPKSbTrGe5abY1Za4AjEAxs/gr+PGicHWBhMF3/7WGatHzX2PNzM8duHMe1o/GzCUY/l8tqN8DufmbgfqRYFvaGNhYnVuZGxlhFkCFTCCAhEwggGWoAMCAQICEQD5MXVoG5Cv4R1GzLTk5/hWMAoGCCqGSM49BAMDMEkxCzAJBgNVBAYTAlVTMQ8wDQYDVQQKDAZBbWF6b24xDDAKBgNVBAsMA0FXUzEbMBkGA1UEAwwSYXdzLm5pdHJvLWVuY2xhdmVzMB4XDTE5MTAyODEzMjgwNVoXDTQ5MTAyODE0MjgwNVowSTELMAkGA1UEBhMCVVMxDzANBgNVBAoMBkFtYXpvbjEMMAoGA1UECwwDQVdTMRswGQYDVQQDDBJhd3Mubml0cm8tZW5jbGF2ZXMwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAAT8AlTrpgjB82hw4prakL5GODKSc26JS//2ctmJREtQUeU0pLH22+PAvFgaMrexdgcO3hLWmj/qIRtm51LPfdHdCV9vE3D0FwhD2dwQASHkz2MBKAlmRIfJeWKEME3FP/SjQjBAMA8GA1UdEwEB/wQFMAMBAf8wHQYDVR0OBBYEFJAltQ3ZBUfnlsOW+nKdz5mp30uWMA4GA1UdDwEB/wQEAwIBhjAKBggqhkjOPQQDAwNpADBmAjEAo38vkaHJvV7nuGJ8FpjSVQOOHwND+VtjqWKMPTmAlUWhHry/LjtV2K7ucbTD1q3zAjEAovObFgWycCil3UugabUBbmW0+96P4AYdalMZf5za9dlDvGH8K+sDy2/ujSMC89/2WQLCMIICvjCCAkWgAwIBAgIRAJe9bXmFC6wxdiiaHjZ+fHkwCgYIKoZIzj0EAwMwSTELMAkGA1UEBhMCVVMxDzANBgNVBAoMBkFtYXpvbjEMMAoGA1UECwwDQVdTMRswGQYDVQQDDBJhd3Mubml0cm8tZW5jbGF2ZXMwHhcNMjQxMjI3MTM0ODA3WhcNMjUwMTE2MTQ0ODA3WjBkMQswCQYDVQQGEwJVUzEPMA0GA1UECgwGQW1hem9uMQwwCgYDVQQLDANBV1MxNjA0BgNVBAMMLTMwMTNlOGNiNWFiMGFmNjMudXMtZWFzdC0yLmF3cy5uaXRyby1lbmNsYXZlczB2MBAGByqGSM49AgEGBSuBBAAiA2IABNe9lyxm2+i6tVvXjIFGiXsh3ZoCG4hIJRUjMyFqaZ0umkuzIxQcuX/S+wKbuzRTt4wBvozCdGEVRwUnb+Bypp9bufEUQ7Rtj3dgipBlD6aKrbojBfCOzy7YRFGQ7aomtaOB1TCB0jASBgNVHRMBAf8ECDAGAQH/AgECMB8GA1UdIwQYMBaAFJAltQ3ZBUfnlsOW+nKdz5mp30uWMB0GA1UdDgQWBBQcMCPkhTovjpLEd0uIOdsXDbhcwTAOBgNVHQ8BAf8EBAMCAYYwbAYDVR0fBGUwYzBhoF+gXYZbaHR0cDovL2F3cy1uaXRyby1lbmNsYXZlcy1jcmwuczMuYW1hem9uYXdzLmNvbS9jcmwvYWI0OTYwY2MtN2Q2My00MmJkLTllOWYtNTkzMzhjYjY3Zjg0LmNybDAKBggqhkjOPQQDAwNnADBkAjB23HQKEIFfSWckzlC7+qoJiXb1U+56bueJH+QOxg0/+69H3iSAPhsdPtP163AEJZICMDSg/snKgdt4rycqVDcMvdy9MRrAskqqIUW1U66pjePCg4kZAi505X/YdAGOhiOl9lkDGTCCAxUwggKaoAMCAQICEALQISvTsbyT/Q2SX/5+FbIwCgYIKoZIzj0EAwMwZDELMAkGA1UEBhMCVVMxDzANBgNVBAoMBkFtYXpvbjEMMAoGA1UECwwDQVdTMTYwNAYDVQQDDC0zMDEzZThjYjVhYjBhZjYzLnVzLWVhc3QtMi5hd3Mubml0cm8tZW5jbGF2ZXMwHhcNMjQxMjMwMDkwMzM1WhcNMjUwMTA1MDgwMzM1WjCBiTE8MDoGA1UEAwwzOWMyMTNkMWYyMTBhNTUxZS56b25hbC51cy1lYXN0LTIuYXdzLm5pdHJvLWVuY2xhdmVzMQwwCgYDVQQLDANBV1MxDzANBgNVBAoMBkFtYXpvbjELMAkGA1UEBhMCVVMxCzAJBgNVBAgMAldBMRAwDgYDVQQHDAdTZWF0dGxlMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAE0lBmZjVU7+Rp0/MgnekIBwiR2SAaGl/H4lHHgtNH/lKFkFi6axD34f/bEBbZaAhx/39JVoD9wD5nUQOQGDnCTvTfUxrqtaha+rAhsjaDzhJUNbyFCIm3BDT3mp1YcD7Do4HqMIHnMBIGA1UdEwEB/wQIMAYBAf8CAQEwHwYDVR0jBBgwFoAUHDAj5IU6L46SxHdLiDnbFw24XMEwHQYDVR0OBBYEFNrqvFNj+IQ8us5l9woFjBrY7YLIMA4GA1UdDwEB/wQEAwIBhjCBgAYDVR0fBHkwdzB1oHOgcYZvaHR0cDovL2NybC11cy1lYXN0LTItYXdzLW5pdHJvLWVuY2xhdmVzLnMzLnVzLWVhc3QtMi5hbWF6b25hd3MuY29tL2NybC8xODk4Y2Y2ZC03M2Y0LTQ0NTgtYjY0Ni1kM2IwMTg5NGZlYTEuY3JsMAoGCCqGSM49BAMDA2kAMGYCMQCMAA1xdR/kdrjoPkWU7ElIrkpw+cq7+v8Jvts+UJFGCfWp+PtEq5X/EAoyUqtApQYCMQCXNI1v5dlFiHQD6lULA5pjTSNfWLlDVcnSJrJ/nCGfS1LlAE+IMDEQ7qFDw1dX6GNZAsIwggK+MIICRKADAgECAhQX61FbQSwNyVZnPdRHS1P9VmjzBjAKBggqhkjOPQQDAzCBiTE8MDoGA1UEAwwzOWMyMTNkMWYyMTBhNTUxZS56b25hbC51cy1lYXN0LTIuYXdzLm5pdHJvLWVuY2xhdmVzMQwwCgYDVQQLDANBV1MxDzANBgNVBAoMBkFtYXpvbjELMAkGA1UEBhMCVVMxCzAJBgNVBAgMAldBMRAwDgYDVQQHDAdTZWF0dGxlMB4XDTI0MTIzMDE1MjExM1oXDTI0MTIzMTE1MjExM1owgY4xCzAJBgNVBAYTAlVTMRMwEQYDVQQIDApXYXNoaW5ndG9uMRAwDgYDVQQHDAdTZWF0dGxlMQ8wDQYDVQQKDAZBbWF6b24xDDAKBgNVBAsMA0FXUzE5MDcGA1UEAwwwaS0wZmU5YTk2ZWQ2MjZjNzZkZi51cy1lYXN0LTIuYXdzLm5pdHJvLWVuY2xhdmVzMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEtIdm7kbaJIEmUzgPbb5N4870jLGB3m7WI6/xdgYZLHGcLuj6jATpyQ6LCUxz/Jq4xZSLdmF5AVckR8iGrx4+/tLqo73Sum5Nk+M06Jo3GKIxN4qTS+NnCnO+lu9DzthAo2YwZDASBgNVHRMBAf8ECDAGAQH/AgEAMA4GA1UdDwEB/wQEAwICBDAdBgNVHQ4EFgQUiQpwBSaX4+TN+q63OYTx9GGMUFQwHwYDVR0jBBgwFoAU2uq8U2P4hDy6zmX3CgWMGtjtgsgwCgYIKoZIzj0EAwMDaAAwZQIwX/BNy+G2z5vxdIQSwN8zmw9iY7qIAUdt48TkBmTqppB6+DjUp5e7jLw10fq8MczRAjEAisvTFdeBYb+Z3UIbkkiXe/Bdc6eVa7j9NeEc40EqmIoHXxLOmUdw0snPU2Iqaib8anB1YmxpY19rZXlFZHVtbXlpdXNlcl9kYXRhWEQSIH6QxIbYSOLkSVJajn6QqPUHZMh+tUEu4+1EGTOnUX4dEiAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAGVub25jZVQBI0VniavN7wEjRWeJq83vASNFZ1hguEwKrQMw/qGbIb/NcPu35hlf/+4vI8Wjhp0Ruen4oJ19d8D8B7nSqVsIAQ1JQeDp+9Fb/Rc1jg16lUrR3LeFiEByVxKJzaUryRlmo5qwuSxAd7VW3jp+7YQ1z/OFFOiu";
+
     #[test]
     fn test_sign_p256() {
         // Generate a random private key
@@ -241,7 +677,7 @@ mod test {
 
     #[test]
     fn test_verify_attestation_document() {
-        let attestation_document = "hEShATgioFkRXqlpbW9kdWxlX2lkeCdpLTBmZTlhOTZlZDYyNmM3NmRmLWVuYzAxOTQwYjBkMzMyYzZiNTNmZGlnZXN0ZlNIQTM4NGl0aW1lc3RhbXAbAAABlBqkLPdkcGNyc7AAWDBqayfwH0L+yJw/GE7G+egQh6+OxInfMClAmcC5MFoa1u3e+ZvXHGISxcnVS3nYDB0BWDBLTVs2YbPvwSkgkAyA4Sbkzng8Ui3mwCoqW/evOiuTJ7hndvGI5L4cHEBKEp29pJMCWDC8bcpDk1ZDBcUYwjlcTirF/BGGtAkKEJfwyHvaVxV+u/vlG6rh4vj2tu5++nAeLJIDWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAEWDCIPn1REwkIhCnSQOmdcrRV2ijE8/ylUzLyNYuVW12HDGdHpHMWaU989Mr4bmspc20FWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAGWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAHWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAIWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAJWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAKWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAALWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAMWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAANWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAOWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAPWDAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABrY2VydGlmaWNhdGVZAoAwggJ8MIICAaADAgECAhABlAsNMyxrUwAAAABnc106MAoGCCqGSM49BAMDMIGOMQswCQYDVQQGEwJVUzETMBEGA1UECAwKV2FzaGluZ3RvbjEQMA4GA1UEBwwHU2VhdHRsZTEPMA0GA1UECgwGQW1hem9uMQwwCgYDVQQLDANBV1MxOTA3BgNVBAMMMGktMGZlOWE5NmVkNjI2Yzc2ZGYudXMtZWFzdC0yLmF3cy5uaXRyby1lbmNsYXZlczAeFw0yNDEyMzEwMjU1NTFaFw0yNDEyMzEwNTU1NTRaMIGTMQswCQYDVQQGEwJVUzETMBEGA1UECAwKV2FzaGluZ3RvbjEQMA4GA1UEBwwHU2VhdHRsZTEPMA0GA1UECgwGQW1hem9uMQwwCgYDVQQLDANBV1MxPjA8BgNVBAMMNWktMGZlOWE5NmVkNjI2Yzc2ZGYtZW5jMDE5NDBiMGQzMzJjNmI1My51cy1lYXN0LTIuYXdzMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEvPqWS5P94NKO0hFpkeKsKcsZ4EJv36Z5V3i0ozlTfBeRlQa2nDZ/FI5ihhlRCj+eaon7GtEN+gtpNzhCr5I/BlmMBs4hABT8oX8Uo7P0uec/At0bUzcQ8cCGISzohF4Sox0wGzAMBgNVHRMBAf8EAjAAMAsGA1UdDwQEAwIGwDAKBggqhkjOPQQDAwNpADBmAjEAm1J4QIiUJIE/IXejgxI8sdqBghYV2m9xNFVUnL7fiyfGCbKqPKSbTrGe5abY1Za4AjEAxs/gr+PGicHWBhMF3/7WGatHzX2PNzM8duHMe1o/GzCUY/l8tqN8DufmbgfqRYFvaGNhYnVuZGxlhFkCFTCCAhEwggGWoAMCAQICEQD5MXVoG5Cv4R1GzLTk5/hWMAoGCCqGSM49BAMDMEkxCzAJBgNVBAYTAlVTMQ8wDQYDVQQKDAZBbWF6b24xDDAKBgNVBAsMA0FXUzEbMBkGA1UEAwwSYXdzLm5pdHJvLWVuY2xhdmVzMB4XDTE5MTAyODEzMjgwNVoXDTQ5MTAyODE0MjgwNVowSTELMAkGA1UEBhMCVVMxDzANBgNVBAoMBkFtYXpvbjEMMAoGA1UECwwDQVdTMRswGQYDVQQDDBJhd3Mubml0cm8tZW5jbGF2ZXMwdjAQBgcqhkjOPQIBBgUrgQQAIgNiAAT8AlTrpgjB82hw4prakL5GODKSc26JS//2ctmJREtQUeU0pLH22+PAvFgaMrexdgcO3hLWmj/qIRtm51LPfdHdCV9vE3D0FwhD2dwQASHkz2MBKAlmRIfJeWKEME3FP/SjQjBAMA8GA1UdEwEB/wQFMAMBAf8wHQYDVR0OBBYEFJAltQ3ZBUfnlsOW+nKdz5mp30uWMA4GA1UdDwEB/wQEAwIBhjAKBggqhkjOPQQDAwNpADBmAjEAo38vkaHJvV7nuGJ8FpjSVQOOHwND+VtjqWKMPTmAlUWhHry/LjtV2K7ucbTD1q3zAjEAovObFgWycCil3UugabUBbmW0+96P4AYdalMZf5za9dlDvGH8K+sDy2/ujSMC89/2WQLCMIICvjCCAkWgAwIBAgIRAJe9bXmFC6wxdiiaHjZ+fHkwCgYIKoZIzj0EAwMwSTELMAkGA1UEBhMCVVMxDzANBgNVBAoMBkFtYXpvbjEMMAoGA1UECwwDQVdTMRswGQYDVQQDDBJhd3Mubml0cm8tZW5jbGF2ZXMwHhcNMjQxMjI3MTM0ODA3WhcNMjUwMTE2MTQ0ODA3WjBkMQswCQYDVQQGEwJVUzEPMA0GA1UECgwGQW1hem9uMQwwCgYDVQQLDANBV1MxNjA0BgNVBAMMLTMwMTNlOGNiNWFiMGFmNjMudXMtZWFzdC0yLmF3cy5uaXRyby1lbmNsYXZlczB2MBAGByqGSM49AgEGBSuBBAAiA2IABNe9lyxm2+i6tVvXjIFGiXsh3ZoCG4hIJRUjMyFqaZ0umkuzIxQcuX/S+wKbuzRTt4wBvozCdGEVRwUnb+Bypp9bufEUQ7Rtj3dgipBlD6aKrbojBfCOzy7YRFGQ7aomtaOB1TCB0jASBgNVHRMBAf8ECDAGAQH/AgECMB8GA1UdIwQYMBaAFJAltQ3ZBUfnlsOW+nKdz5mp30uWMB0GA1UdDgQWBBQcMCPkhTovjpLEd0uIOdsXDbhcwTAOBgNVHQ8BAf8EBAMCAYYwbAYDVR0fBGUwYzBhoF+gXYZbaHR0cDovL2F3cy1uaXRyby1lbmNsYXZlcy1jcmwuczMuYW1hem9uYXdzLmNvbS9jcmwvYWI0OTYwY2MtN2Q2My00MmJkLTllOWYtNTkzMzhjYjY3Zjg0LmNybDAKBggqhkjOPQQDAwNnADBkAjB23HQKEIFfSWckzlC7+qoJiXb1U+56bueJH+QOxg0/+69H3iSAPhsdPtP163AEJZICMDSg/snKgdt4rycqVDcMvdy9MRrAskqqIUW1U66pjePCg4kZAi505X/YdAGOhiOl9lkDGTCCAxUwggKaoAMCAQICEALQISvTsbyT/Q2SX/5+FbIwCgYIKoZIzj0EAwMwZDELMAkGA1UEBhMCVVMxDzANBgNVBAoMBkFtYXpvbjEMMAoGA1UECwwDQVdTMTYwNAYDVQQDDC0zMDEzZThjYjVhYjBhZjYzLnVzLWVhc3QtMi5hd3Mubml0cm8tZW5jbGF2ZXMwHhcNMjQxMjMwMDkwMzM1WhcNMjUwMTA1MDgwMzM1WjCBiTE8MDoGA1UEAwwzOWMyMTNkMWYyMTBhNTUxZS56b25hbC51cy1lYXN0LTIuYXdzLm5pdHJvLWVuY2xhdmVzMQwwCgYDVQQLDANBV1MxDzANBgNVBAoMBkFtYXpvbjELMAkGA1UEBhMCVVMxCzAJBgNVBAgMAldBMRAwDgYDVQQHDAdTZWF0dGxlMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAE0lBmZjVU7+Rp0/MgnekIBwiR2SAaGl/H4lHHgtNH/lKFkFi6axD34f/bEBbZaAhx/39JVoD9wD5nUQOQGDnCTvTfUxrqtaha+rAhsjaDzhJUNbyFCIm3BDT3mp1YcD7Do4HqMIHnMBIGA1UdEwEB/wQIMAYBAf8CAQEwHwYDVR0jBBgwFoAUHDAj5IU6L46SxHdLiDnbFw24XMEwHQYDVR0OBBYEFNrqvFNj+IQ8us5l9woFjBrY7YLIMA4GA1UdDwEB/wQEAwIBhjCBgAYDVR0fBHkwdzB1oHOgcYZvaHR0cDovL2NybC11cy1lYXN0LTItYXdzLW5pdHJvLWVuY2xhdmVzLnMzLnVzLWVhc3QtMi5hbWF6b25hd3MuY29tL2NybC8xODk4Y2Y2ZC03M2Y0LTQ0NTgtYjY0Ni1kM2IwMTg5NGZlYTEuY3JsMAoGCCqGSM49BAMDA2kAMGYCMQCMAA1xdR/kdrjoPkWU7ElIrkpw+cq7+v8Jvts+UJFGCfWp+PtEq5X/EAoyUqtApQYCMQCXNI1v5dlFiHQD6lULA5pjTSNfWLlDVcnSJrJ/nCGfS1LlAE+IMDEQ7qFDw1dX6GNZAsIwggK+MIICRKADAgECAhQX61FbQSwNyVZnPdRHS1P9VmjzBjAKBggqhkjOPQQDAzCBiTE8MDoGA1UEAwwzOWMyMTNkMWYyMTBhNTUxZS56b25hbC51cy1lYXN0LTIuYXdzLm5pdHJvLWVuY2xhdmVzMQwwCgYDVQQLDANBV1MxDzANBgNVBAoMBkFtYXpvbjELMAkGA1UEBhMCVVMxCzAJBgNVBAgMAldBMRAwDgYDVQQHDAdTZWF0dGxlMB4XDTI0MTIzMDE1MjExM1oXDTI0MTIzMTE1MjExM1owgY4xCzAJBgNVBAYTAlVTMRMwEQYDVQQIDApXYXNoaW5ndG9uMRAwDgYDVQQHDAdTZWF0dGxlMQ8wDQYDVQQKDAZBbWF6b24xDDAKBgNVBAsMA0FXUzE5MDcGA1UEAwwwaS0wZmU5YTk2ZWQ2MjZjNzZkZi51cy1lYXN0LTIuYXdzLm5pdHJvLWVuY2xhdmVzMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEtIdm7kbaJIEmUzgPbb5N4870jLGB3m7WI6/xdgYZLHGcLuj6jATpyQ6LCUxz/Jq4xZSLdmF5AVckR8iGrx4+/tLqo73Sum5Nk+M06Jo3GKIxN4qTS+NnCnO+lu9DzthAo2YwZDASBgNVHRMBAf8ECDAGAQH/AgEAMA4GA1UdDwEB/wQEAwICBDAdBgNVHQ4EFgQUiQpwBSaX4+TN+q63OYTx9GGMUFQwHwYDVR0jBBgwFoAU2uq8U2P4hDy6zmX3CgWMGtjtgsgwCgYIKoZIzj0EAwMDaAAwZQIwX/BNy+G2z5vxdIQSwN8zmw9iY7qIAUdt48TkBmTqppB6+DjUp5e7jLw10fq8MczRAjEAisvTFdeBYb+Z3UIbkkiXe/Bdc6eVa7j9NeEc40EqmIoHXxLOmUdw0snPU2Iqaib8anB1YmxpY19rZXlFZHVtbXlpdXNlcl9kYXRhWEQSIH6QxIbYSOLkSVJajn6QqPUHZMh+tUEu4+1EGTOnUX4dEiAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAGVub25jZVQBI0VniavN7wEjRWeJq83vASNFZ1hguEwKrQMw/qGbIb/NcPu35hlf/+4vI8Wjhp0Ruen4oJ19d8D8B7nSqVsIAQ1JQeDp+9Fb/Rc1jg16lUrR3LeFiEByVxKJzaUryRlmo5qwuSxAd7VW3jp+7YQ1z/OFFOiu".to_string();
+        let attestation_document = ATTESTATION_DOCUMENT.to_string();
         let nonce = "0000000000000000000000000000000000000000".to_string();
         let pcr = "vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS".to_string();
         let timestamp = 1719859200;
@@ -249,7 +685,279 @@ mod test {
             attestation_document,
             nonce,
             pcr,
-            timestamp
+            timestamp,
+            None
         ));
     }
+
+    #[test]
+    fn test_verify_attestation_document_any() {
+        let verify = |pcrs_expected: &[&str]| {
+            verify_attestation_document_any(
+                ATTESTATION_DOCUMENT.to_string(),
+                "0000000000000000000000000000000000000000".to_string(),
+                pcrs_expected.iter().map(|pcr| pcr.to_string()).collect(),
+                1719859200,
+                None,
+            )
+        };
+        let old_image = compute_expected_pcr(b"old image".to_vec());
+
+        assert_eq!(
+            verify(&[
+                &old_image,
+                "vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS",
+            ]),
+            Some(1)
+        );
+        assert_eq!(verify(&[&old_image]), None);
+        assert_eq!(verify(&[]), None);
+    }
+
+    #[test]
+    fn test_module_id_allowlist() {
+        let verify = |allowed_module_ids: Vec<&str>| {
+            verify_attestation_document(
+                ATTESTATION_DOCUMENT.to_string(),
+                "0000000000000000000000000000000000000000".to_string(),
+                "vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS".to_string(),
+                1719859200,
+                Some(AttestationOptions {
+                    allowed_module_ids: Some(
+                        allowed_module_ids.into_iter().map(str::to_string).collect(),
+                    ),
+                    ..Default::default()
+                }),
+            )
+        };
+
+        assert!(verify(vec![
+            "i-0123456789abcdef0-enc0194000000000000",
+            "i-0fe9a96ed626c76df-enc01940b0d332c6b53",
+        ]));
+        assert!(!verify(vec!["i-0123456789abcdef0-enc0194000000000000"]));
+        assert!(!verify(vec![]));
+    }
+
+    #[test]
+    fn test_certificate_module_id() {
+        let attestation_document = general_purpose::STANDARD
+            .decode(ATTESTATION_DOCUMENT)
+            .unwrap();
+        let (payload, _) =
+            parse_verify_with(attestation_document, vec![0; 20], 1719859200).unwrap();
+        assert_eq!(payload.module_id, "i-0fe9a96ed626c76df-enc01940b0d332c6b53");
+
+        assert!(check_certificate_module_id(&payload.certificate, &payload.module_id).is_ok());
+        for module_id in [
+            "i-0123456789abcdef0-enc0194000000000000",
+            // A prefix of the module id in the certificate
+            "i-0fe9a96ed626c76df-enc01940b0d332c6b5",
+        ] {
+            assert!(matches!(
+                check_certificate_module_id(&payload.certificate, module_id),
+                Err(AttestationError::ModuleIdMismatch { subject, .. })
+                    if subject == "i-0fe9a96ed626c76df-enc01940b0d332c6b53.us-east-2.aws"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_attestation_freshness_for_session() {
+        // Timestamp of the attestation document above, 2024-12-31T02:55:54Z
+        let attestation_timestamp_ms = 1735613754615;
+
+        assert!(is_attestation_timestamp_within(
+            attestation_timestamp_ms,
+            1735613754 + 60,
+            300
+        ));
+        assert!(is_attestation_timestamp_within(
+            attestation_timestamp_ms,
+            1735613754 - 60,
+            300
+        ));
+        // A session notarized months after the attestation was produced
+        assert!(!is_attestation_timestamp_within(
+            attestation_timestamp_ms,
+            1743465600,
+            300
+        ));
+    }
+
+    #[test]
+    fn test_attestation_validity_window() {
+        let verify = |now: u64, max_age_secs: Option<u64>, max_clock_skew_secs: Option<u64>| {
+            verify_attestation_payload(
+                ATTESTATION_DOCUMENT.to_string(),
+                "0000000000000000000000000000000000000000".to_string(),
+                &["vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS".to_string()],
+                now,
+                &AttestationOptions {
+                    max_age_secs,
+                    max_clock_skew_secs,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // The document was produced at 2024-12-31T02:55:54.615Z
+        assert!(verify(1735613754 + 60, Some(300), None).is_ok());
+        // Without bounds the timestamp is not checked
+        assert!(verify(1719859200, None, None).is_ok());
+        // A document replayed a day later is stale
+        assert!(matches!(
+            verify(1735613754 + 86400, Some(300), None),
+            Err(AttestationError::TimestampOutsideWindow {
+                timestamp_ms: 1735613754615,
+                not_before_ms: 1735699854000,
+                not_after_ms: 1735700214000,
+            })
+        ));
+        // A document ahead of a verifier clock running a few seconds late, which the default
+        // skew allows
+        let now = 1735613752;
+        assert!(verify(now, Some(300), None).is_ok());
+        assert!(verify(now, None, Some(3)).is_ok());
+        // A document beyond the allowed skew
+        assert!(matches!(
+            verify(now, None, Some(1)),
+            Err(AttestationError::TimestampOutsideWindow {
+                timestamp_ms: 1735613754615,
+                not_before_ms: 0,
+                not_after_ms: 1735613753000,
+            })
+        ));
+        assert!(matches!(
+            verify(now - 120, Some(300), None),
+            Err(AttestationError::TimestampOutsideWindow {
+                not_after_ms: 1735613692000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_compute_expected_pcr() {
+        assert_eq!(
+            compute_expected_pcr(Vec::new()),
+            "jw0UXANorWtwviLkHEAO6pG5cdlroiD+yfriWljf/ar3Lb6PZ4PVUSjJ30769vin"
+        );
+        assert_eq!(
+            compute_expected_pcr(b"freysa notary".to_vec()),
+            "yffLL5ySeRo0mabqEk9tGOjQLII8cbHFvXqG4f55u4EaMvC2asp28jWSxeGZI9tL"
+        );
+    }
+
+    #[test]
+    fn test_notary_deployment() {
+        use sha2::{Digest, Sha256};
+
+        const NOTARY_KEY: &str = "0406fdfa148e1916ccc96b40d0149df05825ef54b16b711ccc1b991a4de1c6a12cc3bba705ab1dee116629146a3a0b410e5207fe98481b92d2eb5e872fe721f32a";
+        let config_hash = hex::encode(Sha256::digest(b"config"));
+        let user_data = |key_digest: &[u8], config_hash: &[u8]| {
+            [
+                SHA256_MULTIHASH_PREFIX.as_slice(),
+                key_digest,
+                SHA256_MULTIHASH_PREFIX.as_slice(),
+                config_hash,
+            ]
+            .concat()
+        };
+        let key_digest = Sha256::digest(hex::decode(NOTARY_KEY).unwrap());
+        let bound = user_data(&key_digest, &hex::decode(&config_hash).unwrap());
+
+        assert!(check_deployment_binding(&bound, &config_hash, NOTARY_KEY).is_ok());
+        assert!(check_deployment_binding(&bound, &config_hash.to_uppercase(), NOTARY_KEY).is_ok());
+        assert!(matches!(
+            check_deployment_binding(&bound, &hex::encode([0; 32]), NOTARY_KEY),
+            Err(AttestationError::ConfigHashMismatch(hash)) if hash == config_hash
+        ));
+        assert!(matches!(
+            check_deployment_binding(&bound, &config_hash, "04ff"),
+            Err(AttestationError::NotaryKeyMismatch(_))
+        ));
+        assert!(matches!(
+            check_deployment_binding(&bound[..34], &config_hash, NOTARY_KEY),
+            Err(AttestationError::InvalidUserData(_))
+        ));
+        let mut wrong_prefix = bound.clone();
+        wrong_prefix[34] = 0x13;
+        assert!(matches!(
+            check_deployment_binding(&wrong_prefix, &config_hash, NOTARY_KEY),
+            Err(AttestationError::InvalidUserData(_))
+        ));
+
+        let verify = |pcrs_expected: &[&str], allowed_module_ids: Option<Vec<String>>| {
+            check_notary_deployment(
+                ATTESTATION_DOCUMENT.to_string(),
+                "0000000000000000000000000000000000000000".to_string(),
+                &pcrs_expected
+                    .iter()
+                    .map(|pcr| pcr.to_string())
+                    .collect::<Vec<_>>(),
+                1719859200,
+                &hex::encode([0; 32]),
+                NOTARY_KEY,
+                &AttestationOptions {
+                    allowed_module_ids,
+                    ..Default::default()
+                },
+            )
+        };
+        // The document above attests a zero config hash and the digest of another key
+        assert!(matches!(
+            verify(
+                &["vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS"],
+                None
+            ),
+            Err(AttestationError::NotaryKeyMismatch(digest))
+                if digest == "7e90c486d848e2e449525a8e7e90a8f50764c87eb5412ee3ed441933a7517e1d"
+        ));
+        assert!(matches!(
+            verify(&[&compute_expected_pcr(b"old image".to_vec())], None),
+            Err(AttestationError::PcrMismatch(_))
+        ));
+        // Malformed input is reported rather than trapping the module
+        for (document, nonce) in [
+            ("not base64!", "0000000000000000000000000000000000000000"),
+            (ATTESTATION_DOCUMENT, "not hex"),
+        ] {
+            assert!(matches!(
+                check_notary_deployment(
+                    document.to_string(),
+                    nonce.to_string(),
+                    &[
+                        "vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS"
+                            .to_string()
+                    ],
+                    1719859200,
+                    &hex::encode([0; 32]),
+                    NOTARY_KEY,
+                    &AttestationOptions::default(),
+                ),
+                Err(AttestationError::InvalidDocument(_))
+            ));
+        }
+        // The module id check runs before the user data is checked
+        assert!(matches!(
+            verify(
+                &["vG3KQ5NWQwXFGMI5XE4qxfwRhrQJChCX8Mh72lcVfrv75Ruq4eL49rbufvpwHiyS"],
+                Some(vec!["i-0000000000000000-enc0000000000000000".to_string()])
+            ),
+            Err(AttestationError::ModuleIdNotAllowed(_))
+        ));
+    }
+
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_code_attestation_url() {
+        assert_eq!(
+            code_attestation_url(None, "00ff"),
+            "https://notary.freysa.ai/enclave/attestation?nonce=00ff"
+        );
+        assert_eq!(
+            code_attestation_url(Some("https://staging.notary.example/"), "00ff"),
+            "https://staging.notary.example/enclave/attestation?nonce=00ff"
+        );
+    }
 }
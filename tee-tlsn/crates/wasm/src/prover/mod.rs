@@ -135,8 +135,8 @@ impl JsProver {
         let serialized = json!({
             "application_data": notarized_session.application_data,
             "signature": format!("{}", hex::encode(notarized_session.signature.to_bytes())),
-            "attributes": attestations_vec
-
+            "attributes": attestations_vec,
+            "attestations_signature": hex::encode(notarized_session.attestations_signature.to_bytes()),
         })
         .to_string();
 